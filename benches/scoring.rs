@@ -0,0 +1,72 @@
+/*
+Times queries whose candidate sets span most of a synthetic corpus, and
+conjunctions of a rare and a common word. Compare runs with and without
+the "parallel" feature:
+
+    cargo bench --bench scoring
+    cargo bench --bench scoring --features parallel
+*/
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::Instant;
+
+use mini_search::document::Document;
+use mini_search::searcher::{MinimumShouldMatch, SearchEngine, SearchOptions};
+use mini_search::settings::IndexSettings;
+
+const DOCUMENTS: u64 = 20_000;
+const RUNS: u32 = 5;
+// Documents also holding the rare word, one in this many
+const RARE_EVERY: u64 = 500;
+const WORDS: [&str; 8] = [
+    "search", "engine", "index", "query", "score", "rank", "token", "field",
+];
+
+fn main() {
+    let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+    for id in 0..DOCUMENTS {
+        // Every document gets a few of the words, varying by id
+        let mut content: Vec<&str> = (0..12)
+            .map(|i| WORDS[((id * 7 + i * i) % WORDS.len() as u64) as usize])
+            .collect();
+        if id % RARE_EVERY == 0 {
+            content.push("rare");
+        }
+        engine
+            .add_document(Document {
+                id,
+                title: String::new(),
+                content: content.join(" "),
+                metadata: HashMap::new(),
+            })
+            .unwrap();
+    }
+
+    let any = SearchOptions::new(10);
+    for query in [
+        "search",
+        "search engine index",
+        "query score rank token field",
+    ] {
+        time(&engine, query, &any);
+    }
+    let all = SearchOptions::new(10).minimum_should_match(MinimumShouldMatch::Percent(100));
+    for query in ["rare search", "rare search engine"] {
+        time(&engine, query, &all);
+    }
+}
+
+fn time(engine: &SearchEngine, query: &str, options: &SearchOptions) {
+    black_box(engine.search_with_options(query, options));
+    let start = Instant::now();
+    let mut matches = 0;
+    for _ in 0..RUNS {
+        matches = black_box(engine.search_with_options(query, options)).total_matches;
+    }
+    println!(
+        "{:<32} {:>7} matches  {:>8.2} ms/query",
+        query,
+        matches,
+        start.elapsed().as_secs_f64() * 1000.0 / RUNS as f64
+    );
+}
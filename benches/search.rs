@@ -0,0 +1,113 @@
+/*
+Criterion benchmarks of tokenization throughput, indexing and query
+latency over a generated corpus, to catch regressions as the index
+internals change:
+
+    cargo bench --bench search
+    cargo bench --bench search -- query
+
+Criterion keeps the previous run under target/criterion and reports the
+change against it.
+*/
+use std::collections::HashMap;
+use std::hint::black_box;
+
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use mini_search::document::Document;
+use mini_search::searcher::SearchEngine;
+use mini_search::settings::IndexSettings;
+use mini_search::tokenizer::{AnalyzerPreset, Language, Tokenizer};
+
+const DOCUMENTS: u64 = 5_000;
+const WORDS_PER_DOCUMENT: u64 = 120;
+// Documents indexed per iteration of the indexing benchmark
+const INDEXED_BATCH: u64 = 500;
+const VOCABULARY: [&str; 24] = [
+    "search", "engine", "index", "query", "score", "rank", "token", "field", "document", "posting",
+    "segment", "merge", "phrase", "filter", "facet", "cache", "stemming", "analyzer", "snippet",
+    "vector", "shard", "replica", "schema", "latency",
+];
+
+// A deterministic document whose words follow a skewed distribution
+fn document(id: u64) -> Document {
+    let mut state = id
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    let words: Vec<&str> = (0..WORDS_PER_DOCUMENT)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            // Squaring favors the first words of the vocabulary
+            let r = (state >> 33) as f64 / (1u64 << 31) as f64;
+            VOCABULARY[(r * r * VOCABULARY.len() as f64) as usize]
+        })
+        .collect();
+    Document {
+        id,
+        title: format!("{} {}", words[0], words[1]),
+        content: words.join(" "),
+        metadata: HashMap::new(),
+    }
+}
+
+fn corpus(documents: u64) -> Vec<Document> {
+    (0..documents).map(document).collect()
+}
+
+fn tokenization(c: &mut Criterion) {
+    let text: String = corpus(200)
+        .iter()
+        .map(|document| document.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut group = c.benchmark_group("tokenize");
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    for preset in [AnalyzerPreset::Simple, AnalyzerPreset::English] {
+        let tokenizer = Tokenizer::with_preset(preset, Language::English).unwrap();
+        group.bench_function(preset.as_str(), |b| {
+            b.iter(|| tokenizer.tokenize(black_box(&text)))
+        });
+    }
+    group.finish();
+}
+
+fn indexing(c: &mut Criterion) {
+    let documents = corpus(INDEXED_BATCH);
+    let mut group = c.benchmark_group("index");
+    group.throughput(Throughput::Elements(INDEXED_BATCH));
+    group.bench_function("documents", |b| {
+        b.iter_batched(
+            || documents.clone(),
+            |documents| {
+                let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+                for document in documents {
+                    engine.add_document(document).unwrap();
+                }
+                engine
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn querying(c: &mut Criterion) {
+    let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+    for document in corpus(DOCUMENTS) {
+        engine.add_document(document).unwrap();
+    }
+    let mut group = c.benchmark_group("query");
+    let queries = [
+        ("single_term", "segment"),
+        ("multi_term", "segment merge latency"),
+        ("phrase", "\"search engine\""),
+    ];
+    for (name, query) in queries {
+        group.bench_function(name, |b| b.iter(|| engine.search(black_box(query), 10)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, tokenization, indexing, querying);
+criterion_main!(benches);
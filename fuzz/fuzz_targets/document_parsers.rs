@@ -0,0 +1,38 @@
+/*
+Feeds arbitrary input to every document parser and to the CSV loader.
+Malformed input must come back as an error, never a panic:
+
+    cargo fuzz run document_parsers
+*/
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mini_search::document::DocumentParser;
+use mini_search::document::csv::{CsvMapping, load_csv};
+use mini_search::document::html::HtmlDocumentParser;
+use mini_search::document::json::JsonDocumentParser;
+use mini_search::document::log::LogLineParser;
+use mini_search::document::markdown::MarkdownDocumentParser;
+use mini_search::document::text::TextDocumentParser;
+use mini_search::searcher::SearchEngine;
+use mini_search::settings::IndexSettings;
+
+fuzz_target!(|input: &str| {
+    let parsers: [Box<dyn DocumentParser>; 5] = [
+        Box::new(JsonDocumentParser::default()),
+        Box::new(HtmlDocumentParser::new()),
+        Box::new(MarkdownDocumentParser::new()),
+        Box::new(LogLineParser::default()),
+        Box::new(TextDocumentParser::new()),
+    ];
+    for parser in &parsers {
+        if let Ok(document) = parser.parse(input) {
+            let _ = parser.extract_text(&document);
+        }
+        let _ = parser.parse_mapped(input);
+    }
+    let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+    for mapping in [CsvMapping::default(), CsvMapping::tsv()] {
+        let _ = load_csv(input.as_bytes(), &mut engine, &mapping);
+    }
+});
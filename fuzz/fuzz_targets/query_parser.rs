@@ -0,0 +1,66 @@
+/*
+Feeds arbitrary query strings to the query parser and the search paths
+built on it, over a small index so that terms, phrases, ranges and
+field clauses all reach scoring. Any panic is a bug:
+
+    cargo fuzz run query_parser
+*/
+#![no_main]
+
+use std::collections::HashMap;
+
+use libfuzzer_sys::fuzz_target;
+use mini_search::document::Document;
+use mini_search::searcher::SearchEngine;
+use mini_search::settings::{FieldDef, FieldType, IndexSettings, Schema};
+
+fn engine() -> SearchEngine {
+    let schema = Schema::new()
+        .field(FieldDef::new("tags", FieldType::Keyword).multi(true))
+        .field(FieldDef::new("price", FieldType::Numeric));
+    let mut engine = SearchEngine::new(IndexSettings {
+        schema,
+        ..IndexSettings::default()
+    })
+    .unwrap();
+    let docs = [
+        (
+            1,
+            "Rust book",
+            "ownership, borrowing",
+            "rust, books",
+            "39.5",
+        ),
+        (2, "Search", "inverted index, BM25 scoring", "search", "12"),
+        (3, "Café", "naïve unicode text — ünïcödé", "misc", "0"),
+    ];
+    for (id, title, content, tags, price) in docs {
+        let metadata = HashMap::from([
+            ("tags".to_string(), tags.to_string()),
+            ("price".to_string(), price.to_string()),
+        ]);
+        engine
+            .add_document(Document {
+                id,
+                title: title.to_string(),
+                content: content.to_string(),
+                metadata,
+            })
+            .unwrap();
+    }
+    engine
+}
+
+thread_local! {
+    static ENGINE: SearchEngine = engine();
+}
+
+fuzz_target!(|query: &str| {
+    ENGINE.with(|engine| {
+        let _ = engine.check_query(query);
+        let results = engine.search(query, 10);
+        assert!(results.documents.len() <= 10);
+        let _ = engine.search_iter(query).take(5).count();
+        let _ = engine.suggest(query, 5);
+    });
+});
@@ -0,0 +1,50 @@
+use std::env;
+use std::path::Path;
+use std::process::ExitCode;
+
+use mini_search::searcher::SearchEngine;
+use mini_search::server::Server;
+use mini_search::settings::IndexSettings;
+use mini_search::storage::SETTINGS_FILE;
+
+const USAGE: &str = "usage: mini-search-server <index-dir> [address]";
+// Where the server listens when no address is given
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+// Serve an index read-write, creating it with default settings if missing
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (index, address) = match args.as_slice() {
+        [index] => (index, DEFAULT_ADDRESS),
+        [index, address] => (index, address.as_str()),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    let opened = if Path::new(index).join(SETTINGS_FILE).exists() {
+        SearchEngine::open(index)
+    } else {
+        SearchEngine::create(index, IndexSettings::default())
+    };
+    let mut engine = match opened {
+        Ok(engine) => engine,
+        Err(e) => {
+            eprintln!("failed to open {}: {}", index, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("serving {} on http://{}", index, address);
+    let served = Server::writable(&mut engine, Vec::new()).serve(address);
+    // Keep writes made since the server's last flush
+    if let Err(e) = engine.flush() {
+        eprintln!("failed to flush {}: {}", index, e);
+    }
+    match served {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("server error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+
+// Sparse term weights of one document
+pub type TermVector = HashMap<String, f64>;
+
+// How to group documents
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClusterMethod {
+    // Up to `k` groups around centroids, refined at most `iterations` times
+    KMeans { k: usize, iterations: usize },
+    // Merge the closest groups (average linkage) while they are at least this similar
+    Agglomerative { min_similarity: f64 },
+}
+
+// Pairwise cosine similarities of a set of documents
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarityMatrix {
+    doc_ids: Vec<u64>,
+    values: Vec<f64>, // Row-major, doc_ids.len() squared
+}
+
+impl SimilarityMatrix {
+    pub fn new(doc_ids: Vec<u64>, vectors: &[TermVector]) -> Self {
+        let vectors: Vec<TermVector> = vectors.iter().map(normalize).collect();
+        let n = vectors.len();
+        let mut values = vec![0.0; n * n];
+        for i in 0..n {
+            values[i * n + i] = if vectors[i].is_empty() { 0.0 } else { 1.0 };
+            for j in i + 1..n {
+                let similarity = dot(&vectors[i], &vectors[j]);
+                values[i * n + j] = similarity;
+                values[j * n + i] = similarity;
+            }
+        }
+        SimilarityMatrix { doc_ids, values }
+    }
+
+    pub fn doc_ids(&self) -> &[u64] {
+        &self.doc_ids
+    }
+
+    // Similarity of the documents at positions `i` and `j`
+    pub fn get(&self, i: usize, j: usize) -> f64 {
+        self.values[i * self.doc_ids.len() + j]
+    }
+
+    // Similarity of two documents by id, None if either is not in the matrix
+    pub fn similarity(&self, a: u64, b: u64) -> Option<f64> {
+        let i = self.doc_ids.iter().position(|&id| id == a)?;
+        let j = self.doc_ids.iter().position(|&id| id == b)?;
+        Some(self.get(i, j))
+    }
+}
+
+// Documents grouped by similarity
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Clustering {
+    pub clusters: Vec<Vec<u64>>, // Members of each cluster, in input order
+    pub labels: HashMap<u64, usize>, // Index into `clusters` of each document
+}
+
+impl Clustering {
+    /*
+    Build from one label per document. Labels are renumbered so clusters
+    appear in the order of their first member.
+    */
+    fn from_assignments(doc_ids: &[u64], assignments: &[usize]) -> Self {
+        let mut clustering = Clustering::default();
+        let mut renumbered: HashMap<usize, usize> = HashMap::new();
+        for (&doc_id, &assignment) in doc_ids.iter().zip(assignments) {
+            let label = *renumbered.entry(assignment).or_insert_with(|| {
+                clustering.clusters.push(Vec::new());
+                clustering.clusters.len() - 1
+            });
+            clustering.clusters[label].push(doc_id);
+            clustering.labels.insert(doc_id, label);
+        }
+        clustering
+    }
+
+    pub fn label(&self, doc_id: u64) -> Option<usize> {
+        self.labels.get(&doc_id).copied()
+    }
+}
+
+fn dot(a: &TermVector, b: &TermVector) -> f64 {
+    let (small, large) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    small
+        .iter()
+        .filter_map(|(term, weight)| Some(weight * large.get(term)?))
+        .sum()
+}
+
+fn normalize(vector: &TermVector) -> TermVector {
+    let norm = dot(vector, vector).sqrt();
+    if norm == 0.0 {
+        return TermVector::new();
+    }
+    vector
+        .iter()
+        .map(|(term, weight)| (term.clone(), weight / norm))
+        .collect()
+}
+
+// Cosine similarity of two term vectors, 0 if either is empty
+pub fn cosine(a: &TermVector, b: &TermVector) -> f64 {
+    let norms = (dot(a, a) * dot(b, b)).sqrt();
+    if norms == 0.0 { 0.0 } else { dot(a, b) / norms }
+}
+
+/*
+K-means over cosine similarity. Seeds are picked deterministically: the
+first document, then repeatedly the document least similar to every seed
+so far. Clusters that end up empty are dropped, so fewer than `k` may be
+returned.
+*/
+pub fn kmeans(doc_ids: &[u64], vectors: &[TermVector], k: usize, iterations: usize) -> Clustering {
+    let vectors: Vec<TermVector> = vectors.iter().map(normalize).collect();
+    let k = k.min(vectors.len());
+    if k == 0 {
+        return Clustering::default();
+    }
+
+    let mut centroids = vec![vectors[0].clone()];
+    let mut closest: Vec<f64> = vectors.iter().map(|v| dot(v, &vectors[0])).collect();
+    while centroids.len() < k {
+        let (seed, _) = closest
+            .iter()
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap();
+        centroids.push(vectors[seed].clone());
+        for (similarity, vector) in closest.iter_mut().zip(&vectors) {
+            *similarity = similarity.max(dot(vector, &vectors[seed]));
+        }
+        closest[seed] = f64::INFINITY;
+    }
+
+    let mut assignments = vec![usize::MAX; vectors.len()];
+    for _ in 0..iterations.max(1) {
+        let mut changed = false;
+        for (assignment, vector) in assignments.iter_mut().zip(&vectors) {
+            let best = (0..centroids.len())
+                .max_by(|&a, &b| {
+                    dot(vector, &centroids[a])
+                        .total_cmp(&dot(vector, &centroids[b]))
+                        .then(b.cmp(&a))
+                })
+                .unwrap();
+            changed |= *assignment != best;
+            *assignment = best;
+        }
+        if !changed {
+            break;
+        }
+        for (label, centroid) in centroids.iter_mut().enumerate() {
+            let mut sum = TermVector::new();
+            let members = assignments
+                .iter()
+                .zip(&vectors)
+                .filter(|(a, _)| **a == label);
+            for (_, vector) in members {
+                for (term, weight) in vector {
+                    *sum.entry(term.clone()).or_default() += weight;
+                }
+            }
+            *centroid = normalize(&sum);
+        }
+    }
+    Clustering::from_assignments(doc_ids, &assignments)
+}
+
+/*
+Bottom-up clustering with average linkage: start with one cluster per
+document and merge the most similar pair until no pair reaches
+`min_similarity`.
+*/
+pub fn agglomerative(matrix: &SimilarityMatrix, min_similarity: f64) -> Clustering {
+    let mut clusters: Vec<Vec<usize>> = (0..matrix.doc_ids.len()).map(|i| vec![i]).collect();
+    let linkage = |a: &[usize], b: &[usize]| {
+        let total: f64 = a
+            .iter()
+            .flat_map(|&i| b.iter().map(move |&j| (i, j)))
+            .map(|(i, j)| matrix.get(i, j))
+            .sum();
+        total / (a.len() * b.len()) as f64
+    };
+
+    loop {
+        let mut best: Option<(usize, usize, f64)> = None;
+        for a in 0..clusters.len() {
+            for b in a + 1..clusters.len() {
+                let similarity = linkage(&clusters[a], &clusters[b]);
+                if similarity >= min_similarity && best.is_none_or(|(_, _, s)| similarity > s) {
+                    best = Some((a, b, similarity));
+                }
+            }
+        }
+        let Some((a, b, _)) = best else {
+            break;
+        };
+        let merged = clusters.remove(b);
+        clusters[a].extend(merged);
+    }
+
+    let mut assignments = vec![0; matrix.doc_ids.len()];
+    for (label, members) in clusters.iter().enumerate() {
+        for &member in members {
+            assignments[member] = label;
+        }
+    }
+    Clustering::from_assignments(&matrix.doc_ids, &assignments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vector(terms: &[(&str, f64)]) -> TermVector {
+        terms.iter().map(|&(t, w)| (t.to_string(), w)).collect()
+    }
+
+    fn corpus() -> (Vec<u64>, Vec<TermVector>) {
+        let vectors = vec![
+            vector(&[("rust", 2.0), ("borrow", 1.0)]),
+            vector(&[("pasta", 1.0), ("sauce", 2.0)]),
+            vector(&[("rust", 1.0), ("borrow", 1.5)]),
+            vector(&[("pasta", 2.0), ("sauce", 1.0), ("rust", 0.1)]),
+            vector(&[("garden", 1.0)]),
+        ];
+        (vec![10, 11, 12, 13, 14], vectors)
+    }
+
+    #[test]
+    fn test_similarity_matrix() {
+        let (ids, vectors) = corpus();
+        assert!((cosine(&vectors[0], &vectors[0]) - 1.0).abs() < 1e-9);
+        assert_eq!(cosine(&vectors[0], &TermVector::new()), 0.0);
+
+        let matrix = SimilarityMatrix::new(ids, &vectors);
+        assert!(matrix.similarity(10, 12).unwrap() > 0.8);
+        assert_eq!(matrix.similarity(10, 14), Some(0.0));
+        assert_eq!(matrix.similarity(10, 12), matrix.similarity(12, 10));
+        assert_eq!(matrix.similarity(10, 99), None);
+    }
+
+    #[test]
+    fn test_kmeans() {
+        let (ids, vectors) = corpus();
+        let clustering = kmeans(&ids, &vectors, 3, 10);
+        assert_eq!(
+            clustering.clusters,
+            vec![vec![10, 12], vec![11, 13], vec![14]]
+        );
+        assert_eq!(clustering.label(13), Some(1));
+        assert_eq!(clustering.label(99), None);
+        assert_eq!(kmeans(&ids, &vectors, 10, 10).clusters.len(), 5);
+        assert!(kmeans(&[], &[], 3, 10).clusters.is_empty());
+    }
+
+    #[test]
+    fn test_agglomerative() {
+        let (ids, vectors) = corpus();
+        let matrix = SimilarityMatrix::new(ids, &vectors);
+        let clustering = agglomerative(&matrix, 0.5);
+        assert_eq!(
+            clustering.clusters,
+            vec![vec![10, 12], vec![11, 13], vec![14]]
+        );
+        assert_eq!(agglomerative(&matrix, 1.1).clusters.len(), 5);
+        assert_eq!(agglomerative(&matrix, 0.0).clusters.len(), 1);
+    }
+}
@@ -0,0 +1,171 @@
+use std::ops::Range;
+
+use super::Document;
+use crate::errors::MSErrors;
+use crate::tokenizer::Tokenizer;
+
+// Metadata field of a chunk holding the id of the document it was cut from
+pub const PARENT_FIELD: &str = "parent_id";
+// Metadata field of a chunk holding its index within the parent
+pub const CHUNK_FIELD: &str = "chunk";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChunkUnit {
+    Tokens,    // Analyzed tokens, so stop words are not counted
+    Sentences, // Text ending with '.', '!' or '?'
+}
+
+/*
+Splits long documents into overlapping passages of `size` units, each
+sharing its first `overlap` units with the end of the previous one, so a
+match spanning a boundary is still found whole in one passage.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chunker {
+    pub unit: ChunkUnit,
+    pub size: usize,
+    pub overlap: usize,
+}
+
+impl Chunker {
+    pub fn tokens(size: usize, overlap: usize) -> Self {
+        Chunker {
+            unit: ChunkUnit::Tokens,
+            size,
+            overlap,
+        }
+    }
+
+    pub fn sentences(size: usize, overlap: usize) -> Self {
+        Chunker {
+            unit: ChunkUnit::Sentences,
+            size,
+            overlap,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), MSErrors> {
+        if self.size == 0 || self.overlap >= self.size {
+            return Err(MSErrors::IndexingError(format!(
+                "chunk overlap must be less than the size, got {} of {}",
+                self.overlap, self.size
+            )));
+        }
+        Ok(())
+    }
+
+    // Byte ranges of the passages of `text`, in order
+    pub fn passages(&self, tokenizer: &Tokenizer, text: &str) -> Vec<Range<usize>> {
+        let units: Vec<Range<usize>> = match self.unit {
+            ChunkUnit::Tokens => tokenizer
+                .token_stream(text)
+                .map(|token| token.offset.0..token.offset.1)
+                .collect(),
+            ChunkUnit::Sentences => sentences(text),
+        };
+        let step = self.size.saturating_sub(self.overlap).max(1);
+        let mut passages = Vec::new();
+        let mut first = 0;
+        while first < units.len() {
+            let last = (first + self.size).min(units.len()) - 1;
+            passages.push(units[first].start..units[last].end);
+            if last + 1 == units.len() {
+                break;
+            }
+            first += step;
+        }
+        passages
+    }
+
+    /*
+    The passages of a document's content as child documents with ids from
+    `first_id`. Children keep the parent's title and metadata, plus the
+    parent id and their index among its chunks. Fails if the ids would run
+    past u64::MAX.
+    */
+    pub fn chunk(
+        &self,
+        tokenizer: &Tokenizer,
+        document: &Document,
+        first_id: u64,
+    ) -> Result<Vec<Document>, MSErrors> {
+        self.passages(tokenizer, &document.content)
+            .into_iter()
+            .enumerate()
+            .map(|(i, range)| {
+                let id = first_id.checked_add(i as u64).ok_or_else(|| {
+                    MSErrors::IndexingError(format!(
+                        "no ids left for the chunks of document {}",
+                        document.id
+                    ))
+                })?;
+                let mut metadata = document.metadata.clone();
+                metadata.insert(PARENT_FIELD.to_string(), document.id.to_string());
+                metadata.insert(CHUNK_FIELD.to_string(), i.to_string());
+                Ok(Document {
+                    id,
+                    title: document.title.clone(),
+                    content: document.content[range].to_string(),
+                    metadata,
+                })
+            })
+            .collect()
+    }
+}
+
+// Trimmed sentences of `text`; the last one may lack final punctuation
+fn sentences(text: &str) -> Vec<Range<usize>> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((idx, ch)) = chars.next() {
+        let at_break = chars.peek().is_none_or(|&(_, next)| next.is_whitespace());
+        if matches!(ch, '.' | '!' | '?') && at_break {
+            sentences.push(start..idx + ch.len_utf8());
+            start = idx + ch.len_utf8();
+        }
+    }
+    sentences.push(start..text.len());
+    sentences
+        .into_iter()
+        .filter_map(|range| {
+            let sentence = &text[range.clone()];
+            let trimmed = sentence.trim();
+            let offset = range.start + sentence.len() - sentence.trim_start().len();
+            (!trimmed.is_empty()).then(|| offset..offset + trimmed.len())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::{AnalyzerPreset, Language};
+
+    #[test]
+    fn test_passages() {
+        let tokenizer = Tokenizer::with_preset(AnalyzerPreset::Simple, Language::English).unwrap();
+        let text = "one two three four five six seven";
+        let passages: Vec<&str> = Chunker::tokens(3, 1)
+            .passages(&tokenizer, text)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect();
+        assert_eq!(
+            passages,
+            ["one two three", "three four five", "five six seven"]
+        );
+
+        let text = "First one. Second?  Third, with 3.5 units! Last";
+        let passages: Vec<&str> = Chunker::sentences(2, 0)
+            .passages(&tokenizer, text)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect();
+        assert_eq!(
+            passages,
+            ["First one. Second?", "Third, with 3.5 units! Last"]
+        );
+        assert!(Chunker::tokens(2, 2).validate().is_err());
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use super::Document;
+use crate::errors::MSErrors;
+use crate::import::ImportStats;
+use crate::searcher::SearchEngine;
+
+// A column, by header name or 0-based position
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Name(String),
+    Index(usize),
+}
+
+impl Column {
+    pub fn name(name: &str) -> Self {
+        Column::Name(name.to_string())
+    }
+}
+
+// What to do with a row that cannot be turned into a document
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum RowErrorPolicy {
+    #[default]
+    Skip, // Count it in `ImportStats::skipped` and go on
+    Abort, // Stop and return the error
+}
+
+/*
+Maps the columns of a CSV or TSV file onto documents. Content columns are
+joined with a newline. Every other column becomes metadata, named by its
+header (or its position without one), unless `metadata` restricts it to a
+fixed list. Without an id column, ids are assigned after the engine's
+highest id.
+*/
+#[derive(Debug, Clone)]
+pub struct CsvMapping {
+    pub delimiter: char,
+    pub has_header: bool,
+    pub id: Option<Column>,
+    pub title: Option<Column>,
+    pub content: Vec<Column>,
+    pub metadata: Option<Vec<Column>>,
+    pub on_error: RowErrorPolicy,
+}
+
+impl Default for CsvMapping {
+    fn default() -> Self {
+        CsvMapping {
+            delimiter: ',',
+            has_header: true,
+            id: None,
+            title: Some(Column::name("title")),
+            content: vec![Column::name("content")],
+            metadata: None,
+            on_error: RowErrorPolicy::Skip,
+        }
+    }
+}
+
+impl CsvMapping {
+    // Tab-separated values with the default columns
+    pub fn tsv() -> Self {
+        CsvMapping {
+            delimiter: '\t',
+            ..CsvMapping::default()
+        }
+    }
+}
+
+/*
+Split one record into fields. Fields may be quoted with `"`, in which case
+they can hold delimiters, newlines and doubled quotes. Returns None while a
+quoted field is still open, so the caller can append the next line.
+*/
+fn parse_record(record: &str, delimiter: char) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = record.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ch if ch == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            ch => field.push(ch),
+        }
+    }
+    if quoted {
+        return None;
+    }
+    fields.push(field);
+    Some(fields)
+}
+
+// Resolves mapped columns to positions once the header is known
+struct Layout<'a> {
+    mapping: &'a CsvMapping,
+    header: Vec<String>,
+}
+
+impl Layout<'_> {
+    fn position(&self, column: &Column) -> Result<usize, MSErrors> {
+        match column {
+            Column::Index(index) => Ok(*index),
+            Column::Name(name) => self.header.iter().position(|h| h == name).ok_or_else(|| {
+                MSErrors::ParseError(format!("no column named \"{}\" in the header", name))
+            }),
+        }
+    }
+
+    fn column_name(&self, position: usize) -> String {
+        self.header
+            .get(position)
+            .cloned()
+            .unwrap_or_else(|| position.to_string())
+    }
+
+    fn document(&self, fields: &[String], next_id: u64) -> Result<Document, MSErrors> {
+        let field = |column: &Column| -> Result<&str, MSErrors> {
+            let position = self.position(column)?;
+            fields
+                .get(position)
+                .map(String::as_str)
+                .ok_or_else(|| MSErrors::ParseError(format!("row has no column {}", position + 1)))
+        };
+
+        let id = match &self.mapping.id {
+            Some(column) => {
+                let value = field(column)?.trim();
+                value
+                    .parse()
+                    .map_err(|_| MSErrors::ParseError(format!("invalid id \"{}\"", value)))?
+            }
+            None => next_id,
+        };
+        let title = match &self.mapping.title {
+            Some(column) => field(column)?.to_string(),
+            None => String::new(),
+        };
+        let content = self
+            .mapping
+            .content
+            .iter()
+            .map(field)
+            .collect::<Result<Vec<_>, _>>()?
+            .join("\n");
+        if title.trim().is_empty() && content.trim().is_empty() {
+            return Err(MSErrors::ParseError(
+                "row has neither title nor content".to_string(),
+            ));
+        }
+
+        let metadata = match &self.mapping.metadata {
+            Some(columns) => columns
+                .iter()
+                .map(|column| Ok((self.column_name(self.position(column)?), field(column)?)))
+                .collect::<Result<Vec<_>, MSErrors>>()?,
+            None => {
+                let mapped = self
+                    .mapping
+                    .id
+                    .iter()
+                    .chain(&self.mapping.title)
+                    .chain(&self.mapping.content)
+                    .map(|column| self.position(column))
+                    .collect::<Result<Vec<_>, _>>()?;
+                fields
+                    .iter()
+                    .enumerate()
+                    .filter(|(position, _)| !mapped.contains(position))
+                    .map(|(position, value)| (self.column_name(position), value.as_str()))
+                    .collect()
+            }
+        };
+        let metadata: HashMap<String, String> = metadata
+            .into_iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(name, value)| (name, value.to_string()))
+            .collect();
+
+        Ok(Document {
+            id,
+            title,
+            content,
+            metadata,
+        })
+    }
+}
+
+/*
+Stream the rows of a CSV or TSV file into `engine`. A row that cannot be
+mapped, or that the engine rejects, is handled by `mapping.on_error`;
+errors reading the input always stop the load.
+*/
+pub fn load_csv(
+    reader: impl BufRead,
+    engine: &mut SearchEngine,
+    mapping: &CsvMapping,
+) -> Result<ImportStats, MSErrors> {
+    let mut stats = ImportStats::default();
+    let mut layout = Layout {
+        mapping,
+        header: Vec::new(),
+    };
+    let mut next_id = engine.next_document_id();
+    let mut record = String::new();
+    let mut first_line = 0;
+    let mut header_pending = mapping.has_header;
+
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| MSErrors::IndexingError(e.to_string()))?;
+        if record.is_empty() {
+            first_line = line_number + 1;
+        } else {
+            record.push('\n');
+        }
+        record.push_str(line.trim_end_matches('\r'));
+        let Some(fields) = parse_record(&record, mapping.delimiter) else {
+            continue;
+        };
+        record.clear();
+        if fields.len() == 1 && fields[0].trim().is_empty() {
+            continue;
+        }
+        if header_pending {
+            layout.header = fields.into_iter().map(|h| h.trim().to_string()).collect();
+            header_pending = false;
+            continue;
+        }
+
+        let added = layout
+            .document(&fields, next_id)
+            .and_then(|document| engine.add_document(document));
+        match added {
+            Ok(()) => {
+                if mapping.id.is_none() {
+                    next_id += 1;
+                }
+                stats.imported += 1;
+            }
+            Err(_) if mapping.on_error == RowErrorPolicy::Skip => stats.skipped += 1,
+            Err(e) => return Err(MSErrors::ParseError(format!("line {}: {}", first_line, e))),
+        }
+    }
+    if !record.is_empty() {
+        let error = MSErrors::ParseError(format!("line {}: unterminated quote", first_line));
+        match mapping.on_error {
+            RowErrorPolicy::Skip => stats.skipped += 1,
+            RowErrorPolicy::Abort => return Err(error),
+        }
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::IndexSettings;
+
+    #[test]
+    fn test_parse_record() {
+        assert_eq!(
+            parse_record(r#"a,"b, ""c""",,d"#, ','),
+            Some(vec![
+                "a".to_string(),
+                "b, \"c\"".to_string(),
+                String::new(),
+                "d".to_string()
+            ])
+        );
+        assert_eq!(parse_record("\"open\nfield", ','), None);
+        assert_eq!(parse_record("x\ty", '\t').unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_load_csv() {
+        let input = "sku,title,content,price\n\
+                     7,Mug,\"Blue mug,\nholds 300ml\",12\n\
+                     8,,,5\n\
+                     x,Lamp,Desk lamp,20\n\
+                     9,Chair,Wooden chair,\n";
+        let mapping = CsvMapping {
+            id: Some(Column::name("sku")),
+            ..CsvMapping::default()
+        };
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        let stats = load_csv(input.as_bytes(), &mut engine, &mapping).unwrap();
+        assert_eq!(
+            stats,
+            ImportStats {
+                imported: 2,
+                skipped: 2
+            }
+        );
+        let mug = engine.get_document(7).unwrap();
+        assert_eq!(mug.content, "Blue mug,\nholds 300ml");
+        assert_eq!(mug.metadata.get("price").unwrap(), "12");
+        assert!(engine.get_document(9).unwrap().metadata.is_empty());
+
+        let abort = CsvMapping {
+            on_error: RowErrorPolicy::Abort,
+            ..mapping
+        };
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        let error = load_csv(input.as_bytes(), &mut engine, &abort).unwrap_err();
+        assert!(error.to_string().contains("line 4"));
+    }
+
+    #[test]
+    fn test_load_tsv_by_index() {
+        let input = "first\tlorem ipsum\ten\nsecond\tdolor sit\tde\n";
+        let mapping = CsvMapping {
+            has_header: false,
+            title: Some(Column::Index(0)),
+            content: vec![Column::Index(1)],
+            metadata: Some(vec![Column::Index(2)]),
+            ..CsvMapping::tsv()
+        };
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        let stats = load_csv(input.as_bytes(), &mut engine, &mapping).unwrap();
+        assert_eq!(stats.imported, 2);
+        let second = engine.get_document(1).unwrap();
+        assert_eq!(second.title, "second");
+        assert_eq!(second.metadata.get("2").unwrap(), "de");
+    }
+}
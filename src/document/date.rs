@@ -0,0 +1,150 @@
+// Seconds since the Unix epoch for an RFC 3339 date-time such as
+// 2024-05-01T12:00:00.5+02:00. A bare date is read as midnight UTC, and a
+// time without an offset is read as UTC.
+pub fn parse_rfc3339(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let bytes = text.as_bytes();
+    // Fields are sliced by byte position, which needs single-byte characters
+    if !text.is_ascii() || bytes.len() < 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return None;
+    }
+    let year: i64 = digits(&text[0..4])?;
+    let month: i64 = digits(&text[5..7])?;
+    let day: i64 = digits(&text[8..10])?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    let date = days_from_civil(year, month, day) as f64 * 86_400.0;
+    if bytes.len() == 10 {
+        return Some(date);
+    }
+
+    // Time of day: HH:MM:SS with an optional fraction
+    if !matches!(bytes[10], b'T' | b't' | b' ') || bytes.len() < 19 {
+        return None;
+    }
+    if bytes[13] != b':' || bytes[16] != b':' {
+        return None;
+    }
+    let hour: i64 = digits(&text[11..13])?;
+    let minute: i64 = digits(&text[14..16])?;
+    let second: i64 = digits(&text[17..19])?;
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    let mut rest = &text[19..];
+    let mut fraction = 0.0;
+    if let Some(after_dot) = rest.strip_prefix('.') {
+        let len = after_dot
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(after_dot.len());
+        if len == 0 {
+            return None;
+        }
+        fraction = format!("0.{}", &after_dot[..len]).parse().ok()?;
+        rest = &after_dot[len..];
+    }
+
+    // Offset from UTC: Z, +HH:MM or -HH:MM
+    let offset = match rest {
+        "" | "Z" | "z" => 0,
+        _ => {
+            let sign = match rest.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            if rest.len() != 6 || rest.as_bytes()[3] != b':' {
+                return None;
+            }
+            let hours: i64 = digits(&rest[1..3])?;
+            let minutes: i64 = digits(&rest[4..6])?;
+            sign * (hours * 3600 + minutes * 60)
+        }
+    };
+    Some(date + (hour * 3600 + minute * 60 + second - offset) as f64 + fraction)
+}
+
+// The UTC calendar date of a timestamp, as YYYY-MM-DD
+pub fn format_date(secs: i64) -> String {
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn digits(text: &str) -> Option<i64> {
+    if !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// Inverse of days_from_civil
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rfc3339() {
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z"), Some(0.0));
+        assert_eq!(parse_rfc3339("2024-05-01T12:00:00Z"), Some(1714564800.0));
+        assert_eq!(
+            parse_rfc3339("2024-05-01T14:00:00+02:00"),
+            Some(1714564800.0)
+        );
+        assert_eq!(parse_rfc3339("2024-05-01 12:00:00.5"), Some(1714564800.5));
+        assert_eq!(parse_rfc3339("2024-02-29"), Some(1709164800.0));
+        assert_eq!(parse_rfc3339("1969-12-31T23:59:59Z"), Some(-1.0));
+
+        assert_eq!(parse_rfc3339("2023-02-29"), None);
+        assert_eq!(parse_rfc3339("2024-13-01"), None);
+        assert_eq!(parse_rfc3339("2024-05-01T12:00"), None);
+        assert_eq!(parse_rfc3339("2024-05-01T12:00:00+0200"), None);
+        assert_eq!(parse_rfc3339("yesterday"), None);
+        assert_eq!(parse_rfc3339("2024-05-0é"), None);
+        assert_eq!(parse_rfc3339("2024-05-01T1é:00:00Z"), None);
+        assert_eq!(parse_rfc3339("2024-05-01T12:00:00+0é:00"), None);
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(1709164800 + 86_399), "2024-02-29");
+        assert_eq!(format_date(-1), "1969-12-31");
+        for date in ["2000-02-29", "2023-12-31", "2100-03-01"] {
+            assert_eq!(format_date(parse_rfc3339(date).unwrap() as i64), date);
+        }
+    }
+}
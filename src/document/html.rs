@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+
+use super::offsets::MappedText;
+use super::{Document, DocumentParser, MappedDocument};
+use crate::errors::MSErrors;
+
+// Elements that start a new line of text
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "td",
+    "th",
+    "tr",
+    "ul",
+];
+
+// Elements whose contents are never text
+const RAW_ELEMENTS: &[&str] = &["script", "style", "noscript", "template"];
+
+/*
+Parses an HTML page into a Document. Tags, comments, scripts and styles
+are stripped and entities decoded; block elements become line breaks and
+other whitespace is collapsed. The <title> becomes the document title and
+<meta name|property=... content=...> tags become metadata. Content is the
+plain text itself, so match offsets line up with what is displayed;
+`parse_mapped` maps them back to the page.
+Documents are returned with id 0, callers assign ids.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct HtmlDocumentParser;
+
+impl HtmlDocumentParser {
+    pub fn new() -> Self {
+        HtmlDocumentParser
+    }
+}
+
+// Name, closing flag and attributes of the tag between `<` and `>`
+fn parse_tag(tag: &str) -> (String, bool, Vec<(String, String)>) {
+    let (closing, tag) = match tag.strip_prefix('/') {
+        Some(rest) => (true, rest),
+        None => (false, tag),
+    };
+    let tag = tag.trim_end_matches('/');
+    let name_end = tag
+        .find(|c: char| c.is_whitespace() || c == '/')
+        .unwrap_or(tag.len());
+    let name = tag[..name_end].to_ascii_lowercase();
+
+    let mut attributes = Vec::new();
+    let mut rest = tag[name_end..].trim_start();
+    while !rest.is_empty() {
+        let key_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let key = rest[..key_end].to_ascii_lowercase();
+        rest = rest[key_end..].trim_start();
+        let mut value = "";
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (quoted, len) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let end = after[1..].find(quote).map_or(after.len(), |i| i + 1);
+                    (&after[1..end], (end + 1).min(after.len()))
+                }
+                _ => {
+                    let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                    (&after[..end], end)
+                }
+            };
+            value = quoted;
+            rest = &after[len..];
+        }
+        if !key.is_empty() {
+            attributes.push((key, decode_entities(value)));
+        }
+        rest = rest.trim_start_matches(['/', ' ', '\t', '\n', '\r']);
+    }
+    (name, closing, attributes)
+}
+
+// Replace character references such as &amp; and &#8217;
+fn decode_entities(text: &str) -> String {
+    let mut decoded = MappedText::default();
+    push_decoded(&mut decoded, text, 0);
+    decoded.text
+}
+
+// Append `text`, found in the source at byte `start`, with entities decoded
+fn push_decoded(decoded: &mut MappedText, text: &str, start: usize) {
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        let at = start + text.len() - rest.len();
+        decoded.push_str(&rest[..amp], at);
+        let at = at + amp;
+        rest = &rest[amp..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&len| len <= 10)
+            .and_then(|len| Some((decode_entity(&rest[1..=len])?, len + 2)));
+        match entity {
+            Some((ch, len)) => {
+                decoded.push(ch, (at, at + len));
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&', (at, at + 1));
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest, start + text.len() - rest.len());
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    let code = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        _ => {
+            let number = name.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            return char::from_u32(code);
+        }
+    };
+    Some(code)
+}
+
+impl DocumentParser for HtmlDocumentParser {
+    fn parse(&self, input: &str) -> Result<Document, MSErrors> {
+        self.parse_mapped(input).map(|mapped| mapped.document)
+    }
+
+    fn extract_text(&self, document: &Document) -> String {
+        format!("{}\n{}", document.title, document.content)
+    }
+
+    fn parse_mapped(&self, input: &str) -> Result<MappedDocument, MSErrors> {
+        let mut content = MappedText::default();
+        let mut title: Option<MappedText> = None;
+        let mut in_title = false;
+        let mut metadata = HashMap::new();
+
+        // Source offset of what is left to parse
+        let at = |rest: &str| input.len() - rest.len();
+        let mut rest = input;
+        while let Some(open) = rest.find('<') {
+            let text = match &mut title {
+                Some(title) if in_title => title,
+                _ => &mut content,
+            };
+            push_decoded(text, &rest[..open], at(rest));
+            rest = &rest[open..];
+
+            if let Some(comment) = rest.strip_prefix("<!--") {
+                rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+                continue;
+            }
+            let starts_tag = rest[1..]
+                .starts_with(|c: char| c.is_ascii_alphabetic() || matches!(c, '/' | '!' | '?'));
+            let close = rest.find('>').filter(|_| starts_tag);
+            let Some(close) = close else {
+                // A '<' that does not open a tag is text
+                content.push('<', (at(rest), at(rest) + 1));
+                rest = &rest[1..];
+                continue;
+            };
+            let (name, closing, attributes) = parse_tag(&rest[1..close]);
+            let tag_start = at(rest);
+            rest = &rest[close + 1..];
+
+            if RAW_ELEMENTS.contains(&name.as_str()) && !closing {
+                let end_tag = format!("</{}", name);
+                let end = rest.to_ascii_lowercase().find(&end_tag);
+                rest = end.map_or("", |end| {
+                    let after = &rest[end..];
+                    after.find('>').map_or("", |i| &after[i + 1..])
+                });
+                continue;
+            }
+            match name.as_str() {
+                "title" => {
+                    in_title = !closing;
+                    if in_title {
+                        title.get_or_insert_with(MappedText::default);
+                    }
+                }
+                "meta" => {
+                    let attribute = |key: &str| {
+                        attributes
+                            .iter()
+                            .find(|(k, _)| k == key)
+                            .map(|(_, value)| value.as_str())
+                    };
+                    let key = attribute("name").or_else(|| attribute("property"));
+                    if let (Some(key), Some(value)) = (key, attribute("content")) {
+                        metadata.insert(key.to_ascii_lowercase(), value.trim().to_string());
+                    }
+                }
+                name if BLOCK_ELEMENTS.contains(&name) => {
+                    content.push('\n', (tag_start, tag_start))
+                }
+                _ => {}
+            }
+        }
+        push_decoded(&mut content, rest, at(rest));
+
+        let title = title.map(|title| title.normalize()).unwrap_or_default();
+        let content = content.normalize();
+        if title.is_empty() && content.is_empty() {
+            return Err(MSErrors::ParseError("HTML page has no text".to_string()));
+        }
+        Ok(MappedDocument {
+            document: Document {
+                id: 0,
+                title: title.text,
+                content: content.text,
+                metadata,
+            },
+            title: title.map,
+            content: content.map,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_html() {
+        let html = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <title>Rust &amp; Search</title>
+  <meta name="Author" content="Ada">
+  <meta property="og:type" content='article' />
+  <meta charset="utf-8">
+  <style>body { color: red; }</style>
+  <script type="text/javascript">if (a < b) { alert("<p>"); }</script>
+</head>
+<body>
+  <!-- navigation <p>hidden</p> -->
+  <h1>Fast   search</h1>
+  <p>Tokens &lt;in&gt; <b>bold</b> and&nbsp;text &#8212; done&#x21;</p>
+  <ul><li>one</li><li>two</li></ul>
+  a < b
+</body>
+</html>"#;
+        let doc = HtmlDocumentParser::new().parse(html).unwrap();
+        assert_eq!(doc.title, "Rust & Search");
+        assert_eq!(
+            doc.content,
+            "Fast search\nTokens <in> bold and text \u{2014} done!\none\ntwo\na < b"
+        );
+        assert_eq!(
+            doc.metadata,
+            HashMap::from([
+                ("author".to_string(), "Ada".to_string()),
+                ("og:type".to_string(), "article".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_empty_html() {
+        let parser = HtmlDocumentParser::new();
+        assert!(parser.parse("<html><script>x()</script></html>").is_err());
+        assert_eq!(
+            parser.parse("plain &amp text").unwrap().content,
+            "plain &amp text"
+        );
+    }
+}
@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+use super::offsets::MappedText;
+use super::{Document, DocumentParser, MappedDocument};
+use crate::errors::MSErrors;
+
+/*
+Parses a JSON object into a Document. Fields are named by key, or by a
+dotted path such as "author.name" for nested objects. Content fields are
+joined with a newline. Every other scalar field becomes metadata, unless
+`metadata_fields` restricts it to a fixed list. Without an id field the
+document is returned with id 0 and callers assign ids.
+*/
+#[derive(Debug, Clone)]
+pub struct JsonDocumentParser {
+    pub id_field: Option<String>, // Non-negative integer field to use as document id
+    pub title_field: Option<String>,
+    pub content_fields: Vec<String>,
+    pub metadata_fields: Option<Vec<String>>,
+}
+
+impl Default for JsonDocumentParser {
+    fn default() -> Self {
+        JsonDocumentParser {
+            id_field: None,
+            title_field: Some("title".to_string()),
+            content_fields: vec!["content".to_string()],
+            metadata_fields: None,
+        }
+    }
+}
+
+// Render a value as text; arrays are joined with commas, objects dropped
+fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Array(values) => {
+            let parts: Vec<String> = values.iter().filter_map(value_text).collect();
+            (!parts.is_empty()).then(|| parts.join(","))
+        }
+        Value::Null | Value::Object(_) => None,
+    }
+}
+
+fn lookup<'a>(object: &'a Map<String, Value>, path: &str) -> Option<&'a Value> {
+    let mut parts = path.split('.');
+    let mut value = object.get(parts.next()?)?;
+    for part in parts {
+        value = value.as_object()?.get(part)?;
+    }
+    Some(value)
+}
+
+/*
+Byte ranges of values in the JSON text, which serde_json does not keep.
+Only run on input serde_json has parsed, so the text is well formed.
+*/
+type Range = (usize, usize);
+
+fn skip_whitespace(input: &str, at: usize) -> usize {
+    at + input[at..].len() - input[at..].trim_start().len()
+}
+
+// End of the value starting at `start`
+fn value_end(input: &str, start: usize) -> usize {
+    let bytes = input.as_bytes();
+    let mut at = start;
+    let mut depth = 0;
+    loop {
+        match bytes[at] {
+            b'"' => at = string_end(input, at),
+            b'{' | b'[' => {
+                depth += 1;
+                at += 1;
+            }
+            b'}' | b']' => {
+                depth -= 1;
+                at += 1;
+            }
+            _ if depth == 0 => {
+                // A number, true, false or null
+                let len = input[at..]
+                    .find([',', '}', ']'])
+                    .unwrap_or(input.len() - at);
+                return at + input[at..at + len].trim_end().len();
+            }
+            _ => at += 1,
+        }
+        if depth == 0 {
+            return at;
+        }
+    }
+}
+
+// End of the string literal starting at `start`, past its closing quote
+fn string_end(input: &str, start: usize) -> usize {
+    let bytes = input.as_bytes();
+    let mut at = start + 1;
+    while bytes[at] != b'"' {
+        at += if bytes[at] == b'\\' { 2 } else { 1 };
+    }
+    at + 1
+}
+
+// The values of the array or object starting at `start`, with the keys of an object's
+fn members(input: &str, start: usize) -> Vec<(Option<Range>, Range)> {
+    let is_object = input.as_bytes()[start] == b'{';
+    let mut members = Vec::new();
+    let mut at = skip_whitespace(input, start + 1);
+    while !matches!(input.as_bytes()[at], b'}' | b']') {
+        let mut key = None;
+        if is_object {
+            let key_end = string_end(input, at);
+            key = Some((at, key_end));
+            at = skip_whitespace(input, key_end);
+            at = skip_whitespace(input, at + 1); // ':'
+        }
+        let end = value_end(input, at);
+        members.push((key, (at, end)));
+        at = skip_whitespace(input, end);
+        if input.as_bytes()[at] == b',' {
+            at = skip_whitespace(input, at + 1);
+        }
+    }
+    members
+}
+
+// Range of the value at a dotted `path` in the object at `start`; the last of duplicate keys wins, as in serde_json
+fn value_range(input: &str, start: usize, path: &str) -> Option<Range> {
+    let mut range = (start, value_end(input, start));
+    for part in path.split('.') {
+        if input.as_bytes()[range.0] != b'{' {
+            return None;
+        }
+        range = members(input, range.0)
+            .into_iter()
+            .rev()
+            .find(|(key, _)| {
+                key.and_then(|(start, end)| serde_json::from_str::<String>(&input[start..end]).ok())
+                    .is_some_and(|key| key == part)
+            })?
+            .1;
+    }
+    Some(range)
+}
+
+// The decoded char of the escape sequence at the start of `escape`, and its length
+fn unescape(escape: &str) -> (char, usize) {
+    let hex = |from: usize| {
+        escape
+            .get(from..from + 4)
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+    };
+    match escape.as_bytes().get(1) {
+        Some(b'u') => {
+            let high = hex(2).unwrap_or(0xFFFD);
+            let low =
+                hex(8).filter(|_| escape.get(6..).is_some_and(|rest| rest.starts_with("\\u")));
+            match low {
+                Some(low) if (0xD800..0xDC00).contains(&high) => {
+                    let code = 0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00));
+                    (char::from_u32(code).unwrap_or('\u{FFFD}'), 12)
+                }
+                _ => (char::from_u32(high).unwrap_or('\u{FFFD}'), 6),
+            }
+        }
+        Some(b'n') => ('\n', 2),
+        Some(b't') => ('\t', 2),
+        Some(b'r') => ('\r', 2),
+        Some(b'b') => ('\u{8}', 2),
+        Some(b'f') => ('\u{c}', 2),
+        Some(&other) => (other as char, 2),
+        None => ('\\', 1),
+    }
+}
+
+/*
+`value_text` of the value at `range`, each char mapped to the source it
+was decoded from: a whole escape sequence for escaped chars, the whole
+literal for numbers serde_json renders differently. Commas joining array
+elements are mapped to the end of the element before them.
+*/
+fn mapped_text(input: &str, range: Range, value: &Value) -> Option<MappedText> {
+    let (start, end) = range;
+    let mut text = MappedText::default();
+    match value {
+        Value::String(_) => {
+            let mut at = start + 1;
+            while at < end - 1 {
+                let (ch, len) = match input[at..].chars().next()? {
+                    '\\' => unescape(&input[at..end - 1]),
+                    ch => (ch, ch.len_utf8()),
+                };
+                text.push(ch, (at, at + len));
+                at += len;
+            }
+        }
+        Value::Array(values) => {
+            let elements = members(input, start);
+            for ((_, range), value) in elements.into_iter().zip(values) {
+                let Some(element) = mapped_text(input, range, value) else {
+                    continue;
+                };
+                if !text.is_empty() {
+                    text.push(',', (range.0, range.0));
+                }
+                text.append(&element);
+            }
+            if text.is_empty() {
+                return None;
+            }
+        }
+        _ => {
+            let rendered = value_text(value)?;
+            if rendered == input[start..end] {
+                text.push_str(&rendered, start);
+            } else {
+                for ch in rendered.chars() {
+                    text.push(ch, range);
+                }
+            }
+        }
+    }
+    Some(text)
+}
+
+impl DocumentParser for JsonDocumentParser {
+    fn parse(&self, input: &str) -> Result<Document, MSErrors> {
+        self.parse_mapped(input).map(|mapped| mapped.document)
+    }
+
+    fn extract_text(&self, document: &Document) -> String {
+        format!("{}\n{}", document.title, document.content)
+    }
+
+    fn parse_mapped(&self, input: &str) -> Result<MappedDocument, MSErrors> {
+        let value: Value = serde_json::from_str(input)?;
+        let Value::Object(object) = value else {
+            return Err(MSErrors::ParseError("expected a JSON object".to_string()));
+        };
+        let text = |path: &str| lookup(&object, path).and_then(value_text);
+        let start = skip_whitespace(input, 0);
+        let mapped = |path: &str| {
+            let range = value_range(input, start, path)?;
+            Some((range, mapped_text(input, range, lookup(&object, path)?)?))
+        };
+
+        let title = self
+            .title_field
+            .as_deref()
+            .and_then(mapped)
+            .map(|(_, title)| title)
+            .unwrap_or_default();
+        // Fields are joined by a line break mapped to the end of the field before
+        let mut content = MappedText::default();
+        let mut previous_end = None;
+        for ((_, end), field) in self.content_fields.iter().filter_map(|path| mapped(path)) {
+            if let Some(previous_end) = previous_end {
+                content.push('\n', (previous_end, previous_end));
+            }
+            content.append(&field);
+            previous_end = Some(end);
+        }
+        if title.is_empty() && content.is_empty() {
+            return Err(MSErrors::ParseError(
+                "document has neither title nor content".to_string(),
+            ));
+        }
+
+        let id = match &self.id_field {
+            Some(path) => lookup(&object, path)
+                .and_then(|id| id.as_u64().or_else(|| id.as_str()?.parse().ok()))
+                .ok_or_else(|| {
+                    MSErrors::ParseError(format!("document without an integer \"{}\" field", path))
+                })?,
+            None => 0,
+        };
+
+        let mapped = |name: &String| {
+            Some(name) == self.id_field.as_ref()
+                || Some(name) == self.title_field.as_ref()
+                || self.content_fields.contains(name)
+        };
+        let metadata: HashMap<String, String> = match &self.metadata_fields {
+            Some(fields) => fields
+                .iter()
+                .filter_map(|path| Some((path.clone(), text(path)?)))
+                .collect(),
+            None => object
+                .iter()
+                .filter(|(name, _)| !mapped(name))
+                .filter_map(|(name, value)| Some((name.clone(), value_text(value)?)))
+                .collect(),
+        };
+
+        Ok(MappedDocument {
+            document: Document {
+                id,
+                title: title.text,
+                content: content.text,
+                metadata,
+            },
+            title: title.map,
+            content: content.map,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::offsets::OffsetMap;
+
+    #[test]
+    fn test_parse_json() {
+        let parser = JsonDocumentParser {
+            id_field: Some("id".to_string()),
+            content_fields: vec!["body".to_string(), "author.bio".to_string()],
+            ..JsonDocumentParser::default()
+        };
+        let doc = parser
+            .parse(
+                r#"{"id": 7, "title": "Rust", "body": "Ownership", "author": {"bio": "Writer"},
+                    "tags": ["lang", "systems"], "stars": 4.5, "draft": null}"#,
+            )
+            .unwrap();
+        assert_eq!(doc.id, 7);
+        assert_eq!(doc.title, "Rust");
+        assert_eq!(doc.content, "Ownership\nWriter");
+        assert_eq!(
+            doc.metadata,
+            HashMap::from([
+                ("tags".to_string(), "lang,systems".to_string()),
+                ("stars".to_string(), "4.5".to_string()),
+            ])
+        );
+
+        assert!(parser.parse(r#"{"title": "No id"}"#).is_err());
+        assert!(parser.parse(r#"{"id": 1, "tags": []}"#).is_err());
+        assert!(parser.parse("[1, 2]").is_err());
+        assert!(parser.parse("{").is_err());
+    }
+
+    #[test]
+    fn test_parse_mapped() {
+        let parser = JsonDocumentParser {
+            content_fields: vec!["body".to_string(), "tags".to_string()],
+            ..JsonDocumentParser::default()
+        };
+        let input = r#" {"title": "Caf\u00e9", "body": "Caf\u00e9 \"bar\"\n\ud83e\udd80 ok",
+                        "body": "say \"Caf\u00e9\"", "tags": ["a", 1e2, null, true]} "#;
+        let mapped = parser.parse_mapped(input).unwrap();
+        assert_eq!(mapped.document.title, "Café");
+        assert_eq!(mapped.document.content, "say \"Café\"\na,100.0,true");
+        let source = |map: &OffsetMap, text: &str, word: &str| {
+            let start = text.find(word).unwrap();
+            let (start, end) = map.source_range((start, start + word.len())).unwrap();
+            input[start..end].to_string()
+        };
+        let content = &mapped.document.content;
+        // The last of duplicate keys, as parsed, rather than the first occurrence
+        assert_eq!(source(&mapped.content, content, "Café"), "Caf\\u00e9");
+        assert_eq!(source(&mapped.content, content, "\"C"), "\\\"C");
+        assert_eq!(source(&mapped.content, content, "100.0"), "1e2");
+        assert_eq!(source(&mapped.content, content, "true"), "true");
+        assert_eq!(
+            source(&mapped.title, &mapped.document.title, "é"),
+            "\\u00e9"
+        );
+
+        let input = r#"{"content": "\ud83e\udd80 crab"}"#;
+        let mapped = JsonDocumentParser::default().parse_mapped(input).unwrap();
+        let (start, end) = mapped.content.source_range((0, 4)).unwrap();
+        assert_eq!(&input[start..end], r"\ud83e\udd80");
+    }
+
+    #[test]
+    fn test_metadata_fields() {
+        let parser = JsonDocumentParser {
+            metadata_fields: Some(vec!["author.name".to_string(), "missing".to_string()]),
+            ..JsonDocumentParser::default()
+        };
+        let doc = parser
+            .parse(r#"{"content": "Notes", "author": {"name": "Ada"}, "lang": "en"}"#)
+            .unwrap();
+        assert_eq!(doc.id, 0);
+        assert_eq!(
+            doc.metadata,
+            HashMap::from([("author.name".to_string(), "Ada".to_string())])
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use super::offsets::{OffsetMap, offset_in};
+use super::{Document, DocumentParser, MappedDocument};
+use crate::errors::MSErrors;
+
+// Where to look for a timestamp at the start of a log line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimestampPattern {
+    Iso8601,   // 2024-05-01T12:00:00Z or 2024-05-01 12:00:00.123
+    Bracketed, // [01/May/2024:12:00:00 +0000]
+    Epoch,     // 1714564800 or 1714564800123
+}
+
+impl TimestampPattern {
+    // Return the byte length of the timestamp at the start of `line`, if any
+    fn match_len(&self, line: &str) -> Option<usize> {
+        let bytes = line.as_bytes();
+        match self {
+            TimestampPattern::Iso8601 => {
+                let is_date = bytes.len() >= 10
+                    && bytes[..10].iter().enumerate().all(|(i, b)| match i {
+                        4 | 7 => *b == b'-',
+                        _ => b.is_ascii_digit(),
+                    });
+                if !is_date {
+                    return None;
+                }
+                // Optional time part, separated by 'T' or a single space
+                let has_time = bytes.len() >= 16
+                    && (bytes[10] == b'T' || bytes[10] == b' ')
+                    && bytes[11..13].iter().all(u8::is_ascii_digit)
+                    && bytes[13] == b':';
+                if has_time {
+                    Some(11 + token_len(&line[11..]))
+                } else {
+                    Some(token_len(line))
+                }
+            }
+            TimestampPattern::Bracketed => {
+                // d: digit, a: letter, s: sign, anything else as is
+                const SHAPE: &[u8] = b"[dd/aaa/dddd:dd:dd:dd sdddd]";
+                let is_date = bytes.len() >= SHAPE.len()
+                    && bytes.iter().zip(SHAPE).all(|(b, shape)| match shape {
+                        b'd' => b.is_ascii_digit(),
+                        b'a' => b.is_ascii_alphabetic(),
+                        b's' => *b == b'+' || *b == b'-',
+                        _ => b == shape,
+                    });
+                is_date.then_some(SHAPE.len())
+            }
+            TimestampPattern::Epoch => {
+                let len = token_len(line);
+                let digits = bytes[..len].iter().all(u8::is_ascii_digit);
+                (digits && (len == 10 || len == 13)).then_some(len)
+            }
+        }
+    }
+}
+
+// Length of the leading run of non-whitespace characters
+fn token_len(text: &str) -> usize {
+    text.find(char::is_whitespace).unwrap_or(text.len())
+}
+
+/*
+Parses a single log line into a Document.
+The first matching timestamp pattern is stripped from the line and stored in
+the "timestamp" metadata field; the rest of the line becomes the content.
+Documents are returned with id 0, callers assign ids as lines are ingested.
+*/
+pub struct LogLineParser {
+    patterns: Vec<TimestampPattern>,
+    source: Option<String>,
+}
+
+impl LogLineParser {
+    pub fn new(patterns: Vec<TimestampPattern>) -> Self {
+        LogLineParser {
+            patterns,
+            source: None,
+        }
+    }
+
+    // Record the originating file or stream in the "source" metadata field
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+}
+
+impl Default for LogLineParser {
+    fn default() -> Self {
+        LogLineParser::new(vec![
+            TimestampPattern::Iso8601,
+            TimestampPattern::Bracketed,
+            TimestampPattern::Epoch,
+        ])
+    }
+}
+
+impl DocumentParser for LogLineParser {
+    fn parse(&self, input: &str) -> Result<Document, MSErrors> {
+        self.parse_mapped(input).map(|mapped| mapped.document)
+    }
+
+    fn extract_text(&self, document: &Document) -> String {
+        document.content.clone()
+    }
+
+    // The message is copied out of the line unchanged
+    fn parse_mapped(&self, input: &str) -> Result<MappedDocument, MSErrors> {
+        let line = input.trim_end_matches(['\r', '\n']);
+        if line.trim().is_empty() {
+            return Err(MSErrors::ParseError("empty log line".to_string()));
+        }
+
+        let mut metadata = HashMap::new();
+        let mut message = line;
+        if let Some(len) = self.patterns.iter().find_map(|p| p.match_len(line)) {
+            metadata.insert("timestamp".to_string(), line[..len].to_string());
+            message = line[len..].trim_start();
+        }
+        if let Some(source) = &self.source {
+            metadata.insert("source".to_string(), source.clone());
+        }
+
+        Ok(MappedDocument {
+            document: Document {
+                id: 0,
+                title: String::new(),
+                content: message.to_string(),
+                metadata,
+            },
+            title: OffsetMap::default(),
+            content: OffsetMap::shifted(message, offset_in(input, message)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timestamp(document: &Document) -> Option<&str> {
+        document.metadata.get("timestamp").map(String::as_str)
+    }
+
+    #[test]
+    fn test_timestamp_patterns() {
+        let parser = LogLineParser::default();
+
+        let doc = parser.parse("2024-05-01T12:00:00Z disk full\n").unwrap();
+        assert_eq!(timestamp(&doc), Some("2024-05-01T12:00:00Z"));
+        assert_eq!(doc.content, "disk full");
+
+        let doc = parser.parse("2024-05-01 12:00:00.5 ERROR boom").unwrap();
+        assert_eq!(timestamp(&doc), Some("2024-05-01 12:00:00.5"));
+        assert_eq!(doc.content, "ERROR boom");
+
+        let doc = parser.parse("[01/May/2024:12:00:00 +0000] GET /").unwrap();
+        assert_eq!(timestamp(&doc), Some("[01/May/2024:12:00:00 +0000]"));
+        assert_eq!(doc.content, "GET /");
+
+        // A bracketed log level is part of the message
+        let doc = parser.parse("[ERROR] disk full").unwrap();
+        assert_eq!(timestamp(&doc), None);
+        assert_eq!(doc.content, "[ERROR] disk full");
+
+        let doc = parser.parse("1714564800 started").unwrap();
+        assert_eq!(timestamp(&doc), Some("1714564800"));
+
+        // The message maps past the timestamp, not to an earlier occurrence
+        let line = "2024-05-01T12:00:00Z 2024 again";
+        let mapped = parser.parse_mapped(line).unwrap();
+        assert_eq!(mapped.document.content, "2024 again");
+        assert_eq!(mapped.content.source_range((0, 4)), Some((21, 25)));
+    }
+
+    #[test]
+    fn test_no_timestamp() {
+        let parser = LogLineParser::new(vec![TimestampPattern::Epoch]).with_source("app.log");
+        let doc = parser.parse("12345 plain message").unwrap();
+        assert_eq!(timestamp(&doc), None);
+        assert_eq!(doc.content, "12345 plain message");
+        assert_eq!(doc.metadata.get("source").unwrap(), "app.log");
+        assert!(parser.parse("   ").is_err());
+    }
+}
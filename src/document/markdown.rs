@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+
+use super::offsets::{MappedText, offset_in};
+use super::{Document, DocumentParser, MappedDocument};
+use crate::errors::MSErrors;
+
+/*
+Parses a Markdown note or post into a Document. A leading `---` front
+matter block of `key: value` lines becomes metadata, lists such as
+`tags: [a, b]` or `- a` items being joined with commas. The first heading
+becomes the title (falling back to a front matter title) and the rest is
+indexed as plain text: markup, link targets, fences and rules are removed
+while code, link text and image alt text are kept.
+Documents are returned with id 0, callers assign ids.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownDocumentParser;
+
+impl MarkdownDocumentParser {
+    pub fn new() -> Self {
+        MarkdownDocumentParser
+    }
+}
+
+// Split off the front matter, returning its fields and the remaining text
+fn front_matter(input: &str) -> (HashMap<String, String>, &str) {
+    let mut metadata = HashMap::new();
+    let Some(body) = input
+        .strip_prefix("---\n")
+        .or_else(|| input.strip_prefix("---\r\n"))
+    else {
+        return (metadata, input);
+    };
+    let mut end = None;
+    let mut offset = 0;
+    for line in body.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            end = Some((offset, offset + line.len()));
+            break;
+        }
+        offset += line.len();
+    }
+    let Some((end, rest)) = end else {
+        return (metadata, input);
+    };
+
+    let mut list_key: Option<String> = None;
+    for line in body[..end].lines() {
+        if let (Some(key), Some(item)) = (&list_key, line.trim_start().strip_prefix("- ")) {
+            let values: &mut String = metadata.entry(key.clone()).or_default();
+            if !values.is_empty() {
+                values.push(',');
+            }
+            values.push_str(unquote(item));
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        if key.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let key = key.trim().to_string();
+        let value = value.trim();
+        list_key = value.is_empty().then(|| key.clone());
+        let value = match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            Some(items) => items.split(',').map(unquote).collect::<Vec<_>>().join(","),
+            None => unquote(value).to_string(),
+        };
+        if !value.is_empty() {
+            metadata.insert(key, value);
+        }
+    }
+    (metadata, &body[rest..])
+}
+
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value)
+}
+
+// Remove block markers at the start of a line: quotes, list bullets, tasks
+fn strip_block_markers(line: &str) -> &str {
+    let mut line = line.trim();
+    loop {
+        let before = line;
+        line = line.trim_start_matches('>').trim_start();
+        for bullet in ["- ", "* ", "+ "] {
+            line = line.strip_prefix(bullet).unwrap_or(line);
+        }
+        let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits > 0 && line[digits..].starts_with(". ") {
+            line = &line[digits + 2..];
+        }
+        for task in ["[ ] ", "[x] ", "[X] "] {
+            line = line.strip_prefix(task).unwrap_or(line);
+        }
+        if line == before {
+            return line;
+        }
+    }
+}
+
+// Rules, setext underlines and table separators carry no text
+fn is_decoration(line: &str) -> bool {
+    let line = line.trim();
+    line.len() >= 3
+        && line
+            .chars()
+            .all(|c| matches!(c, '-' | '*' | '_' | '=' | ' '))
+        || line.starts_with('|') && line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+// Plain text of inline markup found in the source at byte `start`
+fn strip_inline(text: &str, start: usize) -> MappedText {
+    let (offsets, chars): (Vec<usize>, Vec<char>) = text
+        .char_indices()
+        .map(|(idx, ch)| (start + idx, ch))
+        .unzip();
+    let source = |i: usize| (offsets[i], offsets[i] + chars[i].len_utf8());
+    let mut plain = MappedText::default();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            // [text](url), ![alt](url) and [text][ref] keep only the text
+            '!' if chars.get(i + 1) == Some(&'[') => i += 1,
+            '[' => {
+                i += 1;
+                continue;
+            }
+            ']' => {
+                let target_end = match chars.get(i + 1) {
+                    Some('(') => chars[i..].iter().position(|&c| c == ')'),
+                    Some('[') => chars[i + 1..].iter().position(|&c| c == ']').map(|p| p + 1),
+                    _ => None,
+                };
+                i += target_end.map_or(1, |end| end + 1);
+            }
+            // Autolinks and inline HTML tags
+            '<' if chars
+                .get(i + 1)
+                .is_some_and(|c| c.is_ascii_alphabetic() || *c == '/') =>
+            {
+                let end = chars[i..].iter().position(|&c| c == '>');
+                match end {
+                    Some(end) => {
+                        let inner: String = chars[i + 1..i + end].iter().collect();
+                        if inner.contains("://") || inner.contains('@') {
+                            (i + 1..i + end).for_each(|j| plain.push(chars[j], source(j)));
+                        }
+                        i += end + 1;
+                    }
+                    None => {
+                        plain.push('<', source(i));
+                        i += 1;
+                    }
+                }
+            }
+            '*' | '`' | '~' => i += 1,
+            // Underscores only mark emphasis at word boundaries
+            '_' => {
+                let inside_word = i > 0
+                    && chars[i - 1].is_alphanumeric()
+                    && chars.get(i + 1).is_some_and(|c| c.is_alphanumeric());
+                if inside_word {
+                    plain.push('_', source(i));
+                }
+                i += 1;
+            }
+            '\\' if chars.get(i + 1).is_some_and(|c| c.is_ascii_punctuation()) => {
+                plain.push(chars[i + 1], (offsets[i], source(i + 1).1));
+                i += 2;
+            }
+            c => {
+                plain.push(c, source(i));
+                i += 1;
+            }
+        }
+    }
+    plain
+}
+
+impl DocumentParser for MarkdownDocumentParser {
+    fn parse(&self, input: &str) -> Result<Document, MSErrors> {
+        self.parse_mapped(input).map(|mapped| mapped.document)
+    }
+
+    fn extract_text(&self, document: &Document) -> String {
+        format!("{}\n{}", document.title, document.content)
+    }
+
+    fn parse_mapped(&self, input: &str) -> Result<MappedDocument, MSErrors> {
+        let (metadata, body) = front_matter(input);
+        let mut title: Option<MappedText> = None;
+        let mut lines = Vec::new(); // Source offset and text of each content line
+        let mut fence: Option<&str> = None;
+
+        let mut next_line = offset_in(input, body);
+        for line in body.split_inclusive('\n') {
+            let start = next_line;
+            next_line += line.len();
+            let line = line.strip_suffix('\n').unwrap_or(line);
+            let trimmed = line.trim();
+            // Code is indexed as is, without its fences
+            if let Some(marker) = fence {
+                if trimmed.starts_with(marker) {
+                    fence = None;
+                } else {
+                    lines.push((start, MappedText::copied(line.trim_end(), start)));
+                }
+                continue;
+            }
+            if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+                fence = Some(marker);
+                continue;
+            }
+            // Link reference definitions: [ref]: url
+            if trimmed.starts_with('[') && trimmed.contains("]:") {
+                continue;
+            }
+            if is_decoration(trimmed) {
+                continue;
+            }
+
+            let heading = trimmed.trim_start_matches('#');
+            if heading.len() < trimmed.len() && (heading.is_empty() || heading.starts_with(' ')) {
+                let heading = heading.trim().trim_end_matches('#').trim();
+                let heading = strip_inline(heading, start + offset_in(line, heading));
+                if title.is_none() {
+                    title = Some(heading);
+                } else {
+                    lines.push((start, heading));
+                }
+                continue;
+            }
+            // Replacing '|' keeps the byte offsets of the line
+            let marked = strip_block_markers(trimmed);
+            let replaced = marked.replace('|', " ");
+            let inner = replaced.trim();
+            let inner_start = start + offset_in(line, marked) + offset_in(&replaced, inner);
+            let text = strip_inline(inner, inner_start).normalize();
+            if !text.is_empty() {
+                lines.push((start, text));
+            }
+        }
+
+        let title = title.unwrap_or_else(|| {
+            let Some(title) = metadata.get("title") else {
+                return MappedText::default();
+            };
+            match input.find(title.as_str()) {
+                Some(start) => MappedText::copied(title, start),
+                // A list value joined from several lines: map it to the whole front matter
+                None => {
+                    let mut mapped = MappedText::default();
+                    let front_matter = (0, offset_in(input, body));
+                    title.chars().for_each(|ch| mapped.push(ch, front_matter));
+                    mapped
+                }
+            }
+        });
+        let mut content = MappedText::default();
+        for (i, (start, line)) in lines.iter().enumerate() {
+            if i > 0 {
+                content.push('\n', (*start, *start));
+            }
+            content.append(line);
+        }
+        if title.is_empty() && content.is_empty() {
+            return Err(MSErrors::ParseError(
+                "Markdown note has no text".to_string(),
+            ));
+        }
+        Ok(MappedDocument {
+            document: Document {
+                id: 0,
+                title: title.text,
+                content: content.text,
+                metadata,
+            },
+            title: title.map,
+            content: content.map,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_markdown() {
+        let markdown = r#"---
+date: 2024-05-01
+tags: [rust, "search"]
+aliases:
+  - engine
+  - index
+draft: false
+---
+# Building a *search* engine
+
+Some **bold** text, `inline_code` and a [link](https://example.com) to
+![the diagram](img.png) with snake_case and _emphasis_ <br/> <https://rust-lang.org>.
+
+## Steps
+- [x] Tokenize
+1. Rank > filter
+> Quoted [ref link][1]
+
+| Term | Count |
+|------|------:|
+| fox  | 12    |
+
+```rust
+let x = 1;
+```
+---
+[1]: https://example.com/ref
+"#;
+        let doc = MarkdownDocumentParser::new().parse(markdown).unwrap();
+        assert_eq!(doc.title, "Building a search engine");
+        assert_eq!(
+            doc.content,
+            "Some bold text, inline_code and a link to\n\
+             the diagram with snake_case and emphasis https://rust-lang.org.\n\
+             Steps\nTokenize\nRank > filter\nQuoted ref link\nTerm Count\nfox 12\nlet x = 1;"
+        );
+        assert_eq!(
+            doc.metadata,
+            HashMap::from([
+                ("date".to_string(), "2024-05-01".to_string()),
+                ("tags".to_string(), "rust,search".to_string()),
+                ("aliases".to_string(), "engine,index".to_string()),
+                ("draft".to_string(), "false".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_title_fallback() {
+        let parser = MarkdownDocumentParser::new();
+        let doc = parser
+            .parse("---\ntitle: 'My note'\n---\nJust text\n")
+            .unwrap();
+        assert_eq!(doc.title, "My note");
+        assert_eq!(doc.content, "Just text");
+        assert_eq!(doc.metadata.get("title").unwrap(), "My note");
+
+        // An unterminated block is not front matter
+        let doc = parser.parse("---\nkey: value\n").unwrap();
+        assert!(doc.metadata.is_empty());
+        assert_eq!(doc.content, "key: value");
+        assert!(parser.parse("---\n\n***\n").is_err());
+    }
+}
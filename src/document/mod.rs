@@ -1,7 +1,20 @@
 use std::collections::HashMap;
 
 use crate::errors::MSErrors;
+use offsets::OffsetMap;
 
+pub mod chunk;
+pub mod csv;
+pub mod date;
+pub mod html;
+pub mod json;
+pub mod log;
+pub mod markdown;
+pub mod offsets;
+pub mod text;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Document {
     pub id: u64,
     pub title: String,
@@ -9,7 +22,46 @@ pub struct Document {
     pub metadata: HashMap<String, String>,
 }
 
+impl Document {
+    /*
+    A copy holding only `fields`, each "title", "content" or a metadata key.
+    The id is always kept; fields left out are empty.
+    */
+    pub fn project(&self, fields: &[String]) -> Document {
+        let wants = |name: &str| fields.iter().any(|field| field == name);
+        let text = |name: &str, value: &String| {
+            if wants(name) {
+                value.clone()
+            } else {
+                String::new()
+            }
+        };
+        Document {
+            id: self.id,
+            title: text("title", &self.title),
+            content: text("content", &self.content),
+            metadata: self
+                .metadata
+                .iter()
+                .filter(|(key, _)| wants(key))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        }
+    }
+}
+
+// A parsed document with maps from its title and content back to the input
+#[derive(Debug, Clone, PartialEq)]
+pub struct MappedDocument {
+    pub document: Document,
+    pub title: OffsetMap,
+    pub content: OffsetMap,
+}
+
 pub trait DocumentParser {
     fn parse(&self, input: &str) -> Result<Document, MSErrors>;
     fn extract_text(&self, document: &Document) -> String;
+
+    // Like `parse`, also mapping title and content offsets back to `input`
+    fn parse_mapped(&self, input: &str) -> Result<MappedDocument, MSErrors>;
 }
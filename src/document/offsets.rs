@@ -0,0 +1,202 @@
+/*
+Maps byte offsets of text a parser produced back to the input it parsed.
+Parsers strip markup, decode entities and collapse whitespace, so a match
+found in a document's content is not at the same bytes of the original
+page. Every char of the text records the source bytes it came from (all
+of `&amp;` for `&`); chars the parser added, such as line breaks for
+block elements, record an empty range where they were added. Mapped
+offsets always fall on char boundaries of the source.
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OffsetMap {
+    spans: Vec<(usize, (usize, usize))>, // Text offset of each char and its source byte range
+    text_len: usize,
+}
+
+impl OffsetMap {
+    // Map of `text` copied unchanged from the source at byte `start`
+    pub fn shifted(text: &str, start: usize) -> Self {
+        MappedText::copied(text, start).map
+    }
+
+    // Source offset of a char boundary of the text, None if `offset` is not one
+    pub fn source_offset(&self, offset: usize) -> Option<usize> {
+        let index = self.char_index(offset)?;
+        Some(self.start_of(index))
+    }
+
+    /*
+    Source byte range of a byte range of the text, covering the source of
+    every char in it. None if either end is not a char boundary of the text.
+    */
+    pub fn source_range(&self, range: (usize, usize)) -> Option<(usize, usize)> {
+        let (start, end) = range;
+        let first = self.char_index(start)?;
+        let last = self.char_index(end)?;
+        let source_start = self.start_of(first);
+        if last <= first {
+            return Some((source_start, source_start));
+        }
+        let (_, (_, source_end)) = self.spans[last - 1];
+        Some((source_start, source_end.max(source_start)))
+    }
+
+    // Index of the char starting at `offset`, the number of chars for the end
+    fn char_index(&self, offset: usize) -> Option<usize> {
+        if offset == self.text_len {
+            return Some(self.spans.len());
+        }
+        self.spans.binary_search_by_key(&offset, |&(at, _)| at).ok()
+    }
+
+    fn start_of(&self, index: usize) -> usize {
+        match self.spans.get(index) {
+            Some(&(_, (start, _))) => start,
+            None => self.spans.last().map_or(0, |&(_, (_, end))| end),
+        }
+    }
+}
+
+// Text being built by a parser together with its offset map
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MappedText {
+    pub(crate) text: String,
+    pub(crate) map: OffsetMap,
+}
+
+impl MappedText {
+    // `text` copied unchanged from the source at byte `start`
+    pub(crate) fn copied(text: &str, start: usize) -> Self {
+        let mut copied = MappedText::default();
+        copied.push_str(text, start);
+        copied
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    // Append `ch`, which came from the source bytes `source`
+    pub(crate) fn push(&mut self, ch: char, source: (usize, usize)) {
+        self.map.spans.push((self.text.len(), source));
+        self.text.push(ch);
+        self.map.text_len = self.text.len();
+    }
+
+    // Append `text`, copied unchanged from the source at byte `start`
+    pub(crate) fn push_str(&mut self, text: &str, start: usize) {
+        for (idx, ch) in text.char_indices() {
+            self.push(ch, (start + idx, start + idx + ch.len_utf8()));
+        }
+    }
+
+    pub(crate) fn append(&mut self, other: &MappedText) {
+        for (ch, source) in other.chars() {
+            self.push(ch, source);
+        }
+    }
+
+    // Chars of the text with their source ranges
+    pub(crate) fn chars(&self) -> impl Iterator<Item = (char, (usize, usize))> + '_ {
+        self.text
+            .chars()
+            .zip(self.map.spans.iter().map(|&(_, source)| source))
+    }
+
+    /*
+    Collapse whitespace within lines and drop empty lines: words are joined
+    by a line break if one separated them, otherwise by a single space.
+    */
+    pub(crate) fn normalize(&self) -> MappedText {
+        let mut normalized = MappedText::default();
+        let mut gap: Option<(char, (usize, usize))> = None;
+        for (ch, source) in self.chars() {
+            if ch == '\n' && gap.is_none_or(|(sep, _)| sep != '\n') {
+                gap = Some(('\n', source));
+            } else if ch.is_whitespace() {
+                gap.get_or_insert((' ', source));
+            } else {
+                if let Some((sep, source)) = gap.take()
+                    && !normalized.is_empty()
+                {
+                    normalized.push(sep, source);
+                }
+                normalized.push(ch, source);
+            }
+        }
+        normalized
+    }
+}
+
+// Byte offset of `inner`, a slice of `outer`, within it
+pub(crate) fn offset_in(outer: &str, inner: &str) -> usize {
+    inner.as_ptr() as usize - outer.as_ptr() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::DocumentParser;
+    use crate::document::html::HtmlDocumentParser;
+    use crate::document::markdown::MarkdownDocumentParser;
+    use crate::highlight::match_offsets;
+    use crate::tokenizer::{Language, Tokenizer};
+
+    // Source text of each match of `words` in the parsed content
+    fn matches_in_source(parser: &dyn DocumentParser, input: &str, words: &[&str]) -> Vec<String> {
+        let mapped = parser.parse_mapped(input).unwrap();
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
+        let terms: Vec<String> = words
+            .iter()
+            .flat_map(|word| tokenizer.tokenize(word))
+            .map(|token| token.term)
+            .collect();
+        match_offsets(&tokenizer, &mapped.document.content, &terms)
+            .into_iter()
+            .map(|range| {
+                let (start, end) = mapped.content.source_range(range).unwrap();
+                input[start..end].to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_offset_map() {
+        let map = OffsetMap::shifted("héllo wörld", 3);
+        assert_eq!(map.source_range((0, 6)), Some((3, 9)));
+        assert_eq!(map.source_offset(13), Some(16));
+        // Inside the two bytes of 'é'
+        assert_eq!(map.source_offset(2), None);
+        assert_eq!(map.source_range((0, 14)), None);
+
+        let mut text = MappedText::default();
+        text.push_str("  día \n\n  señal ", 10);
+        let normalized = text.normalize();
+        assert_eq!(normalized.text, "día\nseñal");
+        assert_eq!(normalized.map.source_range((5, 11)), Some((21, 27)));
+    }
+
+    #[test]
+    fn test_parsed_offsets() {
+        let html = "<html><head><title>Caf&#233; &amp; Crème</title></head>\
+                    <body><p>Über   <b>straße</b></p><p>naïve &#x2603; café</p></body></html>";
+        let mapped = HtmlDocumentParser::new().parse_mapped(html).unwrap();
+        assert_eq!(mapped.document.content, "Über straße\nnaïve \u{2603} café");
+        let snowman = mapped.document.content.find('\u{2603}').unwrap();
+        let (start, end) = mapped.content.source_range((snowman, snowman + 3)).unwrap();
+        assert_eq!(&html[start..end], "&#x2603;");
+        let creme = mapped.document.title.find("Crème").unwrap();
+        let (start, end) = mapped.title.source_range((creme, creme + 6)).unwrap();
+        assert_eq!(&html[start..end], "Crème");
+        assert_eq!(
+            matches_in_source(&HtmlDocumentParser::new(), html, &["Straße", "café"]),
+            ["straße", "café"]
+        );
+
+        let markdown = "# Ünïcode\n\n- **Grüße** aus [Köln](https://köln.de) — `naïve`\n";
+        assert_eq!(
+            matches_in_source(&MarkdownDocumentParser::new(), markdown, &["grüße", "köln"]),
+            ["Grüße", "Köln"]
+        );
+    }
+}
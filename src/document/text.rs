@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use super::offsets::{OffsetMap, offset_in};
+use super::{Document, DocumentParser, MappedDocument};
+use crate::errors::MSErrors;
+
+/*
+Parses plain text into a Document with the whole input as content and no
+title. Documents are returned with id 0, callers assign ids.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct TextDocumentParser;
+
+impl TextDocumentParser {
+    pub fn new() -> Self {
+        TextDocumentParser
+    }
+}
+
+impl DocumentParser for TextDocumentParser {
+    fn parse(&self, input: &str) -> Result<Document, MSErrors> {
+        let content = input.trim();
+        if content.is_empty() {
+            return Err(MSErrors::ParseError("text is empty".to_string()));
+        }
+        Ok(Document {
+            id: 0,
+            title: String::new(),
+            content: content.to_string(),
+            metadata: HashMap::new(),
+        })
+    }
+
+    fn extract_text(&self, document: &Document) -> String {
+        format!("{}\n{}", document.title, document.content)
+    }
+
+    fn parse_mapped(&self, input: &str) -> Result<MappedDocument, MSErrors> {
+        let document = self.parse(input)?;
+        let start = offset_in(input, input.trim());
+        Ok(MappedDocument {
+            content: OffsetMap::shifted(&document.content, start),
+            title: OffsetMap::default(),
+            document,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_text() {
+        let parser = TextDocumentParser::new();
+        assert_eq!(
+            parser.parse("\n  Some notes\nmore\n").unwrap().content,
+            "Some notes\nmore"
+        );
+        assert!(parser.parse(" \n\t").is_err());
+    }
+}
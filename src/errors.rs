@@ -1,3 +1,6 @@
+use std::io;
+use std::path::PathBuf;
+
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -6,10 +9,116 @@ pub enum MSErrors {
     DocumentNotFound,
     #[error("Indexing error: {0}")]
     IndexingError(String),
+    #[error("Tokenize error: {0}")]
+    TokenizeError(String),
     #[error("Search error: {0}")]
     SearchError(String),
     #[error("Storage error: {0}")]
     StorageError(String),
     #[error("Parse error: {0}")]
     ParseError(String),
+    #[error("Busy: {0}")]
+    Busy(String),
+    #[error("Lock error: {0}")]
+    LockError(String),
+    #[error("Document {doc_id} already exists")]
+    DuplicateDocument { doc_id: u64 },
+    #[error("Document {doc_id}: field {field} {message}")]
+    InvalidField {
+        doc_id: u64,
+        field: String,
+        message: String,
+    },
+    #[error("Invalid term \"{term}\": {message}")]
+    InvalidTerm { term: String, message: String },
+    #[error("Query parse error at byte {position}: {message}, expected {}", expected.join(" or "))]
+    QueryParseError {
+        position: usize,
+        expected: Vec<String>,
+        message: String,
+    },
+    #[error("Schema mismatch on field {field}: {message}")]
+    SchemaMismatch { field: String, message: String },
+    #[error("I/O error on {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Corrupt data at byte {offset}: {message}")]
+    Corrupt { offset: usize, message: String },
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl MSErrors {
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        MSErrors::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    /*
+    Stable numeric code of the error, for callers that cannot match on the
+    enum (FFI bindings, HTTP clients). The hundreds give the category;
+    codes are never reused or renumbered.
+    */
+    pub fn code(&self) -> u16 {
+        match self {
+            MSErrors::DocumentNotFound => 100,
+            MSErrors::DuplicateDocument { .. } => 101,
+            MSErrors::IndexingError(_) => 200,
+            MSErrors::InvalidField { .. } => 201,
+            MSErrors::TokenizeError(_) => 202,
+            MSErrors::SchemaMismatch { .. } => 203,
+            MSErrors::SearchError(_) => 300,
+            MSErrors::InvalidTerm { .. } => 301,
+            MSErrors::QueryParseError { .. } => 302,
+            MSErrors::StorageError(_) => 400,
+            MSErrors::Io { .. } => 401,
+            MSErrors::Corrupt { .. } => 402,
+            MSErrors::ParseError(_) => 500,
+            MSErrors::Json(_) => 501,
+            MSErrors::Busy(_) => 600,
+            MSErrors::LockError(_) => 601,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_codes_and_sources() {
+        let error = MSErrors::io(
+            "index/documents",
+            io::Error::new(io::ErrorKind::NotFound, "gone"),
+        );
+        assert_eq!(error.code(), 401);
+        assert_eq!(error.to_string(), "I/O error on index/documents: gone");
+        assert_eq!(error.source().unwrap().to_string(), "gone");
+
+        let error = MSErrors::from(serde_json::from_str::<u64>("x").unwrap_err());
+        assert_eq!(error.code(), 501);
+        assert!(error.source().is_some());
+        assert_eq!(MSErrors::DuplicateDocument { doc_id: 3 }.code(), 101);
+        let error = MSErrors::TokenizeError("no stemmer for klingon".to_string());
+        assert_eq!(error.code(), 202);
+        assert_eq!(error.to_string(), "Tokenize error: no stemmer for klingon");
+        assert!(MSErrors::ParseError(String::new()).source().is_none());
+
+        let error = MSErrors::QueryParseError {
+            position: 7,
+            expected: vec!["]".to_string(), "TO".to_string()],
+            message: "unclosed range".to_string(),
+        };
+        assert_eq!(error.code(), 302);
+        assert_eq!(
+            error.to_string(),
+            "Query parse error at byte 7: unclosed range, expected ] or TO"
+        );
+    }
 }
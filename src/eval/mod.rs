@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use crate::errors::MSErrors;
+use crate::searcher::{SearchEngine, SearchOptions};
+
+// Hits retrieved per query, the depth at which average precision is computed
+pub const EVAL_DEPTH: usize = 1000;
+
+/*
+Relevance judgments, as in a TREC qrels file: one `query_id iteration
+doc_id relevance` line per judged document, fields separated by
+whitespace, the iteration being ignored. Relevance is a grade, any grade
+above 0 counting as relevant; documents that were not judged count as
+not relevant.
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Qrels {
+    judgments: HashMap<String, HashMap<u64, u32>>, // Query id -> doc id -> grade
+}
+
+impl Qrels {
+    pub fn new() -> Self {
+        Qrels::default()
+    }
+
+    pub fn parse(text: &str) -> Result<Self, MSErrors> {
+        let mut qrels = Qrels::new();
+        for (number, line) in text.lines().enumerate() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let judgment = match fields.as_slice() {
+                [] => continue,
+                [query_id, _, doc_id, grade] => doc_id
+                    .parse()
+                    .ok()
+                    .zip(grade.parse().ok())
+                    .map(|(doc_id, grade)| (query_id, doc_id, grade)),
+                _ => None,
+            };
+            let (query_id, doc_id, grade) = judgment.ok_or_else(|| {
+                MSErrors::ParseError(format!("invalid qrels line {}: {}", number + 1, line))
+            })?;
+            qrels.judge(query_id, doc_id, grade);
+        }
+        Ok(qrels)
+    }
+
+    pub fn judge(&mut self, query_id: &str, doc_id: u64, grade: u32) {
+        self.judgments
+            .entry(query_id.to_string())
+            .or_default()
+            .insert(doc_id, grade);
+    }
+
+    pub fn grade(&self, query_id: &str, doc_id: u64) -> u32 {
+        self.judgments
+            .get(query_id)
+            .and_then(|grades| grades.get(&doc_id))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // Number of documents judged relevant to the query
+    pub fn relevant(&self, query_id: &str) -> usize {
+        self.judgments.get(query_id).map_or(0, |grades| {
+            grades.values().filter(|&&grade| grade > 0).count()
+        })
+    }
+
+    // Grades of the query's judged documents, best first
+    fn ideal_grades(&self, query_id: &str) -> Vec<u32> {
+        let mut grades: Vec<u32> = self
+            .judgments
+            .get(query_id)
+            .map(|grades| grades.values().copied().collect())
+            .unwrap_or_default();
+        grades.sort_by(|a, b| b.cmp(a));
+        grades
+    }
+}
+
+/*
+Queries to evaluate, one `query_id query text` line each, the id being
+separated from the text by a tab or the first space.
+*/
+pub fn parse_queries(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter_map(|line| {
+            let (query_id, query) = line.trim().split_once(['\t', ' '])?;
+            Some((query_id.to_string(), query.trim().to_string()))
+        })
+        .collect()
+}
+
+// Fraction of the first `k` hits that are relevant; missing hits count as misses
+pub fn precision_at(ranking: &[u64], qrels: &Qrels, query_id: &str, k: usize) -> f64 {
+    if k == 0 {
+        return 0.0;
+    }
+    let hits = ranking.iter().take(k);
+    let relevant = hits
+        .filter(|&&doc_id| qrels.grade(query_id, doc_id) > 0)
+        .count();
+    relevant as f64 / k as f64
+}
+
+// Mean of the precision at the rank of each relevant document, 0 for those not retrieved
+pub fn average_precision(ranking: &[u64], qrels: &Qrels, query_id: &str) -> f64 {
+    let relevant = qrels.relevant(query_id);
+    if relevant == 0 {
+        return 0.0;
+    }
+    let mut found = 0;
+    let mut sum = 0.0;
+    for (rank, &doc_id) in ranking.iter().enumerate() {
+        if qrels.grade(query_id, doc_id) > 0 {
+            found += 1;
+            sum += found as f64 / (rank + 1) as f64;
+        }
+    }
+    sum / relevant as f64
+}
+
+/*
+Normalized discounted cumulative gain of the first `k` hits: each hit
+gains `2^grade - 1`, discounted by `log2(rank + 1)`, and the sum is
+divided by that of the ideal ranking of the judged documents.
+*/
+pub fn ndcg_at(ranking: &[u64], qrels: &Qrels, query_id: &str, k: usize) -> f64 {
+    let dcg = |grades: &mut dyn Iterator<Item = u32>| -> f64 {
+        grades
+            .take(k)
+            .enumerate()
+            .map(|(rank, grade)| (2f64.powi(grade as i32) - 1.0) / (rank as f64 + 2.0).log2())
+            .sum()
+    };
+    let ideal = dcg(&mut qrels.ideal_grades(query_id).into_iter());
+    if ideal == 0.0 {
+        return 0.0;
+    }
+    dcg(&mut ranking.iter().map(|&doc_id| qrels.grade(query_id, doc_id))) / ideal
+}
+
+// Scores of one query
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryEvaluation {
+    pub query_id: String,
+    pub precision: f64,         // At k
+    pub average_precision: f64, // Over the first EVAL_DEPTH hits
+    pub ndcg: f64,              // At k
+}
+
+// Scores of a set of queries and their means
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub k: usize,
+    pub queries: Vec<QueryEvaluation>,
+    pub mean_precision: f64,
+    pub mean_average_precision: f64, // MAP
+    pub mean_ndcg: f64,
+}
+
+/*
+Run each query against the engine and score its ranking with the
+judgments. Queries without any relevant document are left out, as no
+ranking of theirs can be scored; with none left the means are 0. Compare
+the means of two runs, e.g. before and after changing BM25 parameters or
+an analyzer, to see whether ranking improved.
+*/
+pub fn evaluate(
+    engine: &SearchEngine,
+    queries: &[(String, String)],
+    qrels: &Qrels,
+    k: usize,
+) -> Evaluation {
+    let options = SearchOptions::new(k.max(EVAL_DEPTH));
+    let evaluations: Vec<QueryEvaluation> = queries
+        .iter()
+        .filter(|(query_id, _)| qrels.relevant(query_id) > 0)
+        .map(|(query_id, query)| {
+            let results = engine.search_with_options(query, &options);
+            let ranking: Vec<u64> = results.documents.iter().map(|d| d.id).collect();
+            QueryEvaluation {
+                query_id: query_id.clone(),
+                precision: precision_at(&ranking, qrels, query_id, k),
+                average_precision: average_precision(&ranking, qrels, query_id),
+                ndcg: ndcg_at(&ranking, qrels, query_id, k),
+            }
+        })
+        .collect();
+    let mean = |score: fn(&QueryEvaluation) -> f64| {
+        if evaluations.is_empty() {
+            return 0.0;
+        }
+        evaluations.iter().map(score).sum::<f64>() / evaluations.len() as f64
+    };
+    Evaluation {
+        k,
+        mean_precision: mean(|e| e.precision),
+        mean_average_precision: mean(|e| e.average_precision),
+        mean_ndcg: mean(|e| e.ndcg),
+        queries: evaluations,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Document;
+    use crate::settings::IndexSettings;
+
+    #[test]
+    fn test_metrics() {
+        let qrels = Qrels::parse("q1 0 1 2\nq1 0 3 1\nq1 0 4 0\n\nq2 0 9 1\n").unwrap();
+        assert!(Qrels::parse("q1 0 one 1").is_err());
+        let ranking = [3, 2, 1, 4];
+        assert_eq!(precision_at(&ranking, &qrels, "q1", 2), 0.5);
+        assert_eq!(precision_at(&ranking, &qrels, "q1", 10), 0.2);
+        // Relevant at ranks 1 and 3
+        assert!(
+            (average_precision(&ranking, &qrels, "q1") - (1.0 + 2.0 / 3.0) / 2.0).abs() < 1e-12
+        );
+        assert_eq!(average_precision(&ranking, &qrels, "q3"), 0.0);
+        let dcg = 1.0 + 3.0 / 4f64.log2();
+        let ideal = 3.0 + 1.0 / 3f64.log2();
+        assert!((ndcg_at(&ranking, &qrels, "q1", 3) - dcg / ideal).abs() < 1e-12);
+        assert_eq!(ndcg_at(&[1, 3], &qrels, "q1", 3), 1.0);
+        assert_eq!(
+            parse_queries("q1\trust book\nq2 go\n"),
+            [
+                ("q1".to_string(), "rust book".to_string()),
+                ("q2".to_string(), "go".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        let docs = [(1, "rust ownership"), (2, "rust"), (3, "go channels")];
+        for (id, content) in docs {
+            engine
+                .add_document(Document {
+                    id,
+                    title: String::new(),
+                    content: content.to_string(),
+                    metadata: HashMap::new(),
+                })
+                .unwrap();
+        }
+        let queries = parse_queries("q1 rust ownership\nq2 channels\nq3 unjudged");
+        let qrels = Qrels::parse("q1 0 1 1\nq2 0 3 1\nq2 0 2 1").unwrap();
+        let evaluation = evaluate(&engine, &queries, &qrels, 1);
+        assert_eq!(evaluation.queries.len(), 2);
+        assert_eq!(evaluation.queries[0].precision, 1.0);
+        // q2 finds 3 but never 2
+        assert_eq!(evaluation.queries[1].average_precision, 0.5);
+        assert_eq!(evaluation.mean_average_precision, 0.75);
+        assert_eq!(evaluation.mean_precision, 1.0);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::tokenizer::Tokenizer;
+
+// A piece of text, marked if it matched a query term
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fragment {
+    pub text: String,
+    pub highlighted: bool,
+}
+
+// How often one query term occurs in a text
+#[derive(Debug, Clone, PartialEq)]
+pub struct TermCount {
+    pub term: String, // Analyzed query term
+    pub text: String, // First occurrence as written, lowercased, for display
+    pub count: usize,
+}
+
+// One match with the text around it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Occurrence {
+    pub term: String,
+    pub offset: (usize, usize), // Byte range of the match in the text
+    pub snippet: Vec<Fragment>,
+}
+
+// Every match in a text, for in-document match navigation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Occurrences {
+    pub occurrences: Vec<Occurrence>, // In text order, at most the requested number
+    pub counts: Vec<TermCount>,       // Uncapped, in query term order, matched terms only
+}
+
+impl Occurrences {
+    pub fn total(&self) -> usize {
+        self.counts.iter().map(|c| c.count).sum()
+    }
+
+    // True if some matches were left out of `occurrences`
+    pub fn is_truncated(&self) -> bool {
+        self.occurrences.len() < self.total()
+    }
+}
+
+// Byte ranges of tokens in `text` whose term is one of `terms`
+pub fn match_offsets(tokenizer: &Tokenizer, text: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    tokenizer
+        .tokenize(text)
+        .into_iter()
+        .filter(|token| terms.contains(&token.term))
+        .map(|token| token.offset)
+        .collect()
+}
+
+// Split the whole text into highlighted and plain fragments
+pub fn highlight(tokenizer: &Tokenizer, text: &str, terms: &[String]) -> Vec<Fragment> {
+    let matches = match_offsets(tokenizer, text, terms);
+    fragments(text, &matches, 0, text.len())
+}
+
+/*
+Cut a window of roughly `max_len` bytes around the first match.
+The window is widened to character boundaries, and "…" marks text cut
+on either side. Without any match the start of the text is returned.
+*/
+pub fn snippet(
+    tokenizer: &Tokenizer,
+    text: &str,
+    terms: &[String],
+    max_len: usize,
+) -> Vec<Fragment> {
+    let matches = match_offsets(tokenizer, text, terms);
+    let first = matches.first().map_or(0, |&(start, _)| start);
+    window(text, &matches, first, max_len)
+}
+
+/*
+Every occurrence of the query terms, up to `limit`, each with a snippet
+window around it, plus the number of occurrences of each term.
+*/
+pub fn occurrences(
+    tokenizer: &Tokenizer,
+    text: &str,
+    terms: &[String],
+    limit: usize,
+    max_len: usize,
+) -> Occurrences {
+    let tokens: Vec<_> = tokenizer
+        .tokenize(text)
+        .into_iter()
+        .filter(|token| terms.contains(&token.term))
+        .collect();
+    let matches: Vec<(usize, usize)> = tokens.iter().map(|token| token.offset).collect();
+
+    let counts = terms
+        .iter()
+        .filter_map(|term| {
+            let mut occurrences = tokens.iter().filter(|token| token.term == *term);
+            let first = occurrences.next()?;
+            Some(TermCount {
+                term: term.clone(),
+                text: text[first.offset.0..first.offset.1].to_lowercase(),
+                count: occurrences.count() + 1,
+            })
+        })
+        .collect();
+    let occurrences = tokens
+        .iter()
+        .take(limit)
+        .map(|token| Occurrence {
+            term: token.term.clone(),
+            offset: token.offset,
+            snippet: window(text, &matches, token.offset.0, max_len),
+        })
+        .collect();
+    Occurrences {
+        occurrences,
+        counts,
+    }
+}
+
+// Fragments of a window of roughly `max_len` bytes around the byte `center`
+fn window(text: &str, matches: &[(usize, usize)], center: usize, max_len: usize) -> Vec<Fragment> {
+    if text.len() <= max_len {
+        return fragments(text, matches, 0, text.len());
+    }
+
+    // Keep some leading context before the match
+    let start = floor_boundary(text, center.saturating_sub(max_len / 4));
+    let end = ceil_boundary(text, (start + max_len).min(text.len()));
+
+    let mut result = fragments(text, matches, start, end);
+    if start > 0 {
+        result.insert(0, plain("…"));
+    }
+    if end < text.len() {
+        result.push(plain("…"));
+    }
+    result
+}
+
+fn plain(text: &str) -> Fragment {
+    Fragment {
+        text: text.to_string(),
+        highlighted: false,
+    }
+}
+
+// Fragments of text[start..end] given sorted, non-overlapping match ranges
+fn fragments(text: &str, matches: &[(usize, usize)], start: usize, end: usize) -> Vec<Fragment> {
+    let mut result = Vec::new();
+    let mut cursor = start;
+    for &(match_start, match_end) in matches {
+        if match_end <= start || match_start >= end {
+            continue;
+        }
+        let (match_start, match_end) = (match_start.max(start), match_end.min(end));
+        if cursor < match_start {
+            result.push(plain(&text[cursor..match_start]));
+        }
+        result.push(Fragment {
+            text: text[match_start..match_end].to_string(),
+            highlighted: true,
+        });
+        cursor = match_end;
+    }
+    if cursor < end {
+        result.push(plain(&text[cursor..end]));
+    }
+    result
+}
+
+fn floor_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn ceil_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Language;
+
+    fn render(fragments: &[Fragment]) -> String {
+        fragments
+            .iter()
+            .map(|f| {
+                if f.highlighted {
+                    format!("[{}]", f.text)
+                } else {
+                    f.text.clone()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_highlight() {
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
+        let terms = vec!["fox".to_string()];
+        let fragments = highlight(&tokenizer, "The quick foxes, a fox!", &terms);
+        assert_eq!(render(&fragments), "The quick [foxes], a [fox]!");
+    }
+
+    #[test]
+    fn test_snippet_window() {
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
+        let terms: Vec<String> = tokenizer
+            .tokenize("turtle")
+            .into_iter()
+            .map(|t| t.term)
+            .collect();
+        let text = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. A slow turtle walks by the café.";
+        let fragments = snippet(&tokenizer, text, &terms, 30);
+        let rendered = render(&fragments);
+        assert!(rendered.starts_with('…'));
+        assert!(rendered.ends_with('…'));
+        assert!(rendered.contains("[turtle]"));
+
+        // Short texts are returned whole
+        let fragments = snippet(&tokenizer, "slow turtle", &terms, 30);
+        assert_eq!(render(&fragments), "slow [turtle]");
+    }
+
+    #[test]
+    fn test_occurrences() {
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
+        let terms = vec!["fox".to_string(), "jump".to_string(), "owl".to_string()];
+        let text = "Foxes jump. A fox jumps over the fox den, and jumping foxes rest.";
+        let result = occurrences(&tokenizer, text, &terms, 3, 20);
+
+        let counts: Vec<(&str, &str, usize)> = result
+            .counts
+            .iter()
+            .map(|c| (c.term.as_str(), c.text.as_str(), c.count))
+            .collect();
+        assert_eq!(counts, vec![("fox", "foxes", 4), ("jump", "jump", 3)]);
+        assert_eq!(result.total(), 7);
+        assert!(result.is_truncated());
+
+        let offsets: Vec<(usize, usize)> = result.occurrences.iter().map(|o| o.offset).collect();
+        assert_eq!(offsets, vec![(0, 5), (6, 10), (14, 17)]);
+        assert_eq!(result.occurrences[2].term, "fox");
+        let snippet = render(&result.occurrences[2].snippet);
+        assert!(snippet.starts_with('…') && snippet.contains("[fox] [jumps]"));
+    }
+}
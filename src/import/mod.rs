@@ -0,0 +1,12 @@
+// Bridges that move documents between mini-search and other engines or dumps
+pub mod ndjson;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+#[cfg(feature = "tantivy")]
+pub mod tantivy;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ImportStats {
+    pub imported: usize,
+    pub skipped: usize, // Unparsable records, or documents with neither title nor content
+}
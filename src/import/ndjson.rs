@@ -0,0 +1,74 @@
+use std::io::BufRead;
+
+use super::ImportStats;
+use crate::document::DocumentParser;
+use crate::document::json::JsonDocumentParser;
+use crate::errors::MSErrors;
+use crate::searcher::SearchEngine;
+
+/*
+Bulk load a newline-delimited JSON dump into `engine`, one object per line.
+Blank lines are ignored and lines the parser rejects are counted as
+skipped. Ids come from the parser's id field when set, otherwise they are
+assigned after the engine's highest id.
+*/
+pub fn import_ndjson(
+    reader: impl BufRead,
+    engine: &mut SearchEngine,
+    parser: &JsonDocumentParser,
+) -> Result<ImportStats, MSErrors> {
+    let mut stats = ImportStats::default();
+    let mut next_id = engine.next_document_id();
+    for line in reader.lines() {
+        let line = line.map_err(|e| MSErrors::IndexingError(e.to_string()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Ok(mut document) = parser.parse(&line) else {
+            stats.skipped += 1;
+            continue;
+        };
+        if parser.id_field.is_none() {
+            document.id = next_id;
+            next_id += 1;
+        }
+        engine.add_document(document)?;
+        stats.imported += 1;
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::IndexSettings;
+
+    #[test]
+    fn test_import_ndjson() {
+        let input = r#"{"title": "Rust", "content": "Ownership and borrowing"}
+{"title": "Go", "content": "Goroutines", "lang": "en"}
+
+not json
+{"lang": "en"}
+{"content": "Borrowing rules"}
+"#;
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        let stats = import_ndjson(
+            input.as_bytes(),
+            &mut engine,
+            &JsonDocumentParser::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            stats,
+            ImportStats {
+                imported: 3,
+                skipped: 2
+            }
+        );
+        assert_eq!(engine.search("borrowing", 10).total_matches, 2);
+        let go = &engine.search("goroutines", 10).documents[0];
+        assert_eq!(go.id, 1);
+        assert_eq!(go.metadata.get("lang").unwrap(), "en");
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::{BTreeSet, HashMap};
+
+use rusqlite::Connection;
+use rusqlite::types::{Value, ValueRef};
+
+use super::ImportStats;
+use crate::document::Document;
+use crate::errors::MSErrors;
+use crate::searcher::SearchEngine;
+
+/*
+Maps the columns of an SQLite FTS5 table onto mini-search documents.
+The rowid becomes the document id. Every column other than the title and
+content becomes metadata, unless `metadata_columns` restricts it to a fixed
+list. The defaults match the tables written by `export_fts5`.
+*/
+#[derive(Debug, Clone)]
+pub struct Fts5Mapping {
+    pub table: String,
+    pub title_column: Option<String>,
+    pub content_column: String,
+    pub metadata_columns: Option<Vec<String>>,
+}
+
+impl Fts5Mapping {
+    pub fn new(table: &str) -> Self {
+        Fts5Mapping {
+            table: table.to_string(),
+            title_column: Some("title".to_string()),
+            content_column: "content".to_string(),
+            metadata_columns: None,
+        }
+    }
+}
+
+fn sqlite_error(e: rusqlite::Error) -> MSErrors {
+    MSErrors::StorageError(format!("sqlite: {}", e))
+}
+
+// Quote an identifier for use in SQL
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn value_text(value: ValueRef) -> Option<String> {
+    match value {
+        ValueRef::Integer(n) => Some(n.to_string()),
+        ValueRef::Real(n) => Some(n.to_string()),
+        ValueRef::Text(text) => Some(String::from_utf8_lossy(text).into_owned()),
+        ValueRef::Null | ValueRef::Blob(_) => None,
+    }
+}
+
+// Add every row of an FTS5 table to `engine`
+pub fn import_fts5(
+    conn: &Connection,
+    engine: &mut SearchEngine,
+    mapping: &Fts5Mapping,
+) -> Result<ImportStats, MSErrors> {
+    let sql = format!("SELECT rowid, * FROM {}", quote(&mapping.table));
+    let mut statement = conn.prepare(&sql).map_err(sqlite_error)?;
+    let columns: Vec<String> = statement
+        .column_names()
+        .into_iter()
+        .skip(1)
+        .map(String::from)
+        .collect();
+    for required in mapping.title_column.iter().chain([&mapping.content_column]) {
+        if !columns.contains(required) {
+            return Err(MSErrors::ParseError(format!(
+                "table {} has no column {}",
+                mapping.table, required
+            )));
+        }
+    }
+
+    let mut stats = ImportStats::default();
+    let mut rows = statement.query([]).map_err(sqlite_error)?;
+    while let Some(row) = rows.next().map_err(sqlite_error)? {
+        let rowid: i64 = row.get(0).map_err(sqlite_error)?;
+        let id = u64::try_from(rowid)
+            .map_err(|_| MSErrors::ParseError(format!("negative rowid {}", rowid)))?;
+
+        let mut title = String::new();
+        let mut content = String::new();
+        let mut metadata = HashMap::new();
+        for (i, column) in columns.iter().enumerate() {
+            let Some(text) = value_text(row.get_ref(i + 1).map_err(sqlite_error)?) else {
+                continue;
+            };
+            if Some(column) == mapping.title_column.as_ref() {
+                title = text;
+            } else if *column == mapping.content_column {
+                content = text;
+            } else if mapping
+                .metadata_columns
+                .as_ref()
+                .is_none_or(|allowed| allowed.contains(column))
+            {
+                metadata.insert(column.clone(), text);
+            }
+        }
+        if title.is_empty() && content.is_empty() {
+            stats.skipped += 1;
+            continue;
+        }
+        engine.add_document(Document {
+            id,
+            title,
+            content,
+            metadata,
+        })?;
+        stats.imported += 1;
+    }
+    Ok(stats)
+}
+
+/*
+Write the engine's documents into an FTS5 table, creating it if needed.
+The table gets title and content columns plus one UNINDEXED column per
+metadata field, and each row's rowid is the document id. Existing rows
+with the same ids are replaced. Returns the number of rows written.
+*/
+pub fn export_fts5(
+    engine: &SearchEngine,
+    conn: &Connection,
+    table: &str,
+) -> Result<usize, MSErrors> {
+    let fields: Vec<&String> = engine
+        .documents()
+        .flat_map(|document| document.metadata.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    if fields.iter().any(|f| *f == "title" || *f == "content") {
+        return Err(MSErrors::StorageError(
+            "metadata fields named title or content cannot be exported".to_string(),
+        ));
+    }
+
+    let mut columns = vec!["title".to_string(), "content".to_string()];
+    columns.extend(fields.iter().map(|f| format!("{} UNINDEXED", quote(f))));
+    let transaction = conn.unchecked_transaction().map_err(sqlite_error)?;
+    transaction
+        .execute_batch(&format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS {} USING fts5({});",
+            quote(table),
+            columns.join(", ")
+        ))
+        .map_err(sqlite_error)?;
+
+    let names: Vec<String> = ["title", "content"]
+        .into_iter()
+        .map(quote)
+        .chain(fields.iter().map(|f| quote(f)))
+        .collect();
+    let placeholders = vec!["?"; names.len() + 1].join(", ");
+    let delete = format!("DELETE FROM {} WHERE rowid = ?", quote(table));
+    let insert = format!(
+        "INSERT INTO {} (rowid, {}) VALUES ({})",
+        quote(table),
+        names.join(", "),
+        placeholders
+    );
+
+    let mut written = 0;
+    {
+        let mut delete = transaction.prepare(&delete).map_err(sqlite_error)?;
+        let mut insert = transaction.prepare(&insert).map_err(sqlite_error)?;
+        let mut documents: Vec<&Document> = engine.documents().collect();
+        documents.sort_by_key(|d| d.id);
+        for document in documents {
+            let rowid = i64::try_from(document.id).map_err(|_| {
+                MSErrors::StorageError(format!("document id {} is too large", document.id))
+            })?;
+            let mut values = vec![
+                Value::Integer(rowid),
+                Value::Text(document.title.clone()),
+                Value::Text(document.content.clone()),
+            ];
+            values.extend(fields.iter().map(|f| {
+                document
+                    .metadata
+                    .get(*f)
+                    .map_or(Value::Null, |v| Value::Text(v.clone()))
+            }));
+            delete.execute([rowid]).map_err(sqlite_error)?;
+            insert
+                .execute(rusqlite::params_from_iter(values))
+                .map_err(sqlite_error)?;
+            written += 1;
+        }
+    }
+    transaction.commit().map_err(sqlite_error)?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::IndexSettings;
+
+    fn document(id: u64, title: &str, content: &str, metadata: &[(&str, &str)]) -> Document {
+        Document {
+            id,
+            title: title.to_string(),
+            content: content.to_string(),
+            metadata: metadata
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_import_fts5() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE VIRTUAL TABLE notes USING fts5(title, content, lang UNINDEXED);
+             INSERT INTO notes (rowid, title, content, lang) VALUES
+                 (3, 'Foxes', 'The quick brown fox', 'en'),
+                 (8, 'Turtles', 'Slow turtle', NULL),
+                 (9, '', '', 'de');",
+        )
+        .unwrap();
+
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        let stats = import_fts5(&conn, &mut engine, &Fts5Mapping::new("notes")).unwrap();
+        assert_eq!(
+            stats,
+            ImportStats {
+                imported: 2,
+                skipped: 1
+            }
+        );
+        let fox = engine.get_document(3).unwrap();
+        assert_eq!(fox.metadata.get("lang").map(String::as_str), Some("en"));
+        assert!(engine.get_document(8).unwrap().metadata.is_empty());
+        assert_eq!(engine.search("turtle", 10).documents[0].id, 8);
+
+        let mut mapping = Fts5Mapping::new("notes");
+        mapping.content_column = "body".to_string();
+        assert!(import_fts5(&conn, &mut engine, &mapping).is_err());
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine
+            .add_document(document(1, "Foxes", "quick fox", &[("lang", "en")]))
+            .unwrap();
+        engine
+            .add_document(document(2, "Birds", "a \"bird\"", &[("tag", "sky")]))
+            .unwrap();
+
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(export_fts5(&engine, &conn, "docs").unwrap(), 2);
+        // Exporting again replaces rows rather than duplicating them
+        assert_eq!(export_fts5(&engine, &conn, "docs").unwrap(), 2);
+        let rowid: i64 = conn
+            .query_row("SELECT rowid FROM docs WHERE docs MATCH 'fox'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(rowid, 1);
+
+        let mut imported = SearchEngine::new(IndexSettings::default()).unwrap();
+        import_fts5(&conn, &mut imported, &Fts5Mapping::new("docs")).unwrap();
+        assert_eq!(imported.document_count(), 2);
+        for id in [1, 2] {
+            assert_eq!(imported.get_document(id), engine.get_document(id));
+        }
+    }
+}
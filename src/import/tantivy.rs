@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use ::tantivy::Document as _;
+use ::tantivy::schema::OwnedValue;
+use ::tantivy::{Index, TantivyDocument, TantivyError};
+
+use super::ImportStats;
+use crate::document::Document;
+use crate::errors::MSErrors;
+use crate::searcher::SearchEngine;
+
+// Number of decompressed store blocks kept in memory while reading a segment
+const STORE_CACHE_BLOCKS: usize = 16;
+
+/*
+Maps the stored fields of a Tantivy schema onto mini-search documents.
+Title and content fields are joined with a newline when a field holds
+several values. Every other stored field becomes metadata, unless
+`metadata_fields` restricts it to a fixed list.
+*/
+#[derive(Debug, Clone)]
+pub struct FieldMapping {
+    pub id_field: Option<String>, // u64 field to reuse as document id
+    pub title_field: Option<String>,
+    pub content_fields: Vec<String>,
+    pub metadata_fields: Option<Vec<String>>,
+}
+
+impl Default for FieldMapping {
+    fn default() -> Self {
+        FieldMapping {
+            id_field: None,
+            title_field: Some("title".to_string()),
+            content_fields: vec!["body".to_string()],
+            metadata_fields: None,
+        }
+    }
+}
+
+fn tantivy_error(e: TantivyError) -> MSErrors {
+    MSErrors::StorageError(format!("tantivy: {}", e))
+}
+
+// Render a stored value as text; binary and structured values are dropped
+fn value_text(value: &OwnedValue) -> Option<String> {
+    match value {
+        OwnedValue::Str(text) => Some(text.clone()),
+        OwnedValue::PreTokStr(text) => Some(text.text.clone()),
+        OwnedValue::U64(n) => Some(n.to_string()),
+        OwnedValue::I64(n) => Some(n.to_string()),
+        OwnedValue::F64(n) => Some(n.to_string()),
+        OwnedValue::Bool(b) => Some(b.to_string()),
+        OwnedValue::Date(date) => Some(date.into_timestamp_secs().to_string()),
+        OwnedValue::Facet(facet) => Some(facet.to_path_string()),
+        OwnedValue::IpAddr(addr) => Some(addr.to_string()),
+        OwnedValue::Array(values) => {
+            let parts: Vec<String> = values.iter().filter_map(value_text).collect();
+            (!parts.is_empty()).then(|| parts.join(","))
+        }
+        OwnedValue::Null | OwnedValue::Bytes(_) | OwnedValue::Object(_) => None,
+    }
+}
+
+fn field_text(fields: &HashMap<String, Vec<OwnedValue>>, name: &str) -> Option<String> {
+    let parts: Vec<String> = fields.get(name)?.iter().filter_map(value_text).collect();
+    (!parts.is_empty()).then(|| parts.join("\n"))
+}
+
+/*
+Read every live document stored in the Tantivy index at `path` and add it
+to `engine`. Ids come from `mapping.id_field` when set, otherwise they are
+assigned after the engine's highest id. Only stored fields can be imported;
+fields that were indexed without STORED are not recoverable.
+*/
+pub fn import_index(
+    path: impl AsRef<Path>,
+    engine: &mut SearchEngine,
+    mapping: &FieldMapping,
+) -> Result<ImportStats, MSErrors> {
+    let index = Index::open_in_dir(path.as_ref()).map_err(tantivy_error)?;
+    let schema = index.schema();
+    let reader = index.reader().map_err(tantivy_error)?;
+    let searcher = reader.searcher();
+
+    let mut stats = ImportStats::default();
+    let mut next_id = engine.next_document_id();
+    for segment in searcher.segment_readers() {
+        let store = segment
+            .get_store_reader(STORE_CACHE_BLOCKS)
+            .map_err(|e| tantivy_error(e.into()))?;
+        for stored in store.iter::<TantivyDocument>(segment.alive_bitset()) {
+            let stored = stored.map_err(tantivy_error)?;
+            let fields: HashMap<String, Vec<OwnedValue>> =
+                stored.to_named_doc(&schema).0.into_iter().collect();
+
+            let title = mapping
+                .title_field
+                .as_deref()
+                .and_then(|name| field_text(&fields, name))
+                .unwrap_or_default();
+            let content = mapping
+                .content_fields
+                .iter()
+                .filter_map(|name| field_text(&fields, name))
+                .collect::<Vec<_>>()
+                .join("\n");
+            if title.is_empty() && content.is_empty() {
+                stats.skipped += 1;
+                continue;
+            }
+
+            let id = match &mapping.id_field {
+                Some(name) => match fields.get(name).and_then(|values| values.first()) {
+                    Some(OwnedValue::U64(id)) => *id,
+                    _ => {
+                        return Err(MSErrors::ParseError(format!(
+                            "document without a u64 \"{}\" field",
+                            name
+                        )));
+                    }
+                },
+                None => {
+                    next_id += 1;
+                    next_id - 1
+                }
+            };
+
+            let mapped = |name: &String| {
+                Some(name) == mapping.id_field.as_ref()
+                    || Some(name) == mapping.title_field.as_ref()
+                    || mapping.content_fields.contains(name)
+            };
+            let metadata = fields
+                .keys()
+                .filter(|name| !mapped(name))
+                .filter(|name| {
+                    mapping
+                        .metadata_fields
+                        .as_ref()
+                        .is_none_or(|allowed| allowed.contains(name))
+                })
+                .filter_map(|name| Some((name.clone(), field_text(&fields, name)?)))
+                .collect();
+
+            engine.add_document(Document {
+                id,
+                title,
+                content,
+                metadata,
+            })?;
+            stats.imported += 1;
+        }
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::IndexSettings;
+    use ::tantivy::doc;
+    use ::tantivy::schema::{STORED, Schema, TEXT};
+
+    #[test]
+    fn test_import_index() {
+        let dir = std::env::temp_dir().join(format!("mini-search-tantivy-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut builder = Schema::builder();
+        let title = builder.add_text_field("title", TEXT | STORED);
+        let body = builder.add_text_field("body", TEXT | STORED);
+        let lang = builder.add_text_field("lang", STORED);
+        let hidden = builder.add_text_field("hidden", TEXT);
+        let index = Index::create_in_dir(&dir, builder.build()).unwrap();
+        let mut writer = index.writer(15_000_000).unwrap();
+        writer
+            .add_document(doc!(title => "Rust", body => "Systems programming", lang => "en", hidden => "secret"))
+            .unwrap();
+        writer
+            .add_document(doc!(title => "Tantivy", body => "A search engine library"))
+            .unwrap();
+        writer.add_document(doc!(lang => "fr")).unwrap();
+        writer.commit().unwrap();
+
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        let stats = import_index(&dir, &mut engine, &FieldMapping::default()).unwrap();
+        assert_eq!(
+            stats,
+            ImportStats {
+                imported: 2,
+                skipped: 1
+            }
+        );
+
+        let results = engine.search("search engine", 10);
+        assert_eq!(results.documents.len(), 1);
+        assert_eq!(results.documents[0].title, "Tantivy");
+        let rust = &engine.search("systems", 10).documents[0];
+        assert_eq!(rust.metadata.get("lang").map(String::as_str), Some("en"));
+        assert!(!rust.metadata.contains_key("hidden"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::sync::Arc;
+
+use super::{BlockPostings, DocId, Posting};
+
+/*
+Term -> postings map, sorted by term. Terms iterate in byte order, so
+anything built by walking the index (dumps, exports) comes out the same
+whatever order documents were added in, and terms sharing a prefix or
+falling in a range are found by scanning only that part of the map.
+Postings are shared between clones and copied on write, so a clone of the
+index copies only the postings changed after it.
+*/
+#[derive(Clone, Default)]
+pub(super) struct TermDictionary {
+    terms: BTreeMap<String, Arc<BlockPostings>>,
+}
+
+impl TermDictionary {
+    pub fn get(&self, term: &str) -> Option<&BlockPostings> {
+        self.terms.get(term).map(Arc::as_ref)
+    }
+
+    pub fn insert(&mut self, term: String, posting: Posting) {
+        Arc::make_mut(self.terms.entry(term).or_default()).insert(posting);
+    }
+
+    // Drop the document's posting for `term`, and the term once it has none left
+    pub fn remove_posting(&mut self, term: &str, doc_id: DocId) {
+        let emptied = self.terms.get_mut(term).is_some_and(|postings| {
+            let postings = Arc::make_mut(postings);
+            postings.remove(doc_id);
+            postings.is_empty()
+        });
+        if emptied {
+            self.terms.remove(term);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &BlockPostings)> {
+        self.terms
+            .iter()
+            .map(|(term, postings)| (term, postings.as_ref()))
+    }
+
+    // Terms within the bounds, in order
+    pub fn range<'a>(
+        &'a self,
+        start: Bound<&str>,
+        end: Bound<&str>,
+    ) -> impl Iterator<Item = (&'a String, &'a BlockPostings)> {
+        self.terms
+            .range::<str, _>((start, end))
+            .map(|(term, postings)| (term, postings.as_ref()))
+    }
+
+    // Terms starting with `prefix`, in order
+    pub fn prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (&'a String, &'a BlockPostings)> {
+        self.range(Bound::Included(prefix), Bound::Unbounded)
+            .take_while(move |(term, _)| term.starts_with(prefix))
+    }
+}
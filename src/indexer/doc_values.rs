@@ -0,0 +1,394 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
+
+use super::{DocId, GeoPoint};
+use crate::document::date::parse_rfc3339;
+use crate::settings::{FieldType, Schema};
+
+// Numbers are indexed as is, RFC 3339 dates as seconds since the epoch
+pub fn parse_number(value: &str) -> Option<f64> {
+    match value.trim().parse::<f64>() {
+        Ok(number) => number.is_finite().then_some(number),
+        Err(_) => parse_rfc3339(value),
+    }
+}
+
+// Values of a multi-valued field, written separated by commas
+pub fn split_values(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+// Column-oriented copy of document metadata, keyed by field then document
+#[derive(Default, Clone)]
+pub struct DocValues {
+    columns: HashMap<String, HashMap<DocId, String>>,
+    numbers: HashMap<String, NumericColumn>, // Values that parse as numbers
+    points: HashMap<String, HashMap<DocId, GeoPoint>>, // Values of geo-point fields
+    multi: HashSet<String>, // Multi-valued fields, whose values are split on commas
+}
+
+// A value of a numeric column and its document, ordered by value then document
+#[derive(Clone, Copy)]
+struct NumericEntry(f64, DocId);
+
+impl NumericEntry {
+    // -0.0 is stored as 0.0, so it sorts with the values equal to it
+    fn new(value: f64, doc_id: DocId) -> Self {
+        NumericEntry(value + 0.0, doc_id)
+    }
+}
+
+impl Ord for NumericEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+impl PartialOrd for NumericEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for NumericEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for NumericEntry {}
+
+// Numeric values of one field, also kept sorted for range lookups
+#[derive(Default, Clone)]
+struct NumericColumn {
+    values: HashMap<DocId, Vec<f64>>, // In ascending order, one unless multi-valued
+    sorted: BTreeSet<NumericEntry>,
+}
+
+impl NumericColumn {
+    fn insert(&mut self, doc_id: DocId, mut values: Vec<f64>) {
+        self.remove(doc_id);
+        values.sort_by(f64::total_cmp);
+        values.dedup();
+        for &value in &values {
+            self.sorted.insert(NumericEntry::new(value, doc_id));
+        }
+        self.values.insert(doc_id, values);
+    }
+
+    fn remove(&mut self, doc_id: DocId) {
+        for value in self.values.remove(&doc_id).unwrap_or_default() {
+            self.sorted.remove(&NumericEntry::new(value, doc_id));
+        }
+    }
+}
+
+impl DocValues {
+    pub fn new() -> Self {
+        DocValues {
+            columns: HashMap::new(),
+            numbers: HashMap::new(),
+            points: HashMap::new(),
+            multi: HashSet::new(),
+        }
+    }
+
+    pub fn add_document(&mut self, doc_id: DocId, metadata: &HashMap<String, String>) {
+        self.add_with_schema(doc_id, metadata, &Schema::default());
+    }
+
+    /*
+    Add metadata typed by `schema`: unindexed fields are skipped and only
+    numeric and date fields are indexed as numbers and geo-point fields as
+    points. Fields missing from the schema are indexed as numbers whenever
+    they parse as one. Each value of a multi-valued field is indexed on its
+    own; a number that does not parse is left out.
+    */
+    pub fn add_with_schema(
+        &mut self,
+        doc_id: DocId,
+        metadata: &HashMap<String, String>,
+        schema: &Schema,
+    ) {
+        for (field, value) in metadata {
+            let def = schema.get(field);
+            let field_type = def.map(|def| (def.indexed, def.field_type));
+            let numeric = match field_type {
+                Some((false, _) | (_, FieldType::Vector)) => continue,
+                Some((true, field_type)) => {
+                    matches!(field_type, FieldType::Numeric | FieldType::Date)
+                }
+                None => true,
+            };
+            self.columns
+                .entry(field.clone())
+                .or_default()
+                .insert(doc_id, value.clone());
+            if let Some(raw) = def.and_then(|def| schema.raw_field(def)) {
+                if def.is_some_and(|def| def.multi) {
+                    self.multi.insert(raw.clone());
+                }
+                self.columns
+                    .entry(raw)
+                    .or_default()
+                    .insert(doc_id, value.clone());
+            }
+            if matches!(field_type, Some((_, FieldType::GeoPoint)))
+                && let Some(point) = GeoPoint::parse(value)
+            {
+                self.points
+                    .entry(field.clone())
+                    .or_default()
+                    .insert(doc_id, point);
+            }
+            let numbers: Vec<f64> = if def.is_some_and(|def| def.multi) {
+                self.multi.insert(field.clone());
+                split_values(value).filter_map(parse_number).collect()
+            } else {
+                parse_number(value).into_iter().collect()
+            };
+            if numeric && !numbers.is_empty() {
+                self.numbers
+                    .entry(field.clone())
+                    .or_default()
+                    .insert(doc_id, numbers);
+            }
+        }
+    }
+
+    pub fn remove_document(&mut self, doc_id: DocId) {
+        self.columns.retain(|_, column| {
+            column.remove(&doc_id);
+            !column.is_empty()
+        });
+        self.numbers.retain(|_, column| {
+            column.remove(doc_id);
+            !column.values.is_empty()
+        });
+        self.points.retain(|_, column| {
+            column.remove(&doc_id);
+            !column.is_empty()
+        });
+    }
+
+    pub fn get(&self, field: &str, doc_id: DocId) -> Option<&str> {
+        self.columns
+            .get(field)
+            .and_then(|column| column.get(&doc_id))
+            .map(String::as_str)
+    }
+
+    // Every value of `field`: the value itself, or its parts if multi-valued
+    pub fn values(&self, field: &str, doc_id: DocId) -> Vec<&str> {
+        match self.get(field, doc_id) {
+            Some(value) if self.multi.contains(field) => split_values(value).collect(),
+            Some(value) => vec![value],
+            None => Vec::new(),
+        }
+    }
+
+    /*
+    Numeric value of `field`, if it was indexed as a number or date; the
+    smallest one for a multi-valued field.
+    */
+    pub fn get_number(&self, field: &str, doc_id: DocId) -> Option<f64> {
+        self.get_numbers(field, doc_id).first().copied()
+    }
+
+    // Every numeric value of `field`, in ascending order
+    pub fn get_numbers(&self, field: &str, doc_id: DocId) -> &[f64] {
+        self.numbers
+            .get(field)
+            .and_then(|column| column.values.get(&doc_id))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    // Location of `field`, if it is a geo-point field
+    pub fn get_point(&self, field: &str, doc_id: DocId) -> Option<GeoPoint> {
+        self.points
+            .get(field)
+            .and_then(|column| column.get(&doc_id))
+            .copied()
+    }
+
+    /*
+    Documents with a value of `field` within the inclusive bounds, in
+    ascending order of their smallest such value.
+    */
+    pub fn range(&self, field: &str, min: Option<f64>, max: Option<f64>) -> Vec<DocId> {
+        let Some(column) = self.numbers.get(field) else {
+            return Vec::new();
+        };
+        if min.is_some_and(f64::is_nan)
+            || max.is_some_and(f64::is_nan)
+            || min.zip(max).is_some_and(|(min, max)| min > max)
+        {
+            return Vec::new();
+        }
+        let lower = min.map_or(Bound::Unbounded, |min| {
+            Bound::Included(NumericEntry::new(min, DocId(0)))
+        });
+        let upper = max.map_or(Bound::Unbounded, |max| {
+            Bound::Included(NumericEntry::new(max, DocId(u64::MAX)))
+        });
+        let mut seen = HashSet::new();
+        column
+            .sorted
+            .range((lower, upper))
+            .map(|entry| entry.1)
+            .filter(|&doc_id| seen.insert(doc_id))
+            .collect()
+    }
+
+    // Count the values of `field` over a set of documents, each value once per document
+    pub fn facet_counts(
+        &self,
+        field: &str,
+        doc_ids: impl Iterator<Item = DocId>,
+    ) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        if self.columns.contains_key(field) {
+            for doc_id in doc_ids {
+                let values: HashSet<&str> = self.values(field, doc_id).into_iter().collect();
+                for value in values {
+                    *counts.entry(value.to_string()).or_insert(0) += 1;
+                }
+            }
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_facet_counts() {
+        let mut doc_values = DocValues::new();
+        doc_values.add_document(DocId(1), &metadata(&[("category", "news")]));
+        doc_values.add_document(DocId(2), &metadata(&[("category", "blog")]));
+        doc_values.add_document(DocId(3), &metadata(&[("category", "news"), ("lang", "en")]));
+
+        let counts = doc_values.facet_counts("category", [1, 2, 3].map(DocId).into_iter());
+        assert_eq!(
+            counts,
+            HashMap::from([("news".to_string(), 2), ("blog".to_string(), 1)])
+        );
+        assert!(
+            doc_values
+                .facet_counts("missing", [1].map(DocId).into_iter())
+                .is_empty()
+        );
+
+        doc_values.remove_document(DocId(3));
+        assert_eq!(doc_values.get("category", DocId(3)), None);
+        assert_eq!(doc_values.get("category", DocId(1)), Some("news"));
+    }
+
+    #[test]
+    fn test_numeric_range() {
+        let mut doc_values = DocValues::new();
+        doc_values.add_document(DocId(1), &metadata(&[("price", "25"), ("name", "mug")]));
+        doc_values.add_document(DocId(2), &metadata(&[("price", "9.5")]));
+        doc_values.add_document(DocId(3), &metadata(&[("price", "50")]));
+        doc_values.add_document(DocId(4), &metadata(&[("price", "n/a")]));
+        doc_values.add_document(
+            DocId(5),
+            &metadata(&[("published", "2024-05-01T12:00:00Z")]),
+        );
+
+        assert_eq!(doc_values.get_number("price", DocId(2)), Some(9.5));
+        assert_eq!(doc_values.get_number("price", DocId(4)), None);
+        assert_eq!(doc_values.get_number("name", DocId(1)), None);
+        assert_eq!(
+            doc_values.get_number("published", DocId(5)),
+            Some(1714564800.0)
+        );
+        assert_eq!(
+            doc_values.range("price", Some(10.0), Some(50.0)),
+            vec![DocId(1), DocId(3)]
+        );
+        assert_eq!(
+            doc_values.range("price", None, Some(25.0)),
+            vec![DocId(2), DocId(1)]
+        );
+        assert_eq!(
+            doc_values.range("price", Some(60.0), Some(10.0)),
+            Vec::<DocId>::new()
+        );
+        assert!(doc_values.range("missing", None, None).is_empty());
+        doc_values.add_document(DocId(6), &metadata(&[("delta", "-0")]));
+        assert_eq!(doc_values.range("delta", Some(0.0), None), vec![DocId(6)]);
+        assert!(doc_values.range("price", Some(f64::NAN), None).is_empty());
+
+        doc_values.remove_document(DocId(1));
+        assert_eq!(
+            doc_values.range("price", None, None),
+            vec![DocId(2), DocId(3)]
+        );
+    }
+
+    #[test]
+    fn test_schema_types() {
+        use crate::settings::FieldDef;
+
+        let schema = Schema::new()
+            .field(FieldDef::new("zip", FieldType::Keyword))
+            .field(FieldDef::new("secret", FieldType::Text).indexed(false));
+        let mut doc_values = DocValues::new();
+        doc_values.add_with_schema(
+            DocId(1),
+            &metadata(&[("zip", "01234"), ("secret", "x"), ("price", "5")]),
+            &schema,
+        );
+        assert_eq!(doc_values.get("zip", DocId(1)), Some("01234"));
+        assert_eq!(doc_values.get_number("zip", DocId(1)), None);
+        assert_eq!(doc_values.get("secret", DocId(1)), None);
+        assert_eq!(doc_values.get_number("price", DocId(1)), Some(5.0));
+    }
+
+    #[test]
+    fn test_multi_valued() {
+        use crate::settings::FieldDef;
+
+        let schema = Schema::new()
+            .field(FieldDef::new("tags", FieldType::Keyword).multi(true))
+            .field(FieldDef::new("sizes", FieldType::Numeric).multi(true));
+        let mut doc_values = DocValues::new();
+        let first = metadata(&[("tags", "rust, search,rust"), ("sizes", "42, 7")]);
+        doc_values.add_with_schema(DocId(1), &first, &schema);
+        let second = metadata(&[("tags", "search"), ("sizes", "12")]);
+        doc_values.add_with_schema(DocId(2), &second, &schema);
+
+        assert_eq!(
+            doc_values.values("tags", DocId(1)),
+            ["rust", "search", "rust"]
+        );
+        let counts = doc_values.facet_counts("tags", [1, 2].map(DocId).into_iter());
+        assert_eq!(
+            counts,
+            HashMap::from([("rust".to_string(), 1), ("search".to_string(), 2)])
+        );
+        assert_eq!(doc_values.get_numbers("sizes", DocId(1)), [7.0, 42.0]);
+        assert_eq!(doc_values.get_number("sizes", DocId(1)), Some(7.0));
+        assert_eq!(
+            doc_values.range("sizes", Some(5.0), None),
+            vec![DocId(1), DocId(2)]
+        );
+        assert_eq!(doc_values.range("sizes", Some(40.0), None), vec![DocId(1)]);
+        doc_values.remove_document(DocId(1));
+        assert_eq!(doc_values.range("sizes", None, None), vec![DocId(2)]);
+    }
+}
@@ -0,0 +1,53 @@
+// Mean radius of the earth, in kilometers
+const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+// A location in degrees of latitude and longitude
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    // None if the latitude or longitude is out of range
+    pub fn new(lat: f64, lon: f64) -> Option<Self> {
+        let valid = (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon);
+        valid.then_some(GeoPoint { lat, lon })
+    }
+
+    /*
+    Parse a point written as `lat,lon`, e.g. `52.52, 13.405`, the way
+    geo-point metadata values are written. None if either number is
+    invalid or out of range.
+    */
+    pub fn parse(value: &str) -> Option<Self> {
+        let (lat, lon) = value.split_once(',')?;
+        GeoPoint::new(lat.trim().parse().ok()?, lon.trim().parse().ok()?)
+    }
+
+    // Great-circle distance in kilometers, by the haversine formula
+    pub fn distance_km(&self, other: &GeoPoint) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.lon - self.lon).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        let berlin = GeoPoint::parse("52.5200, 13.4050").unwrap();
+        let paris = GeoPoint::parse("48.8566,2.3522").unwrap();
+        assert!((berlin.distance_km(&paris) - 878.0).abs() < 2.0);
+        assert_eq!(berlin.distance_km(&berlin), 0.0);
+        assert_eq!(GeoPoint::parse("91, 0"), None);
+        assert_eq!(GeoPoint::parse("52.52"), None);
+        assert_eq!(GeoPoint::parse("north, east"), None);
+    }
+}
@@ -1,93 +1,408 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::Bound;
 
-use super::tokenizer::{Language, Token, Tokenizer};
+use roaring::RoaringTreemap;
 
-pub struct PostingsList {
-    pub documents: Vec<DocumentPosting>,
-    pub total_frequency: u64,
+use super::tokenizer::{Token, TokenKind, Tokenizer};
+use crate::settings::IndexRecordOption;
+
+mod dictionary;
+mod doc_values;
+mod geo;
+mod postings;
+mod statistics;
+mod vectors;
+
+use dictionary::TermDictionary;
+pub use doc_values::DocValues;
+pub(crate) use doc_values::{parse_number, split_values};
+pub use geo::GeoPoint;
+pub use postings::{BlockPostings, PostingsCursor};
+pub use statistics::Statistics;
+pub use vectors::{VectorIndex, format_vector, parse_vector};
+
+/*
+Id of an indexed document: the `Document::id` it was added with. Kept
+distinct from plain integers so ids are never truncated or mixed up with
+positions and counts; convert with `From` where documents enter or leave
+the index.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DocId(pub u64);
+
+impl DocId {
+    // Highest valid id, leaving room for the id after it
+    pub const MAX: DocId = DocId(u64::MAX - 1);
+
+    // The id, or None when it is above `DocId::MAX`
+    pub fn new(id: u64) -> Option<Self> {
+        (id <= DocId::MAX.0).then_some(DocId(id))
+    }
+
+    pub fn get(self) -> u64 {
+        self.0
+    }
 }
 
-pub struct DocumentPosting {
-    pub doc_id: u64,
-    pub term_frequency: u32,
-    pub positions: Vec<usize>, // for phrase queries
+impl From<u64> for DocId {
+    fn from(id: u64) -> Self {
+        DocId(id)
+    }
 }
 
-// Type alias for document ID
-pub type DocId = usize;
+impl From<DocId> for u64 {
+    fn from(id: DocId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for DocId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
 
 // Represents a single occurrence of a term in a document
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Posting {
     pub doc_id: DocId,
-    pub positions: Vec<usize>,        // Token positions in the document
-    pub offsets: Vec<(usize, usize)>, // Character offsets in the original text
+    pub term_freq: u32, // Occurrences in the document, 1 unless frequencies are recorded
+    pub positions: Vec<usize>, // Token positions in the document, empty unless recorded
+    pub offsets: Vec<(usize, usize)>, // Character offsets in the original text, as positions
+    pub kind: TokenKind, // Kind of the term's first occurrence
+    pub payloads: Vec<(usize, Vec<u8>)>, // Payloads by position, kept with positions
+}
+
+// Occurrences of one term within a document, and the kind of the first one
+#[derive(Default)]
+struct TermOccurrences {
+    positions: Vec<usize>,
+    offsets: Vec<(usize, usize)>,
+    payloads: Vec<(usize, Vec<u8>)>,
+    kind: TokenKind,
 }
 
+// Indexed term of `term` within a metadata field
+pub fn field_term(field: &str, term: &str) -> String {
+    format!("{}:{}", field, term)
+}
+
+#[derive(Clone)]
 pub struct InvertedIndex {
-    index: HashMap<String, Vec<Posting>>,
+    index: TermDictionary,
+    doc_terms: HashMap<DocId, Vec<String>>, // Terms of each document, for removal
     tokenizer: Tokenizer,
+    record: IndexRecordOption, // What postings of document text keep
+    field_records: HashMap<String, IndexRecordOption>, // Same for metadata fields, when not the default
 }
 
 impl InvertedIndex {
     // Create a new inverted index with a given tokenizer
     pub fn new(tokenizer: Tokenizer) -> Self {
         InvertedIndex {
-            index: HashMap::new(),
+            index: TermDictionary::default(),
+            doc_terms: HashMap::new(),
             tokenizer,
+            record: IndexRecordOption::default(),
+            field_records: HashMap::new(),
         }
     }
 
+    // Set what the postings of document text keep; call before indexing
+    pub fn with_record(mut self, record: IndexRecordOption) -> Self {
+        self.record = record;
+        self
+    }
+
+    // Set what the postings of a metadata field keep; call before indexing
+    pub fn set_field_record(&mut self, field: &str, record: IndexRecordOption) {
+        self.field_records.insert(field.to_string(), record);
+    }
+
+    pub fn record(&self) -> IndexRecordOption {
+        self.record
+    }
+
     // Add a document to the index
     pub fn index_document(&mut self, doc_id: DocId, text: &str) {
         let tokens = self.tokenizer.tokenize(text);
+        self.index_tokens(doc_id, tokens);
+    }
 
-        // Group tokens by term to build postings
-        let mut term_positions: HashMap<String, (Vec<usize>, Vec<(usize, usize)>)> = HashMap::new();
+    // Add a document from tokens produced by this index's tokenizer
+    pub fn index_tokens(&mut self, doc_id: DocId, tokens: Vec<Token>) {
+        let terms = self.add_postings(doc_id, tokens, self.record);
+        self.doc_terms.insert(doc_id, terms);
+    }
+
+    /*
+    Add postings for the terms of one metadata field of an indexed document.
+    Terms are stored as `field:term` so they only match field queries, and
+    offsets are relative to the field value.
+    */
+    pub fn index_field(&mut self, doc_id: DocId, field: &str, mut tokens: Vec<Token>) {
+        for token in &mut tokens {
+            token.term = field_term(field, &token.term);
+        }
+        let record = self.field_records.get(field).copied().unwrap_or_default();
+        let terms = self.add_postings(doc_id, tokens, record);
+        self.doc_terms.entry(doc_id).or_default().extend(terms);
+    }
 
+    /*
+    Group tokens by term into one posting each, returning the terms in byte
+    order, so that anything rebuilt from a document's terms comes out the
+    same on every run.
+    */
+    fn add_postings(
+        &mut self,
+        doc_id: DocId,
+        tokens: Vec<Token>,
+        record: IndexRecordOption,
+    ) -> Vec<String> {
+        let mut occurrences: BTreeMap<String, TermOccurrences> = BTreeMap::new();
         for token in tokens {
-            let entry = term_positions
+            let entry = occurrences
                 .entry(token.term)
-                .or_insert((Vec::new(), Vec::new()));
-            entry.0.push(token.position);
-            entry.1.push(token.offset);
+                .or_insert_with(|| TermOccurrences {
+                    kind: token.kind,
+                    ..TermOccurrences::default()
+                });
+            entry.positions.push(token.position);
+            entry.offsets.push(token.offset);
+            if let Some(payload) = token.payload {
+                entry.payloads.push((token.position, payload));
+            }
         }
 
-        // Update the inverted index
-        for (term, (positions, offsets)) in term_positions {
+        // Update the inverted index, keeping only what `record` asks for
+        let mut terms = Vec::with_capacity(occurrences.len());
+        for (term, mut occurrence) in occurrences {
+            let term_freq = if record.has_freqs() {
+                occurrence.positions.len() as u32
+            } else {
+                1
+            };
+            if !record.has_positions() {
+                occurrence = TermOccurrences {
+                    kind: occurrence.kind,
+                    ..TermOccurrences::default()
+                };
+            }
             let posting = Posting {
                 doc_id,
-                positions,
-                offsets,
+                term_freq,
+                positions: occurrence.positions,
+                offsets: occurrence.offsets,
+                kind: occurrence.kind,
+                payloads: occurrence.payloads,
             };
-            self.index
-                .entry(term)
-                .or_insert_with(Vec::new)
-                .push(posting);
+            terms.push(term.clone());
+            self.index.insert(term, posting);
         }
+        terms
+    }
+
+    // Remove a document's postings, returning false if it was not indexed
+    pub fn remove_document(&mut self, doc_id: DocId) -> bool {
+        let Some(terms) = self.doc_terms.remove(&doc_id) else {
+            return false;
+        };
+        for term in terms {
+            self.index.remove_posting(&term, doc_id);
+        }
+        true
     }
 
     // Retrieve postings for a given term
-    pub fn get_postings(&self, term: &str) -> Option<&Vec<Posting>> {
+    pub fn get_postings(&self, term: &str) -> Option<&BlockPostings> {
         self.index.get(term)
     }
 
-    // Get all indexed terms (useful for debugging or query processing)
+    // The posting of `term` in one document
+    pub fn posting(&self, term: &str, doc_id: DocId) -> Option<&Posting> {
+        self.get_postings(term)?.get(doc_id)
+    }
+
+    /*
+    Documents containing every one of `terms`. The rarest term's postings
+    are walked while cursors over the others leapfrog to each of its
+    documents, skipping whole blocks, so a rare term ANDed with a common
+    one only touches a few blocks of the common term's list.
+    */
+    pub fn intersect(&self, terms: &[String]) -> RoaringTreemap {
+        let Some(mut lists) = terms
+            .iter()
+            .map(|term| self.get_postings(term))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return RoaringTreemap::new();
+        };
+        lists.sort_by_key(|postings| postings.len());
+        let Some((rarest, others)) = lists.split_first() else {
+            return RoaringTreemap::new();
+        };
+        let mut cursors: Vec<PostingsCursor> = others.iter().map(|p| p.cursor()).collect();
+        rarest
+            .iter()
+            .map(|posting| posting.doc_id)
+            .filter(|&doc_id| {
+                cursors
+                    .iter_mut()
+                    .all(|cursor| cursor.advance_to(doc_id) == Some(doc_id))
+            })
+            .map(DocId::get)
+            .collect()
+    }
+
+    /*
+    Documents containing any of `terms`. The postings lists are merged in
+    document order, taking the lowest current document of all lists from a
+    heap, so each document is visited once and the ids arrive sorted.
+    */
+    pub fn union(&self, terms: &[String]) -> RoaringTreemap {
+        let lists: Vec<&BlockPostings> = terms
+            .iter()
+            .filter_map(|term| self.get_postings(term))
+            .collect();
+        let mut heap: BinaryHeap<Reverse<(DocId, usize, usize)>> = lists
+            .iter()
+            .enumerate()
+            .filter_map(|(list, postings)| Some(Reverse((postings.first()?.doc_id, list, 0))))
+            .collect();
+        let mut docs = Vec::new();
+        while let Some(Reverse((doc_id, list, at))) = heap.pop() {
+            if docs.last() != Some(&doc_id.get()) {
+                docs.push(doc_id.get());
+            }
+            if let Some(next) = lists[list][..].get(at + 1) {
+                heap.push(Reverse((next.doc_id, list, at + 1)));
+            }
+        }
+        RoaringTreemap::from_sorted_iter(docs).expect("merged postings are sorted")
+    }
+
+    // Ids of the documents containing `term`, as a bitmap for set operations
+    pub fn term_docs(&self, term: &str) -> RoaringTreemap {
+        let docs = self.get_postings(term).into_iter().flatten();
+        RoaringTreemap::from_sorted_iter(docs.map(|posting| posting.doc_id.get()))
+            .expect("postings are sorted by document id")
+    }
+
+    // Occurrences of `term` across all documents, 0 if it is not indexed
+    pub fn total_frequency(&self, term: &str) -> u64 {
+        self.index
+            .get(term)
+            .map_or(0, BlockPostings::total_frequency)
+    }
+
+    // Distinct terms of an indexed document
+    pub fn document_terms(&self, doc_id: DocId) -> Option<&[String]> {
+        self.doc_terms.get(&doc_id).map(Vec::as_slice)
+    }
+
+    // Get all indexed terms in byte order (useful for debugging or query processing)
     pub fn terms(&self) -> impl Iterator<Item = &String> {
-        self.index.keys()
+        self.index.iter().map(|(term, _)| term)
+    }
+
+    // Indexed terms starting with `prefix`, in order
+    pub fn terms_with_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a String> {
+        self.index.prefix(prefix).map(|(term, _)| term)
+    }
+
+    // Indexed terms within the bounds, in order
+    pub fn terms_in_range(
+        &self,
+        start: Bound<&str>,
+        end: Bound<&str>,
+    ) -> impl Iterator<Item = &String> {
+        self.index.range(start, end).map(|(term, _)| term)
+    }
+
+    /*
+    Indexed terms matching a pattern where `*` stands for any run of
+    characters and `?` for a single one. Only the terms sharing the
+    pattern's literal prefix are checked, so patterns should not start
+    with a wildcard on large indexes.
+    */
+    pub fn wildcard_terms<'a>(&'a self, pattern: &'a str) -> impl Iterator<Item = &'a String> {
+        let literal = pattern.find(['*', '?']).map_or(pattern, |i| &pattern[..i]);
+        self.terms_with_prefix(literal)
+            .filter(move |term| wildcard_match(pattern, term))
+    }
+
+    /*
+    Write every term with its postings, one term per line as
+    `term<TAB>doc:pos,pos doc:pos`, in term order. Postings without
+    positions are written with their frequency instead, as `doc*freq`. The
+    output only depends on the indexed documents, not on the order they
+    were added in.
+    */
+    pub fn write_dump(&self, out: &mut impl Write) -> io::Result<()> {
+        for (term, postings) in self.index.iter() {
+            write!(out, "{}\t", term)?;
+            for (i, posting) in postings.iter().enumerate() {
+                let separator = if i == 0 { "" } else { " " };
+                write!(out, "{}{}", separator, posting.doc_id)?;
+                if posting.positions.is_empty() {
+                    write!(out, "*{}", posting.term_freq)?;
+                } else {
+                    let positions: Vec<String> =
+                        posting.positions.iter().map(usize::to_string).collect();
+                    write!(out, ":{}", positions.join(","))?;
+                }
+            }
+            writeln!(out)?;
+        }
+        Ok(())
     }
 }
 
+// Whether `text` matches a `*` and `?` pattern as a whole
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), text.chars().collect());
+    let (mut p, mut t) = (0, 0);
+    let mut star = None; // Pattern index after the last `*`, and the text index it matched up to
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Let the last `*` swallow one more character
+                Some((after, matched)) => {
+                    star = Some((after, matched + 1));
+                    p = after;
+                    t = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tokenizer::{AnalyzerPreset, BaseTokenizer, Language, TokenFilter};
 
     #[test]
     fn test_index_document() {
-        let tokenizer = Tokenizer::new(Language::English);
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
         let mut index = InvertedIndex::new(tokenizer);
 
-        let doc_id = 1;
+        let doc_id = DocId(1);
         let text = "The quick fox jumps";
         index.index_document(doc_id, text);
 
@@ -97,9 +412,12 @@ mod tests {
         assert_eq!(
             postings[0],
             Posting {
-                doc_id: 1,
+                doc_id: DocId(1),
+                term_freq: 1,
                 positions: vec![0],
                 offsets: vec![(4, 9)],
+                kind: TokenKind::Word,
+                payloads: Vec::new(),
             }
         );
 
@@ -109,9 +427,12 @@ mod tests {
         assert_eq!(
             postings[0],
             Posting {
-                doc_id: 1,
+                doc_id: DocId(1),
+                term_freq: 1,
                 positions: vec![1],
                 offsets: vec![(10, 13)],
+                kind: TokenKind::Word,
+                payloads: Vec::new(),
             }
         );
 
@@ -121,9 +442,12 @@ mod tests {
         assert_eq!(
             postings[0],
             Posting {
-                doc_id: 1,
+                doc_id: DocId(1),
+                term_freq: 1,
                 positions: vec![2],
                 offsets: vec![(14, 19)],
+                kind: TokenKind::Word,
+                payloads: Vec::new(),
             }
         );
 
@@ -133,11 +457,11 @@ mod tests {
 
     #[test]
     fn test_multiple_documents() {
-        let tokenizer = Tokenizer::new(Language::English);
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
         let mut index = InvertedIndex::new(tokenizer);
 
-        index.index_document(1, "The quick fox");
-        index.index_document(2, "Fox jumps high");
+        index.index_document(DocId(1), "The quick fox");
+        index.index_document(DocId(2), "Fox jumps high");
 
         // Check postings for "fox"
         let postings = index.get_postings("fox").unwrap();
@@ -145,17 +469,23 @@ mod tests {
         assert_eq!(
             postings[0],
             Posting {
-                doc_id: 1,
+                doc_id: DocId(1),
+                term_freq: 1,
                 positions: vec![1],
                 offsets: vec![(10, 13)],
+                kind: TokenKind::Word,
+                payloads: Vec::new(),
             }
         );
         assert_eq!(
             postings[1],
             Posting {
-                doc_id: 2,
+                doc_id: DocId(2),
+                term_freq: 1,
                 positions: vec![0],
                 offsets: vec![(0, 3)],
+                kind: TokenKind::Word,
+                payloads: Vec::new(),
             }
         );
 
@@ -165,19 +495,131 @@ mod tests {
         assert_eq!(
             postings[0],
             Posting {
-                doc_id: 2,
+                doc_id: DocId(2),
+                term_freq: 1,
                 positions: vec![1],
                 offsets: vec![(4, 9)],
+                kind: TokenKind::Word,
+                payloads: Vec::new(),
             }
         );
     }
 
+    #[test]
+    fn test_remove_document() {
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
+        let mut index = InvertedIndex::new(tokenizer);
+
+        index.index_document(DocId(1), "The quick fox");
+        index.index_document(DocId(2), "Fox jumps high, fox");
+        assert_eq!(index.total_frequency("fox"), 3);
+        assert!(index.remove_document(DocId(1)));
+        assert!(!index.remove_document(DocId(1)));
+
+        let postings = index.get_postings("fox").unwrap();
+        assert_eq!(postings.len(), 1);
+        assert_eq!(postings[0].doc_id, DocId(2));
+        assert_eq!(index.total_frequency("fox"), 2);
+        assert_eq!(index.get_postings("quick"), None);
+    }
+
+    #[test]
+    fn test_intersect() {
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
+        let mut index = InvertedIndex::new(tokenizer);
+        for doc_id in (0..1000).rev() {
+            let text = if doc_id % 250 == 7 {
+                "common rare"
+            } else {
+                "common"
+            };
+            index.index_document(DocId(doc_id), text);
+        }
+        let terms = ["common".to_string(), "rare".to_string()];
+        let docs: Vec<u64> = index.intersect(&terms).iter().collect();
+        assert_eq!(docs, [7, 257, 507, 757]);
+        assert_eq!(index.posting("rare", DocId(507)).unwrap().positions, [1]);
+        assert!(
+            index
+                .intersect(&["common".to_string(), "absent".to_string()])
+                .is_empty()
+        );
+
+        index.index_document(DocId(1500), "rare");
+        let terms = ["rare".to_string(), "absent".to_string(), "rare".to_string()];
+        let docs: Vec<u64> = index.union(&terms).iter().collect();
+        assert_eq!(docs, [7, 257, 507, 757, 1500]);
+        let all = index.union(&["common".to_string(), "rare".to_string()]);
+        assert_eq!(all.len(), 1001);
+    }
+
+    #[test]
+    fn test_term_lookups() {
+        let tokenizer = Tokenizer::with_preset(AnalyzerPreset::Simple, Language::English).unwrap();
+        let mut index = InvertedIndex::new(tokenizer);
+        index.index_document(DocId(1), "search searcher seat sea season index");
+
+        let prefixed: Vec<&String> = index.terms_with_prefix("sea").collect();
+        assert_eq!(prefixed, ["sea", "search", "searcher", "season", "seat"]);
+        let ranged: Vec<&String> = index
+            .terms_in_range(Bound::Included("search"), Bound::Excluded("seat"))
+            .collect();
+        assert_eq!(ranged, ["search", "searcher", "season"]);
+        let matched: Vec<&String> = index.wildcard_terms("sea?").collect();
+        assert_eq!(matched, ["seat"]);
+        let matched: Vec<&String> = index.wildcard_terms("s*r").collect();
+        assert_eq!(matched, ["searcher"]);
+        let matched: Vec<&String> = index.wildcard_terms("*x").collect();
+        assert_eq!(matched, ["index"]);
+        assert!(wildcard_match("a*b*c", "aXbYbZc"));
+        assert!(!wildcard_match("a*b", "aXbY"));
+    }
+
     #[test]
     fn test_empty_document() {
-        let tokenizer = Tokenizer::new(Language::English);
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
         let mut index = InvertedIndex::new(tokenizer);
 
-        index.index_document(1, "");
+        index.index_document(DocId(1), "");
         assert_eq!(index.terms().count(), 0);
     }
+
+    // Marks numbers with their value as a payload
+    #[derive(Debug)]
+    struct NumberPayloads;
+
+    impl TokenFilter for NumberPayloads {
+        fn filter(&self, mut token: Token, out: &mut Vec<Token>) {
+            if token.kind == TokenKind::Number {
+                token.payload = token
+                    .term
+                    .parse::<u32>()
+                    .ok()
+                    .map(|n| n.to_le_bytes().to_vec());
+            }
+            out.push(token);
+        }
+    }
+
+    #[test]
+    fn test_token_kinds_and_payloads() {
+        let tokenizer = Tokenizer::new(Language::English)
+            .unwrap()
+            .with_base(BaseTokenizer::Alphanumeric)
+            .with_filter(NumberPayloads);
+        let mut index = InvertedIndex::new(tokenizer);
+        index.index_document(DocId(1), "Errors 404 and 500, then 404 again");
+
+        let posting = index.posting("404", DocId(1)).unwrap();
+        assert_eq!(posting.kind, TokenKind::Number);
+        assert_eq!(
+            posting.payloads,
+            [
+                (1, 404u32.to_le_bytes().to_vec()),
+                (3, 404u32.to_le_bytes().to_vec())
+            ]
+        );
+        let posting = index.posting("error", DocId(1)).unwrap();
+        assert_eq!((posting.kind, posting.payloads.len()), (TokenKind::Word, 0));
+    }
 }
@@ -0,0 +1,191 @@
+use std::ops::Deref;
+use std::slice;
+
+use super::{DocId, Posting};
+
+// Postings per block, each with one skip entry
+const BLOCK_SIZE: usize = 64;
+
+/*
+Postings of one term sorted by document id, in fixed-size blocks. `skips`
+holds the last document id of each block, so lookups and cursors jump to
+the only block that can hold a document instead of scanning every posting
+before it. Derefs to the postings as a slice. The term's total frequency
+is kept up to date alongside.
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockPostings {
+    postings: Vec<Posting>,
+    skips: Vec<DocId>, // Last document id of each block
+    total_freq: u64,   // Sum of the postings' term frequencies
+}
+
+impl BlockPostings {
+    // Add a posting in document order; appending the highest id is cheapest
+    pub fn insert(&mut self, posting: Posting) {
+        let at = self.position(posting.doc_id, 0);
+        self.total_freq += posting.term_freq as u64;
+        self.postings.insert(at, posting);
+        self.relink(at / BLOCK_SIZE);
+    }
+
+    // Drop the document's postings, returning false if it had none
+    pub fn remove(&mut self, doc_id: DocId) -> bool {
+        let start = self.position(doc_id, 0);
+        let end = start
+            + self.postings[start..]
+                .iter()
+                .take_while(|p| p.doc_id == doc_id)
+                .count();
+        if start == end {
+            return false;
+        }
+        let removed: u64 = self
+            .postings
+            .drain(start..end)
+            .map(|p| p.term_freq as u64)
+            .sum();
+        self.total_freq -= removed;
+        self.relink(start / BLOCK_SIZE);
+        true
+    }
+
+    pub fn get(&self, doc_id: DocId) -> Option<&Posting> {
+        self.postings
+            .get(self.position(doc_id, 0))
+            .filter(|p| p.doc_id == doc_id)
+    }
+
+    // Occurrences of the term across all documents
+    pub fn total_frequency(&self) -> u64 {
+        self.total_freq
+    }
+
+    pub fn cursor(&self) -> PostingsCursor<'_> {
+        PostingsCursor {
+            postings: self,
+            at: 0,
+        }
+    }
+
+    /*
+    Index of the first posting at or after `doc_id`, searching from `from`.
+    The skips are galloped over, doubling the step until a block ending at
+    or after `doc_id` is passed, then binary searched within the last
+    step; a cursor moving to a nearby document reads only a few skips.
+    */
+    fn position(&self, doc_id: DocId, from: usize) -> usize {
+        let first_block = (from / BLOCK_SIZE).min(self.skips.len());
+        let skips = &self.skips[first_block..];
+        let mut bound = 1;
+        while bound <= skips.len() && skips[bound - 1] < doc_id {
+            bound *= 2;
+        }
+        let low = bound / 2;
+        let high = bound.min(skips.len());
+        let block = first_block + low + skips[low..high].partition_point(|&last| last < doc_id);
+        let start = (block * BLOCK_SIZE).max(from);
+        let end = ((block + 1) * BLOCK_SIZE).min(self.postings.len());
+        if start >= end {
+            return self.postings.len();
+        }
+        start + self.postings[start..end].partition_point(|p| p.doc_id < doc_id)
+    }
+
+    // Rebuild the skip entries of `block` and every block after it
+    fn relink(&mut self, block: usize) {
+        self.skips.truncate(block);
+        let rest = self.postings.get(block * BLOCK_SIZE..).unwrap_or_default();
+        self.skips.extend(
+            rest.chunks(BLOCK_SIZE)
+                .map(|chunk| chunk[chunk.len() - 1].doc_id),
+        );
+    }
+}
+
+impl Deref for BlockPostings {
+    type Target = [Posting];
+
+    fn deref(&self) -> &[Posting] {
+        &self.postings
+    }
+}
+
+impl<'a> IntoIterator for &'a BlockPostings {
+    type Item = &'a Posting;
+    type IntoIter = slice::Iter<'a, Posting>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.postings.iter()
+    }
+}
+
+// Forward-only position in a postings list, for intersecting lists
+pub struct PostingsCursor<'a> {
+    postings: &'a BlockPostings,
+    at: usize,
+}
+
+impl PostingsCursor<'_> {
+    // Document of the current posting, None once past the end
+    pub fn doc(&self) -> Option<DocId> {
+        self.postings.postings.get(self.at).map(|p| p.doc_id)
+    }
+
+    /*
+    Move to the first posting at or after `target` and return its
+    document. Whole blocks ending before the target are skipped without
+    being read.
+    */
+    pub fn advance_to(&mut self, target: DocId) -> Option<DocId> {
+        if self.doc()? < target {
+            self.at = self.postings.position(target, self.at);
+        }
+        self.doc()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::TokenKind;
+
+    fn posting(doc_id: u64) -> Posting {
+        Posting {
+            doc_id: DocId(doc_id),
+            term_freq: 1,
+            positions: vec![0],
+            offsets: vec![(0, 1)],
+            kind: TokenKind::Word,
+            payloads: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_block_postings() {
+        let mut postings = BlockPostings::default();
+        // Out of order, spanning several blocks
+        for doc_id in (0..500).rev().step_by(3).chain([3]) {
+            postings.insert(posting(doc_id));
+        }
+        let ids: Vec<u64> = postings.iter().map(|p| p.doc_id.get()).collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(postings.skips.len(), postings.len().div_ceil(BLOCK_SIZE));
+        assert_eq!(postings.total_frequency(), postings.len() as u64);
+        assert_eq!(postings.get(DocId(496)).map(|p| p.doc_id.get()), Some(496));
+        assert!(postings.get(DocId(497)).is_none());
+
+        let mut cursor = postings.cursor();
+        assert_eq!(cursor.advance_to(DocId(0)), Some(DocId(1)));
+        assert_eq!(cursor.advance_to(DocId(300)), Some(DocId(301)));
+        assert_eq!(cursor.advance_to(DocId(302)), Some(DocId(304)));
+        assert_eq!(cursor.advance_to(DocId(301)), Some(DocId(304)));
+        assert_eq!(cursor.advance_to(DocId(500)), None);
+
+        assert!(postings.remove(DocId(301)));
+        assert!(!postings.remove(DocId(301)));
+        assert_eq!(postings.total_frequency(), postings.len() as u64);
+        assert_eq!(postings.cursor().advance_to(DocId(300)), Some(DocId(304)));
+        assert_eq!(postings.skips.len(), postings.len().div_ceil(BLOCK_SIZE));
+    }
+}
@@ -0,0 +1,90 @@
+use super::InvertedIndex;
+
+/*
+Collection-level term statistics, for scorers and tools that need corpus
+frequencies without depending on how an index stores its postings.
+Frequencies of unknown terms are 0. Implementors may estimate them, e.g.
+a ranker scoring with approximate statistics.
+*/
+pub trait Statistics {
+    // Number of documents containing `term`
+    fn doc_freq(&self, term: &str) -> usize;
+
+    // Occurrences of `term` across all documents
+    fn total_term_freq(&self, term: &str) -> u64;
+
+    // Number of documents in the collection
+    fn doc_count(&self) -> usize;
+
+    // Number of tokens in the collection, the sum of all document lengths
+    fn total_length(&self) -> u64;
+
+    /*
+    Highest field boost of any document, at least 1. Occurrences are
+    weighted by their field's boost, so a term frequency may exceed the
+    document length by this factor.
+    */
+    fn max_field_boost(&self) -> f64 {
+        1.0
+    }
+
+    // Mean document length in tokens, 0 for an empty collection
+    fn avg_doc_length(&self) -> f64 {
+        match self.doc_count() {
+            0 => 0.0,
+            docs => self.total_length() as f64 / docs as f64,
+        }
+    }
+}
+
+/*
+Exact statistics of the postings. The index does not keep document
+lengths, so its total length is the sum of the term frequencies, which
+walks the whole dictionary.
+*/
+impl Statistics for InvertedIndex {
+    fn doc_freq(&self, term: &str) -> usize {
+        self.get_postings(term).map_or(0, |postings| postings.len())
+    }
+
+    fn total_term_freq(&self, term: &str) -> u64 {
+        self.total_frequency(term)
+    }
+
+    fn doc_count(&self) -> usize {
+        self.doc_terms.len()
+    }
+
+    fn total_length(&self) -> u64 {
+        self.index
+            .iter()
+            .map(|(_, postings)| postings.total_frequency())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::DocId;
+    use crate::tokenizer::{Language, Tokenizer};
+
+    #[test]
+    fn test_index_statistics() {
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
+        let mut index = InvertedIndex::new(tokenizer);
+        assert_eq!(index.avg_doc_length(), 0.0);
+        index.index_document(DocId(1), "The quick fox");
+        index.index_document(DocId(2), "Fox jumps high, fox");
+        assert_eq!(
+            (index.doc_freq("fox"), index.total_term_freq("fox")),
+            (2, 3)
+        );
+        assert_eq!(
+            (index.doc_freq("wolf"), index.total_term_freq("wolf")),
+            (0, 0)
+        );
+        assert_eq!((index.doc_count(), index.total_length()), (2, 6));
+        assert_eq!(index.avg_doc_length(), 3.0);
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use super::DocId;
+
+/*
+Parse a vector written as numbers separated by commas or whitespace,
+optionally in square brackets, e.g. `[0.12, -0.5, 3]`. None if there are
+no numbers or one is invalid or not finite.
+*/
+pub fn parse_vector(value: &str) -> Option<Vec<f32>> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix('[')
+        .and_then(|value| value.strip_suffix(']'))
+        .unwrap_or(value);
+    let vector: Vec<f32> = value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|number| !number.is_empty())
+        .map(|number| number.parse::<f32>().ok().filter(|n| n.is_finite()))
+        .collect::<Option<_>>()?;
+    (!vector.is_empty()).then_some(vector)
+}
+
+// Write a vector as `parse_vector` reads it, to put it in document metadata
+pub fn format_vector(vector: &[f32]) -> String {
+    let numbers: Vec<String> = vector.iter().map(f32::to_string).collect();
+    format!("[{}]", numbers.join(", "))
+}
+
+/*
+Dense vectors of one field, such as embeddings computed by the caller.
+Vectors are kept at unit length, so the dot product of two is their
+cosine similarity. Nearest neighbors are found by brute force, comparing
+the query with every vector.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct VectorIndex {
+    dims: usize,
+    vectors: HashMap<DocId, Vec<f32>>,
+}
+
+impl VectorIndex {
+    pub fn new(dims: usize) -> Self {
+        VectorIndex {
+            dims,
+            vectors: HashMap::new(),
+        }
+    }
+
+    pub fn dims(&self) -> usize {
+        self.dims
+    }
+
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    // Add or replace the vector of a document; false if it has the wrong length
+    pub fn insert(&mut self, doc_id: DocId, vector: &[f32]) -> bool {
+        if vector.len() != self.dims {
+            return false;
+        }
+        self.vectors.insert(doc_id, normalized(vector));
+        true
+    }
+
+    pub fn remove(&mut self, doc_id: DocId) {
+        self.vectors.remove(&doc_id);
+    }
+
+    /*
+    The `k` documents accepted by `accept` whose vectors are most similar
+    to `query`, most similar first, with their cosine similarity. Ties go
+    to the lower id. Empty if the query has the wrong length.
+    */
+    pub fn nearest(
+        &self,
+        query: &[f32],
+        k: usize,
+        accept: impl Fn(DocId) -> bool,
+    ) -> Vec<(DocId, f32)> {
+        if query.len() != self.dims {
+            return Vec::new();
+        }
+        let query = normalized(query);
+        let mut hits: Vec<(DocId, f32)> = self
+            .vectors
+            .iter()
+            .filter(|&(&doc_id, _)| accept(doc_id))
+            .map(|(&doc_id, vector)| (doc_id, dot(&query, vector)))
+            .collect();
+        hits.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        hits.truncate(k);
+        hits
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+// Scaled to unit length; a zero vector stays zero and is similar to nothing
+fn normalized(vector: &[f32]) -> Vec<f32> {
+    let norm = dot(vector, vector).sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|x| x / norm).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest() {
+        assert_eq!(parse_vector("[1, -0.5 2e1]"), Some(vec![1.0, -0.5, 20.0]));
+        assert_eq!(
+            parse_vector(&format_vector(&[0.25, 3.0])),
+            Some(vec![0.25, 3.0])
+        );
+        assert_eq!(parse_vector("[]"), None);
+        assert_eq!(parse_vector("1, x"), None);
+        assert_eq!(parse_vector("1, NaN"), None);
+
+        let mut index = VectorIndex::new(2);
+        assert!(index.insert(DocId(1), &[1.0, 0.0]));
+        assert!(index.insert(DocId(2), &[0.0, 3.0]));
+        assert!(index.insert(DocId(3), &[2.0, 2.0]));
+        assert!(!index.insert(DocId(4), &[1.0]));
+        let hits = index.nearest(&[1.0, 0.1], 2, |_| true);
+        assert_eq!(hits.iter().map(|(id, _)| id.0).collect::<Vec<_>>(), [1, 3]);
+        assert!((hits[1].1 - 0.774).abs() < 1e-3);
+        let hits = index.nearest(&[1.0, 0.1], 5, |doc_id| doc_id != DocId(1));
+        assert_eq!(hits.iter().map(|(id, _)| id.0).collect::<Vec<_>>(), [3, 2]);
+        assert!(index.nearest(&[1.0, 0.0, 0.0], 5, |_| true).is_empty());
+        index.remove(DocId(3));
+        assert_eq!(index.len(), 2);
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::document::DocumentParser;
+use crate::document::html::HtmlDocumentParser;
+use crate::document::json::JsonDocumentParser;
+use crate::document::markdown::MarkdownDocumentParser;
+use crate::document::text::TextDocumentParser;
+use crate::errors::MSErrors;
+use crate::searcher::SearchEngine;
+
+// Outcome of a crawl
+#[derive(Debug, Default)]
+pub struct CrawlStats {
+    pub indexed: usize,
+    pub unsupported: usize, // Files with no parser for their extension
+    pub failed: Vec<(PathBuf, MSErrors)>, // Files that could not be read, parsed or indexed
+}
+
+/*
+Walks a directory tree and indexes every file it has a parser for, chosen
+by extension (case-insensitive). Files are visited in path order, so ids
+are assigned reproducibly from the engine's next free id. Each document
+gets its file path as `path` metadata, and the file stem as title when
+the parser found none. Hidden files and directories are skipped unless
+`include_hidden` is set.
+*/
+pub struct Crawler {
+    parsers: HashMap<String, Box<dyn DocumentParser>>,
+    include_hidden: bool,
+    max_file_size: Option<u64>, // Larger files are reported as failed
+}
+
+impl Default for Crawler {
+    fn default() -> Self {
+        Crawler::new()
+    }
+}
+
+impl Crawler {
+    // A crawler for txt, md/markdown, html/htm and json files
+    pub fn new() -> Self {
+        Crawler {
+            parsers: HashMap::new(),
+            include_hidden: false,
+            max_file_size: None,
+        }
+        .parser("txt", TextDocumentParser::new())
+        .parser("md", MarkdownDocumentParser::new())
+        .parser("markdown", MarkdownDocumentParser::new())
+        .parser("html", HtmlDocumentParser::new())
+        .parser("htm", HtmlDocumentParser::new())
+        .parser("json", JsonDocumentParser::default())
+    }
+
+    // Parse files with this extension using `parser`, replacing any previous one
+    pub fn parser(mut self, extension: &str, parser: impl DocumentParser + 'static) -> Self {
+        self.parsers
+            .insert(extension.to_ascii_lowercase(), Box::new(parser));
+        self
+    }
+
+    // Stop handling files with this extension
+    pub fn without(mut self, extension: &str) -> Self {
+        self.parsers.remove(&extension.to_ascii_lowercase());
+        self
+    }
+
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
+
+    /*
+    Index every supported file under `root`. Problems with single files are
+    collected in the stats; only an unreadable root is an error.
+    */
+    pub fn crawl(
+        &self,
+        root: impl AsRef<Path>,
+        engine: &mut SearchEngine,
+    ) -> Result<CrawlStats, MSErrors> {
+        let root = root.as_ref();
+        let mut files = Vec::new();
+        if root.is_file() {
+            files.push(root.to_path_buf());
+        } else {
+            let entries = fs::read_dir(root).map_err(|e| MSErrors::io(root, e))?;
+            self.collect_files(entries, &mut files);
+        }
+        files.sort();
+
+        let mut stats = CrawlStats::default();
+        let mut next_id = engine.next_document_id();
+        for path in files {
+            let Some(parser) = self.parser_for(&path) else {
+                stats.unsupported += 1;
+                continue;
+            };
+            match self.index_file(&path, parser, next_id, engine) {
+                Ok(()) => {
+                    next_id += 1;
+                    stats.indexed += 1;
+                }
+                Err(e) => stats.failed.push((path, e)),
+            }
+        }
+        Ok(stats)
+    }
+
+    fn parser_for(&self, path: &Path) -> Option<&dyn DocumentParser> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        self.parsers.get(&extension).map(|parser| parser.as_ref())
+    }
+
+    // Gather files depth-first; unreadable subdirectories are skipped
+    fn collect_files(&self, entries: fs::ReadDir, files: &mut Vec<PathBuf>) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if hidden && !self.include_hidden {
+                continue;
+            }
+            // Symlinks are not followed, so cycles cannot occur
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if let Ok(entries) = fs::read_dir(&path) {
+                    self.collect_files(entries, files);
+                }
+            } else if file_type.is_file() {
+                files.push(path);
+            }
+        }
+    }
+
+    fn index_file(
+        &self,
+        path: &Path,
+        parser: &dyn DocumentParser,
+        id: u64,
+        engine: &mut SearchEngine,
+    ) -> Result<(), MSErrors> {
+        let io_error = |e| MSErrors::io(path, e);
+        let size = fs::metadata(path).map_err(io_error)?.len();
+        if let Some(max) = self.max_file_size.filter(|&max| size > max) {
+            return Err(MSErrors::IndexingError(format!(
+                "file is {} bytes, over the {} byte limit",
+                size, max
+            )));
+        }
+        let bytes = fs::read(path).map_err(io_error)?;
+        let text = String::from_utf8(bytes)
+            .map_err(|_| MSErrors::ParseError("file is not valid UTF-8".to_string()))?;
+
+        let mut document = parser.parse(&text)?;
+        document.id = id;
+        if let Some(stem) = path.file_stem().filter(|_| document.title.is_empty()) {
+            document.title = stem.to_string_lossy().into_owned();
+        }
+        document
+            .metadata
+            .insert("path".to_string(), path.display().to_string());
+        engine.add_document(document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::IndexSettings;
+
+    #[test]
+    fn test_crawl() {
+        let root = std::env::temp_dir().join(format!("mini-search-crawl-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("notes/deep")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("a.txt"), "Plain text about foxes").unwrap();
+        fs::write(root.join("notes/b.MD"), "# Foxes\nThey are quick").unwrap();
+        fs::write(
+            root.join("notes/deep/c.html"),
+            "<title>Fox page</title><p>Red fox</p>",
+        )
+        .unwrap();
+        fs::write(
+            root.join("d.json"),
+            r#"{"title": "Fox", "content": "json"}"#,
+        )
+        .unwrap();
+        fs::write(root.join("e.json"), "not json").unwrap();
+        fs::write(root.join("f.png"), [0u8, 1, 2]).unwrap();
+        fs::write(root.join(".git/g.txt"), "hidden fox").unwrap();
+
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        let stats = Crawler::new().crawl(&root, &mut engine).unwrap();
+        assert_eq!(stats.indexed, 4);
+        assert_eq!(stats.unsupported, 1);
+        assert_eq!(stats.failed.len(), 1);
+        assert!(stats.failed[0].0.ends_with("e.json"));
+
+        // Ids follow path order
+        let first = engine.get_document(0).unwrap();
+        assert_eq!(first.title, "a");
+        assert_eq!(
+            first.metadata.get("path").unwrap(),
+            &root.join("a.txt").display().to_string()
+        );
+        assert_eq!(engine.get_document(2).unwrap().title, "Foxes");
+        assert_eq!(engine.get_document(3).unwrap().title, "Fox page");
+        assert_eq!(engine.search("fox", 10).total_matches, 4);
+
+        let stats = Crawler::new()
+            .without("json")
+            .include_hidden(true)
+            .max_file_size(16)
+            .crawl(&root, &mut engine)
+            .unwrap();
+        assert_eq!(stats.unsupported, 3);
+        assert_eq!(stats.indexed, 1);
+        assert_eq!(engine.get_document(4).unwrap().content, "hidden fox");
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,270 @@
+use std::fs::File;
+use std::io::{BufRead, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crate::document::DocumentParser;
+use crate::errors::MSErrors;
+use crate::searcher::SearchEngine;
+
+mod crawl;
+
+pub use crawl::{CrawlStats, Crawler};
+
+// Path reported for IO errors of a stream source
+const STREAM_PATH: &str = "<stream>";
+
+enum Source {
+    File {
+        path: PathBuf,
+        offset: u64,      // Bytes consumed so far
+        pending: Vec<u8>, // Trailing partial line waiting for its newline
+    },
+    Reader {
+        reader: Box<dyn BufRead + Send>,
+        finished: bool,
+    },
+}
+
+/*
+Follows a growing log file (or a stream such as stdin) and indexes each new line.
+Only complete lines are indexed; a partial trailing line is kept until its
+newline arrives. If the file shrinks (truncation or rotation in place) the
+tailer starts again from the beginning.
+*/
+pub struct LogTailer<P: DocumentParser> {
+    source: Source,
+    parser: P,
+    next_id: Option<u64>,
+    errors: usize,
+}
+
+impl<P: DocumentParser> LogTailer<P> {
+    // Tail a file from its beginning
+    pub fn file(path: impl Into<PathBuf>, parser: P) -> Self {
+        LogTailer::new(
+            Source::File {
+                path: path.into(),
+                offset: 0,
+                pending: Vec::new(),
+            },
+            parser,
+        )
+    }
+
+    // Read lines from a stream until it is closed, e.g. `std::io::stdin().lock()`
+    pub fn reader(reader: impl BufRead + Send + 'static, parser: P) -> Self {
+        LogTailer::new(
+            Source::Reader {
+                reader: Box::new(reader),
+                finished: false,
+            },
+            parser,
+        )
+    }
+
+    fn new(source: Source, parser: P) -> Self {
+        LogTailer {
+            source,
+            parser,
+            next_id: None,
+            errors: 0,
+        }
+    }
+
+    // Skip the existing file contents and only index lines appended from now on
+    pub fn from_end(mut self) -> Result<Self, MSErrors> {
+        if let Source::File { path, offset, .. } = &mut self.source {
+            *offset = std::fs::metadata(&*path)
+                .map_err(|e| MSErrors::io(&*path, e))?
+                .len();
+        }
+        Ok(self)
+    }
+
+    // Number of lines the parser rejected or the engine failed to index
+    pub fn errors(&self) -> usize {
+        self.errors
+    }
+
+    // True once a stream source has reached end of input; files never finish
+    pub fn is_finished(&self) -> bool {
+        matches!(self.source, Source::Reader { finished: true, .. })
+    }
+
+    /*
+    Index all complete lines available right now, returning how many were
+    indexed. Lines that fail to parse or index are counted in `errors` and
+    skipped, so one bad line does not lose the rest of the batch.
+    */
+    pub fn poll(&mut self, engine: &mut SearchEngine) -> Result<usize, MSErrors> {
+        let lines = self.read_lines()?;
+        let mut next_id = self.next_id.unwrap_or_else(|| engine.next_document_id());
+        let mut indexed = 0;
+        for line in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match self.parser.parse(&line) {
+                Ok(mut document) => {
+                    document.id = next_id;
+                    next_id += 1;
+                    match engine.add_document(document) {
+                        Ok(()) => indexed += 1,
+                        Err(_) => self.errors += 1,
+                    }
+                }
+                Err(_) => self.errors += 1,
+            }
+        }
+        self.next_id = Some(next_id);
+        Ok(indexed)
+    }
+
+    // Poll every `interval` until `stop` is set or a stream source is exhausted
+    pub fn follow(
+        &mut self,
+        engine: &mut SearchEngine,
+        interval: Duration,
+        stop: &AtomicBool,
+    ) -> Result<usize, MSErrors> {
+        let mut total = 0;
+        while !stop.load(Ordering::Relaxed) && !self.is_finished() {
+            let indexed = self.poll(engine)?;
+            total += indexed;
+            if indexed == 0 && !self.is_finished() {
+                thread::sleep(interval);
+            }
+        }
+        Ok(total)
+    }
+
+    fn read_lines(&mut self) -> Result<Vec<String>, MSErrors> {
+        match &mut self.source {
+            Source::File {
+                path,
+                offset,
+                pending,
+            } => {
+                let io_error = |e| MSErrors::io(&*path, e);
+                let mut file = File::open(&*path).map_err(io_error)?;
+                let len = file.metadata().map_err(io_error)?.len();
+                if len < *offset {
+                    *offset = 0;
+                    pending.clear();
+                }
+                file.seek(SeekFrom::Start(*offset)).map_err(io_error)?;
+                let read = file.read_to_end(pending).map_err(io_error)?;
+                *offset += read as u64;
+
+                // Hand out every complete line, keep the remainder
+                let Some(last_newline) = pending.iter().rposition(|&b| b == b'\n') else {
+                    return Ok(Vec::new());
+                };
+                let rest = pending.split_off(last_newline + 1);
+                let complete = std::mem::replace(pending, rest);
+                Ok(String::from_utf8_lossy(&complete)
+                    .lines()
+                    .map(String::from)
+                    .collect())
+            }
+            Source::Reader { reader, finished } => {
+                let mut line = String::new();
+                let read = reader
+                    .read_line(&mut line)
+                    .map_err(|e| MSErrors::io(STREAM_PATH, e))?;
+                if read == 0 {
+                    *finished = true;
+                    return Ok(Vec::new());
+                }
+                Ok(vec![line])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::log::LogLineParser;
+    use crate::searcher::{Capacity, EvictionPolicy};
+    use crate::settings::IndexSettings;
+    use std::io::Write;
+
+    #[test]
+    fn test_tail_file() {
+        let path =
+            std::env::temp_dir().join(format!("mini-search-tail-{}.log", std::process::id()));
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "2024-05-01T12:00:00Z disk full").unwrap();
+        write!(file, "2024-05-01T12:00:01Z network").unwrap();
+
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        let mut tailer = LogTailer::file(&path, LogLineParser::default());
+        assert_eq!(tailer.poll(&mut engine).unwrap(), 1);
+
+        // The partial line is indexed once it is completed
+        writeln!(file, " down").unwrap();
+        assert_eq!(tailer.poll(&mut engine).unwrap(), 1);
+        assert_eq!(
+            engine.search("network", 10).documents[0].content,
+            "network down"
+        );
+
+        // Truncation restarts from the beginning of the file
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "rotated").unwrap();
+        assert_eq!(tailer.poll(&mut engine).unwrap(), 1);
+        assert_eq!(engine.document_count(), 3);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_poll_skips_lines_that_fail_to_index() {
+        let path = std::env::temp_dir().join(format!(
+            "mini-search-tail-errors-{}.log",
+            std::process::id()
+        ));
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "2024-05-01T12:00:00Z first").unwrap();
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        let mut tailer = LogTailer::file(&path, LogLineParser::default());
+        assert_eq!(tailer.poll(&mut engine).unwrap(), 1);
+
+        // The id the tailer uses next is taken, so only that line fails
+        let taken = engine.next_document_id();
+        let mut document = LogLineParser::default().parse("taken").unwrap();
+        document.id = taken;
+        engine.add_document(document).unwrap();
+        writeln!(file, "2024-05-01T12:00:01Z second").unwrap();
+        writeln!(file, "2024-05-01T12:00:02Z third").unwrap();
+        assert_eq!(tailer.poll(&mut engine).unwrap(), 1);
+        assert_eq!(tailer.errors(), 1);
+        assert_eq!(engine.search("third", 10).total_matches, 1);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_follow_reader_with_ephemeral_engine() {
+        let input = "1714564800 first\n1714564801 second\n\n1714564802 third\n";
+        let capacity = Capacity {
+            max_documents: Some(2),
+            ..Capacity::default()
+        };
+        let mut engine =
+            SearchEngine::ephemeral(IndexSettings::default(), capacity, EvictionPolicy::Fifo)
+                .unwrap();
+        let mut tailer = LogTailer::reader(std::io::Cursor::new(input), LogLineParser::default());
+
+        let stop = AtomicBool::new(false);
+        let indexed = tailer
+            .follow(&mut engine, Duration::from_millis(1), &stop)
+            .unwrap();
+        assert_eq!(indexed, 3);
+        assert!(tailer.is_finished());
+        assert_eq!(engine.document_count(), 2);
+        assert_eq!(engine.search("first", 10).total_matches, 0);
+    }
+}
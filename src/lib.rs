@@ -1,7 +1,20 @@
+pub mod cluster;
 pub mod document;
 pub mod errors;
+pub mod eval;
+pub mod highlight;
+pub mod import;
 pub mod indexer;
+pub mod ingest;
 pub mod rank;
 pub mod searcher;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod settings;
 pub mod storage;
+pub mod suggest;
+#[cfg(feature = "test-util")]
+pub mod testing;
 pub mod tokenizer;
+#[cfg(feature = "tui")]
+pub mod tui;
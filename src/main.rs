@@ -1,3 +1,284 @@
-fn main() {
-    println!("Hello, world!");
+use std::env;
+use std::path::Path;
+use std::process::ExitCode;
+
+use mini_search::ingest::Crawler;
+use mini_search::searcher::{SearchEngine, read_query_log, replay};
+use mini_search::settings::IndexSettings;
+use mini_search::storage::{FORMAT_VERSION, SETTINGS_FILE};
+
+const USAGE: &str = "usage: mini-search index <index-dir> <dir|file>
+       mini-search search <index-dir> <query> [limit]
+       mini-search stats <index-dir>
+       mini-search compact <index-dir>
+       mini-search tui <index-dir>
+       mini-search serve <index-dir> [address] [facet-field...]
+       mini-search migrate <old-index-dir> <new-index-dir>
+       mini-search upgrade <index-dir>
+       mini-search replay <index-dir> <query-log>";
+// Where the demo server listens when no address is given
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+// Results printed by `search` when no limit is given
+const DEFAULT_LIMIT: usize = 10;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.as_slice() {
+        [command, index, path] if command == "index" => index_files(index, path),
+        [command, index, query, rest @ ..] if command == "search" && rest.len() <= 1 => {
+            match rest
+                .first()
+                .map_or(Ok(DEFAULT_LIMIT), |limit| limit.parse())
+            {
+                Ok(limit) => search(index, query, limit),
+                Err(_) => {
+                    eprintln!("the limit must be a number");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        [command, index] if command == "stats" => stats(index),
+        [command, index] if command == "compact" => compact(index),
+        [command, index] if command == "tui" => tui(index),
+        [command, index, rest @ ..] if command == "serve" => {
+            let address = rest.first().map_or(DEFAULT_ADDRESS, String::as_str);
+            serve(index, address, rest.get(1..).unwrap_or_default().to_vec())
+        }
+        [command, old, new] if command == "migrate" => migrate(old, new),
+        [command, index] if command == "upgrade" => upgrade(index),
+        [command, index, log] if command == "replay" => replay_log(index, log),
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Open an index, reporting failures on stderr
+fn open(index: &str) -> Option<SearchEngine> {
+    match SearchEngine::open(index) {
+        Ok(engine) => Some(engine),
+        Err(e) => {
+            eprintln!("failed to open {}: {}", index, e);
+            None
+        }
+    }
+}
+
+// Crawl `path` into the index, creating it with default settings if missing
+fn index_files(index: &str, path: &str) -> ExitCode {
+    let opened = if Path::new(index).join(SETTINGS_FILE).exists() {
+        SearchEngine::open(index)
+    } else {
+        SearchEngine::create(index, IndexSettings::default())
+    };
+    let mut engine = match opened {
+        Ok(engine) => engine,
+        Err(e) => {
+            eprintln!("failed to open {}: {}", index, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let stats = match Crawler::new().crawl(path, &mut engine) {
+        Ok(stats) => stats,
+        Err(e) => {
+            eprintln!("failed to read {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    for (file, e) in &stats.failed {
+        eprintln!("skipped {}: {}", file.display(), e);
+    }
+    if let Err(e) = engine.flush() {
+        eprintln!("failed to save {}: {}", index, e);
+        return ExitCode::FAILURE;
+    }
+    println!(
+        "indexed {} files ({} unsupported, {} failed)",
+        stats.indexed,
+        stats.unsupported,
+        stats.failed.len()
+    );
+    ExitCode::SUCCESS
+}
+
+fn search(index: &str, query: &str, limit: usize) -> ExitCode {
+    let Some(engine) = open(index) else {
+        return ExitCode::FAILURE;
+    };
+    let results = engine.search(query, limit);
+    for (rank, (document, score)) in results.documents.iter().zip(&results.scores).enumerate() {
+        println!(
+            "{:>3}. [{}] {}  ({:.3})",
+            rank + 1,
+            document.id,
+            document.title,
+            score
+        );
+    }
+    println!(
+        "{} matches in {} ms",
+        results.total_matches, results.query_time_ms
+    );
+    if !results.suggestions.is_empty() {
+        let suggestions: Vec<&str> = results
+            .suggestions
+            .iter()
+            .map(|s| s.suggestion.as_str())
+            .collect();
+        println!("did you mean: {}", suggestions.join(" "));
+    }
+    ExitCode::SUCCESS
+}
+
+fn stats(index: &str) -> ExitCode {
+    let Some(engine) = open(index) else {
+        return ExitCode::FAILURE;
+    };
+    let segments = engine.cold_segments();
+    let cold: usize = segments.iter().map(|s| s.doc_count).sum();
+    let health = engine.health();
+    println!("format:        {}", FORMAT_VERSION);
+    println!("documents:     {}", engine.document_count() + cold);
+    println!("  in memory:   {}", engine.document_count());
+    println!("  cold:        {} in {} segments", cold, segments.len());
+    println!("next id:       {}", engine.next_document_id());
+    println!("status:        {:?}", health.status);
+    if let Some(bytes) = health.disk_available_bytes {
+        println!("disk free:     {} MiB", bytes / (1024 * 1024));
+    }
+    ExitCode::SUCCESS
+}
+
+fn compact(index: &str) -> ExitCode {
+    let Some(mut engine) = open(index) else {
+        return ExitCode::FAILURE;
+    };
+    match engine.compact() {
+        Ok(removed) => {
+            println!(
+                "removed {} segments, {} left",
+                removed,
+                engine.cold_segments().len()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("compaction failed: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(feature = "tui")]
+fn tui(index: &str) -> ExitCode {
+    let engine = match mini_search::searcher::SearchEngine::open(index) {
+        Ok(engine) => engine,
+        Err(e) => {
+            eprintln!("failed to open {}: {}", index, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    match mini_search::tui::run(&engine) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("terminal error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(not(feature = "tui"))]
+fn tui(_index: &str) -> ExitCode {
+    eprintln!("mini-search was built without the \"tui\" feature");
+    ExitCode::FAILURE
+}
+
+#[cfg(feature = "server")]
+fn serve(index: &str, address: &str, facets: Vec<String>) -> ExitCode {
+    let engine = match mini_search::searcher::SearchEngine::open(index) {
+        Ok(engine) => engine,
+        Err(e) => {
+            eprintln!("failed to open {}: {}", index, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("serving {} on http://{}", index, address);
+    match mini_search::server::Server::new(&engine, facets).serve(address) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("server error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(not(feature = "server"))]
+fn serve(_index: &str, _address: &str, _facets: Vec<String>) -> ExitCode {
+    eprintln!("mini-search was built without the \"server\" feature");
+    ExitCode::FAILURE
+}
+
+fn migrate(old: &str, new: &str) -> ExitCode {
+    match mini_search::storage::migrate(old, new) {
+        Ok(stats) => {
+            println!(
+                "migrated {} documents in {} segments from format {} to {}",
+                stats.documents,
+                stats.segments,
+                stats.from_version,
+                mini_search::storage::FORMAT_VERSION
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("migration failed: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn upgrade(index: &str) -> ExitCode {
+    match mini_search::storage::upgrade(index) {
+        Ok(stats) => {
+            println!(
+                "upgraded {} documents in {} segments from format {} to {}",
+                stats.documents, stats.segments, stats.from_version, FORMAT_VERSION
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("upgrade failed: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Re-run a recorded query log and compare latencies with the recording
+fn replay_log(index: &str, log: &str) -> ExitCode {
+    let Some(engine) = open(index) else {
+        return ExitCode::FAILURE;
+    };
+    let records = match read_query_log(log) {
+        Ok(records) => records,
+        Err(e) => {
+            eprintln!("failed to read {}: {}", log, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let report = replay(&engine, &records);
+    println!("replayed {} queries", report.queries);
+    for (name, summary) in [("recorded", report.recorded), ("replayed", report.replayed)] {
+        println!(
+            "{:>8}: mean {:?}, p50 {:?}, p95 {:?}, max {:?}",
+            name, summary.mean, summary.p50, summary.p95, summary.max
+        );
+    }
+    for (query, recorded, replayed) in &report.changed_matches {
+        println!(
+            "matches changed from {} to {}: {}",
+            recorded, replayed, query
+        );
+    }
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,154 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+// Counters per sketch row; estimates overshoot by about total / WIDTH
+const SKETCH_WIDTH: usize = 4096;
+const SKETCH_DEPTH: usize = 4;
+// Number of document lengths kept to estimate the average length
+const LENGTH_SAMPLE_SIZE: usize = 1024;
+
+/*
+Count-min sketch over terms. Each term increments one counter per row and
+its count is estimated as the smallest of those counters, so estimates
+never fall below the true count while every counter stays non-negative.
+Counters can be decremented, which keeps deletes constant time.
+*/
+#[derive(Clone)]
+pub struct CountMinSketch {
+    counters: Vec<u32>,
+}
+
+impl CountMinSketch {
+    pub fn new() -> Self {
+        CountMinSketch {
+            counters: vec![0; SKETCH_WIDTH * SKETCH_DEPTH],
+        }
+    }
+
+    fn slots(term: &str) -> impl Iterator<Item = usize> + '_ {
+        (0..SKETCH_DEPTH).map(move |row| {
+            let mut hasher = DefaultHasher::new();
+            row.hash(&mut hasher);
+            term.hash(&mut hasher);
+            row * SKETCH_WIDTH + (hasher.finish() as usize) % SKETCH_WIDTH
+        })
+    }
+
+    pub fn increment(&mut self, term: &str) {
+        for slot in CountMinSketch::slots(term) {
+            self.counters[slot] = self.counters[slot].saturating_add(1);
+        }
+    }
+
+    pub fn decrement(&mut self, term: &str) {
+        for slot in CountMinSketch::slots(term) {
+            self.counters[slot] = self.counters[slot].saturating_sub(1);
+        }
+    }
+
+    pub fn estimate(&self, term: &str) -> usize {
+        CountMinSketch::slots(term)
+            .map(|slot| self.counters[slot] as usize)
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+impl Default for CountMinSketch {
+    fn default() -> Self {
+        CountMinSketch::new()
+    }
+}
+
+/*
+Reservoir sample of document lengths. Every indexed length has the same
+chance of being kept, and removals only adjust the document count, so the
+average drifts slightly under heavy deletes.
+*/
+#[derive(Clone)]
+pub struct LengthSample {
+    lengths: Vec<usize>,
+    seen: u64,
+    rng: u64, // xorshift state
+}
+
+impl LengthSample {
+    pub fn new() -> Self {
+        LengthSample {
+            lengths: Vec::with_capacity(LENGTH_SAMPLE_SIZE),
+            seen: 0,
+            rng: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    fn next_random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    pub fn add(&mut self, length: usize) {
+        self.seen += 1;
+        if self.lengths.len() < LENGTH_SAMPLE_SIZE {
+            self.lengths.push(length);
+        } else {
+            let slot = (self.next_random() % self.seen) as usize;
+            if slot < LENGTH_SAMPLE_SIZE {
+                self.lengths[slot] = length;
+            }
+        }
+    }
+
+    pub fn average(&self) -> f64 {
+        if self.lengths.is_empty() {
+            return 0.0;
+        }
+        self.lengths.iter().sum::<usize>() as f64 / self.lengths.len() as f64
+    }
+}
+
+impl Default for LengthSample {
+    fn default() -> Self {
+        LengthSample::new()
+    }
+}
+
+// Approximate corpus statistics kept instead of exact ones
+#[derive(Default, Clone)]
+pub struct ApproximateStats {
+    pub doc_freqs: CountMinSketch,
+    pub lengths: LengthSample,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_min_sketch() {
+        let mut sketch = CountMinSketch::new();
+        for i in 0..2000 {
+            sketch.increment(&format!("term{}", i % 100));
+        }
+        sketch.increment("rare");
+        for i in 0..100 {
+            let estimate = sketch.estimate(&format!("term{}", i));
+            assert!((20..=25).contains(&estimate), "estimate {}", estimate);
+        }
+        assert!(sketch.estimate("rare") >= 1);
+
+        sketch.decrement("rare");
+        assert_eq!(sketch.estimate("missing"), 0);
+    }
+
+    #[test]
+    fn test_length_sample() {
+        let mut sample = LengthSample::new();
+        assert_eq!(sample.average(), 0.0);
+        for i in 0..10_000 {
+            sample.add(if i % 2 == 0 { 10 } else { 30 });
+        }
+        assert!((sample.average() - 20.0).abs() < 2.0);
+    }
+}
@@ -0,0 +1,155 @@
+/*
+Standalone BM25 scoring. Callers supply the corpus and term statistics
+themselves, so candidates retrieved by another system can be reranked
+without building an index. The engine scores with the same formula and the
+statistics of its own index.
+*/
+use super::Similarity;
+use crate::indexer::Statistics;
+
+// Statistics of the whole collection being searched
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CorpusStats {
+    pub total_docs: usize,
+    pub avg_doc_length: f64, // Average number of tokens per document
+}
+
+impl CorpusStats {
+    pub fn of(stats: &dyn Statistics) -> Self {
+        CorpusStats {
+            total_docs: stats.doc_count(),
+            avg_doc_length: stats.avg_doc_length(),
+        }
+    }
+}
+
+// Inverse document frequency, always positive
+pub(super) fn idf(corpus: &CorpusStats, doc_freq: usize) -> f64 {
+    let (n, df) = (corpus.total_docs as f64, doc_freq as f64);
+    ((n - df + 0.5) / (df + 0.5) + 1.0).ln()
+}
+
+// Statistics of one query term for one candidate document
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TermStats {
+    pub doc_freq: usize, // Number of documents containing the term
+    pub term_freq: f64,  // Occurrences in the candidate, possibly weighted
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bm25 {
+    pub k1: f64, // Term frequency saturation
+    pub b: f64,  // Length normalization
+}
+
+impl Default for Bm25 {
+    fn default() -> Self {
+        Bm25 { k1: 1.5, b: 0.75 }
+    }
+}
+
+impl Bm25 {
+    pub fn new(k1: f64, b: f64) -> Self {
+        Bm25 { k1, b }
+    }
+
+    // Inverse document frequency, always positive
+    pub fn idf(&self, corpus: &CorpusStats, doc_freq: usize) -> f64 {
+        idf(corpus, doc_freq)
+    }
+
+    // Largest contribution a term can make, whatever its frequency and the document length
+    pub fn max_term_score(&self, corpus: &CorpusStats, doc_freq: usize) -> f64 {
+        self.idf(corpus, doc_freq) * (self.k1 + 1.0)
+    }
+
+    // Length normalization of a document, 1 at the average length
+    pub fn length_norm(&self, corpus: &CorpusStats, doc_length: usize) -> f64 {
+        let relative_length = if corpus.avg_doc_length > 0.0 {
+            doc_length as f64 / corpus.avg_doc_length
+        } else {
+            1.0
+        };
+        1.0 - self.b + self.b * relative_length
+    }
+
+    // Contribution of a single term to a document's score
+    pub fn term_score(&self, corpus: &CorpusStats, doc_length: usize, term: &TermStats) -> f64 {
+        if term.term_freq <= 0.0 {
+            return 0.0;
+        }
+        let tf = term.term_freq;
+        let numerator = tf * (self.k1 + 1.0);
+        let denominator = tf + self.k1 * self.length_norm(corpus, doc_length);
+        self.idf(corpus, term.doc_freq) * numerator / denominator
+    }
+
+    // Score of a document of `doc_length` tokens for all query terms
+    pub fn score(&self, corpus: &CorpusStats, doc_length: usize, terms: &[TermStats]) -> f64 {
+        terms
+            .iter()
+            .map(|term| self.term_score(corpus, doc_length, term))
+            .sum()
+    }
+}
+
+// The ranker's default similarity
+impl Similarity for Bm25 {
+    fn score_term(
+        &self,
+        stats: &dyn Statistics,
+        term: &str,
+        term_freq: f64,
+        doc_length: usize,
+    ) -> f64 {
+        let term = TermStats {
+            doc_freq: stats.doc_freq(term),
+            term_freq,
+        };
+        self.term_score(&CorpusStats::of(stats), doc_length, &term)
+    }
+
+    fn term_bound(&self, stats: &dyn Statistics, term: &str) -> f64 {
+        self.max_term_score(&CorpusStats::of(stats), stats.doc_freq(term))
+    }
+
+    fn doc_norm(&self, stats: &dyn Statistics, doc_length: usize) -> f64 {
+        self.length_norm(&CorpusStats::of(stats), doc_length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rerank_external_candidates() {
+        let bm25 = Bm25::default();
+        let corpus = CorpusStats {
+            total_docs: 1000,
+            avg_doc_length: 100.0,
+        };
+        let term = |doc_freq, term_freq| TermStats {
+            doc_freq,
+            term_freq,
+        };
+
+        // (id, length, stats for the query terms "rust" and "async")
+        let candidates = [
+            ("a", 100, vec![term(50, 1.0), term(400, 6.0)]),
+            ("b", 100, vec![term(50, 3.0), term(400, 1.0)]),
+            ("c", 400, vec![term(50, 3.0), term(400, 1.0)]),
+        ];
+        let mut scored: Vec<(&str, f64)> = candidates
+            .iter()
+            .map(|(id, length, terms)| (*id, bm25.score(&corpus, *length, terms)))
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        // The rare term outweighs the common one, and long documents are penalized
+        let ids: Vec<&str> = scored.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec!["b", "a", "c"]);
+        assert_eq!(bm25.score(&corpus, 100, &[term(50, 0.0)]), 0.0);
+        assert!(bm25.idf(&corpus, 1000) > 0.0);
+    }
+}
@@ -0,0 +1,201 @@
+/*
+Divergence from randomness (Amati and van Rijsbergen), built from three
+components like Lucene's DFRSimilarity and Elasticsearch's `DFR`
+similarity: a basic model measuring how unlikely the term's frequency in
+the document is under a random distribution, an after effect damping
+that information gain as the term repeats, and a normalization adjusting
+the frequency to the document's length.
+*/
+use super::Similarity;
+use crate::indexer::Statistics;
+
+// Term frequency normalization by document length, shared by DFR and IB
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Normalization {
+    None,
+    H1(f64), // tf * c * avgdl / dl
+    H2(f64), // tf * log2(1 + c * avgdl / dl)
+    H3(f64), // Dirichlet prior with the given mu
+    Z(f64),  // tf * (avgdl / dl)^z, z between 0 and 0.5
+}
+
+impl Normalization {
+    pub(super) fn tfn(
+        &self,
+        stats: &dyn Statistics,
+        term: &str,
+        term_freq: f64,
+        doc_length: usize,
+    ) -> f64 {
+        let (avg, length) = (stats.avg_doc_length(), doc_length as f64);
+        match *self {
+            Normalization::None => term_freq,
+            Normalization::H1(c) => term_freq * c * avg / length,
+            Normalization::H2(c) => term_freq * (1.0 + c * avg / length).log2(),
+            Normalization::H3(mu) => {
+                let collection = (stats.total_term_freq(term) as f64 + 1.0)
+                    / (stats.total_length() as f64 + 1.0);
+                (term_freq + mu * collection) / (length + mu) * mu
+            }
+            Normalization::Z(z) => term_freq * (avg / length).powf(z),
+        }
+    }
+}
+
+// Randomness model of term occurrences
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BasicModel {
+    G,   // Geometric approximation of Bose-Einstein
+    IF,  // Inverse term frequency
+    In,  // Inverse document frequency
+    Ine, // Inverse expected document frequency
+}
+
+impl BasicModel {
+    // Information gain `a + s * tfn` of the term, as (a, s)
+    fn gain(&self, stats: &dyn Statistics, term: &str) -> (f64, f64) {
+        let n = stats.doc_count() as f64;
+        let total = stats.total_term_freq(term) as f64;
+        match self {
+            BasicModel::G => {
+                let lambda = (total + 1.0) / (n + total + 1.0);
+                ((lambda + 1.0).log2(), ((1.0 + lambda) / lambda).log2())
+            }
+            BasicModel::IF => (0.0, (1.0 + (n + 1.0) / (total + 0.5)).log2()),
+            BasicModel::In => {
+                let doc_freq = stats.doc_freq(term) as f64;
+                (0.0, ((n + 1.0) / (doc_freq + 0.5)).log2())
+            }
+            BasicModel::Ine => {
+                let expected = n * (1.0 - ((n - 1.0) / n).powf(total));
+                (0.0, ((n + 1.0) / (expected + 0.5)).log2())
+            }
+        }
+    }
+}
+
+// Damping of the gain as the term repeats in the document
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AfterEffect {
+    None,
+    L, // Laplace's law of succession
+    B, // Ratio of two Bernoulli processes
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dfr {
+    pub basic_model: BasicModel,
+    pub after_effect: AfterEffect,
+    pub normalization: Normalization,
+}
+
+impl Default for Dfr {
+    fn default() -> Self {
+        Dfr::new(BasicModel::In, AfterEffect::L, Normalization::H2(1.0))
+    }
+}
+
+impl Dfr {
+    pub fn new(
+        basic_model: BasicModel,
+        after_effect: AfterEffect,
+        normalization: Normalization,
+    ) -> Self {
+        Dfr {
+            basic_model,
+            after_effect,
+            normalization,
+        }
+    }
+
+    // Factor of the after effect, without its 1 / (tfn + 1)
+    fn after_effect_ratio(&self, stats: &dyn Statistics, term: &str) -> f64 {
+        match self.after_effect {
+            AfterEffect::None | AfterEffect::L => 1.0,
+            AfterEffect::B => {
+                let total = stats.total_term_freq(term) as f64 + 1.0;
+                (total + 1.0) / (stats.doc_freq(term) as f64 + 1.0)
+            }
+        }
+    }
+}
+
+impl Similarity for Dfr {
+    fn score_term(
+        &self,
+        stats: &dyn Statistics,
+        term: &str,
+        term_freq: f64,
+        doc_length: usize,
+    ) -> f64 {
+        if term_freq <= 0.0 || doc_length == 0 {
+            return 0.0;
+        }
+        let tfn = self.normalization.tfn(stats, term, term_freq, doc_length);
+        let (a, s) = self.basic_model.gain(stats, term);
+        let gain = a + s * tfn;
+        match self.after_effect {
+            AfterEffect::None => gain,
+            _ => gain * self.after_effect_ratio(stats, term) / (tfn + 1.0),
+        }
+    }
+
+    // (a + s * tfn) / (tfn + 1) lies between a and s; without an after effect the gain grows unbounded
+    fn term_bound(&self, stats: &dyn Statistics, term: &str) -> f64 {
+        let (a, s) = self.basic_model.gain(stats, term);
+        match self.after_effect {
+            AfterEffect::None => f64::INFINITY,
+            _ => a.max(s) * self.after_effect_ratio(stats, term),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::{DocId, InvertedIndex};
+    use crate::tokenizer::{Language, Tokenizer};
+
+    #[test]
+    fn test_dfr() {
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
+        let mut index = InvertedIndex::new(tokenizer);
+        index.index_document(DocId(1), "rust borrow checker rust");
+        index.index_document(DocId(2), "go garbage collector go routines go");
+        index.index_document(DocId(3), "rust and go");
+        // 3 documents of 4 tokens on average, "rust" in 2 of them
+
+        let in_l = Dfr::new(BasicModel::In, AfterEffect::L, Normalization::H1(1.0));
+        let tfn = 2.0 * 4.0 / 4.0;
+        let expected = tfn * (4.0f64 / 2.5).log2() / (tfn + 1.0);
+        let score = in_l.score_term(&index, "rust", 2.0, 4);
+        assert!((score - expected).abs() < 1e-12);
+        assert_eq!(Normalization::None.tfn(&index, "rust", 2.0, 4), 2.0);
+        let z = Normalization::Z(0.3).tfn(&index, "rust", 2.0, 16);
+        assert!((z - 2.0 * 0.25f64.powf(0.3)).abs() < 1e-12);
+
+        // Every combination scores repeated terms higher, within its bound
+        for basic_model in [
+            BasicModel::G,
+            BasicModel::IF,
+            BasicModel::In,
+            BasicModel::Ine,
+        ] {
+            for after_effect in [AfterEffect::None, AfterEffect::L, AfterEffect::B] {
+                for normalization in [
+                    Normalization::None,
+                    Normalization::H1(1.0),
+                    Normalization::H2(1.0),
+                    Normalization::H3(800.0),
+                    Normalization::Z(0.3),
+                ] {
+                    let dfr = Dfr::new(basic_model, after_effect, normalization);
+                    let once = dfr.score_term(&index, "rust", 1.0, 4);
+                    assert!(once > 0.0, "{:?}", dfr);
+                    assert!(dfr.score_term(&index, "rust", 2.0, 4) > once, "{:?}", dfr);
+                    assert!(once <= dfr.term_bound(&index, "rust"), "{:?}", dfr);
+                }
+            }
+        }
+    }
+}
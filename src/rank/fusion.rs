@@ -0,0 +1,135 @@
+use std::collections::{HashMap, HashSet};
+
+// The usual `k` of reciprocal rank fusion, from the paper that introduced it
+pub const RRF_K: f64 = 60.0;
+
+/*
+Merge ranked lists of document ids, each best first, by reciprocal rank
+fusion: a document scores `1 / (k + rank)` in every list it appears in,
+ranks starting at 1, and the scores are added up. Lists can come from
+anywhere, e.g. a title-only and a content search, or searches of two
+indexes; only the order within each list matters. A document listed
+twice in one list counts at its best rank. Returns every document with
+its fused score, best first, ties going to the lower id.
+*/
+pub fn fuse<L, I>(lists: L, k: f64) -> Vec<(u64, f64)>
+where
+    L: IntoIterator<Item = I>,
+    I: IntoIterator<Item = u64>,
+{
+    let mut scores: HashMap<u64, f64> = HashMap::new();
+    for list in lists {
+        let mut seen = HashSet::new();
+        let distinct = list.into_iter().filter(|&doc_id| seen.insert(doc_id));
+        for (rank, doc_id) in distinct.enumerate() {
+            *scores.entry(doc_id).or_default() += 1.0 / (k + rank as f64 + 1.0);
+        }
+    }
+    let mut fused: Vec<(u64, f64)> = scores.into_iter().collect();
+    fused.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    fused
+}
+
+/*
+How `SearchEngine::hybrid_search` merges its lexical (BM25) and vector
+(k-NN) hits into one ranking. Reciprocal rank fusion only looks at the
+rank of a document in each list, so it needs no tuning across scores of
+different scales; `k` damps the lead of the very first hits, 60 being
+the usual choice. Weighted fusion rescales each list's scores to 0..1
+(best hit 1, worst 0) and adds them up with the given weights. A document
+missing from a list gets nothing from it.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Fusion {
+    ReciprocalRank { k: f64 },
+    Weighted { lexical: f64, vector: f64 },
+}
+
+impl Default for Fusion {
+    fn default() -> Self {
+        Fusion::ReciprocalRank { k: RRF_K }
+    }
+}
+
+impl Fusion {
+    // Combined scores of the documents of both lists, each given best first
+    pub(crate) fn combine(&self, lexical: &[(u64, f64)], vector: &[(u64, f64)]) -> Vec<(u64, f64)> {
+        let mut scores: HashMap<u64, f64> = HashMap::new();
+        match *self {
+            Fusion::ReciprocalRank { k } => {
+                let ids = |list: &[(u64, f64)]| list.iter().map(|&(doc_id, _)| doc_id).collect();
+                let lists: [Vec<u64>; 2] = [ids(lexical), ids(vector)];
+                return fuse(lists, k);
+            }
+            Fusion::Weighted {
+                lexical: lexical_weight,
+                vector: vector_weight,
+            } => {
+                for (list, weight) in [(lexical, lexical_weight), (vector, vector_weight)] {
+                    let (min, max) = list.iter().fold((f64::MAX, f64::MIN), |(min, max), hit| {
+                        (min.min(hit.1), max.max(hit.1))
+                    });
+                    for &(doc_id, score) in list {
+                        // A list whose scores are all equal counts fully
+                        let scaled = if max > min {
+                            (score - min) / (max - min)
+                        } else {
+                            1.0
+                        };
+                        *scores.entry(doc_id).or_default() += weight * scaled;
+                    }
+                }
+            }
+        }
+        scores.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranked(mut scores: Vec<(u64, f64)>) -> Vec<u64> {
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        scores.into_iter().map(|(doc_id, _)| doc_id).collect()
+    }
+
+    #[test]
+    fn test_combine() {
+        let lexical = [(1, 12.0), (2, 8.0), (5, 3.0), (3, 0.5)];
+        let vector = [(3, 0.95), (2, 0.9), (4, 0.2)];
+        // 2 is near the top of both lists
+        assert_eq!(
+            ranked(Fusion::default().combine(&lexical, &vector)),
+            [2, 3, 1, 4, 5]
+        );
+        let lexical_only = Fusion::Weighted {
+            lexical: 1.0,
+            vector: 0.0,
+        };
+        assert_eq!(
+            ranked(lexical_only.combine(&lexical, &vector)),
+            [1, 2, 5, 3, 4]
+        );
+        let balanced = Fusion::Weighted {
+            lexical: 0.5,
+            vector: 0.5,
+        };
+        let scores: HashMap<u64, f64> = balanced.combine(&lexical, &vector).into_iter().collect();
+        assert!((scores[&2] - (0.5 * 7.5 / 11.5 + 0.5 * 0.7 / 0.75)).abs() < 1e-9);
+        assert_eq!(scores[&4], 0.0);
+    }
+
+    #[test]
+    fn test_fuse() {
+        let fused = fuse([vec![1, 2, 3], vec![3, 1, 1, 4], vec![]], RRF_K);
+        let ids: Vec<u64> = fused.iter().map(|&(doc_id, _)| doc_id).collect();
+        assert_eq!(ids, [1, 3, 2, 4]);
+        assert!((fused[0].1 - (1.0 / 61.0 + 1.0 / 62.0)).abs() < 1e-12);
+        // The repeated 1 does not push 4 down
+        assert!((fused[3].1 - 1.0 / 63.0).abs() < 1e-12);
+        assert!(fuse(Vec::<Vec<u64>>::new(), RRF_K).is_empty());
+    }
+}
@@ -0,0 +1,116 @@
+/*
+Information-based models (Clinchant and Gaussier), like Lucene's
+IBSimilarity and Elasticsearch's `IB` similarity: the score is the
+information content of the normalized term frequency under a heavy-tailed
+distribution whose parameter, lambda, comes from the term's collection
+frequency.
+*/
+use super::Similarity;
+use super::dfr::Normalization;
+use crate::indexer::Statistics;
+
+// Probability distribution of normalized term frequencies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    LL,  // Log-logistic
+    SPL, // Smoothed power law
+}
+
+// Where the distribution's parameter comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lambda {
+    DF,  // Share of documents containing the term
+    TTF, // Occurrences of the term per document
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ib {
+    pub distribution: Distribution,
+    pub lambda: Lambda,
+    pub normalization: Normalization,
+}
+
+impl Default for Ib {
+    fn default() -> Self {
+        Ib::new(Distribution::LL, Lambda::DF, Normalization::H2(1.0))
+    }
+}
+
+impl Ib {
+    pub fn new(distribution: Distribution, lambda: Lambda, normalization: Normalization) -> Self {
+        Ib {
+            distribution,
+            lambda,
+            normalization,
+        }
+    }
+
+    fn lambda(&self, stats: &dyn Statistics, term: &str) -> f64 {
+        let occurrences = match self.lambda {
+            Lambda::DF => stats.doc_freq(term) as f64,
+            Lambda::TTF => stats.total_term_freq(term) as f64,
+        };
+        (occurrences + 1.0) / (stats.doc_count() as f64 + 1.0)
+    }
+}
+
+impl Similarity for Ib {
+    fn score_term(
+        &self,
+        stats: &dyn Statistics,
+        term: &str,
+        term_freq: f64,
+        doc_length: usize,
+    ) -> f64 {
+        if term_freq <= 0.0 || doc_length == 0 {
+            return 0.0;
+        }
+        let tfn = self.normalization.tfn(stats, term, term_freq, doc_length);
+        let lambda = self.lambda(stats, term);
+        match self.distribution {
+            Distribution::LL => ((tfn + lambda) / lambda).ln(),
+            Distribution::SPL => {
+                // Undefined at 1, as in Lucene
+                let lambda = if lambda == 1.0 { 0.99 } else { lambda };
+                -((lambda.powf(tfn / (tfn + 1.0)) - lambda) / (1.0 - lambda)).ln()
+            }
+        }
+    }
+
+    // Both distributions grow without bound with the frequency
+    fn term_bound(&self, _stats: &dyn Statistics, _term: &str) -> f64 {
+        f64::INFINITY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::{DocId, InvertedIndex};
+    use crate::tokenizer::{Language, Tokenizer};
+
+    #[test]
+    fn test_ib() {
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
+        let mut index = InvertedIndex::new(tokenizer);
+        index.index_document(DocId(1), "rust borrow checker rust");
+        index.index_document(DocId(2), "go garbage collector go routines go");
+        index.index_document(DocId(3), "rust and go");
+
+        // "rust" is in 2 of 3 documents: lambda = 3 / 4
+        let ll = Ib::new(Distribution::LL, Lambda::DF, Normalization::None);
+        let score = ll.score_term(&index, "rust", 2.0, 4);
+        assert!((score - (2.75f64 / 0.75).ln()).abs() < 1e-12);
+
+        for distribution in [Distribution::LL, Distribution::SPL] {
+            for lambda in [Lambda::DF, Lambda::TTF] {
+                let ib = Ib::new(distribution, lambda, Normalization::H2(1.0));
+                let once = ib.score_term(&index, "rust", 1.0, 4);
+                assert!(once > 0.0, "{:?}", ib);
+                assert!(ib.score_term(&index, "rust", 2.0, 4) > once, "{:?}", ib);
+                assert!(ib.score_term(&index, "borrow", 1.0, 4) > once, "{:?}", ib);
+                assert!(ib.score_term(&index, "rust", 1.0, 8) < once, "{:?}", ib);
+            }
+        }
+    }
+}
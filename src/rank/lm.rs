@@ -0,0 +1,160 @@
+/*
+Query likelihood language models: a document is scored by how likely its
+language model, smoothed with that of the whole collection, is to
+generate the query. As in Lucene, each term scores the log of its
+likelihood relative to the collection's, so that terms missing from a
+document can be left out, and negative scores are clamped to 0.
+*/
+use super::Similarity;
+use crate::errors::MSErrors;
+use crate::indexer::Statistics;
+
+// Probability of the term in the collection; never 0, so unseen terms stay finite
+fn collection_probability(stats: &dyn Statistics, term: &str) -> f64 {
+    (stats.total_term_freq(term) as f64 + 1.0) / (stats.total_length() as f64 + 1.0)
+}
+
+/*
+Bayesian smoothing with a Dirichlet prior: the collection model counts as
+`mu` extra tokens of each document, so short documents lean on it more.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LmDirichlet {
+    pub mu: f64, // Weight of the collection model, in tokens
+}
+
+impl Default for LmDirichlet {
+    fn default() -> Self {
+        LmDirichlet { mu: 2000.0 }
+    }
+}
+
+impl LmDirichlet {
+    // Fails unless `mu` is positive, as scores would be infinite or NaN
+    pub fn new(mu: f64) -> Result<Self, MSErrors> {
+        if !(mu.is_finite() && mu > 0.0) {
+            return Err(MSErrors::ParseError(format!("mu must be > 0, got {}", mu)));
+        }
+        Ok(LmDirichlet { mu })
+    }
+}
+
+impl Similarity for LmDirichlet {
+    fn score_term(
+        &self,
+        stats: &dyn Statistics,
+        term: &str,
+        term_freq: f64,
+        doc_length: usize,
+    ) -> f64 {
+        if term_freq <= 0.0 {
+            return 0.0;
+        }
+        let collection = collection_probability(stats, term);
+        let score = (1.0 + term_freq / (self.mu * collection)).ln()
+            + (self.mu / (doc_length as f64 + self.mu)).ln();
+        score.max(0.0)
+    }
+
+    // Approached as a document made only of the term, in the most boosted field, grows
+    fn term_bound(&self, stats: &dyn Statistics, term: &str) -> f64 {
+        stats.max_field_boost().ln() - collection_probability(stats, term).ln()
+    }
+}
+
+/*
+Jelinek-Mercer smoothing: a fixed mix of the document and collection
+models, `lambda` being the collection's share. Around 0.1 suits short
+queries, around 0.7 long ones.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LmJelinekMercer {
+    pub lambda: f64, // Weight of the collection model, between 0 and 1
+}
+
+impl Default for LmJelinekMercer {
+    fn default() -> Self {
+        LmJelinekMercer { lambda: 0.1 }
+    }
+}
+
+impl LmJelinekMercer {
+    // Fails unless `lambda` is strictly between 0 and 1, as scores would be infinite or NaN
+    pub fn new(lambda: f64) -> Result<Self, MSErrors> {
+        if !(lambda > 0.0 && lambda < 1.0) {
+            return Err(MSErrors::ParseError(format!(
+                "lambda must be in (0, 1), got {}",
+                lambda
+            )));
+        }
+        Ok(LmJelinekMercer { lambda })
+    }
+}
+
+impl Similarity for LmJelinekMercer {
+    fn score_term(
+        &self,
+        stats: &dyn Statistics,
+        term: &str,
+        term_freq: f64,
+        doc_length: usize,
+    ) -> f64 {
+        if term_freq <= 0.0 || doc_length == 0 {
+            return 0.0;
+        }
+        let collection = collection_probability(stats, term);
+        let document = term_freq / doc_length as f64;
+        (1.0 + (1.0 - self.lambda) * document / (self.lambda * collection)).ln()
+    }
+
+    // Reached by a document made only of the term, in the most boosted field
+    fn term_bound(&self, stats: &dyn Statistics, term: &str) -> f64 {
+        let collection = collection_probability(stats, term);
+        let document = stats.max_field_boost();
+        (1.0 + (1.0 - self.lambda) * document / (self.lambda * collection)).ln()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::{DocId, InvertedIndex};
+    use crate::tokenizer::{Language, Tokenizer};
+
+    #[test]
+    fn test_language_models() {
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
+        let mut index = InvertedIndex::new(tokenizer);
+        index.index_document(DocId(1), "rust borrow checker rust");
+        index.index_document(DocId(2), "go garbage collector go routines go");
+        // 10 tokens, "rust" twice: p(rust|C) = 3 / 11
+        let collection: f64 = 3.0 / 11.0;
+
+        let dirichlet = LmDirichlet::new(10.0).unwrap();
+        let expected = (1.0 + 2.0 / (10.0 * collection)).ln() + (10.0 / 14.0f64).ln();
+        let score = dirichlet.score_term(&index, "rust", 2.0, 4);
+        assert!((score - expected).abs() < 1e-12);
+        assert!(score <= dirichlet.term_bound(&index, "rust"));
+        // A long document's prior outweighs a rare occurrence
+        assert_eq!(dirichlet.score_term(&index, "rust", 1.0, 1000), 0.0);
+        assert_eq!(dirichlet.score_term(&index, "rust", 0.0, 4), 0.0);
+
+        let jelinek_mercer = LmJelinekMercer::default();
+        let expected = (1.0 + 0.9 * 0.5 / (0.1 * collection)).ln();
+        let score = jelinek_mercer.score_term(&index, "rust", 2.0, 4);
+        assert!((score - expected).abs() < 1e-12);
+        assert!(score < jelinek_mercer.term_bound(&index, "rust"));
+        // Frequency relative to the length matters, not the count
+        assert_eq!(
+            jelinek_mercer.score_term(&index, "rust", 1.0, 2),
+            jelinek_mercer.score_term(&index, "rust", 2.0, 4)
+        );
+
+        for mu in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(LmDirichlet::new(mu).is_err(), "{}", mu);
+        }
+        for lambda in [0.0, 1.0, -0.5, f64::NAN] {
+            assert!(LmJelinekMercer::new(lambda).is_err(), "{}", lambda);
+        }
+    }
+}
@@ -1,168 +1,630 @@
-use super::indexer::{DocId, InvertedIndex, Posting};
-use super::tokenizer::{Language, Tokenizer};
+use super::indexer::{DocId, InvertedIndex, Posting, Statistics, field_term};
+use super::tokenizer::Token;
 use std::collections::{HashMap, HashSet};
 
-// BM25Ranker struct to hold corpus statistics and parameters
-pub struct BM25Ranker {
-    tokenizer: Tokenizer,
-    index: InvertedIndex,
+use roaring::RoaringTreemap;
+
+mod approx;
+mod bm25;
+mod dfr;
+mod fusion;
+mod ib;
+mod lm;
+mod similarity;
+
+use approx::ApproximateStats;
+pub use bm25::{Bm25, CorpusStats, TermStats};
+pub use dfr::{AfterEffect, BasicModel, Dfr, Normalization};
+pub use fusion::{Fusion, RRF_K, fuse};
+pub use ib::{Distribution, Ib, Lambda};
+pub use lm::{LmDirichlet, LmJelinekMercer};
+pub use similarity::Similarity;
+
+// Contribution of one query term to a document's score
+#[derive(Debug, Clone, PartialEq)]
+pub struct TermExplanation {
+    pub term: String,
+    pub occurrences: usize, // Times the term appears in the document
+    pub term_freq: f64,     // Occurrences weighted by field boosts
+    pub doc_freq: usize,
+    pub idf: f64,         // Rarity of the term in the collection
+    pub length_norm: f64, // 1 for a document of average length
+    pub score: f64,
+}
+
+/*
+What scoring needs to know about each document beyond its postings:
+lengths, boosts and the collection totals derived from them. Kept next to
+the inverted index by its owner, which adds and removes documents in both.
+*/
+#[derive(Clone, Default)]
+pub struct DocStats {
     doc_lengths: HashMap<DocId, usize>, // Number of tokens per document
+    total_doc_length: usize,            // Sum of all document lengths
     avg_doc_length: f64,                // Average document length
     total_docs: usize,                  // Total number of documents
-    k1: f64,                            // BM25 parameter for term frequency saturation
-    b: f64,                             // BM25 parameter for length normalization
+    doc_boosts: HashMap<DocId, f64>,    // Static boosts set at index time
+    field_boosts: HashMap<DocId, Vec<(usize, f64)>>, // (end position, boost) per field
+    max_field_boost: f64, // Highest field boost ever added, kept when its document is removed
+    approximate: Option<ApproximateStats>, // Used for scoring instead of exact stats
 }
 
-impl BM25Ranker {
-    // Create a new BM25Ranker
-    pub fn new(tokenizer: Tokenizer, index: InvertedIndex) -> Self {
-        BM25Ranker {
-            tokenizer,
-            index,
-            doc_lengths: HashMap::new(),
-            avg_doc_length: 0.0,
-            total_docs: 0,
-            k1: 1.5, // Common default
-            b: 0.75, // Common default
-        }
+impl DocStats {
+    pub fn new() -> Self {
+        DocStats::default()
+    }
+
+    /*
+    Score with approximate statistics: document frequencies come from a
+    count-min sketch and the average length from a sample of documents, so
+    both are updated in constant time. Call before adding any document.
+    */
+    pub fn enable_approximate_stats(&mut self) {
+        self.approximate = Some(ApproximateStats::default());
     }
 
-    // Add a document and update corpus statistics
-    pub fn index_document(&mut self, doc_id: DocId, text: &str) {
-        // Tokenize to get document length
-        let tokens = self.tokenizer.tokenize(text);
+    /*
+    Record a document about to be indexed from `tokens`. `fields` holds the
+    byte length and boost of each field of the analyzed text, in order; the
+    last field may claim `usize::MAX` bytes. Each field covers a contiguous
+    range of token positions, whose end is kept to look up field boosts
+    when scoring.
+    */
+    pub fn add_document(
+        &mut self,
+        doc_id: DocId,
+        tokens: &[Token],
+        fields: &[(usize, f64)],
+        boost: f64,
+    ) {
         let doc_length = tokens.len();
 
+        if fields.iter().any(|&(_, field_boost)| field_boost != 1.0) {
+            let mut ranges = Vec::with_capacity(fields.len());
+            let mut field_end: usize = 0;
+            for &(field_len, field_boost) in fields {
+                field_end = field_end.saturating_add(field_len);
+                let end_position = tokens.iter().take_while(|t| t.offset.0 < field_end).count();
+                ranges.push((end_position, field_boost));
+                self.max_field_boost = self.max_field_boost.max(field_boost);
+                field_end = field_end.saturating_add(1); // Separator
+            }
+            self.field_boosts.insert(doc_id, ranges);
+        }
+        if boost != 1.0 {
+            self.doc_boosts.insert(doc_id, boost);
+        }
+
+        if let Some(approximate) = &mut self.approximate {
+            let terms: HashSet<&str> = tokens.iter().map(|t| t.term.as_str()).collect();
+            for term in terms {
+                approximate.doc_freqs.increment(term);
+            }
+            approximate.lengths.add(doc_length);
+        }
+
         // Update document lengths and corpus stats
         self.doc_lengths.insert(doc_id, doc_length);
+        self.total_doc_length += doc_length;
         self.total_docs += 1;
         self.update_avg_doc_length();
+    }
 
-        // Delegate indexing to the inverted index
-        self.index.index_document(doc_id, text);
+    /*
+    Record the analyzed value of a metadata field, searchable as
+    `field:term`, before it is indexed. Field terms do not count towards
+    the document length, and their positions are moved after the
+    document's own so field boosts never apply.
+    */
+    pub fn add_field(&mut self, doc_id: DocId, field: &str, tokens: &mut [Token]) {
+        let doc_length = self.doc_lengths.get(&doc_id).copied().unwrap_or(0);
+        for token in tokens.iter_mut() {
+            token.position += doc_length;
+        }
+        if let Some(approximate) = &mut self.approximate {
+            let terms: HashSet<String> =
+                tokens.iter().map(|t| field_term(field, &t.term)).collect();
+            for term in terms {
+                approximate.doc_freqs.increment(&term);
+            }
+        }
+    }
+
+    /*
+    Forget a document, given its indexed terms; call before removing it
+    from the index. Returns false if the document was not added.
+    */
+    pub fn remove_document(&mut self, doc_id: DocId, terms: &[String]) -> bool {
+        let Some(doc_length) = self.doc_lengths.remove(&doc_id) else {
+            return false;
+        };
+        self.total_doc_length -= doc_length;
+        self.total_docs -= 1;
+        self.doc_boosts.remove(&doc_id);
+        self.field_boosts.remove(&doc_id);
+        if let Some(approximate) = &mut self.approximate {
+            for term in terms {
+                approximate.doc_freqs.decrement(term);
+            }
+        }
+        self.update_avg_doc_length();
+        true
     }
 
     // Update average document length
     fn update_avg_doc_length(&mut self) {
         if self.total_docs > 0 {
-            let total_length: usize = self.doc_lengths.values().sum();
-            self.avg_doc_length = total_length as f64 / self.total_docs as f64;
+            self.avg_doc_length = self.total_doc_length as f64 / self.total_docs as f64;
+        } else {
+            self.avg_doc_length = 0.0;
         }
     }
 
-    // Compute IDF for a term
-    fn compute_idf(&self, term: &str) -> f64 {
-        let n_qi = self
-            .index
-            .get_postings(term)
-            .map_or(0, |postings| postings.len());
-        ((self.total_docs as f64 - n_qi as f64 + 0.5) / (n_qi as f64 + 0.5) + 1.0).ln()
+    pub fn doc_boost(&self, doc_id: DocId) -> f64 {
+        self.doc_boosts.get(&doc_id).copied().unwrap_or(1.0)
     }
 
-    // Compute BM25 score for a document given query terms
-    fn compute_score(&self, doc_id: DocId, query_terms: &[String]) -> f64 {
-        let doc_length = *self.doc_lengths.get(&doc_id).unwrap_or(&0) as f64;
-        if doc_length == 0.0 {
-            return 0.0;
+    // Tokens in a document, not counting metadata fields
+    pub fn doc_length(&self, doc_id: DocId) -> Option<usize> {
+        self.doc_lengths.get(&doc_id).copied()
+    }
+
+    /*
+    Term frequency with each occurrence weighted by its field boost. Field
+    boosts are looked up by position, so without recorded positions every
+    occurrence counts once.
+    */
+    fn weighted_tf(&self, doc_id: DocId, posting: &Posting) -> f64 {
+        match self.field_boosts.get(&doc_id) {
+            Some(ranges) if !posting.positions.is_empty() => posting
+                .positions
+                .iter()
+                .map(|&position| {
+                    ranges
+                        .iter()
+                        .find(|&&(end, _)| position < end)
+                        .map_or(1.0, |&(_, boost)| boost)
+                })
+                .sum(),
+            _ => posting.term_freq as f64,
+        }
+    }
+}
+
+/*
+Scores documents of an inverted index with a similarity, reading lengths
+and boosts from the index's DocStats. A ranker only borrows them, so
+create one per query rather than keeping it around.
+*/
+#[derive(Clone, Copy)]
+pub struct Ranker<'a> {
+    index: &'a InvertedIndex,
+    docs: &'a DocStats,
+    similarity: &'a dyn Similarity,
+    corpus: CorpusStats, // Computed once, the approximate mean being sampled
+}
+
+impl<'a> Ranker<'a> {
+    pub fn new(
+        index: &'a InvertedIndex,
+        docs: &'a DocStats,
+        similarity: &'a dyn Similarity,
+    ) -> Self {
+        let avg_doc_length = match &docs.approximate {
+            Some(approximate) => approximate.lengths.average(),
+            None => docs.avg_doc_length,
+        };
+        Ranker {
+            index,
+            docs,
+            similarity,
+            corpus: CorpusStats {
+                total_docs: docs.total_docs,
+                avg_doc_length,
+            },
         }
+    }
+
+    pub fn index(&self) -> &'a InvertedIndex {
+        self.index
+    }
+
+    // Statistics of the indexed collection
+    pub fn corpus_stats(&self) -> CorpusStats {
+        self.corpus
+    }
+
+    // Number of documents containing `term`, estimated in approximate mode
+    pub fn doc_freq(&self, term: &str) -> usize {
+        match &self.docs.approximate {
+            Some(approximate) => approximate.doc_freqs.estimate(term),
+            None => self.index.doc_freq(term),
+        }
+    }
+
+    pub fn doc_boost(&self, doc_id: DocId) -> f64 {
+        self.docs.doc_boost(doc_id)
+    }
+
+    pub fn doc_length(&self, doc_id: DocId) -> Option<usize> {
+        self.docs.doc_length(doc_id)
+    }
+
+    // Upper bound of `term`'s contribution to any document's score, before doc boosts
+    pub(crate) fn term_upper_bound(&self, term: &str) -> f64 {
+        self.similarity.term_bound(self, term)
+    }
 
-        let mut score = 0.0;
-        for term in query_terms {
-            if let Some(postings) = self.index.get_postings(term) {
-                if let Some(posting) = postings.iter().find(|p| p.doc_id == doc_id) {
-                    let tf = posting.positions.len() as f64; // Term frequency
-                    let idf = self.compute_idf(term);
-                    let numerator = tf * (self.k1 + 1.0);
-                    let denominator =
-                        tf + self.k1 * (1.0 - self.b + self.b * doc_length / self.avg_doc_length);
-                    score += idf * numerator / denominator;
+    // The analyzed fields of a document, rebuilt from its postings in position order
+    pub fn document_tokens(&self, doc_id: DocId) -> Vec<Token> {
+        // Metadata field terms are positioned after the document's own
+        let doc_length = self.doc_length(doc_id).unwrap_or(0);
+        let mut tokens = Vec::with_capacity(doc_length);
+        for term in self.index.document_terms(doc_id).unwrap_or_default() {
+            let Some(posting) = self.index.posting(term, doc_id) else {
+                continue;
+            };
+            for (&position, &offset) in posting.positions.iter().zip(&posting.offsets) {
+                if position < doc_length {
+                    let payload = posting
+                        .payloads
+                        .iter()
+                        .find(|(at, _)| *at == position)
+                        .map(|(_, payload)| payload.clone());
+                    tokens.push(Token {
+                        term: term.clone(),
+                        position,
+                        offset,
+                        kind: posting.kind,
+                        payload,
+                    });
                 }
             }
         }
-        score
+        // Tokens sharing a position, such as n-grams, stay in term order
+        tokens.sort_by_key(|t| t.position);
+        tokens
     }
 
-    // Rank documents for a query
-    pub fn rank(&self, query: &str) -> Vec<(DocId, f64)> {
-        // Tokenize query and remove duplicates
-        let query_tokens = self.tokenizer.tokenize(query);
-        let query_terms: Vec<String> = query_tokens.into_iter().map(|t| t.term).collect();
-        let unique_terms: HashSet<String> = HashSet::from_iter(query_terms.iter().cloned());
+    // Breakdown of `compute_score` for each query term found in the document
+    pub fn explain_terms(&self, doc_id: DocId, query_terms: &[String]) -> Vec<TermExplanation> {
+        let doc_length = self.doc_length(doc_id).unwrap_or(0);
+        query_terms
+            .iter()
+            .filter_map(|term| {
+                let posting = self.index.posting(term, doc_id)?;
+                let doc_freq = self.doc_freq(term);
+                let term_freq = self.docs.weighted_tf(doc_id, posting);
+                Some(TermExplanation {
+                    term: term.clone(),
+                    occurrences: posting.term_freq as usize,
+                    term_freq,
+                    doc_freq,
+                    idf: bm25::idf(&self.corpus, doc_freq),
+                    length_norm: self.similarity.doc_norm(self, doc_length),
+                    score: self
+                        .similarity
+                        .score_term(self, term, term_freq, doc_length),
+                })
+            })
+            .collect()
+    }
+
+    // Score of a document for the query terms, with its boost applied
+    pub(crate) fn compute_score(&self, doc_id: DocId, query_terms: &[String]) -> f64 {
+        let doc_length = self.doc_length(doc_id).unwrap_or(0);
+        if doc_length == 0 {
+            return 0.0;
+        }
+
+        let score: f64 = query_terms
+            .iter()
+            .filter_map(|term| {
+                let posting = self.index.posting(term, doc_id)?;
+                let term_freq = self.docs.weighted_tf(doc_id, posting);
+                Some(
+                    self.similarity
+                        .score_term(self, term, term_freq, doc_length),
+                )
+            })
+            .sum();
+        score * self.doc_boost(doc_id)
+    }
+
+    /*
+    The `limit` most characteristic terms of a document by TF-IDF, best
+    first. Terms found in no other document are skipped since they cannot
+    match anything else.
+    */
+    pub fn top_terms(&self, doc_id: DocId, limit: usize) -> Vec<String> {
+        let mut weighted: Vec<(&String, f64)> = self
+            .index
+            .document_terms(doc_id)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|term| {
+                let posting = self.index.posting(term, doc_id)?;
+                let doc_freq = self.doc_freq(term);
+                (doc_freq > 1).then(|| {
+                    let tf = self.docs.weighted_tf(doc_id, posting);
+                    (term, tf * bm25::idf(&self.corpus, doc_freq))
+                })
+            })
+            .collect();
+        weighted.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        weighted
+            .into_iter()
+            .take(limit)
+            .map(|(term, _)| term.clone())
+            .collect()
+    }
+
+    // TF-IDF weight of every term of a document, empty if it is not indexed
+    pub fn term_vector(&self, doc_id: DocId) -> HashMap<String, f64> {
+        self.index
+            .document_terms(doc_id)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|term| {
+                let posting = self.index.posting(term, doc_id)?;
+                let tf = self.docs.weighted_tf(doc_id, posting);
+                Some((
+                    term.clone(),
+                    tf * bm25::idf(&self.corpus, self.doc_freq(term)),
+                ))
+            })
+            .collect()
+    }
+
+    // Rank the documents containing any of the analyzed query terms
+    pub fn rank(&self, query_terms: &[String]) -> Vec<(DocId, f64)> {
+        // Remove duplicates, keeping the query's order so that scores are
+        // summed the same way on every run
+        let mut seen = HashSet::new();
+        let unique_terms: Vec<String> = query_terms
+            .iter()
+            .filter(|term| seen.insert(term.as_str()))
+            .cloned()
+            .collect();
 
         // Collect documents containing any query term
-        let mut candidate_docs: HashSet<DocId> = HashSet::new();
+        let mut candidate_docs = RoaringTreemap::new();
         for term in &unique_terms {
-            if let Some(postings) = self.index.get_postings(term) {
-                for posting in postings {
-                    candidate_docs.insert(posting.doc_id);
-                }
-            }
+            candidate_docs |= self.index.term_docs(term);
         }
 
         // Compute scores for each candidate document
         let mut results: Vec<(DocId, f64)> = candidate_docs
             .into_iter()
-            .map(|doc_id| {
-                (
-                    doc_id,
-                    self.compute_score(
-                        doc_id,
-                        &unique_terms.clone().into_iter().collect::<Vec<_>>(),
-                    ),
-                )
-            })
+            .map(DocId)
+            .map(|doc_id| (doc_id, self.compute_score(doc_id, &unique_terms)))
             .filter(|&(_, score)| score > 0.0)
             .collect();
 
-        // Sort by score in descending order
-        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        // Sort by score in descending order, ties going to the lower id
+        results.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
         results
     }
 }
 
+/*
+The statistics documents are scored with: document frequencies are
+estimated and the mean length sampled when approximate statistics are
+enabled, term frequencies are always exact.
+*/
+impl Statistics for Ranker<'_> {
+    fn doc_freq(&self, term: &str) -> usize {
+        Ranker::doc_freq(self, term)
+    }
+
+    fn total_term_freq(&self, term: &str) -> u64 {
+        self.index.total_term_freq(term)
+    }
+
+    fn doc_count(&self) -> usize {
+        self.corpus.total_docs
+    }
+
+    fn total_length(&self) -> u64 {
+        self.docs.total_doc_length as u64
+    }
+
+    fn avg_doc_length(&self) -> f64 {
+        self.corpus.avg_doc_length
+    }
+
+    fn max_field_boost(&self) -> f64 {
+        self.docs.max_field_boost.max(1.0)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tokenizer::{Language, Tokenizer};
+
+    // An index and its document statistics, filled the way the engine does
+    struct Collection {
+        tokenizer: Tokenizer,
+        index: InvertedIndex,
+        docs: DocStats,
+        bm25: Bm25,
+    }
+
+    impl Collection {
+        fn new() -> Self {
+            let tokenizer = Tokenizer::new(Language::English).unwrap();
+            Collection {
+                index: InvertedIndex::new(tokenizer.clone()),
+                tokenizer,
+                docs: DocStats::new(),
+                bm25: Bm25::default(),
+            }
+        }
+
+        fn add(&mut self, doc_id: u64, text: &str) {
+            self.add_fields(doc_id, &[(text, 1.0)], 1.0);
+        }
+
+        fn add_fields(&mut self, doc_id: u64, fields: &[(&str, f64)], boost: f64) {
+            let text: Vec<&str> = fields.iter().map(|(text, _)| *text).collect();
+            let tokens = self.tokenizer.tokenize(&text.join("\n"));
+            let fields: Vec<(usize, f64)> = fields
+                .iter()
+                .map(|(text, field_boost)| (text.len(), *field_boost))
+                .collect();
+            self.docs
+                .add_document(DocId(doc_id), &tokens, &fields, boost);
+            self.index.index_tokens(DocId(doc_id), tokens);
+        }
+
+        fn remove(&mut self, doc_id: u64) -> bool {
+            let terms = self.index.document_terms(DocId(doc_id)).unwrap_or_default();
+            let removed = self.docs.remove_document(DocId(doc_id), terms);
+            self.index.remove_document(DocId(doc_id));
+            removed
+        }
+
+        fn ranker(&self) -> Ranker<'_> {
+            Ranker::new(&self.index, &self.docs, &self.bm25)
+        }
+
+        fn rank(&self, query: &str) -> Vec<(DocId, f64)> {
+            let tokens = self.tokenizer.tokenize(query);
+            let terms: Vec<String> = tokens.into_iter().map(|t| t.term).collect();
+            self.ranker().rank(&terms)
+        }
+    }
 
     #[test]
     fn test_bm25_ranking() {
-        let tokenizer = Tokenizer::new(Language::English);
-        let index = InvertedIndex::new(tokenizer.clone());
-        let mut ranker = BM25Ranker::new(tokenizer, index);
+        let mut collection = Collection::new();
 
-        // Index some documents
-        ranker.index_document(1, "The quick fox jumps");
-        ranker.index_document(2, "Fox jumps high");
-        ranker.index_document(3, "Slow turtle walks");
+        // Index some documents; "The" is a stop word, so doc 1 needs a fourth term to be longer
+        collection.add(1, "The quick brown fox jumps");
+        collection.add(2, "Fox jumps high");
+        collection.add(3, "Slow turtle walks");
 
         // Query for "fox jumps"
-        let results = ranker.rank("fox jumps");
+        let results = collection.rank("fox jumps");
 
         // Expected: Doc 2 and Doc 1 should rank higher than Doc 3
         assert_eq!(results.len(), 2);
-        assert_eq!(results[0].0, 2); // "Fox jumps high" should rank higher (more query terms, shorter doc)
-        assert_eq!(results[1].0, 1);
+        assert_eq!(results[0].0, DocId(2)); // "Fox jumps high" should rank higher (more query terms, shorter doc)
+        assert_eq!(results[1].0, DocId(1));
         assert!(results[0].1 > results[1].1); // Doc 2 should have a higher score
     }
 
+    #[test]
+    fn test_remove_document() {
+        let mut collection = Collection::new();
+
+        collection.add(1, "The quick fox jumps");
+        collection.add(2, "Fox jumps high");
+        assert!(collection.remove(1));
+        assert!(!collection.remove(1));
+
+        let results = collection.rank("fox");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, DocId(2));
+    }
+
+    #[test]
+    fn test_document_boost() {
+        let mut collection = Collection::new();
+
+        collection.add(1, "Fox jumps high");
+        collection.add_fields(2, &[("The quick brown fox jumps", 1.0)], 2.0);
+
+        // The longer document wins thanks to its boost
+        let results = collection.rank("fox jumps");
+        assert_eq!(results[0].0, DocId(2));
+        assert_eq!(collection.docs.doc_boost(DocId(1)), 1.0);
+    }
+
+    #[test]
+    fn test_field_boost() {
+        let mut collection = Collection::new();
+
+        collection.add_fields(1, &[("Turtle news", 1.0), ("The fox jumps", 1.0)], 1.0);
+        collection.add_fields(2, &[("Fox news", 3.0), ("The turtle jumps", 1.0)], 1.0);
+
+        let results = collection.rank("fox");
+        assert_eq!(results[0].0, DocId(2));
+        assert!(results[0].1 > results[1].1);
+
+        // Boosted frequencies may exceed the length, but not the bounds
+        collection.add_fields(3, &[("Fox fox", 3.0)], 1.0);
+        let dirichlet = LmDirichlet::new(1.0).unwrap();
+        let jelinek_mercer = LmJelinekMercer::default();
+        for similarity in [&dirichlet as &dyn Similarity, &jelinek_mercer] {
+            let ranker = Ranker::new(&collection.index, &collection.docs, similarity);
+            assert_eq!(ranker.max_field_boost(), 3.0);
+            let bound = ranker.term_upper_bound("fox");
+            for (doc_id, score) in ranker.rank(&["fox".to_string()]) {
+                assert!(score <= bound, "{:?}: {} > {}", doc_id, score, bound);
+            }
+        }
+    }
+
+    #[test]
+    fn test_approximate_stats() {
+        let mut exact = Collection::new();
+        let mut approximate = Collection::new();
+        approximate.docs.enable_approximate_stats();
+        for collection in [&mut exact, &mut approximate] {
+            collection.add(1, "The quick brown fox jumps");
+            collection.add(2, "Fox jumps high");
+            collection.add(3, "Slow turtle walks");
+            collection.add(4, "A turtle and a fox");
+            collection.remove(4);
+        }
+
+        // Small collections fit in the sketch and sample without error
+        let (exact_ranker, ranker) = (exact.ranker(), approximate.ranker());
+        assert_eq!(ranker.doc_freq("fox"), 2);
+        assert_eq!(ranker.doc_freq("turtl"), 1);
+        let ranked: Vec<DocId> = approximate.rank("fox jumps").iter().map(|r| r.0).collect();
+        assert_eq!(ranked, vec![DocId(2), DocId(1)]);
+        assert!(ranker.compute_score(DocId(1), &["fox".to_string()]) > 0.0);
+        assert_eq!(exact_ranker.doc_freq("fox"), ranker.doc_freq("fox"));
+        let stats: &dyn Statistics = &exact_ranker;
+        assert_eq!((stats.doc_count(), stats.total_term_freq("fox")), (3, 2));
+        assert_eq!(
+            stats.avg_doc_length(),
+            exact_ranker.corpus_stats().avg_doc_length
+        );
+    }
+
+    #[test]
+    fn test_top_terms() {
+        let mut collection = Collection::new();
+
+        collection.add(1, "fox fox turtle walks alone");
+        collection.add(2, "A fox and a turtle");
+        collection.add(3, "Turtle soup");
+
+        // "fox" is repeated and rarer than "turtl"; unique words are dropped
+        let ranker = collection.ranker();
+        assert_eq!(ranker.top_terms(DocId(1), 5), vec!["fox", "turtl"]);
+        assert_eq!(ranker.top_terms(DocId(1), 1), vec!["fox"]);
+        assert!(ranker.top_terms(DocId(9), 5).is_empty());
+    }
+
     #[test]
     fn test_empty_query() {
-        let tokenizer = Tokenizer::new(Language::English);
-        let index = InvertedIndex::new(tokenizer.clone());
-        let ranker = BM25Ranker::new(tokenizer, index);
+        let collection = Collection::new();
 
-        let results = ranker.rank("");
+        let results = collection.rank("");
         assert_eq!(results, vec![]);
     }
 
     #[test]
     fn test_no_relevant_docs() {
-        let tokenizer = Tokenizer::new(Language::English);
-        let index = InvertedIndex::new(tokenizer.clone());
-        let mut ranker = BM25Ranker::new(tokenizer, index);
+        let mut collection = Collection::new();
 
-        ranker.index_document(1, "The quick fox");
-        let results = ranker.rank("turtle");
+        collection.add(1, "The quick fox");
+        let results = collection.rank("turtle");
         assert_eq!(results, vec![]);
     }
 }
@@ -0,0 +1,26 @@
+use crate::indexer::Statistics;
+
+/*
+How the occurrences of a query term in a document turn into a score. A
+document scores the sum of its query terms' scores, times its boost.
+Collection statistics are read through `Statistics`, so a similarity
+scores alike over exact, approximate or external statistics.
+*/
+pub trait Similarity: Send + Sync {
+    // Score of `term` occurring `term_freq` times, possibly weighted, in a document of `doc_length` tokens
+    fn score_term(
+        &self,
+        stats: &dyn Statistics,
+        term: &str,
+        term_freq: f64,
+        doc_length: usize,
+    ) -> f64;
+
+    // Highest score `term` can reach in any document, to skip candidates that cannot make the top hits
+    fn term_bound(&self, stats: &dyn Statistics, term: &str) -> f64;
+
+    // Length normalization of a document, reported in explanations; 1 if lengths are not normalized
+    fn doc_norm(&self, _stats: &dyn Statistics, _doc_length: usize) -> f64 {
+        1.0
+    }
+}
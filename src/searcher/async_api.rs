@@ -0,0 +1,97 @@
+use std::sync::{Arc, PoisonError, RwLock};
+
+use tokio::task;
+
+use super::{SearchEngine, SearchOptions, SearchResults};
+use crate::document::Document;
+use crate::errors::MSErrors;
+
+/*
+A shareable handle for using an engine from async code. Searching and
+indexing run on tokio's blocking thread pool, so they never stall the
+executor; searches share a read lock, indexing takes the write lock.
+Must be used from within a tokio runtime.
+*/
+#[derive(Clone)]
+pub struct AsyncSearchEngine {
+    engine: Arc<RwLock<SearchEngine>>,
+}
+
+impl AsyncSearchEngine {
+    pub fn new(engine: SearchEngine) -> Self {
+        AsyncSearchEngine {
+            engine: Arc::new(RwLock::new(engine)),
+        }
+    }
+
+    pub async fn search_async(
+        &self,
+        query: &str,
+        options: SearchOptions,
+    ) -> Result<SearchResults, MSErrors> {
+        let engine = self.engine.clone();
+        let query = query.to_string();
+        task::spawn_blocking(move || {
+            let engine = engine.read().map_err(poisoned)?;
+            Ok(engine.search_with_options(&query, &options))
+        })
+        .await
+        .map_err(|e| MSErrors::SearchError(format!("search task failed: {}", e)))?
+    }
+
+    pub async fn index_document_async(&self, document: Document) -> Result<(), MSErrors> {
+        let engine = self.engine.clone();
+        task::spawn_blocking(move || engine.write().map_err(poisoned)?.add_document(document))
+            .await
+            .map_err(|e| MSErrors::IndexingError(format!("indexing task failed: {}", e)))?
+    }
+
+    pub async fn flush_async(&self) -> Result<(), MSErrors> {
+        let engine = self.engine.clone();
+        task::spawn_blocking(move || engine.read().map_err(poisoned)?.flush())
+            .await
+            .map_err(|e| MSErrors::StorageError(format!("flush task failed: {}", e)))?
+    }
+
+    // Run `f` with the engine locked for reading, on the calling thread
+    pub fn with_engine<T>(&self, f: impl FnOnce(&SearchEngine) -> T) -> T {
+        f(&self.engine.read().unwrap())
+    }
+}
+
+// A task panicked while holding the engine lock, so the engine may be half updated
+fn poisoned<T>(_: PoisonError<T>) -> MSErrors {
+    MSErrors::LockError("engine lock poisoned by a panicked task".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::IndexSettings;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_async_engine() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let engine = AsyncSearchEngine::new(SearchEngine::new(IndexSettings::default()).unwrap());
+        runtime.block_on(async {
+            let document = Document {
+                id: 1,
+                title: "Async".to_string(),
+                content: "searching without blocking".to_string(),
+                metadata: HashMap::new(),
+            };
+            engine.index_document_async(document.clone()).await.unwrap();
+            assert!(engine.index_document_async(document).await.is_err());
+
+            let results = engine
+                .search_async("blocking", SearchOptions::new(10))
+                .await
+                .unwrap();
+            assert_eq!(results.documents[0].id, 1);
+        });
+        assert_eq!(engine.with_engine(SearchEngine::document_count), 1);
+    }
+}
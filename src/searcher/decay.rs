@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use super::tiering;
+use crate::errors::MSErrors;
+use crate::indexer::{DocId, DocValues};
+
+// Shape of the fall from 1 as a value moves away from the origin
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DecayCurve {
+    Gauss,  // Gentle near the origin, then steep
+    Exp,    // Steepest near the origin, never reaching 0
+    Linear, // Straight down, reaching 0 at `offset + scale / (1 - decay)`
+}
+
+/*
+A decay function over a numeric or date field, multiplying the score of
+each hit by how close its value lies to an origin, e.g. to favor recent
+documents. Within `offset` of the origin the factor is 1, at `offset +
+scale` it is `decay`, and past that it keeps falling along the curve.
+Dates are compared in seconds. Hits without a value keep their score;
+invalid parameters, a scale that is not positive or a decay outside
+(0, 1), leave every score unchanged, and are rejected by `try_search`.
+When deserialized, `offset` and `decay` default as in the builders.
+*/
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Decay {
+    pub field: String,
+    pub curve: DecayCurve,
+    pub origin: f64,
+    pub scale: f64, // Distance past the offset at which the factor is `decay`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub offset: f64, // Distance from the origin that is not penalized
+    #[cfg_attr(feature = "serde", serde(default = "default_decay"))]
+    pub decay: f64, // Factor at `offset + scale`, 0.5 by default
+}
+
+fn default_decay() -> f64 {
+    0.5
+}
+
+impl Decay {
+    pub fn new(curve: DecayCurve, field: &str, origin: f64, scale: f64) -> Self {
+        Decay {
+            field: field.to_string(),
+            curve,
+            origin,
+            scale,
+            offset: 0.0,
+            decay: default_decay(),
+        }
+    }
+
+    pub fn gauss(field: &str, origin: f64, scale: f64) -> Self {
+        Decay::new(DecayCurve::Gauss, field, origin, scale)
+    }
+
+    pub fn exp(field: &str, origin: f64, scale: f64) -> Self {
+        Decay::new(DecayCurve::Exp, field, origin, scale)
+    }
+
+    pub fn linear(field: &str, origin: f64, scale: f64) -> Self {
+        Decay::new(DecayCurve::Linear, field, origin, scale)
+    }
+
+    // Decay by the age of a date field, the origin being now
+    pub fn by_age(curve: DecayCurve, field: &str, scale: Duration) -> Self {
+        Decay::new(
+            curve,
+            field,
+            tiering::now_secs() as f64,
+            scale.as_secs_f64(),
+        )
+    }
+
+    pub fn offset(mut self, offset: f64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn decay(mut self, decay: f64) -> Self {
+        self.decay = decay;
+        self
+    }
+
+    // A positive scale and a decay within (0, 1)
+    pub fn validate(&self) -> Result<(), MSErrors> {
+        if self.scale.is_nan() || self.scale <= 0.0 {
+            return Err(MSErrors::SearchError(format!(
+                "decay on {}: scale {} is not positive",
+                self.field, self.scale
+            )));
+        }
+        if self.decay.is_nan() || self.decay <= 0.0 || self.decay >= 1.0 {
+            return Err(MSErrors::SearchError(format!(
+                "decay on {}: {} is not within (0, 1)",
+                self.field, self.decay
+            )));
+        }
+        Ok(())
+    }
+
+    // Factor applied to the score of a hit whose field holds `value`
+    pub fn factor(&self, value: f64) -> f64 {
+        if self.validate().is_err() || value.is_nan() {
+            return 1.0;
+        }
+        let distance = ((value - self.origin).abs() - self.offset.max(0.0)).max(0.0) / self.scale;
+        match self.curve {
+            DecayCurve::Gauss => self.decay.powf(distance * distance),
+            DecayCurve::Exp => self.decay.powf(distance),
+            DecayCurve::Linear => (1.0 - distance * (1.0 - self.decay)).max(0.0),
+        }
+    }
+
+    pub(crate) fn doc_factor(&self, doc_values: &DocValues, doc_id: DocId) -> f64 {
+        doc_values
+            .get_number(&self.field, doc_id)
+            .map_or(1.0, |value| self.factor(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decay_curves() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+        for decay in [
+            Decay::gauss("price", 100.0, 20.0),
+            Decay::exp("price", 100.0, 20.0),
+            Decay::linear("price", 100.0, 20.0),
+        ] {
+            let decay = decay.offset(5.0);
+            assert_eq!(decay.factor(100.0), 1.0);
+            assert_eq!(decay.factor(95.0), 1.0);
+            assert!(close(decay.factor(125.0), 0.5));
+            assert!(close(decay.factor(75.0), 0.5));
+            assert!(decay.factor(135.0) < 0.5);
+            assert_eq!(decay.clone().decay(1.0).factor(500.0), 1.0);
+        }
+        assert!(close(Decay::gauss("x", 0.0, 1.0).factor(2.0), 0.0625));
+        assert!(close(Decay::exp("x", 0.0, 1.0).factor(2.0), 0.25));
+        assert_eq!(Decay::linear("x", 0.0, 1.0).factor(2.0), 0.0);
+        assert_eq!(Decay::linear("x", 0.0, 1.0).factor(f64::NAN), 1.0);
+    }
+
+    #[test]
+    fn test_decay_validate() {
+        assert!(Decay::gauss("x", 0.0, 1.0).validate().is_ok());
+        assert!(Decay::gauss("x", 0.0, 0.0).validate().is_err());
+        assert!(Decay::gauss("x", 0.0, f64::NAN).validate().is_err());
+        assert!(Decay::gauss("x", 0.0, 1.0).decay(1.0).validate().is_err());
+        assert!(Decay::gauss("x", 0.0, 1.0).decay(0.0).validate().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_decay_serde_defaults() {
+        let json = r#"{"field":"age","curve":"exp","origin":0.0,"scale":10.0}"#;
+        let decay: Decay = serde_json::from_str(json).unwrap();
+        assert_eq!(decay, Decay::exp("age", 0.0, 10.0));
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::document::Document;
+use crate::indexer::DocId;
+
+// Which document to drop when an ephemeral engine is over capacity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EvictionPolicy {
+    Fifo, // Oldest indexed document first
+    Lru,  // Least recently returned by a search first
+}
+
+// Limits for an ephemeral engine; `None` means unbounded
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Capacity {
+    pub max_documents: Option<usize>,
+    pub max_memory_bytes: Option<usize>,
+}
+
+// Tracks document order and approximate memory use for eviction
+pub(crate) struct EvictionQueue {
+    capacity: Capacity,
+    policy: EvictionPolicy,
+    clock: u64,
+    order: BTreeMap<u64, DocId>, // Tick of last insert/access -> document
+    ticks: HashMap<DocId, (u64, usize)>, // Document -> (tick, approximate size)
+    memory_bytes: usize,
+}
+
+impl EvictionQueue {
+    pub fn new(capacity: Capacity, policy: EvictionPolicy) -> Self {
+        EvictionQueue {
+            capacity,
+            policy,
+            clock: 0,
+            order: BTreeMap::new(),
+            ticks: HashMap::new(),
+            memory_bytes: 0,
+        }
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        self.memory_bytes
+    }
+
+    pub fn insert(&mut self, doc_id: DocId, document: &Document) {
+        let size = approximate_size(document);
+        self.clock += 1;
+        self.order.insert(self.clock, doc_id);
+        self.ticks.insert(doc_id, (self.clock, size));
+        self.memory_bytes += size;
+    }
+
+    pub fn remove(&mut self, doc_id: DocId) {
+        if let Some((tick, size)) = self.ticks.remove(&doc_id) {
+            self.order.remove(&tick);
+            self.memory_bytes -= size;
+        }
+    }
+
+    // Record that a document was returned by a search; only matters for LRU
+    pub fn touch(&mut self, doc_id: DocId) {
+        if self.policy != EvictionPolicy::Lru {
+            return;
+        }
+        if let Some((tick, _)) = self.ticks.get_mut(&doc_id) {
+            self.order.remove(tick);
+            self.clock += 1;
+            *tick = self.clock;
+            self.order.insert(self.clock, doc_id);
+        }
+    }
+
+    // Pick the next document to evict, always keeping at least one
+    pub fn next_victim(&self) -> Option<DocId> {
+        let over_documents = self
+            .capacity
+            .max_documents
+            .is_some_and(|max| self.ticks.len() > max);
+        let over_memory = self
+            .capacity
+            .max_memory_bytes
+            .is_some_and(|max| self.memory_bytes > max);
+        if (over_documents || over_memory) && self.ticks.len() > 1 {
+            self.order.values().next().copied()
+        } else {
+            None
+        }
+    }
+}
+
+// Rough heap footprint of a document and its postings
+fn approximate_size(document: &Document) -> usize {
+    let text = document.title.len() + document.content.len();
+    let metadata: usize = document
+        .metadata
+        .iter()
+        .map(|(k, v)| k.len() + v.len())
+        .sum();
+    // Postings store positions and offsets, roughly doubling the text size
+    2 * text + metadata + std::mem::size_of::<Document>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(id: u64, content: &str) -> Document {
+        Document {
+            id,
+            title: String::new(),
+            content: content.to_string(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_fifo_victim_order() {
+        let capacity = Capacity {
+            max_documents: Some(2),
+            ..Capacity::default()
+        };
+        let mut queue = EvictionQueue::new(capacity, EvictionPolicy::Fifo);
+        queue.insert(DocId(1), &doc(1, "a"));
+        queue.insert(DocId(2), &doc(2, "b"));
+        assert_eq!(queue.next_victim(), None);
+
+        queue.insert(DocId(3), &doc(3, "c"));
+        queue.touch(DocId(1));
+        assert_eq!(queue.next_victim(), Some(DocId(1)));
+    }
+
+    #[test]
+    fn test_lru_touch_and_memory() {
+        let capacity = Capacity {
+            max_memory_bytes: Some(1),
+            ..Capacity::default()
+        };
+        let mut queue = EvictionQueue::new(capacity, EvictionPolicy::Lru);
+        queue.insert(DocId(1), &doc(1, "first"));
+        assert_eq!(queue.next_victim(), None);
+
+        queue.insert(DocId(2), &doc(2, "second"));
+        queue.touch(DocId(1));
+        assert_eq!(queue.next_victim(), Some(DocId(2)));
+
+        queue.remove(DocId(2));
+        queue.remove(DocId(1));
+        assert_eq!(queue.memory_bytes(), 0);
+    }
+}
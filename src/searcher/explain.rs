@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use crate::rank::TermExplanation;
+
+// Time spent in each phase of an explained query
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PhaseTimings {
+    pub parse: Duration,
+    pub candidates: Duration, // Postings lookup and filters
+    pub scoring: Duration,    // BM25 and phrase matching
+    pub sort: Duration,
+}
+
+/*
+Why a document scores what it does for a query, from
+`SearchEngine::explain`. The score is the sum of the term scores, times
+the document boost, the phrase boost and the decays, unless a reranker
+rescored the hit. A document that did not match still gets its term
+breakdown, with `rank` unset.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    pub doc_id: u64,
+    pub score: f64,
+    pub rank: Option<usize>, // 1-based position among all matches
+    pub total_matches: usize,
+    pub terms: Vec<TermExplanation>, // Query terms found in the document
+    pub doc_boost: f64,              // Static boost set at index time
+    pub phrase_boost: f64,           // From quoted phrases, 1 without any
+    pub decay: f64,                  // Product of the search's decays, 1 without any
+    pub doc_length: usize,           // Tokens in the document
+    pub avg_doc_length: f64,
+    pub total_docs: usize,
+    pub timings: PhaseTimings,
+}
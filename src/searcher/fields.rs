@@ -0,0 +1,72 @@
+/*
+Pull `field:value` and `field:"several words"` clauses out of a query
+string for the fields `is_field` accepts, returning the remaining text and
+the (field, value) pairs. Other text containing a colon is left as is.
+*/
+pub(crate) fn extract_field_clauses(
+    query: &str,
+    is_field: impl Fn(&str) -> bool,
+) -> (String, Vec<(String, String)>) {
+    let mut text = String::new();
+    let mut clauses = Vec::new();
+    let mut rest = query;
+    while let Some(colon) = rest.find(':') {
+        let field_start = rest[..colon]
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let field = &rest[field_start..colon];
+        if !is_field(field) {
+            text.push_str(&rest[..=colon]);
+            rest = &rest[colon + 1..];
+            continue;
+        }
+        let after = &rest[colon + 1..];
+        let (value, len) = match after
+            .strip_prefix('"')
+            .and_then(|v| Some((v, v.find('"')?)))
+        {
+            Some((quoted, end)) => (&quoted[..end], end + 2),
+            None => {
+                let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        text.push_str(&rest[..field_start]);
+        if !value.is_empty() {
+            clauses.push((field.to_string(), value.to_string()));
+        }
+        rest = &after[len..];
+    }
+    text.push_str(rest);
+    (text, clauses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_field_clauses() {
+        let (text, clauses) = extract_field_clauses(
+            r#"fox author:"Ada Lovelace" note:12:30 tag:rust lazy"#,
+            |field| matches!(field, "author" | "tag"),
+        );
+        assert_eq!(
+            text.split_whitespace().collect::<Vec<_>>(),
+            ["fox", "note:12:30", "lazy"]
+        );
+        assert_eq!(
+            clauses,
+            vec![
+                ("author".to_string(), "Ada Lovelace".to_string()),
+                ("tag".to_string(), "rust".to_string()),
+            ]
+        );
+
+        // Whitespace of several bytes before the field
+        let (text, clauses) = extract_field_clauses("fox\u{3000}tag:rust", |field| field == "tag");
+        assert_eq!(text, "fox\u{3000}");
+        assert_eq!(clauses, vec![("tag".to_string(), "rust".to_string())]);
+    }
+}
@@ -0,0 +1,261 @@
+use crate::errors::MSErrors;
+use crate::indexer::{DocId, DocValues, GeoPoint, parse_number};
+
+// Structured conditions on document metadata, checked before scoring
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Filter {
+    Eq(String, String),                      // field == value
+    In(String, Vec<String>),                 // field is one of the values
+    Range(String, Option<f64>, Option<f64>), // min <= field <= max, None is unbounded
+    WithinRadius(String, GeoPoint, f64),     // Geo-point field within km of the point
+    Not(Box<Filter>),
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+}
+
+impl Filter {
+    pub fn eq(field: &str, value: &str) -> Self {
+        Filter::Eq(field.to_string(), value.to_string())
+    }
+
+    pub fn one_of<S: AsRef<str>>(field: &str, values: impl IntoIterator<Item = S>) -> Self {
+        Filter::In(
+            field.to_string(),
+            values.into_iter().map(|v| v.as_ref().to_string()).collect(),
+        )
+    }
+
+    pub fn range(field: &str, min: Option<f64>, max: Option<f64>) -> Self {
+        Filter::Range(field.to_string(), min, max)
+    }
+
+    // Documents dated at or after an RFC 3339 date
+    pub fn after(field: &str, date: &str) -> Result<Self, MSErrors> {
+        Ok(Filter::range(field, Some(parse_date(date)?), None))
+    }
+
+    // Documents dated at or before an RFC 3339 date
+    pub fn before(field: &str, date: &str) -> Result<Self, MSErrors> {
+        Ok(Filter::range(field, None, Some(parse_date(date)?)))
+    }
+
+    // Documents whose geo-point `field` lies within `km` of (lat, lon)
+    pub fn within_radius(field: &str, lat: f64, lon: f64, km: f64) -> Result<Self, MSErrors> {
+        let center = geo_point(lat, lon)?;
+        if km.is_nan() || km < 0.0 {
+            return Err(MSErrors::InvalidTerm {
+                term: km.to_string(),
+                message: "not a valid radius in km".to_string(),
+            });
+        }
+        Ok(Filter::WithinRadius(field.to_string(), center, km))
+    }
+
+    pub fn negate(self) -> Self {
+        Filter::Not(Box::new(self))
+    }
+
+    /*
+    Documents without the field never match Eq, In, Range or WithinRadius.
+    A multi-valued field matches Eq, In and Range if any of its values does.
+    */
+    pub fn matches(&self, doc_values: &DocValues, doc_id: DocId) -> bool {
+        match self {
+            Filter::Eq(field, value) => doc_values.values(field, doc_id).contains(&value.as_str()),
+            Filter::In(field, values) => doc_values
+                .values(field, doc_id)
+                .iter()
+                .any(|v| values.iter().any(|value| value == v)),
+            Filter::Range(field, min, max) => {
+                doc_values.get_numbers(field, doc_id).iter().any(|&value| {
+                    min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+                })
+            }
+            Filter::WithinRadius(field, center, km) => doc_values
+                .get_point(field, doc_id)
+                .is_some_and(|point| point.distance_km(center) <= *km),
+            Filter::Not(filter) => !filter.matches(doc_values, doc_id),
+            Filter::And(filters) => filters.iter().all(|f| f.matches(doc_values, doc_id)),
+            Filter::Or(filters) => filters.iter().any(|f| f.matches(doc_values, doc_id)),
+        }
+    }
+}
+
+// The point at (lat, lon), or an error naming the invalid coordinates
+pub(super) fn geo_point(lat: f64, lon: f64) -> Result<GeoPoint, MSErrors> {
+    GeoPoint::new(lat, lon).ok_or_else(|| MSErrors::InvalidTerm {
+        term: format!("{},{}", lat, lon),
+        message: "not a valid latitude and longitude".to_string(),
+    })
+}
+
+/*
+Pull `field:[min TO max]` clauses out of a query string, returning the
+remaining text and one Range filter per clause. Bounds are numbers or
+RFC 3339 dates, they are inclusive and `*` leaves a side open. Malformed
+clauses are left in the text.
+*/
+pub(crate) fn extract_ranges(query: &str) -> (String, Vec<Filter>) {
+    let mut text = String::new();
+    let mut filters = Vec::new();
+    let mut rest = query;
+    while let Some(open) = rest.find(":[") {
+        let Some(close) = rest[open..].find(']').map(|i| open + i) else {
+            break;
+        };
+        let field_start = rest[..open]
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        match parse_range(&rest[field_start..open], &rest[open + 2..close]) {
+            Some(filter) => {
+                text.push_str(&rest[..field_start]);
+                filters.push(filter);
+            }
+            None => text.push_str(&rest[..=close]),
+        }
+        rest = &rest[close + 1..];
+    }
+    text.push_str(rest);
+    (text, filters)
+}
+
+/*
+Check the `field:[min TO max]` clauses of a query, which `extract_ranges`
+would otherwise leave in the text. Errors point at the byte of the query
+where the clause goes wrong.
+*/
+pub(crate) fn check_ranges(query: &str) -> Result<(), MSErrors> {
+    let error = |position, expected: &[&str], message: String| MSErrors::QueryParseError {
+        position,
+        expected: expected.iter().map(|token| token.to_string()).collect(),
+        message,
+    };
+    let mut from = 0;
+    while let Some(open) = query[from..].find(":[").map(|i| from + i) {
+        let Some(close) = query[open..].find(']').map(|i| open + i) else {
+            return Err(error(query.len(), &["]"], "unclosed range".to_string()));
+        };
+        let bounds = &query[open + 2..close];
+        let Some(to) = bounds.find(" TO ") else {
+            return Err(error(close, &["TO"], "range without TO".to_string()));
+        };
+        for (start, bound) in [
+            (open + 2, &bounds[..to]),
+            (open + 2 + to + 4, &bounds[to + 4..]),
+        ] {
+            let trimmed = bound.trim();
+            if trimmed != "*" && parse_number(trimmed).is_none() {
+                let position = start + bound.len() - bound.trim_start().len();
+                let message = format!("invalid range bound \"{}\"", trimmed);
+                return Err(error(position, &["number", "date", "*"], message));
+            }
+        }
+        from = close + 1;
+    }
+    Ok(())
+}
+
+fn parse_date(date: &str) -> Result<f64, MSErrors> {
+    crate::document::date::parse_rfc3339(date).ok_or_else(|| MSErrors::InvalidTerm {
+        term: date.to_string(),
+        message: "not an RFC 3339 date".to_string(),
+    })
+}
+
+fn parse_range(field: &str, bounds: &str) -> Option<Filter> {
+    if field.is_empty() {
+        return None;
+    }
+    let bound = |bound: &str| match bound.trim() {
+        "*" => Some(None),
+        bound => parse_number(bound).map(Some),
+    };
+    let (min, max) = bounds.split_once(" TO ")?;
+    Some(Filter::range(field, bound(min)?, bound(max)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_filter_matches() {
+        let mut doc_values = DocValues::new();
+        let metadata = HashMap::from([
+            ("lang".to_string(), "en".to_string()),
+            ("tag".to_string(), "rust".to_string()),
+            ("price".to_string(), "20".to_string()),
+        ]);
+        doc_values.add_document(DocId(1), &metadata);
+
+        assert!(Filter::eq("lang", "en").matches(&doc_values, DocId(1)));
+        assert!(!Filter::eq("lang", "de").matches(&doc_values, DocId(1)));
+        assert!(Filter::one_of("tag", ["go", "rust"]).matches(&doc_values, DocId(1)));
+        assert!(!Filter::eq("missing", "x").matches(&doc_values, DocId(1)));
+        assert!(
+            Filter::eq("lang", "de")
+                .negate()
+                .matches(&doc_values, DocId(1))
+        );
+        assert!(Filter::range("price", Some(10.0), Some(20.0)).matches(&doc_values, DocId(1)));
+        assert!(!Filter::range("price", Some(25.0), None).matches(&doc_values, DocId(1)));
+        assert!(!Filter::range("lang", None, None).matches(&doc_values, DocId(1)));
+
+        let both = Filter::And(vec![Filter::eq("lang", "en"), Filter::eq("tag", "go")]);
+        let either = Filter::Or(vec![Filter::eq("lang", "en"), Filter::eq("tag", "go")]);
+        assert!(!both.matches(&doc_values, DocId(1)));
+        assert!(either.matches(&doc_values, DocId(1)));
+    }
+
+    #[test]
+    fn test_extract_ranges() {
+        let (text, filters) = extract_ranges("red mug price:[10 TO 50] words:[* TO 3.5]");
+        assert_eq!(text.trim(), "red mug");
+        assert_eq!(
+            filters,
+            vec![
+                Filter::range("price", Some(10.0), Some(50.0)),
+                Filter::range("words", None, Some(3.5)),
+            ]
+        );
+
+        let (_, filters) = extract_ranges("published:[2024-05-01 TO *]");
+        assert_eq!(
+            filters,
+            vec![Filter::after("published", "2024-05-01").unwrap()]
+        );
+        assert!(Filter::before("published", "soon").is_err());
+
+        assert!(check_ranges("red mug price:[10 TO 50] published:[2024-05-01 TO *]").is_ok());
+        for (query, position, expected) in [
+            ("price:[10 TO 50", 15, "]"),
+            ("price:[10 50]", 12, "TO"),
+            ("price:[10 TO  x] mug", 14, "number"),
+        ] {
+            match check_ranges(query) {
+                Err(MSErrors::QueryParseError {
+                    position: at,
+                    expected: tokens,
+                    ..
+                }) => {
+                    assert_eq!(at, position, "{}", query);
+                    assert_eq!(tokens[0], expected, "{}", query);
+                }
+                other => panic!("{}: expected QueryParseError, got {:?}", query, other),
+            }
+        }
+
+        let (text, filters) = extract_ranges("a:[x TO 2] [1 TO 2]");
+        assert_eq!(text, "a:[x TO 2] [1 TO 2]");
+        assert!(filters.is_empty());
+
+        // Whitespace of several bytes before the field
+        let (text, filters) = extract_ranges("mug\u{3000}price:[10 TO 50]\u{a0}words:[* TO 3]");
+        assert_eq!(text, "mug\u{3000}\u{a0}");
+        assert_eq!(filters.len(), 2);
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use roaring::RoaringTreemap;
+
+use super::Filter;
+use crate::indexer::{DocId, DocValues};
+
+// Uses of a filter before the documents it matches are cached
+const MIN_USES: u32 = 2;
+
+struct CachedFilter {
+    filter: Filter,
+    uses: u32,
+    last_used: u64,
+    docs: Option<Arc<RoaringTreemap>>, // Set once the filter has been used MIN_USES times
+}
+
+// What a search does with a filter, as told by `FilterCache::get`
+pub(super) enum Lookup {
+    Cached(Arc<RoaringTreemap>),
+    Build, // Used often enough: build its set, then `insert` it
+    Uncached,
+}
+
+// The documents among `doc_ids` that `filter` matches, to `insert` into the cache
+pub(super) fn build(
+    filter: &Filter,
+    doc_values: &DocValues,
+    doc_ids: impl Iterator<Item = DocId>,
+) -> RoaringTreemap {
+    doc_ids
+        .filter(|&doc_id| filter.matches(doc_values, doc_id))
+        .map(DocId::get)
+        .collect()
+}
+
+/*
+Document sets of frequently used filters, as roaring bitmaps. Repeated
+filtered searches (e.g. `lang=en`) then check set membership instead of
+evaluating the filter on every candidate. Cached sets are updated as
+documents are added and removed, so they never go stale. Sets are built
+by the caller between `get` and `insert`, so a cache behind a lock is not
+held while every document is scanned.
+*/
+pub(super) struct FilterCache {
+    entries: HashMap<String, CachedFilter>, // Keyed by the filter's debug form
+    capacity: usize, // Most filters tracked at once; the least recently used is dropped first
+    clock: u64,
+}
+
+impl FilterCache {
+    // A cache tracking up to `capacity` filters, `cache_size` in the settings; 0 caches none
+    pub fn new(capacity: usize) -> Self {
+        FilterCache {
+            entries: HashMap::new(),
+            capacity,
+            clock: 0,
+        }
+    }
+
+    // Track up to `capacity` filters from now on, dropping the least recently used
+    pub fn resize(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            self.evict();
+        }
+    }
+
+    /*
+    Record a use of `filter` and return the documents it matches if they
+    are cached, or whether it is now used often enough to cache them.
+    */
+    pub fn get(&mut self, filter: &Filter) -> Lookup {
+        self.use_filter(filter, 1)
+    }
+
+    // Same, asking for the set to be built now, however often it was used
+    pub fn preload(&mut self, filter: &Filter) -> Lookup {
+        self.use_filter(filter, MIN_USES)
+    }
+
+    /*
+    Cache the documents `filter` matches, built after `get` asked for
+    them. If another search cached them first, those are kept; if the
+    filter was evicted meanwhile, `docs` is returned uncached.
+    */
+    pub fn insert(&mut self, filter: &Filter, docs: RoaringTreemap) -> Arc<RoaringTreemap> {
+        match self.entries.get_mut(&format!("{:?}", filter)) {
+            Some(entry) => entry.docs.get_or_insert_with(|| Arc::new(docs)).clone(),
+            None => Arc::new(docs),
+        }
+    }
+
+    fn use_filter(&mut self, filter: &Filter, uses: u32) -> Lookup {
+        if self.capacity == 0 {
+            return Lookup::Uncached;
+        }
+        self.clock += 1;
+        let key = format!("{:?}", filter);
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            self.evict();
+        }
+        let entry = self.entries.entry(key).or_insert_with(|| CachedFilter {
+            filter: filter.clone(),
+            uses: 0,
+            last_used: 0,
+            docs: None,
+        });
+        entry.uses += uses;
+        entry.last_used = self.clock;
+        match &entry.docs {
+            Some(docs) => Lookup::Cached(docs.clone()),
+            None if entry.uses >= MIN_USES => Lookup::Build,
+            None => Lookup::Uncached,
+        }
+    }
+
+    // Add a newly indexed document to the sets of the filters it matches
+    pub fn add_document(&mut self, doc_values: &DocValues, doc_id: DocId) {
+        for entry in self.entries.values_mut() {
+            if let Some(docs) = &mut entry.docs
+                && entry.filter.matches(doc_values, doc_id)
+            {
+                Arc::make_mut(docs).insert(doc_id.get());
+            }
+        }
+    }
+
+    pub fn remove_document(&mut self, doc_id: DocId) {
+        for docs in self.entries.values_mut().filter_map(|e| e.docs.as_mut()) {
+            Arc::make_mut(docs).remove(doc_id.get());
+        }
+    }
+
+    // Number of filters whose document sets are cached
+    pub fn cached(&self) -> usize {
+        self.entries.values().filter(|e| e.docs.is_some()).count()
+    }
+
+    fn evict(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone());
+        if let Some(key) = oldest {
+            self.entries.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_filter_cache() {
+        let mut doc_values = DocValues::new();
+        for (doc_id, lang) in [(1, "en"), (2, "de"), (3, "en")] {
+            let metadata = HashMap::from([("lang".to_string(), lang.to_string())]);
+            doc_values.add_document(DocId(doc_id), &metadata);
+        }
+        let mut cache = FilterCache::new(64);
+        let english = Filter::eq("lang", "en");
+        assert!(matches!(cache.get(&english), Lookup::Uncached));
+        assert!(matches!(cache.get(&english), Lookup::Build));
+        let built = build(&english, &doc_values, (1..=3).map(DocId));
+        let docs = cache.insert(&english, built);
+        assert_eq!(docs.iter().collect::<Vec<_>>(), [1, 3]);
+        // A set built by a concurrent search loses to the cached one
+        let docs = cache.insert(&english, RoaringTreemap::new());
+        assert_eq!(docs.len(), 2);
+
+        let metadata = HashMap::from([("lang".to_string(), "en".to_string())]);
+        doc_values.add_document(DocId(4), &metadata);
+        cache.add_document(&doc_values, DocId(4));
+        cache.remove_document(DocId(1));
+        let Lookup::Cached(docs) = cache.get(&english) else {
+            panic!("not cached");
+        };
+        assert_eq!(docs.iter().collect::<Vec<_>>(), [3, 4]);
+        assert_eq!(cache.cached(), 1);
+
+        for i in 0..64 {
+            cache.get(&Filter::eq("n", &i.to_string()));
+        }
+        assert_eq!(cache.entries.len(), 64);
+        assert_eq!(cache.cached(), 0);
+        assert!(matches!(cache.preload(&english), Lookup::Build));
+        cache.insert(&english, build(&english, &doc_values, (1..=4).map(DocId)));
+        assert_eq!(cache.cached(), 1);
+    }
+
+    #[test]
+    fn test_filter_cache_capacity() {
+        let mut cache = FilterCache::new(3);
+        for i in 0..5 {
+            cache.get(&Filter::eq("n", &i.to_string()));
+        }
+        assert_eq!(cache.entries.len(), 3);
+        // Shrinking drops the least recently used filters
+        cache.resize(1);
+        assert_eq!(cache.entries.len(), 1);
+        assert!(
+            cache
+                .entries
+                .contains_key(&format!("{:?}", Filter::eq("n", "4")))
+        );
+
+        let mut disabled = FilterCache::new(0);
+        let english = Filter::eq("lang", "en");
+        assert!(matches!(disabled.preload(&english), Lookup::Uncached));
+        assert!(disabled.entries.is_empty());
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+// Free space below which a persistent engine reports itself unhealthy
+pub const MIN_DISK_HEADROOM: u64 = 64 * 1024 * 1024;
+
+// How long changes may stay unflushed before the engine reports degraded
+pub const MAX_UNFLUSHED_AGE: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum HealthStatus {
+    Healthy,
+    Degraded,  // Serving, but changes are at risk
+    Unhealthy, // Should not receive traffic
+}
+
+// Snapshot of an engine's state for health and readiness endpoints
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Health {
+    pub status: HealthStatus,
+    pub persistent: bool,                  // False for in-memory engines
+    pub pending_changes: usize,            // Adds and removals not flushed yet
+    pub cold_segments: usize,              // Segments moved to disk by tiering
+    pub last_flush_age: Option<Duration>,  // Since the last flush, or since opening
+    pub disk_available_bytes: Option<u64>, // Free space for the index directory, if known
+    pub poisoned_locks: bool,              // A thread panicked while holding engine state
+}
+
+impl Health {
+    // Ready to serve queries; degraded engines still are
+    pub fn is_ready(&self) -> bool {
+        self.status != HealthStatus::Unhealthy
+    }
+}
+
+// What `SearchEngine::warm_up` prepared
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WarmUp {
+    pub segments_loaded: usize, // Cold segments now held in memory
+    pub queries_run: usize,     // Including those taken from the query log
+    pub filters_cached: usize,  // Cached filters, summed over the hot engine and loaded segments
+    pub elapsed: Duration,
+}
+
+// Unflushed changes, for the health report
+pub(crate) struct CommitState {
+    pub pending_changes: usize,
+    pub last_flush: Instant,
+}
+
+impl Default for CommitState {
+    fn default() -> Self {
+        CommitState {
+            pending_changes: 0,
+            last_flush: Instant::now(),
+        }
+    }
+}
+
+impl CommitState {
+    pub fn flushed(&mut self) {
+        self.pending_changes = 0;
+        self.last_flush = Instant::now();
+    }
+}
+
+// Free space on the disk holding `path`, None when it cannot be read
+#[cfg(feature = "disk-space")]
+pub(crate) fn available_space(path: &Path) -> Option<u64> {
+    fs4::available_space(path).ok()
+}
+
+// Without the disk-space feature headroom is never known
+#[cfg(not(feature = "disk-space"))]
+pub(crate) fn available_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+/*
+Worst status implied by the individual checks. Unknown disk headroom is
+not held against the engine; only a reading below the minimum is.
+*/
+pub(crate) fn status(health: &Health) -> HealthStatus {
+    let low_disk = health.persistent
+        && health
+            .disk_available_bytes
+            .is_some_and(|bytes| bytes < MIN_DISK_HEADROOM);
+    let stale = health.pending_changes > 0
+        && health
+            .last_flush_age
+            .is_some_and(|age| age > MAX_UNFLUSHED_AGE);
+    if health.poisoned_locks || low_disk {
+        HealthStatus::Unhealthy
+    } else if stale {
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Healthy
+    }
+}
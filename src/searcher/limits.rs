@@ -0,0 +1,160 @@
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::errors::MSErrors;
+
+// Caps on the queries an engine accepts; `None` means unbounded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueryLimits {
+    pub max_concurrent: Option<usize>,
+    pub queries_per_second: Option<f64>, // Token bucket refill rate
+    pub burst: usize,                    // Token bucket size
+    pub queue_timeout: Duration,         // How long a query may wait for a slot
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        QueryLimits {
+            max_concurrent: None,
+            queries_per_second: None,
+            burst: 1,
+            queue_timeout: Duration::ZERO,
+        }
+    }
+}
+
+struct LimiterState {
+    running: usize,
+    tokens: f64,
+    refilled: Instant,
+}
+
+/*
+Admission control for queries: a concurrency cap plus a token bucket.
+Queries wait on a condition variable until both allow them in, or fail
+with MSErrors::Busy once the queue timeout has passed.
+*/
+pub(crate) struct Limiter {
+    limits: QueryLimits,
+    state: Mutex<LimiterState>,
+    released: Condvar, // Signalled when a running query finishes
+}
+
+// A running query; dropping it frees its slot
+pub(crate) struct Permit<'a> {
+    limiter: Option<&'a Limiter>, // None when nothing is limited, so no slot was taken
+}
+
+impl Limiter {
+    pub fn new(limits: QueryLimits) -> Self {
+        Limiter {
+            limits,
+            state: Mutex::new(LimiterState {
+                running: 0,
+                tokens: limits.burst.max(1) as f64,
+                refilled: Instant::now(),
+            }),
+            released: Condvar::new(),
+        }
+    }
+
+    pub fn acquire(&self) -> Result<Permit<'_>, MSErrors> {
+        // Without limits there is no state to share, so no lock to take
+        if self.limits.max_concurrent.is_none() && self.limits.queries_per_second.is_none() {
+            return Ok(Permit { limiter: None });
+        }
+        let deadline = Instant::now() + self.limits.queue_timeout;
+        let burst = self.limits.burst.max(1) as f64;
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            if let Some(rate) = self.limits.queries_per_second {
+                let elapsed = now.duration_since(state.refilled).as_secs_f64();
+                state.tokens = (state.tokens + elapsed * rate).min(burst);
+            }
+            state.refilled = now;
+
+            let slot_free = self
+                .limits
+                .max_concurrent
+                .is_none_or(|max| state.running < max);
+            let token_free = self.limits.queries_per_second.is_none() || state.tokens >= 1.0;
+            if slot_free && token_free {
+                state.running += 1;
+                if self.limits.queries_per_second.is_some() {
+                    state.tokens -= 1.0;
+                }
+                return Ok(Permit {
+                    limiter: Some(self),
+                });
+            }
+
+            let remaining = deadline.saturating_duration_since(now);
+            if remaining.is_zero() {
+                return Err(MSErrors::Busy(if slot_free {
+                    "query rate limit exceeded".to_string()
+                } else {
+                    "too many concurrent queries".to_string()
+                }));
+            }
+            // Wake up when the next token is due, or when a query finishes
+            let wait = match self.limits.queries_per_second {
+                Some(rate) if slot_free => {
+                    Duration::from_secs_f64((1.0 - state.tokens) / rate).min(remaining)
+                }
+                _ => remaining,
+            };
+            state = self.released.wait_timeout(state, wait).unwrap().0;
+        }
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if let Some(limiter) = self.limiter {
+            limiter.state.lock().unwrap().running -= 1;
+            limiter.released.notify_one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrency_cap() {
+        let limiter = Limiter::new(QueryLimits {
+            max_concurrent: Some(1),
+            ..QueryLimits::default()
+        });
+        let permit = limiter.acquire().unwrap();
+        assert!(matches!(limiter.acquire(), Err(MSErrors::Busy(_))));
+        drop(permit);
+        assert!(limiter.acquire().is_ok());
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let limiter = Limiter::new(QueryLimits {
+            queries_per_second: Some(50.0),
+            burst: 2,
+            ..QueryLimits::default()
+        });
+        assert!(limiter.acquire().is_ok());
+        assert!(limiter.acquire().is_ok());
+        assert!(limiter.acquire().is_err());
+
+        // Waiting in the queue lets the bucket refill
+        let limiter = Limiter::new(QueryLimits {
+            queries_per_second: Some(50.0),
+            queue_timeout: Duration::from_secs(1),
+            ..QueryLimits::default()
+        });
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire().unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+}
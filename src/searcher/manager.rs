@@ -0,0 +1,337 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::SearchEngine;
+use crate::errors::MSErrors;
+use crate::settings::IndexSettings;
+use crate::storage::{SETTINGS_FILE, Storage};
+
+// File under the root holding the aliases, one `alias = index` per line
+const ALIASES_FILE: &str = "aliases";
+
+/*
+Named indexes under one root directory, each in a directory of its own
+with its own settings, so one process can serve independent corpora.
+Indexes are loaded on first use and stay open until dropped. Names are
+used as directory names, so they are limited to ASCII letters, digits,
+'-', '_' and '.', and may not start with '.'.
+
+An alias is a second name for an index, accepted wherever an index name
+is. Pointing an alias at a freshly built index swaps it in for every
+client that looks indexes up through the alias, with no moment where the
+alias is missing: the aliases file is replaced in a single rename.
+*/
+pub struct IndexManager {
+    storage: Storage, // The root directory, holding the aliases file
+    open: BTreeMap<String, SearchEngine>,
+    aliases: BTreeMap<String, String>, // Alias -> index name
+}
+
+impl IndexManager {
+    // Manage the indexes under `root`, creating the directory if needed
+    pub fn open(root: impl AsRef<Path>) -> Result<Self, MSErrors> {
+        let storage = Storage::create(root)?;
+        let mut aliases = BTreeMap::new();
+        if storage.exists(ALIASES_FILE) {
+            let bytes = storage.read(ALIASES_FILE)?;
+            let text = String::from_utf8(bytes).map_err(|_| {
+                MSErrors::StorageError("aliases file is not valid UTF-8".to_string())
+            })?;
+            for line in text.lines().filter(|line| !line.trim().is_empty()) {
+                let Some((alias, index)) = line.split_once('=') else {
+                    return Err(MSErrors::ParseError(format!(
+                        "invalid alias line: {}",
+                        line
+                    )));
+                };
+                aliases.insert(alias.trim().to_string(), index.trim().to_string());
+            }
+        }
+        Ok(IndexManager {
+            storage,
+            open: BTreeMap::new(),
+            aliases,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.storage.path()
+    }
+
+    // The index a name refers to: the target of an alias, or the name itself
+    pub fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases.get(name).map_or(name, String::as_str)
+    }
+
+    // Every alias with the index it points to, sorted by alias
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.aliases
+    }
+
+    // Point `alias` at an existing index, replacing its previous target
+    pub fn set_alias(&mut self, alias: &str, index: &str) -> Result<(), MSErrors> {
+        check_name(alias)?;
+        if self.exists(alias) {
+            return Err(MSErrors::StorageError(format!(
+                "{:?} is already an index name",
+                alias
+            )));
+        }
+        if !self.exists(index) {
+            return Err(MSErrors::StorageError(format!(
+                "no index named {:?}",
+                index
+            )));
+        }
+        let mut aliases = self.aliases.clone();
+        aliases.insert(alias.to_string(), index.to_string());
+        self.save_aliases(aliases)
+    }
+
+    // Remove an alias, returning the index it pointed to
+    pub fn remove_alias(&mut self, alias: &str) -> Result<Option<String>, MSErrors> {
+        let mut aliases = self.aliases.clone();
+        let removed = aliases.remove(alias);
+        if removed.is_some() {
+            self.save_aliases(aliases)?;
+        }
+        Ok(removed)
+    }
+
+    // Write the aliases before switching to them, so a failed write changes nothing
+    fn save_aliases(&mut self, aliases: BTreeMap<String, String>) -> Result<(), MSErrors> {
+        let text: String = aliases
+            .iter()
+            .map(|(alias, index)| format!("{} = {}\n", alias, index))
+            .collect();
+        self.storage.write(ALIASES_FILE, text.as_bytes())?;
+        self.aliases = aliases;
+        Ok(())
+    }
+
+    // Names of the indexes on disk, sorted
+    pub fn list(&self) -> Result<Vec<String>, MSErrors> {
+        let entries = fs::read_dir(self.path()).map_err(|e| MSErrors::io(self.path(), e))?;
+        let mut names = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| MSErrors::io(self.path(), e))?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if check_name(&name).is_ok() && entry.path().join(SETTINGS_FILE).is_file() {
+                names.push(name);
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        check_name(name).is_ok() && self.path().join(name).join(SETTINGS_FILE).is_file()
+    }
+
+    pub fn create(
+        &mut self,
+        name: &str,
+        settings: IndexSettings,
+    ) -> Result<&mut SearchEngine, MSErrors> {
+        check_name(name)?;
+        if self.aliases.contains_key(name) {
+            return Err(MSErrors::StorageError(format!(
+                "{:?} is already an alias",
+                name
+            )));
+        }
+        let engine = SearchEngine::create(self.path().join(name), settings)?;
+        Ok(self
+            .open
+            .entry(name.to_string())
+            .insert_entry(engine)
+            .into_mut())
+    }
+
+    // An index or alias target, loaded from disk unless it is already open
+    pub fn index(&mut self, name: &str) -> Result<&mut SearchEngine, MSErrors> {
+        let name = self.resolve(name).to_string();
+        let name = name.as_str();
+        check_name(name)?;
+        if !self.open.contains_key(name) {
+            let engine = SearchEngine::open(self.path().join(name))?;
+            self.open.insert(name.to_string(), engine);
+        }
+        Ok(self.open.get_mut(name).unwrap())
+    }
+
+    // An index or alias target that is already open, for read-only access
+    pub fn get(&self, name: &str) -> Option<&SearchEngine> {
+        self.open.get(self.resolve(name))
+    }
+
+    // Close an index and delete its directory; it must have no aliases left
+    pub fn drop_index(&mut self, name: &str) -> Result<(), MSErrors> {
+        if !self.exists(name) {
+            return Err(MSErrors::StorageError(format!("no index named {:?}", name)));
+        }
+        if let Some((alias, _)) = self.aliases.iter().find(|(_, index)| *index == name) {
+            return Err(MSErrors::StorageError(format!(
+                "index {:?} is still the target of alias {:?}",
+                name, alias
+            )));
+        }
+        self.open.remove(name);
+        let path = self.path().join(name);
+        fs::remove_dir_all(&path).map_err(|e| MSErrors::io(path, e))
+    }
+
+    // Flush every open index
+    pub fn flush(&self) -> Result<(), MSErrors> {
+        for engine in self.open.values() {
+            engine.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn check_name(name: &str) -> Result<(), MSErrors> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(MSErrors::StorageError(format!(
+            "invalid index name {:?}",
+            name
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Document;
+    use crate::tokenizer::AnalyzerPreset;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_index_manager() {
+        let dir = std::env::temp_dir().join(format!("mini-search-manager-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut manager = IndexManager::open(&dir).unwrap();
+        let keyword = IndexSettings {
+            analyzer: AnalyzerPreset::Keyword,
+            ..IndexSettings::default()
+        };
+        manager.create("logs", IndexSettings::default()).unwrap();
+        manager.create("tags", keyword).unwrap();
+        assert!(manager.create("logs", IndexSettings::default()).is_err());
+        assert!(
+            manager
+                .create("../escape", IndexSettings::default())
+                .is_err()
+        );
+
+        let document = Document {
+            id: 1,
+            title: String::new(),
+            content: "Running Jobs".to_string(),
+            metadata: HashMap::new(),
+        };
+        for name in ["logs", "tags"] {
+            manager
+                .index(name)
+                .unwrap()
+                .add_document(document.clone())
+                .unwrap();
+        }
+        manager.flush().unwrap();
+
+        let mut manager = IndexManager::open(&dir).unwrap();
+        assert_eq!(manager.list().unwrap(), ["logs", "tags"]);
+        assert!(manager.get("logs").is_none());
+        assert_eq!(
+            manager
+                .index("logs")
+                .unwrap()
+                .search("run", 10)
+                .total_matches,
+            1
+        );
+        assert_eq!(
+            manager
+                .index("tags")
+                .unwrap()
+                .search("run", 10)
+                .total_matches,
+            0
+        );
+        assert!(manager.index("missing").is_err());
+
+        manager.drop_index("tags").unwrap();
+        assert_eq!(manager.list().unwrap(), ["logs"]);
+        assert!(manager.get("tags").is_none());
+        assert!(manager.drop_index("tags").is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_aliases() {
+        let dir = std::env::temp_dir().join(format!("mini-search-aliases-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut manager = IndexManager::open(&dir).unwrap();
+        let document = |content: &str| Document {
+            id: 1,
+            title: String::new(),
+            content: content.to_string(),
+            metadata: HashMap::new(),
+        };
+        manager
+            .create("docs_v1", IndexSettings::default())
+            .unwrap()
+            .add_document(document("old text"))
+            .unwrap();
+        manager.set_alias("prod", "docs_v1").unwrap();
+        assert!(manager.set_alias("prod", "missing").is_err());
+        assert!(manager.set_alias("docs_v1", "docs_v1").is_err());
+        assert!(manager.create("prod", IndexSettings::default()).is_err());
+
+        // Reindex in the background, then swap the alias over
+        manager
+            .create("docs_v2", IndexSettings::default())
+            .unwrap()
+            .add_document(document("new text"))
+            .unwrap();
+        assert_eq!(
+            manager
+                .index("prod")
+                .unwrap()
+                .search("old", 10)
+                .total_matches,
+            1
+        );
+        manager.set_alias("prod", "docs_v2").unwrap();
+        assert_eq!(
+            manager
+                .index("prod")
+                .unwrap()
+                .search("new", 10)
+                .total_matches,
+            1
+        );
+        assert!(manager.drop_index("docs_v2").is_err());
+        manager.drop_index("docs_v1").unwrap();
+        manager.flush().unwrap();
+
+        let mut manager = IndexManager::open(&dir).unwrap();
+        assert_eq!(manager.resolve("prod"), "docs_v2");
+        assert_eq!(manager.list().unwrap(), ["docs_v2"]);
+        assert_eq!(manager.index("prod").unwrap().document_count(), 1);
+        assert_eq!(
+            manager.remove_alias("prod").unwrap().as_deref(),
+            Some("docs_v2")
+        );
+        assert!(manager.aliases().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,42 +1,4486 @@
-use crate::{document::Document, indexer::InvertedIndex, tokenizer::Tokenizer};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
+use roaring::RoaringTreemap;
+
+use crate::{
+    cluster::{self, ClusterMethod, Clustering, SimilarityMatrix, TermVector},
+    document::{
+        Document,
+        chunk::{Chunker, PARENT_FIELD},
+    },
+    errors::MSErrors,
+    highlight::{self, Occurrences},
+    indexer::{
+        DocId, DocValues, GeoPoint, InvertedIndex, Statistics, VectorIndex, field_term,
+        parse_number, parse_vector,
+    },
+    rank::{self, Bm25, DocStats, Fusion, Ranker, Similarity},
+    settings::{FieldType, IndexSettings, MergePolicy, RAW_SUFFIX, SettingsUpdate, StatisticsMode},
+    storage::{SETTINGS_FILE, SegmentMeta, Storage, StoredDocument},
+    suggest::{self, Completion, Suggestion, Trie},
+    tokenizer::{AnalyzerPreset, LANGUAGE_FIELD, Language, LanguageDetector, Token, Tokenizer},
+};
+
+#[cfg(feature = "tokio")]
+mod async_api;
+mod decay;
+mod ephemeral;
+mod explain;
+mod fields;
+mod filter;
+mod filter_cache;
+mod health;
+mod limits;
+mod manager;
+mod options;
+mod phrase;
+mod reader;
+mod recorder;
+mod refresh;
+mod rerank;
+mod rollover;
+mod stream;
+mod tiering;
+mod top_k;
+mod view;
+
+#[cfg(feature = "tokio")]
+pub use async_api::AsyncSearchEngine;
+pub use decay::{Decay, DecayCurve};
+use ephemeral::EvictionQueue;
+pub use ephemeral::{Capacity, EvictionPolicy};
+pub use explain::{Explanation, PhaseTimings};
+pub use filter::Filter;
+use filter_cache::{FilterCache, Lookup};
+use health::CommitState;
+pub use health::{Health, HealthStatus, WarmUp};
+use limits::Limiter;
+pub use limits::QueryLimits;
+pub use manager::IndexManager;
+pub use options::{Direction, MinimumShouldMatch, SearchOptions, SortKey, SortSpec};
+use options::{SortValue, SortableHit};
+use phrase::Phrase;
+pub use reader::{IndexReader, IndexWriter};
+use recorder::QueryRecorder;
+pub use recorder::{LatencySummary, QueryRecord, ReplayReport, read_query_log, replay};
+pub use refresh::RefreshPolicy;
+use refresh::{PendingWrite, WriteBuffer};
+pub use rerank::{LinearReranker, Rerank, RerankFeatures, Reranker};
+pub use rollover::{PartitionedIndex, RolloverPeriod, RolloverPolicy};
+pub use stream::{Hit, SearchIter};
+use tiering::Tiering;
+pub use tiering::TieringPolicy;
+pub use view::{HitView, ResultPage};
+
+// Length in bytes of the snippet shown for each hit
+const SNIPPET_LEN: usize = 160;
+
+// Number of terms taken from the source document by more_like_this
+const MORE_LIKE_THIS_TERMS: usize = 25;
+
+// Most frequent logged queries run by warm_up when given none
+const WARM_UP_QUERIES: usize = 100;
+
+// Fewest hits of each side merged by hybrid_search, so fusion sees more than one page
+const HYBRID_CANDIDATES: usize = 100;
+
+// Candidate sets at least this large are scored on the rayon thread pool
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_CANDIDATES: usize = 2048;
+
+// Scored matches of a query
+struct ScoredMatches {
+    docs: Vec<(u64, f64)>, // Only the top hits when scanned for the top k
+    total: Option<usize>,  // Every match, set when only the top hits were kept
+    approximate: bool,     // Scanning stopped before proving the top hits
+}
+
+// A query split into its parts
+struct ParsedQuery {
+    terms: Vec<String>,        // Distinct analyzed terms
+    ranges: Vec<Filter>,       // From `field:[min TO max]` clauses
+    phrases: Vec<Vec<String>>, // Analyzed terms of each quoted phrase
+    slops: Vec<usize>,         // Slop of the phrase at the same index
+}
+
+/*
+The index state shared with snapshots (index, statistics, documents, doc
+values, words, vectors) is held in Arcs and copied on write, so taking a
+snapshot copies no documents and the first write after it copies only the
+parts it changes.
+*/
 pub struct SearchEngine {
-    index: InvertedIndex,
+    index: Arc<InvertedIndex>,
+    doc_stats: Arc<DocStats>, // Document lengths and boosts, for scoring
+    similarity: Arc<dyn Similarity>, // Scores query terms, BM25 unless set otherwise
+    custom_similarity: bool,  // Set with set_similarity, so k1 and b no longer apply
+    reranker: Option<Rerank>, // Reorders the top hits of searches, when set
     tokenizer: Tokenizer,
+    documents: Arc<HashMap<DocId, Arc<Document>>>,
+    doc_values: Arc<DocValues>,
+    settings: IndexSettings,
+    storage: Option<Storage>, // None for purely in-memory engines
+    eviction: Option<Mutex<EvictionQueue>>, // Set for capped ephemeral engines
+    tiering: Tiering,         // Cold segments on disk
+    words: Arc<Trie>,         // Document frequency of each indexed word
+    query_log: Option<Mutex<Trie>>, // Past queries, when logging is enabled
+    recorder: Option<QueryRecorder>, // Appends every search to a file, when recording
+    limiter: Limiter,         // Applied to try_search queries
+    commits: Mutex<CommitState>, // Changes since the last flush
+    field_tokenizers: HashMap<String, Tokenizer>, // Analyzers of searchable metadata fields
+    search_tokenizers: HashMap<String, Tokenizer>, // Query analyzers of fields that set their own
+    language_tokenizers: HashMap<Language, Tokenizer>, // Analyzers of detected languages other than the index's
+    vectors: Arc<HashMap<String, VectorIndex>>,        // Dense vectors of indexed vector fields
+    unstored: Arc<HashMap<DocId, HashMap<String, String>>>, // Values left out of returned documents
+    buffer: WriteBuffer,                               // Writes not visible to searches yet
+    filter_cache: Mutex<FilterCache>,                  // Document sets of frequently used filters
 }
 
 impl SearchEngine {
+    // Create an in-memory engine, failing on invalid settings
+    pub fn new(settings: IndexSettings) -> Result<Self, MSErrors> {
+        settings.validate()?;
+        let tokenizer = settings.tokenizer(settings.analyzer)?;
+        let mut index = InvertedIndex::new(tokenizer.clone()).with_record(settings.record);
+        for field in &settings.schema.fields {
+            index.set_field_record(&field.name, field.record);
+        }
+        let mut doc_stats = DocStats::new();
+        if settings.statistics == StatisticsMode::Approximate {
+            doc_stats.enable_approximate_stats();
+        }
+        let mut field_tokenizers = HashMap::new();
+        for field in settings.schema.fields.iter().filter(|field| field.indexed) {
+            // Keyword and enum values are indexed verbatim, as a single term
+            let preset = match field.field_type {
+                FieldType::Text => field.analyzer.unwrap_or(settings.analyzer),
+                FieldType::Keyword | FieldType::Enum => AnalyzerPreset::Keyword,
+                // Lowercased, so that `field:True` finds `true` too
+                FieldType::Bool => AnalyzerPreset::Simple,
+                _ => continue,
+            };
+            field_tokenizers.insert(field.name.clone(), settings.tokenizer(preset)?);
+            if let Some(raw) = settings.schema.raw_field(field) {
+                field_tokenizers.insert(raw, settings.tokenizer(AnalyzerPreset::Keyword)?);
+            }
+        }
+        let search_tokenizers = settings
+            .schema
+            .fields
+            .iter()
+            .filter(|field| field.indexed)
+            .filter_map(|field| {
+                let preset = field.search_analyzer?;
+                Some(settings.tokenizer(preset).map(|t| (field.name.clone(), t)))
+            })
+            .collect::<Result<_, _>>()?;
+        let language_tokenizers = Language::ALL
+            .into_iter()
+            .filter(|&language| settings.detect_language && language != settings.language)
+            .map(|language| {
+                let tokenizer = settings.tokenizer_in(settings.analyzer, language)?;
+                Ok((language, tokenizer))
+            })
+            .collect::<Result<_, MSErrors>>()?;
+        let vectors = settings
+            .schema
+            .fields
+            .iter()
+            .filter(|field| field.indexed && field.field_type == FieldType::Vector)
+            .map(|field| {
+                (
+                    field.name.clone(),
+                    VectorIndex::new(field.dims.unwrap_or_default()),
+                )
+            })
+            .collect();
+        let filter_cache = FilterCache::new(settings.cache_size);
+        Ok(SearchEngine {
+            index: Arc::new(index),
+            doc_stats: Arc::new(doc_stats),
+            similarity: Arc::new(Bm25::new(settings.k1, settings.b)),
+            custom_similarity: false,
+            reranker: None,
+            tokenizer,
+            documents: Arc::default(),
+            doc_values: Arc::new(DocValues::new()),
+            settings,
+            storage: None,
+            eviction: None,
+            tiering: Tiering::default(),
+            words: Arc::new(Trie::new()),
+            query_log: None,
+            recorder: None,
+            limiter: Limiter::new(QueryLimits::default()),
+            commits: Mutex::new(CommitState::default()),
+            field_tokenizers,
+            search_tokenizers,
+            language_tokenizers,
+            vectors: Arc::new(vectors),
+            unstored: Arc::default(),
+            buffer: WriteBuffer::default(),
+            filter_cache: Mutex::new(filter_cache),
+        })
+    }
+
+    // Create an in-memory engine that evicts documents once `capacity` is exceeded
+    pub fn ephemeral(
+        settings: IndexSettings,
+        capacity: Capacity,
+        policy: EvictionPolicy,
+    ) -> Result<Self, MSErrors> {
+        let mut engine = SearchEngine::new(settings)?;
+        engine.eviction = Some(Mutex::new(EvictionQueue::new(capacity, policy)));
+        Ok(engine)
+    }
+
+    // Create a new index directory with the given settings
+    pub fn create(path: impl AsRef<Path>, settings: IndexSettings) -> Result<Self, MSErrors> {
+        settings.validate()?;
+        let storage = Storage::create(path)?;
+        if storage.exists(SETTINGS_FILE) {
+            return Err(MSErrors::StorageError(format!(
+                "an index already exists at {}",
+                storage.path().display()
+            )));
+        }
+        storage.save_settings(&settings)?;
+        let mut engine = SearchEngine::new(settings)?;
+        engine.storage = Some(storage);
+        Ok(engine)
+    }
+
+    /*
+    Open an existing index directory, loading its settings and documents.
+    Cold segments are only read, and their checksums verified, when first
+    searched; a damaged one is then left out of the results.
+    */
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, MSErrors> {
+        SearchEngine::open_with(path, false)
+    }
+
+    // Same, also verifying every cold segment now, which reads each in full
+    pub fn open_verified(path: impl AsRef<Path>) -> Result<Self, MSErrors> {
+        SearchEngine::open_with(path, true)
+    }
+
+    fn open_with(path: impl AsRef<Path>, verify_segments: bool) -> Result<Self, MSErrors> {
+        let storage = Storage::open(path)?;
+        let settings = storage.load_settings()?;
+        let documents = storage.load_documents()?;
+        let mut engine = SearchEngine::new(settings)?;
+        for stored in documents {
+            engine.add_stored_document(stored)?;
+        }
+        let segments = storage.load_segments()?;
+        if verify_segments {
+            for segment in &segments {
+                storage.verify_file(&segment.file_name())?;
+            }
+        }
+        engine.tiering = Tiering::new(segments);
+        engine.storage = Some(storage);
+        engine.commits.get_mut().unwrap().flushed();
+        Ok(engine)
+    }
+
+    /*
+    The searchable state for `IndexWriter` to publish: committed documents
+    and cold segments, without pending writes, query limits, the query log
+    or eviction. The index state is shared, not copied; loaded segments,
+    hit counts and cached filters start afresh.
+    */
+    pub(crate) fn snapshot(&self) -> SearchEngine {
+        SearchEngine {
+            index: self.index.clone(),
+            doc_stats: self.doc_stats.clone(),
+            similarity: self.similarity.clone(),
+            custom_similarity: self.custom_similarity,
+            reranker: self.reranker.clone(),
+            tokenizer: self.tokenizer.clone(),
+            documents: self.documents.clone(),
+            doc_values: self.doc_values.clone(),
+            settings: self.settings.clone(),
+            storage: self.storage.clone(),
+            eviction: None,
+            tiering: Tiering::new(self.tiering.segments.clone()),
+            words: self.words.clone(),
+            query_log: None,
+            recorder: None,
+            limiter: Limiter::new(QueryLimits::default()),
+            commits: Mutex::new(CommitState::default()),
+            field_tokenizers: self.field_tokenizers.clone(),
+            search_tokenizers: self.search_tokenizers.clone(),
+            language_tokenizers: self.language_tokenizers.clone(),
+            vectors: self.vectors.clone(),
+            unstored: self.unstored.clone(),
+            buffer: WriteBuffer::default(),
+            filter_cache: Mutex::new(FilterCache::new(self.settings.cache_size)),
+        }
+    }
+
+    pub fn settings(&self) -> &IndexSettings {
+        &self.settings
+    }
+
+    /*
+    Score query terms with `similarity` instead of BM25, in cold segments
+    too. Not saved with the index; settings updates of k1 and b no longer
+    change scores once it is set.
+    */
+    pub fn set_similarity(&mut self, similarity: impl Similarity + 'static) {
+        self.similarity = Arc::new(similarity);
+        self.custom_similarity = true;
+        self.tiering.unload();
+    }
+
+    /*
+    Rerank the top hits of searches sorted by score with a second-stage
+    model, or stop reranking with None. Hits of cold segments are reranked
+    along with the hot ones; the reranker is not saved with the index.
+    */
+    pub fn set_reranker(&mut self, rerank: Option<Rerank>) {
+        self.reranker = rerank;
+    }
+
+    // Scores the documents held in memory; create one per query
+    fn ranker(&self) -> Ranker<'_> {
+        Ranker::new(&self.index, &self.doc_stats, self.similarity.as_ref())
+    }
+
+    // Update runtime settings; the index itself is left untouched
+    pub fn update_settings(&mut self, update: SettingsUpdate) -> Result<(), MSErrors> {
+        let mut settings = self.settings.clone();
+        settings.apply(&update)?;
+        if let Some(storage) = &self.storage {
+            storage.save_settings(&settings)?;
+        }
+        if !self.custom_similarity
+            && (settings.k1, settings.b) != (self.settings.k1, self.settings.b)
+        {
+            self.similarity = Arc::new(Bm25::new(settings.k1, settings.b));
+        }
+        self.filter_cache
+            .get_mut()
+            .unwrap()
+            .resize(settings.cache_size);
+        self.settings = settings;
+        // Loaded segments were built with the old settings
+        self.tiering.unload();
+        Ok(())
+    }
+
+    /*
+    Analyze a searchable metadata field with a custom chain instead of its
+    preset, for both indexing and field queries unless a search analyzer is
+    set. Must be set before any document is added. Custom chains are not
+    saved with the settings, so set them again after reopening an index.
+    */
+    pub fn set_field_analyzer(
+        &mut self,
+        field: &str,
+        tokenizer: Tokenizer,
+    ) -> Result<(), MSErrors> {
+        if !self.field_tokenizers.contains_key(field) {
+            return Err(MSErrors::SchemaMismatch {
+                field: field.to_string(),
+                message: "not a searchable schema field".to_string(),
+            });
+        }
+        if self.next_document_id() > 0 {
+            return Err(MSErrors::IndexingError(
+                "field analyzers must be set before adding documents".to_string(),
+            ));
+        }
+        self.field_tokenizers.insert(field.to_string(), tokenizer);
+        Ok(())
+    }
+
+    /*
+    Analyze `field:value` queries on a searchable metadata field with a
+    custom chain, such as plain words for a field indexed as edge n-grams.
+    Can be changed at any time; like field analyzers it is not saved.
+    */
+    pub fn set_field_search_analyzer(
+        &mut self,
+        field: &str,
+        tokenizer: Tokenizer,
+    ) -> Result<(), MSErrors> {
+        if !self.field_tokenizers.contains_key(field) {
+            return Err(MSErrors::SchemaMismatch {
+                field: field.to_string(),
+                message: "not a searchable schema field".to_string(),
+            });
+        }
+        self.search_tokenizers.insert(field.to_string(), tokenizer);
+        Ok(())
+    }
+
+    pub fn add_document(&mut self, document: Document) -> Result<(), MSErrors> {
+        self.add_document_with_boost(document, 1.0)
+    }
+
+    /*
+    Add a document whose relevance score is multiplied by `boost`. Its
+    metadata must match the schema; values of unstored fields, and the
+    content unless the schema stores it, are indexed but left out of the
+    stored document. Under a buffering refresh policy the document is
+    checked now but only searchable after the next commit.
+    */
+    pub fn add_document_with_boost(
+        &mut self,
+        document: Document,
+        boost: f64,
+    ) -> Result<(), MSErrors> {
+        if !self.buffer.is_buffering() {
+            return self.add_stored_document((document, boost, None));
+        }
+        self.check_document(&document, boost)?;
+        self.buffer.push(PendingWrite::Add((document, boost, None)));
+        self.commit_if_due()
+    }
+
+    // Reject documents that cannot be indexed, counting buffered writes
+    fn check_document(&self, document: &Document, boost: f64) -> Result<(), MSErrors> {
+        if !(boost.is_finite() && boost > 0.0) {
+            return Err(MSErrors::IndexingError(format!(
+                "boost must be > 0, got {}",
+                boost
+            )));
+        }
+        if DocId::new(document.id).is_none() {
+            return Err(MSErrors::IndexingError(format!(
+                "document id must be at most {}, got {}",
+                DocId::MAX,
+                document.id
+            )));
+        }
+        if self.will_exist(document.id) {
+            return Err(MSErrors::DuplicateDocument {
+                doc_id: document.id,
+            });
+        }
+        self.settings.schema.check(document)
+    }
+
+    /*
+    Store the detected language of a new document in its metadata, so it
+    is analyzed in that language and can be filtered on. Documents that
+    already name their language, such as those read from disk, keep it.
+    Queries are still analyzed in the index language.
+    */
+    fn detect_language(&self, document: &mut Document) {
+        if !self.settings.detect_language || document.metadata.contains_key(LANGUAGE_FIELD) {
+            return;
+        }
+        let text = format!("{}\n{}", document.title, document.content);
+        if let Some(language) = LanguageDetector::new().detect(&text) {
+            document
+                .metadata
+                .insert(LANGUAGE_FIELD.to_string(), language.as_str().to_string());
+        }
+    }
+
+    // Whether a document is indexed once the pending writes are committed
+    fn will_exist(&self, doc_id: u64) -> bool {
+        match self.buffer.last_write(doc_id) {
+            Some(PendingWrite::Add(_)) => true,
+            Some(PendingWrite::Remove(_)) => false,
+            None => self.documents.contains_key(&DocId(doc_id)),
+        }
+    }
+
+    // Add a document read from disk, reindexing its saved tokens if it has any
+    pub(crate) fn add_stored_document(
+        &mut self,
+        (mut document, boost, tokens): StoredDocument,
+    ) -> Result<(), MSErrors> {
+        self.detect_language(&mut document);
+        self.check_document(&document, boost)?;
+        self.settings.schema.normalize(&mut document);
+        let doc_id = DocId(document.id);
+        match tokens {
+            Some(tokens) => {
+                // The content was dropped, so it takes up the rest of the tokens
+                let fields = [
+                    (document.title.len(), self.settings.title_boost),
+                    (usize::MAX, self.settings.content_boost),
+                ];
+                Arc::make_mut(&mut self.doc_stats).add_document(doc_id, &tokens, &fields, boost);
+                Arc::make_mut(&mut self.index).index_tokens(doc_id, tokens);
+            }
+            None => {
+                let fields = [
+                    (document.title.as_str(), self.settings.title_boost),
+                    (document.content.as_str(), self.settings.content_boost),
+                ];
+                let language = document
+                    .metadata
+                    .get(LANGUAGE_FIELD)
+                    .and_then(|language| language.parse().ok());
+                let tokenizer = language
+                    .and_then(|language| self.language_tokenizers.get(&language))
+                    .unwrap_or(&self.tokenizer);
+                let text = format!("{}\n{}", document.title, document.content);
+                let tokens = tokenizer.tokenize(&text);
+                let fields = fields.map(|(text, field_boost)| (text.len(), field_boost));
+                Arc::make_mut(&mut self.doc_stats).add_document(doc_id, &tokens, &fields, boost);
+                Arc::make_mut(&mut self.index).index_tokens(doc_id, tokens);
+            }
+        }
+        for (field, tokenizer) in &self.field_tokenizers {
+            // Every field with a tokenizer is in the schema or a raw copy of one
+            let schema = &self.settings.schema;
+            let def = schema
+                .get(field)
+                .or_else(|| schema.raw_source(field))
+                .unwrap();
+            let Some(value) = document.metadata.get(&def.name) else {
+                continue;
+            };
+            let values = def.values(value);
+            let mut tokens: Vec<Token> = values
+                .into_iter()
+                .flat_map(|value| tokenizer.tokenize(value))
+                .collect();
+            Arc::make_mut(&mut self.doc_stats).add_field(doc_id, field, &mut tokens);
+            Arc::make_mut(&mut self.index).index_field(doc_id, field, tokens);
+        }
+        for (field, vectors) in Arc::make_mut(&mut self.vectors) {
+            if let Some(vector) = document.metadata.get(field).and_then(|v| parse_vector(v)) {
+                vectors.insert(doc_id, &vector);
+            }
+        }
+        Arc::make_mut(&mut self.doc_values).add_with_schema(
+            doc_id,
+            &document.metadata,
+            &self.settings.schema,
+        );
+        self.filter_cache
+            .get_mut()
+            .unwrap()
+            .add_document(&self.doc_values, doc_id);
+
+        let unstored: HashMap<String, String> = self
+            .settings
+            .schema
+            .fields
+            .iter()
+            .filter(|field| !field.stored)
+            .filter_map(|field| document.metadata.remove_entry(&field.name))
+            .collect();
+        if !unstored.is_empty() {
+            Arc::make_mut(&mut self.unstored).insert(doc_id, unstored);
+        }
+        if !self.settings.schema.store_content {
+            document.content = String::new();
+        }
+        if let Some(eviction) = &mut self.eviction {
+            eviction.get_mut().unwrap().insert(doc_id, &document);
+        }
+        for word in self.words_of(&document) {
+            Arc::make_mut(&mut self.words).insert(&word);
+        }
+        Arc::make_mut(&mut self.documents).insert(doc_id, Arc::new(document));
+        self.commits.get_mut().unwrap().pending_changes += 1;
+        self.evict_over_capacity();
+        Ok(())
+    }
+
+    /*
+    Index a long document as passages cut by `chunker`, each a document of
+    its own with a back-reference to `document.id`, which is not indexed
+    itself and must not be used by another document. Chunks take the free
+    ids past both the used ones and the parent's, so documents added later
+    with `next_document_id` never take the parent id either. Returns the
+    ids of the chunks.
+    */
+    pub fn add_chunked(
+        &mut self,
+        document: Document,
+        chunker: &Chunker,
+    ) -> Result<Vec<u64>, MSErrors> {
+        chunker.validate()?;
+        if self.will_exist(document.id) {
+            return Err(MSErrors::DuplicateDocument {
+                doc_id: document.id,
+            });
+        }
+        let first_id = self.next_document_id().max(document.id.saturating_add(1));
+        let chunks = chunker.chunk(&self.tokenizer, &document, first_id)?;
+        // Check the whole range up front rather than stop halfway through it
+        if chunks
+            .last()
+            .is_some_and(|chunk| DocId::new(chunk.id).is_none())
+        {
+            return Err(MSErrors::IndexingError(format!(
+                "no ids left for the chunks of document {}",
+                document.id
+            )));
+        }
+        let ids = chunks.iter().map(|chunk| chunk.id).collect();
+        for chunk in chunks {
+            self.add_document(chunk)?;
+        }
+        Ok(ids)
+    }
+
+    /*
+    Remove a document from the index, returning it if it existed. Under a
+    buffering refresh policy it stays searchable until the next commit.
+    */
+    pub fn remove_document(&mut self, doc_id: u64) -> Option<Document> {
+        if !self.buffer.is_buffering() {
+            return self.remove_indexed(doc_id);
+        }
+        let document = match self.buffer.last_write(doc_id) {
+            Some(PendingWrite::Add((document, _, _))) => document.clone(),
+            Some(PendingWrite::Remove(_)) => return None,
+            None => self.get_document(doc_id)?.clone(),
+        };
+        self.buffer.push(PendingWrite::Remove(doc_id));
+        // The removal itself cannot fail; failed additions are dropped as in `commit`
+        let _ = self.commit_if_due();
+        Some(document)
+    }
+
+    /*
+    Make every buffered write searchable, in the order it was made, and
+    return how many were applied. A write that fails, such as an addition
+    invalidated by a settings change, is dropped and the first such error
+    is returned after the others are applied.
+    */
+    pub fn commit(&mut self) -> Result<usize, MSErrors> {
+        let writes = self.buffer.drain();
+        let count = writes.len();
+        let mut first_error = None;
+        for write in writes {
+            let applied = match write {
+                PendingWrite::Add(stored) => self.add_stored_document(stored),
+                PendingWrite::Remove(doc_id) => {
+                    self.remove_indexed(doc_id);
+                    Ok(())
+                }
+            };
+            if let Err(e) = applied {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(count), Err)
+    }
+
+    /*
+    Commit if the auto refresh policy calls for it. Writes check on their
+    own, but searches only borrow the engine, so an index that stops
+    receiving writes needs this called before reads to meet its interval.
+    */
+    pub fn commit_if_due(&mut self) -> Result<(), MSErrors> {
+        if self.buffer.is_due() {
+            self.commit()?;
+        }
+        Ok(())
+    }
+
+    // Writes waiting for the next commit
+    pub fn pending_writes(&self) -> usize {
+        self.buffer.len()
+    }
+
+    // Change when writes become searchable, committing any buffered ones first
+    pub fn set_refresh_policy(&mut self, policy: RefreshPolicy) -> Result<(), MSErrors> {
+        let committed = self.commit();
+        self.buffer.policy = policy;
+        committed.map(|_| ())
+    }
+
+    fn remove_indexed(&mut self, doc_id: u64) -> Option<Document> {
+        let doc_id = DocId(doc_id);
+        let document = Arc::make_mut(&mut self.documents).remove(&doc_id)?;
+        let document = Arc::unwrap_or_clone(document);
+        let terms = self.index.document_terms(doc_id).unwrap_or_default();
+        Arc::make_mut(&mut self.doc_stats).remove_document(doc_id, terms);
+        Arc::make_mut(&mut self.index).remove_document(doc_id);
+        Arc::make_mut(&mut self.doc_values).remove_document(doc_id);
+        for vectors in Arc::make_mut(&mut self.vectors).values_mut() {
+            vectors.remove(doc_id);
+        }
+        self.filter_cache.get_mut().unwrap().remove_document(doc_id);
+        Arc::make_mut(&mut self.unstored).remove(&doc_id);
+        if let Some(eviction) = &mut self.eviction {
+            eviction.get_mut().unwrap().remove(doc_id);
+        }
+        for word in self.words_of(&document) {
+            Arc::make_mut(&mut self.words).remove(&word);
+        }
+        self.commits.get_mut().unwrap().pending_changes += 1;
+        Some(document)
+    }
+
+    // Distinct lowercased words of a document, as written
+    fn words_of(&self, document: &Document) -> HashSet<String> {
+        [&document.title, &document.content]
+            .into_iter()
+            .flat_map(|text| {
+                self.tokenizer
+                    .tokenize(text)
+                    .into_iter()
+                    .map(|token| text[token.offset.0..token.offset.1].to_lowercase())
+            })
+            .collect()
+    }
+
+    // Remember every query from now on so `suggest` can complete past queries
+    pub fn enable_query_log(&mut self) {
+        self.query_log
+            .get_or_insert_with(|| Mutex::new(Trie::new()));
+    }
+
+    /*
+    Append the query, latency and total matches of every search from now
+    on to the file at `path`, see `QueryRecord`. The log can be replayed
+    against an index with `replay` to compare performance before and
+    after tuning. Searches run by `warm_up` are not recorded.
+    */
+    pub fn record_queries(&mut self, path: impl AsRef<Path>) -> Result<(), MSErrors> {
+        self.recorder = Some(QueryRecorder::create(path.as_ref())?);
+        Ok(())
+    }
+
+    /*
+    Complete a typed prefix from the words of hot documents and, when the
+    query log is enabled, from past queries. Completions are ordered by
+    frequency: a word counts once per document containing it and a query
+    once per time it was searched for.
+    */
+    pub fn suggest(&self, prefix: &str, limit: usize) -> Vec<Completion> {
+        let prefix = prefix.trim_start().to_lowercase();
+        if prefix.trim().is_empty() {
+            return Vec::new();
+        }
+        let mut frequencies: HashMap<String, usize> = HashMap::new();
+        // Complete the last word of the prefix, keeping the words before it
+        let (head, last) = prefix
+            .rsplit_once(char::is_whitespace)
+            .unwrap_or(("", prefix.as_str()));
+        if !last.is_empty() {
+            for (word, count) in self.words.completions(last, limit) {
+                let text = if head.is_empty() {
+                    word
+                } else {
+                    format!("{} {}", head, word)
+                };
+                *frequencies.entry(text).or_insert(0) += count;
+            }
+        }
+        if let Some(query_log) = &self.query_log {
+            for (query, count) in query_log.lock().unwrap().completions(&prefix, limit) {
+                *frequencies.entry(query).or_insert(0) += count;
+            }
+        }
+
+        let mut completions: Vec<Completion> = frequencies
+            .into_iter()
+            .map(|(text, frequency)| Completion { text, frequency })
+            .collect();
+        completions.sort_by(|a, b| b.frequency.cmp(&a.frequency).then(a.text.cmp(&b.text)));
+        completions.truncate(limit);
+        completions
+    }
+
+    // Approximate memory tracked by an ephemeral engine's capacity limit
+    pub fn memory_usage(&self) -> Option<usize> {
+        self.eviction
+            .as_ref()
+            .map(|eviction| eviction.lock().unwrap().memory_bytes())
+    }
+
+    fn evict_over_capacity(&mut self) {
+        while let Some(victim) = self
+            .eviction
+            .as_mut()
+            .and_then(|eviction| eviction.get_mut().unwrap().next_victim())
+        {
+            self.remove_indexed(victim.get());
+        }
+    }
+
+    pub fn get_document(&self, doc_id: u64) -> Option<&Document> {
+        self.documents.get(&DocId(doc_id)).map(Arc::as_ref)
+    }
+
+    // Every stored document, in no particular order
+    pub fn documents(&self) -> impl Iterator<Item = &Document> {
+        self.documents.values().map(Arc::as_ref)
+    }
+
+    pub fn document_count(&self) -> usize {
+        self.documents.len()
+    }
+
+    // Number of filters used often enough to have their documents cached
+    pub fn cached_filters(&self) -> usize {
+        self.filter_cache.lock().unwrap().cached()
+    }
+
+    /*
+    Prepare for the first searches after opening, so they do not pay for
+    loading. Hot documents, their term dictionary and postings are in
+    memory once opened; warming up loads the most recent cold segments,
+    caches the documents matched by `filters` right away and runs
+    `queries` over every loaded tier. Without queries, the most frequent
+    ones of the query log are run when it is enabled. Warm-up queries are
+    not logged and do not count as hits for tiering or eviction.
+    */
+    pub fn warm_up<S: AsRef<str>>(&self, queries: &[S], filters: &[Filter]) -> WarmUp {
+        let start = Instant::now();
+        let mut queries: Vec<String> = queries.iter().map(|q| q.as_ref().to_string()).collect();
+        if queries.is_empty()
+            && let Some(query_log) = &self.query_log
+        {
+            let logged = query_log.lock().unwrap().completions("", WARM_UP_QUERIES);
+            queries = logged.into_iter().map(|(query, _)| query).collect();
+        }
+        let segments = match &self.storage {
+            Some(storage) => self
+                .tiering
+                .preload(storage, &self.settings, &self.similarity),
+            None => Vec::new(),
+        };
+        let engines = std::iter::once(self).chain(segments.iter().map(Arc::as_ref));
+        let mut filters_cached = 0;
+        for engine in engines {
+            for filter in filters {
+                engine.filter_docs(filter, true);
+            }
+            for query in &queries {
+                engine.score_matches(query, &SearchOptions::default());
+            }
+            filters_cached += engine.cached_filters();
+        }
+        WarmUp {
+            segments_loaded: segments.len(),
+            queries_run: queries.len(),
+            filters_cached,
+            elapsed: start.elapsed(),
+        }
+    }
+
+    /*
+    Smallest id greater than every indexed or pending document id, or
+    u64::MAX once that id is used.
+    */
+    pub fn next_document_id(&self) -> u64 {
+        let hot = self.documents.keys().max().map(|id| id.get());
+        let cold = self.tiering.segments.iter().map(|s| s.max_doc_id).max();
+        let pending = self.buffer.max_added_id();
+        hot.max(cold)
+            .max(pending)
+            .map_or(0, |max| max.saturating_add(1))
+    }
+
+    /*
+    Write the term dictionary of the documents held in memory, see
+    `InvertedIndex::write_dump`. Byte-stable across runs.
+    */
+    pub fn dump_index(&self, out: &mut impl std::io::Write) -> std::io::Result<()> {
+        self.index.write_dump(out)
+    }
+
+    /*
+    Term statistics of the documents held in memory, the ones BM25 scores
+    with; documents in cold segments or the write buffer are not counted.
+    */
+    pub fn statistics(&self) -> impl Statistics + '_ {
+        self.ranker()
+    }
+
+    // Segments moved out of memory by `tier`, oldest first
+    pub fn cold_segments(&self) -> &[SegmentMeta] {
+        &self.tiering.segments
+    }
+
+    /*
+    Move the hot documents selected by `policy` into a new cold segment on
+    disk, returning how many were moved. Cold documents are still searched,
+    but are only loaded when a query runs, and no longer count towards
+    `document_count` or `get_document`.
+    */
+    pub fn tier(&mut self, policy: &TieringPolicy) -> Result<usize, MSErrors> {
+        let Some(storage) = &self.storage else {
+            return Err(MSErrors::StorageError(
+                "tiering requires an index directory".to_string(),
+            ));
+        };
+        let now = tiering::now_secs();
+        let mut cold: Vec<DocId> = self
+            .documents
+            .keys()
+            .copied()
+            .filter(|&doc_id| self.tiering.is_cold(policy, &self.doc_values, doc_id, now))
+            .collect();
+        cold.sort_unstable();
+
+        // With a routing field, each routing value gets its own segment
+        let mut partitions: BTreeMap<Option<&str>, Vec<DocId>> = BTreeMap::new();
+        for &doc_id in &cold {
+            let route = self
+                .settings
+                .routing_field
+                .as_ref()
+                .and_then(|field| self.doc_values.get(field, doc_id));
+            partitions.entry(route).or_default().push(doc_id);
+        }
+        let mut segments = self.tiering.segments.clone();
+        for (route, doc_ids) in partitions {
+            let segment = SegmentMeta {
+                id: segments.last().map_or(0, |s| s.id + 1),
+                doc_count: doc_ids.len(),
+                max_doc_id: doc_ids.last().map_or(0, |id| id.get()),
+                created: now,
+                routing_values: route.into_iter().map(String::from).collect(),
+            };
+            let documents: Vec<_> = doc_ids
+                .iter()
+                .map(|&doc_id| self.stored_document(doc_id))
+                .collect();
+            storage.save_segment_documents(
+                &segment,
+                documents
+                    .iter()
+                    .map(|(d, boost, tokens)| (d.as_ref(), *boost, tokens.as_deref())),
+            )?;
+            segments.push(segment);
+        }
+
+        if !cold.is_empty() {
+            storage.save_segments(&segments)?;
+            self.tiering.segments = segments;
+            for &doc_id in &cold {
+                self.remove_indexed(doc_id.get());
+            }
+            self.flush()?;
+        }
+        let hot: Vec<DocId> = self.documents.keys().copied().collect();
+        self.tiering.reset_hits(hot.into_iter());
+        Ok(cold.len())
+    }
+
+    // Number of cold segments currently loaded in memory
+    pub fn loaded_segments(&self) -> usize {
+        self.tiering.loaded_segments()
+    }
+
+    /*
+    Merge the cold segments holding the same routing values, returning how
+    many segments were removed. Under `MergePolicy::Log` at most
+    `merge_factor` segments, oldest first, are merged into each new one, so
+    a compaction may leave several segments per routing value;
+    `MergePolicy::NoMerge` leaves the segments as they are. The new segment
+    list is saved before the merged files are deleted, so an interrupted
+    compaction only leaves unreferenced files behind.
+    */
+    pub fn compact(&mut self) -> Result<usize, MSErrors> {
+        let Some(storage) = &self.storage else {
+            return Err(MSErrors::StorageError(
+                "compaction requires an index directory".to_string(),
+            ));
+        };
+        let merge_factor = match self.settings.merge_policy {
+            MergePolicy::NoMerge => return Ok(0),
+            MergePolicy::Log { merge_factor } => merge_factor,
+        };
+        let mut groups: BTreeMap<&[String], Vec<&SegmentMeta>> = BTreeMap::new();
+        for segment in &self.tiering.segments {
+            groups
+                .entry(&segment.routing_values)
+                .or_default()
+                .push(segment);
+        }
+        let mut next_id = self.tiering.segments.last().map_or(0, |s| s.id + 1);
+        let mut segments = Vec::new();
+        let mut merged_files = Vec::new();
+        for group in groups.values().flat_map(|group| group.chunks(merge_factor)) {
+            if let [segment] = group {
+                segments.push((*segment).clone());
+                continue;
+            }
+            let mut documents = Vec::new();
+            for segment in group {
+                documents.extend(storage.load_segment_documents(segment)?);
+                merged_files.push(segment.file_name());
+            }
+            documents.sort_by_key(|(d, _, _)| d.id);
+            let merged = SegmentMeta {
+                id: next_id,
+                doc_count: documents.len(),
+                max_doc_id: group.iter().map(|s| s.max_doc_id).max().unwrap_or(0),
+                created: group.iter().map(|s| s.created).min().unwrap_or(0),
+                routing_values: group[0].routing_values.clone(),
+            };
+            next_id += 1;
+            storage.save_segment_documents(
+                &merged,
+                documents
+                    .iter()
+                    .map(|(d, boost, tokens)| (d, *boost, tokens.as_deref())),
+            )?;
+            segments.push(merged);
+        }
+        if merged_files.is_empty() {
+            return Ok(0);
+        }
+
+        segments.sort_by_key(|s| s.id);
+        storage.save_segments(&segments)?;
+        for name in &merged_files {
+            storage.remove(name)?;
+        }
+        let removed = self.tiering.segments.len() - segments.len();
+        self.tiering.replace_segments(segments);
+        Ok(removed)
+    }
+
+    /*
+    A hot document as written to disk for reindexing: with its boost, its
+    unstored values, and its analyzed tokens when the content is not stored.
+    */
+    fn stored_document(&self, doc_id: DocId) -> (Cow<'_, Document>, f64, Option<Vec<Token>>) {
+        let document = self.documents[&doc_id].as_ref();
+        let document = match self.unstored.get(&doc_id) {
+            Some(unstored) => {
+                let mut document = document.clone();
+                document.metadata.extend(unstored.clone());
+                Cow::Owned(document)
+            }
+            None => Cow::Borrowed(document),
+        };
+        let tokens =
+            (!self.settings.schema.store_content).then(|| self.ranker().document_tokens(doc_id));
+        (document, self.doc_stats.doc_boost(doc_id), tokens)
+    }
+
+    // Persist settings and committed documents; a no-op for in-memory engines
+    pub fn flush(&self) -> Result<(), MSErrors> {
+        if let Some(storage) = &self.storage {
+            let mut documents: Vec<_> = self
+                .documents
+                .keys()
+                .map(|&doc_id| self.stored_document(doc_id))
+                .collect();
+            documents.sort_by_key(|(d, _, _)| d.id);
+            storage.save_settings(&self.settings)?;
+            storage.save_documents(
+                documents
+                    .iter()
+                    .map(|(d, boost, tokens)| (d.as_ref(), *boost, tokens.as_deref())),
+            )?;
+            self.commits.lock().unwrap().flushed();
+        }
+        Ok(())
+    }
+
+    // Report write backlog, disk headroom and lock state
+    pub fn health(&self) -> Health {
+        let poisoned_locks = self.commits.is_poisoned()
+            || self.tiering.is_poisoned()
+            || self.filter_cache.is_poisoned()
+            || self.query_log.as_ref().is_some_and(Mutex::is_poisoned)
+            || self
+                .recorder
+                .as_ref()
+                .is_some_and(QueryRecorder::is_poisoned)
+            || self.eviction.as_ref().is_some_and(Mutex::is_poisoned);
+        let (pending_changes, last_flush) = {
+            let commits = self.commits.lock().unwrap_or_else(|e| e.into_inner());
+            (commits.pending_changes, commits.last_flush)
+        };
+        let mut health = Health {
+            status: HealthStatus::Healthy,
+            persistent: self.storage.is_some(),
+            pending_changes,
+            cold_segments: self.tiering.segments.len(),
+            last_flush_age: self.storage.as_ref().map(|_| last_flush.elapsed()),
+            disk_available_bytes: self
+                .storage
+                .as_ref()
+                .and_then(|storage| health::available_space(storage.path())),
+            poisoned_locks,
+        };
+        health.status = health::status(&health);
+        health
+    }
+
+    // Cap the queries accepted by try_search; other searches are not limited
+    pub fn set_query_limits(&mut self, limits: QueryLimits) {
+        self.limiter = Limiter::new(limits);
+    }
+
+    pub fn try_search(&self, query: &str, limit: usize) -> Result<SearchResults, MSErrors> {
+        self.try_search_with_options(query, &SearchOptions::new(limit))
+    }
+
+    /*
+    Search once the query limits allow it, or fail with MSErrors::Busy.
+    Invalid decays fail the search instead of being ignored.
+    */
+    pub fn try_search_with_options(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<SearchResults, MSErrors> {
+        for decay in &options.decays {
+            decay.validate()?;
+        }
+        let _permit = self.limiter.acquire()?;
+        Ok(self.search_with_options(query, options))
+    }
+
     pub fn search(&self, query: &str, limit: usize) -> SearchResults {
+        self.search_with_options(query, &SearchOptions::new(limit))
+    }
+
+    /*
+    Search chunks added with `add_chunked`, aggregated to their parents: each
+    parent is scored by its best chunk, and `limit` and `offset` count
+    parents. Documents that were not chunked are their own parent.
+    */
+    pub fn search_parents(&self, query: &str, options: &SearchOptions) -> Vec<ParentHit> {
+        let mut options = options.clone().collapse(PARENT_FIELD);
+        if let Some(fields) = &mut options.fields {
+            fields.push(PARENT_FIELD.to_string());
+        }
+        let results = self.search_with_options(query, &options);
+        results
+            .documents
+            .into_iter()
+            .zip(results.scores)
+            .zip(results.group_counts)
+            .map(|((best_chunk, score), matching_chunks)| ParentHit {
+                parent_id: best_chunk
+                    .metadata
+                    .get(PARENT_FIELD)
+                    .and_then(|id| id.parse().ok())
+                    .unwrap_or(best_chunk.id),
+                score,
+                best_chunk,
+                matching_chunks,
+            })
+            .collect()
+    }
+
+    pub fn search_with_options(&self, query: &str, options: &SearchOptions) -> SearchResults {
+        let start = Instant::now();
+        let options = self.resolve_sort(options);
+        let options = options.as_ref();
+        if let Some(query_log) = &self.query_log {
+            let normalized = query.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !normalized.is_empty() {
+                query_log.lock().unwrap().insert(&normalized.to_lowercase());
+            }
+        }
+        // A reranker reorders the merged first-stage hits, then they are paged
+        let rerank = self.active_reranker(options);
+        let first_stage = match rerank {
+            Some(rerank) => {
+                let mut first_stage = options.clone();
+                first_stage.offset = 0;
+                first_stage.limit = options
+                    .offset
+                    .saturating_add(options.limit)
+                    .max(rerank.window);
+                Cow::Owned(first_stage)
+            }
+            None => Cow::Borrowed(options),
+        };
+        let (mut results, cold) = match &self.storage {
+            Some(storage) if !self.tiering.segments.is_empty() => {
+                self.search_tiers(query, &first_stage, storage)
+            }
+            _ => (self.search_hot(query, &first_stage), Vec::new()),
+        };
+        if let Some(rerank) = rerank {
+            self.rerank(query, rerank, &mut results, &cold, options);
+        }
+        let latency = start.elapsed();
+        results.query_time_ms = latency.as_millis() as u64;
+        if let Some(recorder) = &self.recorder {
+            let total_matches = results.total_matches;
+            recorder.record(&recorder::record_of(
+                query,
+                options.limit,
+                total_matches,
+                latency,
+            ));
+        }
+        results
+    }
+
+    /*
+    Search the hot documents and every cold segment, then merge the hits.
+    Each tier is scored with its own corpus statistics and returns its top
+    `offset + limit` hits. When collapsing, groups split across tiers are
+    merged but `total_matches` counts them per tier. Cold segments that
+    cannot be read are skipped; the others are returned with the results.
+    */
+    fn search_tiers(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+        storage: &Storage,
+    ) -> (SearchResults, Vec<Arc<SearchEngine>>) {
+        let mut tier_options = options.clone();
+        tier_options.offset = 0;
+        tier_options.limit = options.offset.saturating_add(options.limit);
+        // Merging needs the collapse and sort fields of each hit
+        if let Some(fields) = &mut tier_options.fields {
+            fields.extend(options.collapse.iter().cloned());
+            if let Some(field) = options.sort_by.key.field() {
+                fields.push(field.to_string());
+                fields.extend(field.strip_suffix(RAW_SUFFIX).map(str::to_string));
+            }
+        }
+
+        // Skip segments holding none of the routing values the query filters
+        // on. Segments without values, e.g. tiered before the routing field
+        // was set, may hold any of them.
+        let routes = self
+            .settings
+            .routing_field
+            .as_ref()
+            .and_then(|field| tiering::routed_values(&options.filters, field));
+        let mut parts = vec![self.search_hot(query, &tier_options)];
+        let mut engines = Vec::new();
+        for segment in &self.tiering.segments {
+            if let Some(routes) = &routes
+                && !segment.routing_values.is_empty()
+                && !segment.routing_values.iter().any(|v| routes.contains(v))
+            {
+                continue;
+            }
+            if let Ok(engine) =
+                self.tiering
+                    .segment_engine(segment, storage, &self.settings, &self.similarity)
+            {
+                parts.push(engine.search_with_options(query, &tier_options));
+                engines.push(engine);
+            }
+        }
+        let mut results = merge_results(parts, options);
+        if let Some(fields) = &options.fields {
+            for document in &mut results.documents {
+                *document = document.project(fields);
+            }
+        }
+        (results, engines)
+    }
+
+    /*
+    Iterate over the hits of a query in score order, consuming as few as
+    needed. Only documents held in memory are searched, and fetching a
+    hit's document is left to the caller.
+    */
+    pub fn search_iter(&self, query: &str) -> SearchIter<'_> {
+        let matches = self.score_matches(query, &SearchOptions::default());
+        SearchIter::new(self, matches.docs)
+    }
+
+    fn search_hot(&self, query: &str, options: &SearchOptions) -> SearchResults {
+        let matches = self.score_matches(query, options);
+        let facets = self.count_facets(&matches.docs, &options.facets);
+        let mut results = self.rank_and_limit(matches.docs, options);
+        results.facets = facets;
+        if let Some(total) = matches.total {
+            results.total_matches = total;
+        }
+        results.is_approximate = matches.approximate;
+        results.suggestions = self.suggestions(query);
+        if let Some(eviction) = &self.eviction {
+            let mut eviction = eviction.lock().unwrap();
+            for document in &results.documents {
+                eviction.touch(DocId(document.id));
+            }
+        }
+        self.tiering
+            .record_hits(results.documents.iter().map(|d| DocId(d.id)));
+        results
+    }
+
+    // Every hot document matching the query and filters, with its score
+    fn score_matches(&self, query: &str, options: &SearchOptions) -> ScoredMatches {
+        self.score_matches_timed(query, options, &mut PhaseTimings::default())
+    }
+
+    // Same, timing the parse, candidate and scoring phases
+    fn score_matches_timed(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+        timings: &mut PhaseTimings,
+    ) -> ScoredMatches {
+        let start = Instant::now();
         let parsed_query = self.parse_query(query);
-        let candidate_docs = self.find_candidates(&parsed_query);
-        let scored_docs = self.score_documents(&candidate_docs, &parsed_query);
-        self.rank_and_limit(scored_docs, limit)
+        timings.parse = start.elapsed();
+
+        let start = Instant::now();
+        let mut filters = parsed_query.ranges;
+        let mut candidate_docs = if parsed_query.terms.is_empty() {
+            if options.constant_score && filters.is_empty() {
+                // Only filters select the hits, starting from every document
+                self.documents.keys().map(|doc_id| doc_id.get()).collect()
+            } else {
+                self.range_candidates(&filters)
+            }
+        } else {
+            let min_terms = options
+                .minimum_should_match
+                .map_or(1, |minimum| minimum.required(parsed_query.terms.len()));
+            self.find_candidates(&parsed_query.terms, min_terms)
+        };
+        filters.extend(options.filters.iter().cloned());
+        self.apply_filters(&mut candidate_docs, &filters);
+        timings.candidates = start.elapsed();
+
+        let start = Instant::now();
+        if options.constant_score {
+            let mut docs: Vec<(u64, f64)> =
+                candidate_docs.iter().map(|doc_id| (doc_id, 1.0)).collect();
+            if !parsed_query.phrases.is_empty() {
+                // Phrases only filter here, their boost is dropped
+                self.match_phrases(&mut docs, &parsed_query.phrases, &parsed_query.slops);
+                docs.iter_mut().for_each(|(_, score)| *score = 1.0);
+            }
+            timings.scoring = start.elapsed();
+            return ScoredMatches {
+                docs,
+                total: None,
+                approximate: false,
+            };
+        }
+
+        let top_k = options.approximate_top_k.filter(|_| {
+            !parsed_query.terms.is_empty()
+                && parsed_query.phrases.is_empty()
+                && options.facets.is_empty()
+                && options.collapse.is_none()
+                && options.decays.is_empty()
+                && options.sort_by == SortSpec::score()
+        });
+        if let Some(max_error) = top_k {
+            let bounds = self.score_bounds(&candidate_docs, &parsed_query.terms);
+            let k = options.offset.saturating_add(options.limit);
+            let ranker = self.ranker();
+            let (docs, approximate) = top_k::scan(bounds, k, max_error, |doc_id| {
+                ranker.compute_score(DocId(doc_id), &parsed_query.terms)
+            });
+            timings.scoring = start.elapsed();
+            return ScoredMatches {
+                docs,
+                total: Some(candidate_docs.len() as usize),
+                approximate,
+            };
+        }
+
+        let mut scored_docs = self.score_documents(&candidate_docs, &parsed_query.terms);
+        self.match_phrases(&mut scored_docs, &parsed_query.phrases, &parsed_query.slops);
+        for (doc_id, score) in &mut scored_docs {
+            for decay in &options.decays {
+                *score *= decay.doc_factor(&self.doc_values, DocId(*doc_id));
+            }
+        }
+        timings.scoring = start.elapsed();
+        ScoredMatches {
+            docs: scored_docs,
+            total: None,
+            approximate: false,
+        }
+    }
+
+    /*
+    Break down the score of a hot document for a query searched with
+    `options`, timing each phase of the search as it runs. The score, rank
+    and match count are those of the hot documents, including decays and
+    the reranker. Returns None if the document is not held in memory.
+    */
+    pub fn explain(
+        &self,
+        query: &str,
+        doc_id: u64,
+        options: &SearchOptions,
+    ) -> Option<Explanation> {
+        self.get_document(doc_id)?;
+        let options = self.resolve_sort(options);
+        let options = options.as_ref();
+        let mut timings = PhaseTimings::default();
+        let matches = self.score_matches_timed(query, options, &mut timings);
+        let first_stage = matches
+            .docs
+            .iter()
+            .find(|&&(id, _)| id == doc_id)
+            .map(|&(_, score)| score);
+        let total_matches = matches.total.unwrap_or(matches.docs.len());
+
+        let start = Instant::now();
+        let terms = self.parse_query(query).terms;
+        let mut sorted = self.sort_hits(matches.docs, options);
+        if let Some(rerank) = self.active_reranker(options) {
+            let window = sorted.len().min(rerank.window);
+            for (id, score) in &mut sorted[..window] {
+                *score = self.rerank_score(rerank, &terms, DocId(*id), *score, &[]);
+            }
+            sorted[..window].sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        }
+        let rank = sorted
+            .iter()
+            .position(|&(id, _)| id == doc_id)
+            .map(|i| (i + 1, sorted[i].1));
+        timings.sort = start.elapsed();
+
+        let ranker = self.ranker();
+        let doc_boost = ranker.doc_boost(DocId(doc_id));
+        let terms = ranker.explain_terms(DocId(doc_id), &terms);
+        let term_score: f64 = terms.iter().map(|t| t.score).sum();
+        let decay: f64 = options
+            .decays
+            .iter()
+            .map(|decay| decay.doc_factor(&self.doc_values, DocId(doc_id)))
+            .product();
+        let base_score = term_score * doc_boost * decay;
+        let score = rank.map_or(term_score * doc_boost, |(_, score)| score);
+        let corpus = ranker.corpus_stats();
+        Some(Explanation {
+            doc_id,
+            score,
+            rank: rank.map(|(rank, _)| rank),
+            total_matches,
+            terms,
+            doc_boost,
+            phrase_boost: match first_stage {
+                Some(score) if base_score > 0.0 && !options.constant_score => score / base_score,
+                _ => 1.0,
+            },
+            decay,
+            doc_length: self.doc_stats.doc_length(DocId(doc_id)).unwrap_or(0),
+            avg_doc_length: corpus.avg_doc_length,
+            total_docs: corpus.total_docs,
+            timings,
+        })
     }
 
-    fn parse_query(&self, query: &str) -> Vec<String> {
-        // Tokenize and normalize query
-        vec![]
+    fn score_bounds(&self, doc_ids: &RoaringTreemap, terms: &[String]) -> Vec<(f64, u64)> {
+        // Highest score each candidate could reach: the best possible
+        // contribution of every query term it contains, times its boost
+        let mut bounds: HashMap<u64, f64> = doc_ids.iter().map(|doc_id| (doc_id, 0.0)).collect();
+        let ranker = self.ranker();
+        for term in terms {
+            let term_bound = ranker.term_upper_bound(term);
+            for posting in self.index.get_postings(term).into_iter().flatten() {
+                if let Some(bound) = bounds.get_mut(&posting.doc_id.get()) {
+                    *bound += term_bound;
+                }
+            }
+        }
+        bounds
+            .into_iter()
+            .map(|(doc_id, bound)| (bound * self.doc_stats.doc_boost(DocId(doc_id)), doc_id))
+            .collect()
     }
-    fn find_candidates(&self, terms: &[String]) -> Vec<u64> {
-        // Retrieve candidate documents from the inverted index
-        vec![]
+
+    /*
+    Documents similar to `doc_id`, for "related articles" lists. Its most
+    characteristic terms by TF-IDF are searched as a query, and the source
+    document is left out. Only documents held in memory are considered.
+    */
+    pub fn more_like_this(&self, doc_id: u64, limit: usize) -> SearchResults {
+        let start = Instant::now();
+        let terms = self.ranker().top_terms(DocId(doc_id), MORE_LIKE_THIS_TERMS);
+        let mut candidate_docs = self.find_candidates(&terms, 1);
+        candidate_docs.remove(doc_id);
+        let scored_docs = self.score_documents(&candidate_docs, &terms);
+        let mut results = self.rank_and_limit(scored_docs, &SearchOptions::new(limit));
+        results.query_time_ms = start.elapsed().as_millis() as u64;
+        results
     }
-    fn score_documents(&self, doc_ids: &[u64], terms: &[String]) -> Vec<(u64, f64)> {
-        // Compute relevance scores for each candidate document
-        vec![]
+
+    /*
+    The documents whose vectors in `field` are nearest to `vector` by cosine
+    similarity, for semantic search with embeddings computed by the caller.
+    The first `offset + limit` nearest documents passing the filters are
+    kept, then sorted, collapsed and paged as in `search_with_options`;
+    scores are the similarities. Only documents held in memory are
+    considered.
+    */
+    pub fn knn_search(
+        &self,
+        field: &str,
+        vector: &[f32],
+        options: &SearchOptions,
+    ) -> Result<SearchResults, MSErrors> {
+        let start = Instant::now();
+        let k = options.offset.saturating_add(options.limit);
+        let scored_docs = self.nearest_docs(field, vector, &options.filters, k)?;
+        let mut results = self.rank_and_limit(scored_docs, options);
+        results.query_time_ms = start.elapsed().as_millis() as u64;
+        Ok(results)
     }
-    fn rank_and_limit(&self, scored_docs: Vec<(u64, f64)>, limit: usize) -> SearchResults {
-        // Sort by score and limit results
-        SearchResults {
-            documents: vec![],
-            total_matches: 0,
-            query_time_ms: 0,
+
+    /*
+    Search with a text query and a vector at once, for semantic search that
+    still rewards exact words. The query is scored with BM25 and the vector
+    searched in `field` as in `knn_search`, in parallel with the "parallel"
+    feature; the best hits of each are merged by `fusion`. Filters apply to
+    both, and the merged hits are sorted, collapsed and paged as in
+    `search_with_options`. Only documents held in memory are searched.
+    */
+    pub fn hybrid_search(
+        &self,
+        query: &str,
+        field: &str,
+        vector: &[f32],
+        fusion: Fusion,
+        options: &SearchOptions,
+    ) -> Result<SearchResults, MSErrors> {
+        let start = Instant::now();
+        let k = (options.offset.saturating_add(options.limit)).max(HYBRID_CANDIDATES);
+        let lexical = || {
+            let mut docs = self.score_matches(query, options).docs;
+            docs.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+            docs.truncate(k);
+            docs
+        };
+        let nearest = || self.nearest_docs(field, vector, &options.filters, k);
+        #[cfg(feature = "parallel")]
+        let (lexical, nearest) = rayon::join(lexical, nearest);
+        #[cfg(not(feature = "parallel"))]
+        let (lexical, nearest) = (lexical(), nearest());
+
+        let scored_docs = fusion.combine(&lexical, &nearest?);
+        let facets = self.count_facets(&scored_docs, &options.facets);
+        let mut results = self.rank_and_limit(scored_docs, options);
+        results.facets = facets;
+        results.query_time_ms = start.elapsed().as_millis() as u64;
+        Ok(results)
+    }
+
+    // The `k` documents passing `filters` nearest to `vector` in a vector field
+    fn nearest_docs(
+        &self,
+        field: &str,
+        vector: &[f32],
+        filters: &[Filter],
+        k: usize,
+    ) -> Result<Vec<(u64, f64)>, MSErrors> {
+        let Some(vectors) = self.vectors.get(field) else {
+            return Err(MSErrors::SchemaMismatch {
+                field: field.to_string(),
+                message: "not an indexed vector field".to_string(),
+            });
+        };
+        if vector.len() != vectors.dims() {
+            return Err(MSErrors::SchemaMismatch {
+                field: field.to_string(),
+                message: format!(
+                    "expects vectors of {} numbers, got {}",
+                    vectors.dims(),
+                    vector.len()
+                ),
+            });
         }
+        let allowed = (!filters.is_empty()).then(|| {
+            let mut doc_ids: RoaringTreemap = self.documents.keys().map(|id| id.get()).collect();
+            self.apply_filters(&mut doc_ids, filters);
+            doc_ids
+        });
+        let nearest = vectors
+            .nearest(vector, k, |doc_id| {
+                allowed
+                    .as_ref()
+                    .is_none_or(|docs| docs.contains(doc_id.get()))
+            })
+            .into_iter()
+            .map(|(doc_id, similarity)| (doc_id.get(), f64::from(similarity)))
+            .collect();
+        Ok(nearest)
     }
-}
 
-pub struct SearchResults {
-    pub documents: Vec<Document>,
-    pub total_matches: usize,
-    pub query_time_ms: u64,
+    // Pairwise TF-IDF cosine similarities of in-memory documents; others are left out
+    pub fn similarity_matrix(&self, doc_ids: &[u64]) -> SimilarityMatrix {
+        let (doc_ids, vectors) = self.term_vectors(doc_ids);
+        SimilarityMatrix::new(doc_ids, &vectors)
+    }
+
+    /*
+    Group documents by TF-IDF cosine similarity, for grouping results or
+    exploring a corpus. Only documents held in memory are clustered.
+    */
+    pub fn cluster(&self, doc_ids: &[u64], method: ClusterMethod) -> Clustering {
+        let (doc_ids, vectors) = self.term_vectors(doc_ids);
+        match method {
+            ClusterMethod::KMeans { k, iterations } => {
+                cluster::kmeans(&doc_ids, &vectors, k, iterations)
+            }
+            ClusterMethod::Agglomerative { min_similarity } => {
+                cluster::agglomerative(&SimilarityMatrix::new(doc_ids, &vectors), min_similarity)
+            }
+        }
+    }
+
+    fn term_vectors(&self, doc_ids: &[u64]) -> (Vec<u64>, Vec<TermVector>) {
+        let mut seen = HashSet::new();
+        let ranker = self.ranker();
+        doc_ids
+            .iter()
+            .filter(|&&doc_id| self.get_document(doc_id).is_some() && seen.insert(doc_id))
+            .map(|&doc_id| (doc_id, ranker.term_vector(DocId(doc_id))))
+            .unzip()
+    }
+
+    // Fetch one page of highlighted results; pages past any possible offset are empty
+    pub fn search_page(&self, query: &str, page: usize, page_size: usize) -> ResultPage {
+        let options = SearchOptions::new(page_size).offset(page.saturating_mul(page_size));
+        ResultPage {
+            page,
+            ..self.search_page_with_options(query, &options)
+        }
+    }
+
+    // Fetch highlighted results with filters and facets; the page is `offset / limit`
+    pub fn search_page_with_options(&self, query: &str, options: &SearchOptions) -> ResultPage {
+        let results = self.search_with_options(query, options);
+        let terms = self.parse_query(query).terms;
+        let hits = results
+            .documents
+            .iter()
+            .zip(&results.scores)
+            .enumerate()
+            .map(|(i, (document, &score))| {
+                let mut metadata: Vec<(String, String)> = document
+                    .metadata
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                metadata.sort();
+                HitView {
+                    doc_id: document.id,
+                    rank: options.offset + i + 1,
+                    score,
+                    title: document.title.clone(),
+                    snippet: highlight::snippet(
+                        &self.tokenizer,
+                        &document.content,
+                        &terms,
+                        SNIPPET_LEN,
+                    ),
+                    preview: highlight::highlight(&self.tokenizer, &document.content, &terms),
+                    metadata,
+                }
+            })
+            .collect();
+        ResultPage {
+            query: query.to_string(),
+            page: options.offset / options.limit.max(1),
+            page_size: options.limit,
+            total_matches: results.total_matches,
+            query_time_ms: results.query_time_ms,
+            hits,
+            facets: results.facets,
+        }
+    }
+
+    // Every match of the query in a document's content, for match navigation
+    pub fn occurrences(&self, doc_id: u64, query: &str, limit: usize) -> Option<Occurrences> {
+        let document = self.get_document(doc_id)?;
+        let terms = self.parse_query(query).terms;
+        Some(highlight::occurrences(
+            &self.tokenizer,
+            &document.content,
+            &terms,
+            limit,
+            SNIPPET_LEN,
+        ))
+    }
+
+    /*
+    Check the syntax of a query. Searching never fails on a malformed
+    query, it treats unclosed quotes and bad range clauses as plain words;
+    callers that would rather report them, such as the HTTP API, check the
+    query first.
+    */
+    pub fn check_query(&self, query: &str) -> Result<(), MSErrors> {
+        filter::check_ranges(query)?;
+        phrase::check_quotes(query)
+    }
+
+    fn parse_query(&self, query: &str) -> ParsedQuery {
+        // Split off range clauses and phrase quotes, then tokenize and
+        // normalize the rest, dropping duplicate terms
+        let (text, ranges) = filter::extract_ranges(query);
+        let (text, clauses) = self.extract_field_clauses(&text);
+        let (text, phrases) = phrase::extract_phrases(&text);
+        let mut seen = HashSet::new();
+        let field_terms = clauses.iter().flat_map(|(field, value)| {
+            let tokenizer = self.search_tokenizers.get(field);
+            tokenizer
+                .unwrap_or(&self.field_tokenizers[field])
+                .tokenize(value)
+                .into_iter()
+                .map(|t| field_term(field, &t.term))
+        });
+        let terms = self
+            .tokenizer
+            .tokenize(&text)
+            .into_iter()
+            .map(|t| t.term)
+            .chain(field_terms)
+            .filter(|term| seen.insert(term.clone()))
+            .collect();
+        let (phrases, slops) = phrases
+            .into_iter()
+            .map(|Phrase { text, slop }| {
+                let terms = self.tokenizer.tokenize(&text).into_iter().map(|t| t.term);
+                (terms.collect::<Vec<_>>(), slop)
+            })
+            .filter(|(terms, _)| !terms.is_empty())
+            .unzip();
+        ParsedQuery {
+            terms,
+            ranges,
+            phrases,
+            slops,
+        }
+    }
+
+    // Split off `field:value` clauses on searchable metadata fields
+    fn extract_field_clauses(&self, query: &str) -> (String, Vec<(String, String)>) {
+        if self.field_tokenizers.is_empty() {
+            return (query.to_string(), Vec::new());
+        }
+        fields::extract_field_clauses(query, |field| self.field_tokenizers.contains_key(field))
+    }
+
+    fn suggestions(&self, query: &str) -> Vec<Suggestion> {
+        // Propose corrections for rare or unknown query words, showing both
+        // sides as written rather than as analyzed terms
+        let (text, _) = filter::extract_ranges(query);
+        let (text, _) = self.extract_field_clauses(&text);
+        let (text, _) = phrase::extract_phrases(&text);
+        let mut suggestions = Vec::new();
+        for token in self.tokenizer.tokenize(&text) {
+            let original = &text[token.offset.0..token.offset.1];
+            for (term, distance, doc_freq) in suggest::corrections(&self.index, &token.term) {
+                suggestions.push(Suggestion {
+                    original: original.to_string(),
+                    suggestion: self.surface_form(&term),
+                    distance,
+                    doc_freq,
+                });
+            }
+        }
+        suggestions
+    }
+
+    fn surface_form(&self, term: &str) -> String {
+        // The first indexed occurrence of a term, lowercased
+        let occurrence = self.index.get_postings(term).and_then(|postings| {
+            let posting = postings.first()?;
+            let document = self.documents.get(&posting.doc_id)?;
+            let (start, end) = *posting.offsets.first()?;
+            // Offsets index the title and content joined by a newline
+            let title_len = document.title.len() + 1;
+            match start.checked_sub(title_len) {
+                Some(start) => document.content.get(start..end - title_len),
+                None => document.title.get(start..end),
+            }
+        });
+        occurrence.unwrap_or(term).to_lowercase()
+    }
+
+    fn range_candidates(&self, ranges: &[Filter]) -> RoaringTreemap {
+        // A query made only of range clauses matches every document in the
+        // first range; the others are checked as filters
+        match ranges.first() {
+            Some(Filter::Range(field, min, max)) => self
+                .doc_values
+                .range(field, *min, *max)
+                .into_iter()
+                .map(DocId::get)
+                .collect(),
+            _ => RoaringTreemap::new(),
+        }
+    }
+
+    fn find_candidates(&self, terms: &[String], min_terms: usize) -> RoaringTreemap {
+        // Retrieve documents containing at least `min_terms` of the terms
+        // from the inverted index. `at_least[n]` holds the documents seen
+        // with n or more of the terms so far; each term's documents move
+        // up one count. Requiring every term is an intersection, which
+        // skips through the postings instead, and requiring any is a merge
+        // of the postings in document order.
+        let min_terms = min_terms.max(1);
+        if min_terms > 1 && min_terms == terms.len() {
+            return self.index.intersect(terms);
+        }
+        if min_terms == 1 {
+            return self.index.union(terms);
+        }
+        let mut at_least = vec![RoaringTreemap::new(); min_terms + 1];
+        for (seen, term) in terms.iter().enumerate() {
+            let docs = self.index.term_docs(term);
+            for count in (2..=min_terms.min(seen + 1)).rev() {
+                let promoted = &at_least[count - 1] & &docs;
+                at_least[count] |= promoted;
+            }
+            at_least[1] |= docs;
+        }
+        at_least.swap_remove(min_terms)
+    }
+
+    fn apply_filters(&self, doc_ids: &mut RoaringTreemap, filters: &[Filter]) {
+        // Drop candidates rejected by any filter before they are scored,
+        // using the cached document set of frequently used filters
+        if filters.is_empty() {
+            return;
+        }
+        for filter in filters {
+            match self.filter_docs(filter, false) {
+                Some(docs) => *doc_ids &= docs.as_ref(),
+                None => {
+                    let rejected: RoaringTreemap = doc_ids
+                        .iter()
+                        .filter(|&doc_id| !filter.matches(&self.doc_values, DocId(doc_id)))
+                        .collect();
+                    *doc_ids -= rejected;
+                }
+            }
+        }
+    }
+
+    /*
+    The documents `filter` matches if it is used often enough to cache
+    them, or `preload`ed. The set is built without holding the cache's
+    lock, so other filtered searches go on meanwhile; writes need the
+    engine mutably, so the documents cannot change under it.
+    */
+    fn filter_docs(&self, filter: &Filter, preload: bool) -> Option<Arc<RoaringTreemap>> {
+        let lookup = {
+            let mut cache = self.filter_cache.lock().unwrap();
+            if preload {
+                cache.preload(filter)
+            } else {
+                cache.get(filter)
+            }
+        };
+        match lookup {
+            Lookup::Cached(docs) => Some(docs),
+            Lookup::Build => {
+                let docs =
+                    filter_cache::build(filter, &self.doc_values, self.documents.keys().copied());
+                Some(self.filter_cache.lock().unwrap().insert(filter, docs))
+            }
+            Lookup::Uncached => None,
+        }
+    }
+
+    fn score_documents(&self, doc_ids: &RoaringTreemap, terms: &[String]) -> Vec<(u64, f64)> {
+        // Compute relevance scores for each candidate document, in id
+        // order; without terms every candidate is kept with a score of
+        // zero. Large candidate sets are split across threads with the
+        // "parallel" feature, keeping the candidates' order.
+        let ranker = self.ranker();
+        let score = |doc_id: u64| (doc_id, ranker.compute_score(DocId(doc_id), terms));
+        let keep = |&(_, score): &(u64, f64)| score > 0.0 || terms.is_empty();
+        #[cfg(feature = "parallel")]
+        if doc_ids.len() >= PARALLEL_MIN_CANDIDATES as u64 {
+            use rayon::prelude::*;
+            let doc_ids: Vec<u64> = doc_ids.iter().collect();
+            return doc_ids.into_par_iter().map(score).filter(keep).collect();
+        }
+        doc_ids.iter().map(score).filter(keep).collect()
+    }
+
+    /*
+    Keep only documents containing every phrase within its slop, and boost
+    each by how close its phrases are: an exact phrase doubles the score,
+    and the boost shrinks as the words move apart. Without recorded
+    positions a phrase only requires all of its words, with no boost.
+    */
+    fn match_phrases(
+        &self,
+        scored_docs: &mut Vec<(u64, f64)>,
+        phrases: &[Vec<String>],
+        slops: &[usize],
+    ) {
+        if phrases.is_empty() {
+            return;
+        }
+        if !self.settings.record.has_positions() {
+            let index = &self.index;
+            scored_docs.retain(|&(doc_id, _)| {
+                phrases
+                    .iter()
+                    .flatten()
+                    .all(|term| index.posting(term, DocId(doc_id)).is_some())
+            });
+            return;
+        }
+        scored_docs.retain_mut(|(doc_id, score)| {
+            let mut proximity = 0.0;
+            for (terms, &slop) in phrases.iter().zip(slops) {
+                match self.phrase_distance(DocId(*doc_id), terms) {
+                    Some(distance) if distance <= slop => {
+                        proximity += 1.0 / (1.0 + distance as f64)
+                    }
+                    _ => return false,
+                }
+            }
+            *score *= 1.0 + proximity / phrases.len() as f64;
+            true
+        });
+    }
+
+    fn phrase_distance(&self, doc_id: DocId, terms: &[String]) -> Option<usize> {
+        let positions = terms
+            .iter()
+            .map(|term| {
+                let posting = self.index.posting(term, doc_id)?;
+                Some(posting.positions.as_slice())
+            })
+            .collect::<Option<Vec<_>>>()?;
+        phrase::match_distance(&positions)
+    }
+
+    fn count_facets(
+        &self,
+        scored_docs: &[(u64, f64)],
+        fields: &[String],
+    ) -> HashMap<String, HashMap<String, usize>> {
+        // Count metadata values over every match, not just the returned page
+        fields
+            .iter()
+            .map(|field| {
+                let doc_ids = scored_docs.iter().map(|&(doc_id, _)| DocId(doc_id));
+                (field.clone(), self.doc_values.facet_counts(field, doc_ids))
+            })
+            .collect()
+    }
+
+    fn rank_and_limit(
+        &self,
+        scored_docs: Vec<(u64, f64)>,
+        options: &SearchOptions,
+    ) -> SearchResults {
+        let sorted = self.sort_hits(scored_docs, options);
+        self.limit_hits(sorted, options)
+    }
+
+    // Options sorting on a keyword field by its text rather than as a number
+    fn resolve_sort<'a>(&self, options: &'a SearchOptions) -> Cow<'a, SearchOptions> {
+        match &options.sort_by.key {
+            SortKey::Field(field) if self.settings.schema.is_keyword(field) => {
+                let sort_by = SortSpec::keyword(field, options.sort_by.direction);
+                Cow::Owned(options.clone().sort_by(sort_by))
+            }
+            _ => Cow::Borrowed(options),
+        }
+    }
+
+    // Sort hits by the requested key
+    fn sort_hits(&self, scored_docs: Vec<(u64, f64)>, options: &SearchOptions) -> Vec<(u64, f64)> {
+        let mut sortable: Vec<SortableHit> = scored_docs
+            .into_iter()
+            .map(|(doc_id, score)| {
+                let value = match &options.sort_by.key {
+                    SortKey::Field(field) => self
+                        .doc_values
+                        .get_number(field, DocId(doc_id))
+                        .map(SortValue::Number),
+                    SortKey::Keyword(field) => self
+                        .doc_values
+                        .get(field, DocId(doc_id))
+                        .map(SortValue::Text),
+                    SortKey::Distance(field, origin) => self
+                        .doc_values
+                        .get_point(field, DocId(doc_id))
+                        .map(|point| point.distance_km(origin))
+                        .filter(|distance| !distance.is_nan())
+                        .map(SortValue::Number),
+                    _ => None,
+                };
+                (doc_id, score, value)
+            })
+            .collect();
+        sortable.sort_by(|a, b| options.sort_by.compare(a, b));
+        sortable.into_iter().map(|(d, s, _)| (d, s)).collect()
+    }
+
+    /*
+    Rescore the first hits of merged first-stage results with the reranker,
+    reading each hit's features from the tier holding it, then page them.
+    Hits past the window keep their order and first-stage score.
+    */
+    fn rerank(
+        &self,
+        query: &str,
+        rerank: &Rerank,
+        results: &mut SearchResults,
+        cold: &[Arc<SearchEngine>],
+        options: &SearchOptions,
+    ) {
+        let terms = self.parse_query(query).terms;
+        let documents = std::mem::take(&mut results.documents);
+        let scores = std::mem::take(&mut results.scores);
+        let counts = std::mem::take(&mut results.group_counts);
+        let mut hits: Vec<(Document, f64, usize)> = documents
+            .into_iter()
+            .zip(scores)
+            .zip(counts)
+            .map(|((document, score), count)| (document, score, count))
+            .collect();
+        let window = hits.len().min(rerank.window);
+        for (document, score, _) in &mut hits[..window] {
+            *score = self.rerank_score(rerank, &terms, DocId(document.id), *score, cold);
+        }
+        hits[..window].sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.id.cmp(&b.0.id)));
+        for (document, score, count) in hits.into_iter().skip(options.offset).take(options.limit) {
+            results.documents.push(document);
+            results.scores.push(score);
+            results.group_counts.push(count);
+        }
+    }
+
+    // The reranker, if it applies to searches with `options`
+    fn active_reranker(&self, options: &SearchOptions) -> Option<&Rerank> {
+        self.reranker.as_ref().filter(|rerank| {
+            rerank.window > 0 && !options.constant_score && options.sort_by == SortSpec::score()
+        })
+    }
+
+    // The reranked score of a hit, with features read from the tier holding it
+    fn rerank_score(
+        &self,
+        rerank: &Rerank,
+        terms: &[String],
+        doc_id: DocId,
+        score: f64,
+        cold: &[Arc<SearchEngine>],
+    ) -> f64 {
+        let engine = match self.documents.contains_key(&doc_id) {
+            true => self,
+            false => cold
+                .iter()
+                .find(|engine| engine.documents.contains_key(&doc_id))
+                .map_or(self, |engine| engine.as_ref()),
+        };
+        let features = engine.rerank_features(doc_id, score, terms, &rerank.fields);
+        rerank.reranker.score(&features)
+    }
+
+    fn rerank_features(
+        &self,
+        doc_id: DocId,
+        score: f64,
+        terms: &[String],
+        fields: &[String],
+    ) -> RerankFeatures {
+        let title: HashSet<String> = self
+            .documents
+            .get(&doc_id)
+            .map(|document| self.tokenizer.tokenize(&document.title))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|token| token.term)
+            .collect();
+        RerankFeatures {
+            doc_id: doc_id.get(),
+            score,
+            matched_terms: terms
+                .iter()
+                .filter(|term| self.index.posting(term, doc_id).is_some())
+                .count(),
+            title_matches: terms.iter().filter(|term| title.contains(*term)).count(),
+            doc_length: self.doc_stats.doc_length(doc_id).unwrap_or(0),
+            metadata: fields
+                .iter()
+                .map(|field| self.doc_values.get_number(field, doc_id))
+                .collect(),
+        }
+    }
+
+    // Collapse and page sorted hits, fetching their documents
+    fn limit_hits(&self, scored_docs: Vec<(u64, f64)>, options: &SearchOptions) -> SearchResults {
+        let grouped = match &options.collapse {
+            Some(field) => self.collapse(scored_docs, field),
+            None => scored_docs.into_iter().map(|(d, s)| (d, s, 1)).collect(),
+        };
+
+        let mut results = SearchResults {
+            documents: Vec::new(),
+            scores: Vec::new(),
+            group_counts: Vec::new(),
+            total_matches: grouped.len(),
+            query_time_ms: 0,
+            facets: HashMap::new(),
+            suggestions: Vec::new(),
+            is_approximate: false,
+        };
+        for (doc_id, score, count) in grouped.into_iter().skip(options.offset).take(options.limit) {
+            if let Some(document) = self.get_document(doc_id) {
+                results.documents.push(match &options.fields {
+                    Some(fields) => document.project(fields),
+                    None => document.clone(),
+                });
+                results.scores.push(score);
+                results.group_counts.push(count);
+            }
+        }
+        results
+    }
+
+    fn collapse(&self, sorted_docs: Vec<(u64, f64)>, field: &str) -> Vec<(u64, f64, usize)> {
+        // Keep the first (best ranked) document of each value of `field`,
+        // counting the documents it stands for. Documents without the field
+        // are never collapsed.
+        let mut groups: HashMap<&str, usize> = HashMap::new();
+        let mut collapsed: Vec<(u64, f64, usize)> = Vec::new();
+        for (doc_id, score) in sorted_docs {
+            match self.doc_values.get(field, DocId(doc_id)) {
+                Some(value) => match groups.get(value) {
+                    Some(&group) => collapsed[group].2 += 1,
+                    None => {
+                        groups.insert(value, collapsed.len());
+                        collapsed.push((doc_id, score, 1));
+                    }
+                },
+                None => collapsed.push((doc_id, score, 1)),
+            }
+        }
+        collapsed
+    }
+}
+
+// Value of the sort key read from a returned document, for merging tiers
+fn sort_value<'a>(key: &SortKey, document: &'a Document) -> Option<SortValue<'a>> {
+    match key {
+        SortKey::Field(field) => document
+            .metadata
+            .get(field)
+            .and_then(|v| parse_number(v))
+            .map(SortValue::Number),
+        // Keyword copies are not stored; their value is that of their text field
+        SortKey::Keyword(field) => document
+            .metadata
+            .get(field)
+            .or_else(|| {
+                let source = field.strip_suffix(RAW_SUFFIX)?;
+                document.metadata.get(source)
+            })
+            .map(|v| SortValue::Text(v)),
+        SortKey::Distance(field, origin) => document
+            .metadata
+            .get(field)
+            .and_then(|v| GeoPoint::parse(v))
+            .map(|point| point.distance_km(origin))
+            .filter(|distance| !distance.is_nan())
+            .map(SortValue::Number),
+        _ => None,
+    }
+}
+
+// Merge results of several tiers, each already sorted and limited
+fn merge_results(parts: Vec<SearchResults>, options: &SearchOptions) -> SearchResults {
+    let mut merged = SearchResults {
+        documents: Vec::new(),
+        scores: Vec::new(),
+        group_counts: Vec::new(),
+        total_matches: 0,
+        query_time_ms: 0,
+        facets: HashMap::new(),
+        suggestions: Vec::new(),
+        is_approximate: false,
+    };
+    let mut hits = Vec::new();
+    for part in parts {
+        for suggestion in part.suggestions {
+            if !merged.suggestions.contains(&suggestion) {
+                merged.suggestions.push(suggestion);
+            }
+        }
+        merged.total_matches += part.total_matches;
+        merged.is_approximate |= part.is_approximate;
+        for (field, counts) in part.facets {
+            let merged_counts = merged.facets.entry(field).or_default();
+            for (value, count) in counts {
+                *merged_counts.entry(value).or_insert(0) += count;
+            }
+        }
+        hits.extend(
+            part.documents
+                .into_iter()
+                .zip(part.scores)
+                .zip(part.group_counts),
+        );
+    }
+
+    hits.sort_by(|((a, a_score), _), ((b, b_score), _)| {
+        options.sort_by.compare(
+            &(a.id, *a_score, sort_value(&options.sort_by.key, a)),
+            &(b.id, *b_score, sort_value(&options.sort_by.key, b)),
+        )
+    });
+
+    let mut collapsed: Vec<(Document, f64, usize)> = Vec::new();
+    let mut groups: HashMap<String, usize> = HashMap::new();
+    for ((document, score), count) in hits {
+        let group = options
+            .collapse
+            .as_ref()
+            .and_then(|field| document.metadata.get(field).cloned());
+        if let Some(value) = group {
+            if let Some(&index) = groups.get(&value) {
+                collapsed[index].2 += count;
+                merged.total_matches -= 1;
+                continue;
+            }
+            groups.insert(value, collapsed.len());
+        }
+        collapsed.push((document, score, count));
+    }
+    for (document, score, count) in collapsed
+        .into_iter()
+        .skip(options.offset)
+        .take(options.limit)
+    {
+        merged.documents.push(document);
+        merged.scores.push(score);
+        merged.group_counts.push(count);
+    }
+    merged
+}
+
+// A parent document found through its chunks, see `search_parents`
+#[derive(Debug)]
+pub struct ParentHit {
+    pub parent_id: u64,
+    pub score: f64, // Score of the best chunk
+    pub best_chunk: Document,
+    pub matching_chunks: usize, // Chunks of the parent that matched
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchResults {
+    pub documents: Vec<Document>,
+    pub scores: Vec<f64>,         // Score of the document at the same index
+    pub group_counts: Vec<usize>, // Matches collapsed into the document at the same index
+    pub total_matches: usize,     // Number of groups when collapsing
+    pub query_time_ms: u64,
+    pub facets: HashMap<String, HashMap<String, usize>>, // field -> value -> count
+    pub suggestions: Vec<Suggestion>, // "Did you mean" corrections for rare query words
+    pub is_approximate: bool,         // Top hits may be missing, see `SearchOptions::approximate`
+}
+
+impl SearchResults {
+    /*
+    Merge the hits of several searches with `rank::fuse`, keeping the best
+    `limit`. Each document is taken from the first results holding it, so
+    results from two indexes should not reuse ids for different documents.
+    Scores are the fused scores and `total_matches` counts distinct
+    documents among the hits; facets are dropped, as counts over different
+    queries do not add up.
+    */
+    pub fn fuse(parts: Vec<SearchResults>, k: f64, limit: usize) -> SearchResults {
+        let fused = rank::fuse(
+            parts
+                .iter()
+                .map(|part| part.documents.iter().map(|document| document.id)),
+            k,
+        );
+        let mut documents: HashMap<u64, Document> = HashMap::new();
+        let mut merged = SearchResults {
+            documents: Vec::new(),
+            scores: Vec::new(),
+            group_counts: Vec::new(),
+            total_matches: fused.len(),
+            query_time_ms: 0,
+            facets: HashMap::new(),
+            suggestions: Vec::new(),
+            is_approximate: false,
+        };
+        for part in parts {
+            merged.query_time_ms += part.query_time_ms;
+            merged.is_approximate |= part.is_approximate;
+            for suggestion in part.suggestions {
+                if !merged.suggestions.contains(&suggestion) {
+                    merged.suggestions.push(suggestion);
+                }
+            }
+            for document in part.documents {
+                documents.entry(document.id).or_insert(document);
+            }
+        }
+        for (doc_id, score) in fused.into_iter().take(limit) {
+            if let Some(document) = documents.remove(&doc_id) {
+                merged.documents.push(document);
+                merged.scores.push(score);
+                merged.group_counts.push(1);
+            }
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec;
+    use std::time::Duration;
+
+    fn doc(id: u64, title: &str, content: &str) -> Document {
+        Document {
+            id,
+            title: title.to_string(),
+            content: content.to_string(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    fn doc_with(id: u64, content: &str, metadata: &[(&str, &str)]) -> Document {
+        let mut document = doc(id, "", content);
+        for (key, value) in metadata {
+            document.metadata.insert(key.to_string(), value.to_string());
+        }
+        document
+    }
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("mini-search-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_search() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine
+            .add_document(doc(1, "Foxes", "The quick brown fox jumps"))
+            .unwrap();
+        engine
+            .add_document(doc(2, "Turtles", "Slow turtle walks"))
+            .unwrap();
+        assert!(
+            engine
+                .add_document(doc(2, "Again", "duplicate id"))
+                .is_err()
+        );
+
+        let results = engine.search("fox", 10);
+        assert_eq!(results.total_matches, 1);
+        assert_eq!(results.documents[0].id, 1);
+        assert_eq!(engine.search("unknown", 10).total_matches, 0);
+    }
+
+    #[test]
+    fn test_facets() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine
+            .add_document(doc_with(1, "fox news", &[("category", "news")]))
+            .unwrap();
+        engine
+            .add_document(doc_with(2, "fox blog", &[("category", "blog")]))
+            .unwrap();
+        engine
+            .add_document(doc_with(3, "fox report", &[("category", "news")]))
+            .unwrap();
+        engine
+            .add_document(doc_with(4, "turtle", &[("category", "blog")]))
+            .unwrap();
+
+        let options = SearchOptions::new(1).facet("category");
+        let results = engine.search_with_options("fox", &options);
+        assert_eq!(results.documents.len(), 1);
+        let counts = &results.facets["category"];
+        assert_eq!(counts["news"], 2);
+        assert_eq!(counts["blog"], 1);
+    }
+
+    #[test]
+    fn test_analyzer_setting() {
+        let settings = IndexSettings {
+            analyzer: AnalyzerPreset::Whitespace,
+            ..IndexSettings::default()
+        };
+        let mut engine = SearchEngine::new(settings).unwrap();
+        engine
+            .add_document(doc(1, "", "The jumps of x86-64"))
+            .unwrap();
+
+        // No stemming or stop words, and punctuation stays inside tokens
+        assert_eq!(engine.search("jumps", 10).total_matches, 1);
+        assert_eq!(engine.search("jump", 10).total_matches, 0);
+        assert_eq!(engine.search("the", 10).total_matches, 1);
+        assert_eq!(engine.search("X86-64", 10).total_matches, 1);
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine
+            .add_document(doc_with(1, "fox", &[("lang", "en"), ("tag", "animal")]))
+            .unwrap();
+        engine
+            .add_document(doc_with(2, "fox", &[("lang", "de"), ("tag", "animal")]))
+            .unwrap();
+        engine
+            .add_document(doc_with(3, "fox", &[("lang", "en"), ("tag", "brand")]))
+            .unwrap();
+
+        let options = SearchOptions::new(10).filter(Filter::eq("lang", "en"));
+        let results = engine.search_with_options("fox", &options);
+        let ids: Vec<u64> = results.documents.iter().map(|d| d.id).collect();
+        assert_eq!(ids, vec![1, 3]);
+        assert_eq!(results.total_matches, 2);
+
+        let options = options.filter(Filter::one_of("tag", ["animal", "plant"]));
+        let results = engine.search_with_options("fox", &options);
+        assert_eq!(results.documents.len(), 1);
+        assert_eq!(results.documents[0].id, 1);
+
+        // The repeated language filter is now cached and follows writes
+        assert_eq!(engine.cached_filters(), 1);
+        engine
+            .add_document(doc_with(4, "fox", &[("lang", "en")]))
+            .unwrap();
+        engine.remove_document(1).unwrap();
+        let options = SearchOptions::new(10).filter(Filter::eq("lang", "en"));
+        let results = engine.search_with_options("fox", &options);
+        let ids: Vec<u64> = results.documents.iter().map(|d| d.id).collect();
+        assert_eq!(ids, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_range_query() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine
+            .add_document(doc_with(1, "red mug", &[("price", "8")]))
+            .unwrap();
+        engine
+            .add_document(doc_with(2, "blue mug", &[("price", "12.5")]))
+            .unwrap();
+        engine
+            .add_document(doc_with(3, "red mug", &[("price", "40"), ("stock", "0")]))
+            .unwrap();
+        engine.add_document(doc_with(4, "red mug", &[])).unwrap();
+
+        let ids = |results: SearchResults| -> Vec<u64> {
+            results.documents.iter().map(|d| d.id).collect()
+        };
+        assert_eq!(ids(engine.search("mug price:[10 TO 50]", 10)), vec![2, 3]);
+        assert_eq!(ids(engine.search("red price:[* TO 10]", 10)), vec![1]);
+
+        // Range clauses alone select documents without scoring them
+        let results = engine.search("price:[10 TO *] stock:[* TO 0]", 10);
+        assert_eq!(ids(results), vec![3]);
+
+        let options = SearchOptions::new(10).filter(Filter::range("price", Some(20.0), None));
+        assert_eq!(ids(engine.search_with_options("mug", &options)), vec![3]);
+    }
+
+    #[test]
+    fn test_date_filter_and_sort() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine
+            .add_document(doc_with(1, "rust release", &[("published", "2024-03-01")]))
+            .unwrap();
+        engine
+            .add_document(doc_with(
+                2,
+                "rust release notes",
+                &[("published", "2024-05-01T09:30:00+02:00")],
+            ))
+            .unwrap();
+        engine
+            .add_document(doc_with(
+                3,
+                "rust",
+                &[("published", "2023-11-20T00:00:00Z")],
+            ))
+            .unwrap();
+        engine.add_document(doc_with(4, "rust", &[])).unwrap();
+
+        let ids = |results: SearchResults| -> Vec<u64> {
+            results.documents.iter().map(|d| d.id).collect()
+        };
+        let newest =
+            SearchOptions::new(10).sort_by(SortSpec::field("published", Direction::Descending));
+        assert_eq!(
+            ids(engine.search_with_options("rust", &newest)),
+            vec![2, 1, 3, 4]
+        );
+        let oldest =
+            SearchOptions::new(10).sort_by(SortSpec::field("published", Direction::Ascending));
+        assert_eq!(
+            ids(engine.search_with_options("rust", &oldest)),
+            vec![3, 1, 2, 4]
+        );
+
+        let recent = newest.filter(Filter::after("published", "2024-01-01").unwrap());
+        assert_eq!(ids(engine.search_with_options("rust", &recent)), vec![2, 1]);
+        let results = engine.search("rust published:[* TO 2024-03-01T00:00:00Z]", 10);
+        assert_eq!(ids(results), vec![3, 1]);
+    }
+
+    #[test]
+    fn test_sort_by() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine
+            .add_document(doc_with(1, "fox fox fox", &[("price", "30")]))
+            .unwrap();
+        engine
+            .add_document(doc_with(2, "fox and a turtle", &[("price", "5")]))
+            .unwrap();
+        engine
+            .add_document(doc_with(3, "fox fox", &[("price", "30")]))
+            .unwrap();
+        engine
+            .add_document(doc_with(4, "fox", &[("price", "cheap")]))
+            .unwrap();
+
+        let ids = |sort_by: SortSpec| -> Vec<u64> {
+            let options = SearchOptions::new(10).sort_by(sort_by);
+            let results = engine.search_with_options("fox", &options);
+            results.documents.iter().map(|d| d.id).collect()
+        };
+        assert_eq!(ids(SortSpec::score()), vec![1, 3, 4, 2]);
+        assert_eq!(
+            ids(SortSpec {
+                key: SortKey::Score,
+                direction: Direction::Ascending
+            }),
+            vec![2, 4, 3, 1]
+        );
+        assert_eq!(
+            ids(SortSpec::doc_id(Direction::Descending)),
+            vec![4, 3, 2, 1]
+        );
+
+        // Equal prices fall back to score; a non-numeric price sorts last
+        let by_price = ids(SortSpec::field("price", Direction::Descending));
+        assert_eq!(by_price, vec![1, 3, 2, 4]);
+        let by_price = ids(SortSpec::field("price", Direction::Ascending));
+        assert_eq!(by_price, vec![2, 1, 3, 4]);
+    }
+
+    #[test]
+    fn test_collapse() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        let pages = [
+            (1, "rust rust rust", "a.com"),
+            (2, "rust rust", "a.com"),
+            (3, "rust guide", "b.org"),
+            (4, "rust", "a.com"),
+        ];
+        for (id, content, domain) in pages {
+            engine
+                .add_document(doc_with(id, content, &[("domain", domain)]))
+                .unwrap();
+        }
+        engine.add_document(doc_with(5, "rust book", &[])).unwrap();
+        engine.add_document(doc_with(6, "rust book", &[])).unwrap();
+
+        let options = SearchOptions::new(10).collapse("domain");
+        let results = engine.search_with_options("rust", &options);
+        let ids: Vec<u64> = results.documents.iter().map(|d| d.id).collect();
+        assert_eq!(ids, vec![1, 3, 5, 6]);
+        assert_eq!(results.group_counts, vec![3, 1, 1, 1]);
+        assert_eq!(results.total_matches, 4);
+
+        let results = engine.search("rust", 10);
+        assert_eq!(results.group_counts, vec![1; 6]);
+    }
+
+    #[test]
+    fn test_set_similarity() {
+        // Scores by term frequency alone, ignoring lengths and rarity
+        struct TermCount;
+        impl Similarity for TermCount {
+            fn score_term(&self, _: &dyn Statistics, _: &str, term_freq: f64, _: usize) -> f64 {
+                term_freq
+            }
+
+            fn term_bound(&self, _: &dyn Statistics, _: &str) -> f64 {
+                f64::INFINITY
+            }
+        }
+
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine.add_document(doc(1, "", "rust")).unwrap();
+        engine
+            .add_document(doc(2, "", "rust rust and many other words"))
+            .unwrap();
+        assert_eq!(engine.search("rust", 10).documents[0].id, 1);
+        engine.set_similarity(TermCount);
+        let results = engine.search("rust", 10);
+        assert_eq!(results.documents[0].id, 2);
+        assert_eq!(results.scores, [2.0, 1.0]);
+
+        // New BM25 parameters leave it in place
+        let update = SettingsUpdate {
+            k1: Some(1.2),
+            ..SettingsUpdate::default()
+        };
+        engine.update_settings(update).unwrap();
+        assert_eq!(engine.search("rust", 10).scores, [2.0, 1.0]);
+
+        // Cold segments are scored with it too
+        let dir = temp_dir("engine-similarity");
+        let mut engine = SearchEngine::create(&dir, IndexSettings::default()).unwrap();
+        engine
+            .add_document(doc_with(1, "rust rust rust", &[("at", "2001-01-01")]))
+            .unwrap();
+        engine.add_document(doc(2, "", "rust")).unwrap();
+        let policy = TieringPolicy::Age {
+            field: "at".to_string(),
+            max_age: Duration::from_secs(86_400),
+        };
+        assert_eq!(engine.tier(&policy).unwrap(), 1);
+        engine.search("rust", 10);
+        engine.set_similarity(TermCount);
+        assert_eq!(engine.search("rust", 10).scores, [3.0, 1.0]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_ordered_index_dump() {
+        let dump = |ids: [u64; 3]| {
+            let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+            for id in ids {
+                let content = ["zebra apple", "apple pie", "mango zebra apple"][id as usize];
+                engine.add_document(doc(id, "", content)).unwrap();
+            }
+            let mut out = Vec::new();
+            engine.dump_index(&mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let forward = dump([0, 1, 2]);
+        assert_eq!(forward, dump([2, 0, 1]));
+        assert!(forward.starts_with("appl\t0:1 1:0 2:2\n"));
+        assert!(forward.ends_with("zebra\t0:0 2:1\n"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        let document = doc_with(1, "serialized results", &[("lang", "en")]);
+        engine.add_document(document.clone()).unwrap();
+
+        let json = serde_json::to_value(engine.search("results", 10)).unwrap();
+        assert_eq!(json["documents"][0]["metadata"]["lang"], "en");
+        let results: SearchResults = serde_json::from_value(json).unwrap();
+        assert_eq!(results.documents, [document]);
+
+        // Options read from config, with the rest left at their defaults
+        let options: SearchOptions = serde_json::from_str(
+            r#"{"limit": 5, "filters": [{"eq": ["lang", "en"]}], "sort_by": {"key": "doc_id"}}"#,
+        )
+        .unwrap();
+        let expected = SearchOptions::new(5)
+            .filter(Filter::eq("lang", "en"))
+            .sort_by(SortSpec::doc_id(Direction::Descending));
+        assert_eq!(options, expected);
+        let settings: IndexSettings =
+            serde_json::from_str(r#"{"analyzer": "simple", "merge_policy": "no_merge"}"#).unwrap();
+        assert_eq!(settings.analyzer, AnalyzerPreset::Simple);
+        assert_eq!(settings.k1, IndexSettings::default().k1);
+    }
+
+    #[test]
+    fn test_field_selection() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        let metadata = [("url", "https://example.com"), ("lang", "en")];
+        let mut document = doc_with(1, "a long body about rust", &metadata);
+        document.title = "Rust".to_string();
+        engine.add_document(document.clone()).unwrap();
+
+        let options = SearchOptions::new(10).fields(["title", "url"]);
+        let results = engine.search_with_options("rust", &options);
+        let hit = &results.documents[0];
+        assert_eq!((hit.id, hit.title.as_str()), (1, "Rust"));
+        assert!(hit.content.is_empty());
+        assert_eq!(hit.metadata.len(), 1);
+        assert_eq!(hit.metadata["url"], "https://example.com");
+        assert_eq!(engine.search("rust", 10).documents[0], document);
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine
+            .add_document(doc(1, "Searching", "Fast searching of documents"))
+            .unwrap();
+        engine
+            .add_document(doc(2, "", "Searching logs with a search engine"))
+            .unwrap();
+
+        let results = engine.search("serching logs", 10);
+        assert_eq!(results.suggestions.len(), 1);
+        let suggestion = &results.suggestions[0];
+        assert_eq!(suggestion.original, "serching");
+        assert_eq!(suggestion.suggestion, "searching");
+        assert_eq!(suggestion.doc_freq, 2);
+        assert!(engine.search("searching", 10).suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_suggest() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine
+            .add_document(doc(1, "Search", "Searching and searches"))
+            .unwrap();
+        engine.add_document(doc(2, "", "search engines")).unwrap();
+        engine.add_document(doc(3, "", "seals")).unwrap();
+
+        let texts = |completions: Vec<Completion>| -> Vec<String> {
+            completions.into_iter().map(|c| c.text).collect()
+        };
+        assert_eq!(
+            texts(engine.suggest("Sea", 3)),
+            vec!["search", "seals", "searches"]
+        );
+        assert_eq!(engine.suggest("sea", 1)[0].frequency, 2);
+        assert_eq!(texts(engine.suggest("fast eng", 5)), vec!["fast engines"]);
+
+        engine.remove_document(2);
+        assert_eq!(engine.suggest("search", 1)[0].frequency, 1);
+
+        // Logged queries are completed as a whole
+        engine.enable_query_log();
+        engine.search("seals  pups", 10);
+        engine.search("seals pups", 10);
+        let completions = engine.suggest("seals", 2);
+        assert_eq!(completions[0].text, "seals pups");
+        assert_eq!(completions[0].frequency, 2);
+        assert!(engine.suggest("  ", 5).is_empty());
+    }
+
+    #[test]
+    fn test_phrase_slop() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine
+            .add_document(doc(1, "", "The quick brown fox jumps"))
+            .unwrap();
+        engine
+            .add_document(doc(2, "", "Quick jumps ahead"))
+            .unwrap();
+        engine.add_document(doc(3, "", "Jumps were quick")).unwrap();
+        engine
+            .add_document(doc(4, "", "quick thinking, slow turtle, long jumps"))
+            .unwrap();
+
+        let ids = |query: &str| -> Vec<u64> {
+            let results = engine.search(query, 10);
+            results.documents.iter().map(|d| d.id).collect()
+        };
+        assert_eq!(ids("\"quick jumps\""), vec![2]);
+        // The closer match ranks first; word order must be kept
+        assert_eq!(ids("\"quick jumps\"~2"), vec![2, 1]);
+        assert_eq!(ids("\"quick jumps\"~5"), vec![2, 1, 4]);
+        assert_eq!(ids("\"brown fox\" turtle"), vec![1]);
+        assert_eq!(engine.search("quick jumps", 10).total_matches, 4);
+    }
+
+    #[test]
+    fn test_query_limits() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine.add_document(doc(1, "", "limited")).unwrap();
+        engine.set_query_limits(QueryLimits {
+            queries_per_second: Some(0.001),
+            ..QueryLimits::default()
+        });
+        assert_eq!(engine.try_search("limited", 10).unwrap().total_matches, 1);
+        assert!(matches!(
+            engine.try_search("limited", 10),
+            Err(MSErrors::Busy(_))
+        ));
+        // Plain searches bypass the limits
+        assert_eq!(engine.search("limited", 10).total_matches, 1);
+    }
+
+    #[test]
+    fn test_health() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine.add_document(doc(1, "", "in memory")).unwrap();
+        let health = engine.health();
+        assert_eq!(health.status, HealthStatus::Healthy);
+        assert!(!health.persistent);
+        assert_eq!(health.pending_changes, 1);
+        assert_eq!(health.last_flush_age, None);
+
+        let dir = temp_dir("health");
+        let mut engine = SearchEngine::create(&dir, IndexSettings::default()).unwrap();
+        engine.add_document(doc(1, "", "on disk")).unwrap();
+        engine.remove_document(1);
+        assert_eq!(engine.health().pending_changes, 2);
+        engine.flush().unwrap();
+        let health = engine.health();
+        assert!(health.persistent && health.is_ready());
+        assert_eq!(health.pending_changes, 0);
+        #[cfg(feature = "disk-space")]
+        assert!(health.disk_available_bytes.is_some());
+        assert!(!health.poisoned_locks);
+
+        // Unknown headroom leaves the engine healthy, too little does not
+        let unknown = Health {
+            disk_available_bytes: None,
+            ..health.clone()
+        };
+        assert_eq!(health::status(&unknown), HealthStatus::Healthy);
+        let low = Health {
+            disk_available_bytes: Some(1024),
+            ..health.clone()
+        };
+        assert_eq!(health::status(&low), HealthStatus::Unhealthy);
+
+        // A panic while holding the query log poisons the engine
+        engine.enable_query_log();
+        let engine = std::sync::Arc::new(engine);
+        let shared = engine.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = shared.query_log.as_ref().unwrap().lock().unwrap();
+            panic!("query log poisoned");
+        })
+        .join();
+        assert_eq!(engine.health().status, HealthStatus::Unhealthy);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_minimum_should_match() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine.add_document(doc(1, "", "red wooden chair")).unwrap();
+        engine.add_document(doc(2, "", "red wooden table")).unwrap();
+        engine.add_document(doc(3, "", "red sofa")).unwrap();
+        engine.add_document(doc(4, "", "blue lamp")).unwrap();
+
+        let matches = |minimum: MinimumShouldMatch| {
+            let options = SearchOptions::new(10).minimum_should_match(minimum);
+            let results = engine.search_with_options("red wooden chair", &options);
+            let mut ids: Vec<u64> = results.documents.iter().map(|d| d.id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(matches(MinimumShouldMatch::Count(1)), vec![1, 2, 3]);
+        assert_eq!(matches(MinimumShouldMatch::Count(2)), vec![1, 2]);
+        assert_eq!(matches(MinimumShouldMatch::Count(5)), vec![1]);
+        // Two thirds of three terms, rounded down
+        assert_eq!(matches(MinimumShouldMatch::Percent(66)), vec![1, 2, 3]);
+        assert_eq!(matches(MinimumShouldMatch::Percent(67)), vec![1, 2]);
+        assert_eq!(matches(MinimumShouldMatch::Percent(0)), vec![1, 2, 3]);
+        assert_eq!(MinimumShouldMatch::Percent(50).required(0), 0);
+    }
+
+    #[test]
+    fn test_search_iter() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        for id in 1..=50 {
+            let content = "fox ".repeat(id as usize % 7 + 1) + "filler text";
+            engine.add_document(doc(id, "", &content)).unwrap();
+        }
+        let iter = engine.search_iter("fox");
+        assert_eq!(iter.len(), 50);
+
+        // The lazy order matches a full search
+        let expected = engine.search("fox", 5);
+        let hits: Vec<Hit> = engine.search_iter("fox").take(5).collect();
+        let ids: Vec<u64> = hits.iter().map(|hit| hit.doc_id).collect();
+        let expected_ids: Vec<u64> = expected.documents.iter().map(|d| d.id).collect();
+        assert_eq!(ids, expected_ids);
+        assert_eq!(hits[0].score, expected.scores[0]);
+        assert_eq!(hits[0].document().unwrap().id, expected_ids[0]);
+        assert_eq!(engine.search_iter("turtle").next().map(|h| h.doc_id), None);
+    }
+
+    #[test]
+    fn test_decay() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        let docs = [
+            (1, "rust rust news", "2024-01-01T00:00:00Z"),
+            (2, "rust news", "2024-06-01T00:00:00Z"),
+            (3, "rust news", ""),
+        ];
+        for (id, content, published) in docs {
+            engine
+                .add_document(doc_with(id, content, &[("published", published)]))
+                .unwrap();
+        }
+        let ids = |options: &SearchOptions| -> Vec<u64> {
+            let results = engine.search_with_options("rust", options);
+            results.documents.iter().map(|d| d.id).collect()
+        };
+        assert_eq!(ids(&SearchOptions::new(10)), [1, 2, 3]);
+
+        // Decaying from June, the January document loses most of its score
+        let june = crate::document::date::parse_rfc3339("2024-06-01T00:00:00Z").unwrap();
+        let month = 30.0 * 86400.0;
+        let options = SearchOptions::new(10).decay(Decay::gauss("published", june, month));
+        assert_eq!(ids(&options), [2, 3, 1]);
+        let scores = engine.search_with_options("rust", &options).scores;
+        assert_eq!(scores[0], scores[1]);
+        let undecayed = engine.search("rust", 10).scores[0];
+        assert!(scores[2] < undecayed * 0.01);
+        let options = options.constant_score();
+        assert_eq!(ids(&options), [1, 2, 3]);
+
+        // An invalid decay is ignored by search but rejected by try_search
+        let invalid = SearchOptions::new(10).decay(Decay::gauss("published", june, 0.0));
+        assert_eq!(ids(&invalid), [1, 2, 3]);
+        let err = engine
+            .try_search_with_options("rust", &invalid)
+            .unwrap_err();
+        assert!(matches!(err, MSErrors::SearchError(_)));
+    }
+
+    #[test]
+    fn test_rerank() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        let docs = [
+            (1, "Rust", "rust news", "10"),
+            (2, "", "rust rust rust", "30"),
+            (3, "", "rust and more news", "20"),
+        ];
+        for (id, title, content, price) in docs {
+            let mut document = doc_with(id, content, &[("price", price)]);
+            document.title = title.to_string();
+            engine.add_document(document).unwrap();
+        }
+        let ids = |results: SearchResults| -> Vec<u64> {
+            results.documents.iter().map(|d| d.id).collect()
+        };
+        let first_stage = ids(engine.search("rust", 10));
+
+        // The best two hits are reordered by price, the last stays last
+        let by_price = |features: &RerankFeatures| features.metadata[0].unwrap_or(0.0);
+        engine.set_reranker(Some(Rerank::new(by_price).window(2).field("price")));
+        let mut expected = first_stage[..2].to_vec();
+        expected.sort_by_key(|&id| std::cmp::Reverse([10, 30, 20][id as usize - 1]));
+        expected.push(first_stage[2]);
+        assert_eq!(ids(engine.search("rust", 10)), expected);
+
+        // A linear model on title matches; ties fall back to the doc id
+        let linear = LinearReranker::new(vec![0.0, 0.0, 1.0]);
+        engine.set_reranker(Some(Rerank::new(linear)));
+        let results = engine.search("rust", 10);
+        assert_eq!(results.scores, [1.0, 0.0, 0.0]);
+        assert_eq!(ids(results), [1, 2, 3]);
+
+        // Other sort orders are left alone
+        let options =
+            SearchOptions::new(10).sort_by(SortSpec::field("price", Direction::Ascending));
+        assert_eq!(ids(engine.search_with_options("rust", &options)), [1, 3, 2]);
+        engine.set_reranker(None);
+        assert_eq!(ids(engine.search("rust", 10)), first_stage);
+
+        // Cold hits are merged first, then reranked with their own features
+        let dir = temp_dir("engine-rerank");
+        let mut engine = SearchEngine::create(&dir, IndexSettings::default()).unwrap();
+        let old = [("at", "2001-01-01")];
+        engine
+            .add_document(doc_with(1, "rust rust rust", &old))
+            .unwrap();
+        engine.add_document(doc(2, "", "rust")).unwrap();
+        engine.add_document(doc_with(3, "rust news", &old)).unwrap();
+        engine
+            .add_document(doc(4, "", "rust compiler tooling"))
+            .unwrap();
+        let policy = TieringPolicy::Age {
+            field: "at".to_string(),
+            max_age: Duration::from_secs(86_400),
+        };
+        assert_eq!(engine.tier(&policy).unwrap(), 2);
+        let by_length = |features: &RerankFeatures| features.doc_length as f64;
+        engine.set_reranker(Some(Rerank::new(by_length)));
+        let results = engine.search("rust", 10);
+        assert_eq!(results.scores, [3.0, 3.0, 2.0, 1.0]);
+        assert_eq!(ids(results), [1, 4, 3, 2]);
+        let page = engine.search_with_options("rust", &SearchOptions::new(2).offset(1));
+        assert_eq!(ids(page), [4, 3]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_constant_score() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        let docs = [
+            (3, "rust rust rust", "x", "10"),
+            (1, "rust", "x", "30"),
+            (2, "rust is fast", "y", "20"),
+            (4, "fast rust", "x", "40"),
+        ];
+        for (id, content, tag, price) in docs {
+            engine
+                .add_document(doc_with(id, content, &[("tag", tag), ("price", price)]))
+                .unwrap();
+        }
+
+        let hits = |query: &str, options: SearchOptions| -> Vec<(u64, f64)> {
+            let results = engine.search_with_options(query, &options.constant_score());
+            let ids = results.documents.iter().map(|d| d.id);
+            ids.zip(results.scores).collect()
+        };
+        let tagged = SearchOptions::new(10).filter(Filter::eq("tag", "x"));
+        assert_eq!(hits("", tagged.clone()), [(1, 1.0), (3, 1.0), (4, 1.0)]);
+        let by_price = tagged.sort_by(SortSpec::field("price", Direction::Descending));
+        let ids: Vec<u64> = hits("", by_price).iter().map(|hit| hit.0).collect();
+        assert_eq!(ids, [4, 1, 3]);
+        // Terms and phrases still decide what matches
+        assert_eq!(hits("rust", SearchOptions::new(2)), [(1, 1.0), (2, 1.0)]);
+        assert_eq!(hits("\"fast rust\"", SearchOptions::new(10)), [(4, 1.0)]);
+    }
+
+    #[test]
+    fn test_approximate_top_k() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        for id in 1..=200 {
+            let content = format!("fox {}", "filler ".repeat(id as usize % 10));
+            engine.add_document(doc(id, "", &content)).unwrap();
+        }
+        for id in 201..=203 {
+            engine.add_document(doc(id, "", "fox turtle")).unwrap();
+        }
+
+        // The rare term bounds the other matches, so the top hits are exact
+        let options = SearchOptions::new(3).approximate(0.05);
+        let results = engine.search_with_options("fox turtle", &options);
+        let exact = engine.search("fox turtle", 3);
+        assert_eq!(results.documents, exact.documents);
+        assert_eq!(results.scores, exact.scores);
+        assert_eq!(results.total_matches, 203);
+        assert!(!results.is_approximate);
+
+        // Equal bounds everywhere force an estimate
+        let results = engine.search_with_options("fox", &options);
+        assert_eq!(results.documents.len(), 3);
+        assert_eq!(results.total_matches, 203);
+        assert!(results.is_approximate);
+        assert!(!engine.search("fox", 3).is_approximate);
+    }
+
+    #[test]
+    fn test_occurrences() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine
+            .add_document(doc(1, "Fox", "A fox, another fox and a turtle"))
+            .unwrap();
+        let result = engine.occurrences(1, "fox turtle", 10).unwrap();
+        let counts: Vec<(&str, usize)> = result
+            .counts
+            .iter()
+            .map(|c| (c.text.as_str(), c.count))
+            .collect();
+        assert_eq!(counts, vec![("fox", 2), ("turtle", 1)]);
+        assert_eq!(result.occurrences.len(), 3);
+        assert!(!result.is_truncated());
+        assert!(engine.occurrences(2, "fox", 10).is_none());
+    }
+
+    #[test]
+    fn test_more_like_this() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine
+            .add_document(doc(1, "Rust", "Rust ownership and borrowing explained"))
+            .unwrap();
+        engine
+            .add_document(doc(2, "Borrowing", "The borrowing rules of Rust"))
+            .unwrap();
+        engine
+            .add_document(doc(3, "Gardening", "Ownership of a garden plot"))
+            .unwrap();
+        engine
+            .add_document(doc(4, "Cooking", "Pasta recipes"))
+            .unwrap();
+
+        let results = engine.more_like_this(1, 10);
+        let ids: Vec<u64> = results.documents.iter().map(|d| d.id).collect();
+        assert_eq!(ids, vec![2, 3]);
+        assert!(engine.more_like_this(4, 10).documents.is_empty());
+        assert!(engine.more_like_this(99, 10).documents.is_empty());
+    }
+
+    #[test]
+    fn test_cluster() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine
+            .add_document(doc(1, "Rust", "Rust ownership and borrowing"))
+            .unwrap();
+        engine
+            .add_document(doc(2, "Pasta", "Pasta with tomato sauce"))
+            .unwrap();
+        engine
+            .add_document(doc(3, "Borrowing", "Borrowing rules in Rust"))
+            .unwrap();
+        engine
+            .add_document(doc(4, "Sauce", "A quick tomato sauce for pasta"))
+            .unwrap();
+
+        let matrix = engine.similarity_matrix(&[1, 2, 3, 99]);
+        assert_eq!(matrix.doc_ids(), &[1, 2, 3]);
+        assert!(matrix.similarity(1, 3).unwrap() > matrix.similarity(1, 2).unwrap());
+
+        let expected = vec![vec![1, 3], vec![2, 4]];
+        let kmeans = engine.cluster(
+            &[1, 2, 3, 4],
+            ClusterMethod::KMeans {
+                k: 2,
+                iterations: 10,
+            },
+        );
+        assert_eq!(kmeans.clusters, expected);
+        let agglomerative = engine.cluster(
+            &[1, 2, 3, 4, 1],
+            ClusterMethod::Agglomerative {
+                min_similarity: 0.2,
+            },
+        );
+        assert_eq!(agglomerative.clusters, expected);
+        assert_eq!(agglomerative.label(4), Some(1));
+    }
+
+    #[test]
+    fn test_search_page() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        for id in 1..=5 {
+            let content = format!("fox number {}", "again ".repeat(id as usize));
+            engine.add_document(doc(id, "Fox", &content)).unwrap();
+        }
+
+        let page = engine.search_page("fox", 1, 2);
+        assert_eq!(page.total_matches, 5);
+        assert_eq!(page.total_pages(), 3);
+        assert!(page.has_next() && page.has_previous());
+        assert_eq!(page.hits.len(), 2);
+        assert_eq!(page.hits[0].rank, 3);
+        assert_eq!(page.hits[0].doc_id, 3);
+        assert!(page.hits[0].snippet[0].highlighted);
+        assert_eq!(page.hits[0].snippet[0].text, "fox");
+
+        let last = engine.search_page("fox", 2, 2);
+        assert_eq!(last.hits.len(), 1);
+        assert!(!last.has_next());
+    }
+
+    #[test]
+    fn test_search_page_overflow() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine.add_document(doc(1, "Fox", "a fox")).unwrap();
+        for page_size in [1, 10] {
+            let page = engine.search_page("fox", usize::MAX, page_size);
+            assert!(page.hits.is_empty());
+            assert_eq!(page.page, usize::MAX);
+            assert_eq!(page.total_matches, 1);
+            assert!(page.has_previous() && !page.has_next());
+        }
+        let far = SearchOptions::new(usize::MAX).offset(usize::MAX);
+        assert!(engine.search_with_options("fox", &far).documents.is_empty());
+    }
+
+    #[test]
+    fn test_ephemeral_fifo() {
+        let capacity = Capacity {
+            max_documents: Some(2),
+            ..Capacity::default()
+        };
+        let mut engine =
+            SearchEngine::ephemeral(IndexSettings::default(), capacity, EvictionPolicy::Fifo)
+                .unwrap();
+        engine.add_document(doc(1, "", "error disk full")).unwrap();
+        engine
+            .add_document(doc(2, "", "error network down"))
+            .unwrap();
+        engine
+            .add_document(doc(3, "", "warning disk slow"))
+            .unwrap();
+
+        assert_eq!(engine.document_count(), 2);
+        assert!(engine.get_document(1).is_none());
+        assert!(engine.memory_usage().unwrap() > 0);
+        assert_eq!(engine.search("error", 10).total_matches, 1);
+    }
+
+    #[test]
+    fn test_ephemeral_lru() {
+        let capacity = Capacity {
+            max_documents: Some(2),
+            ..Capacity::default()
+        };
+        let mut engine =
+            SearchEngine::ephemeral(IndexSettings::default(), capacity, EvictionPolicy::Lru)
+                .unwrap();
+        engine.add_document(doc(1, "", "error disk full")).unwrap();
+        engine
+            .add_document(doc(2, "", "error network down"))
+            .unwrap();
+        engine.search("disk", 10);
+        engine.add_document(doc(3, "", "warning cpu hot")).unwrap();
+
+        // Document 1 was read more recently than 2, so 2 is evicted
+        assert!(engine.get_document(1).is_some());
+        assert!(engine.get_document(2).is_none());
+    }
+
+    #[test]
+    fn test_boosts() {
+        let settings = IndexSettings {
+            title_boost: 3.0,
+            ..IndexSettings::default()
+        };
+        let mut engine = SearchEngine::new(settings).unwrap();
+        engine
+            .add_document(doc(1, "Turtles", "a fox and a turtle"))
+            .unwrap();
+        engine
+            .add_document(doc(2, "Foxes", "a turtle and a fox"))
+            .unwrap();
+        engine
+            .add_document_with_boost(doc(3, "Birds", "a fox and a bird"), 5.0)
+            .unwrap();
+        assert!(engine.add_document_with_boost(doc(4, "", ""), 0.0).is_err());
+        assert!(engine.add_document(doc(u64::MAX, "", "")).is_err());
+
+        let ids: Vec<u64> = engine
+            .search("fox", 10)
+            .documents
+            .iter()
+            .map(|d| d.id)
+            .collect();
+        assert_eq!(ids, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_refresh_policy() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine.add_document(doc(1, "", "visible fox")).unwrap();
+        engine.set_refresh_policy(RefreshPolicy::Manual).unwrap();
+
+        engine.add_document(doc(2, "", "buffered fox")).unwrap();
+        assert!(matches!(
+            engine.add_document(doc(2, "", "again")),
+            Err(MSErrors::DuplicateDocument { doc_id: 2 })
+        ));
+        assert_eq!(engine.remove_document(1).unwrap().content, "visible fox");
+        assert!(engine.remove_document(1).is_none());
+        assert_eq!(engine.next_document_id(), 3);
+        assert_eq!(engine.pending_writes(), 2);
+        // Nothing changes until the commit
+        let ids = |engine: &SearchEngine| -> Vec<u64> {
+            engine
+                .search("fox", 10)
+                .documents
+                .iter()
+                .map(|d| d.id)
+                .collect()
+        };
+        assert_eq!(ids(&engine), [1]);
+        assert_eq!(engine.commit().unwrap(), 2);
+        assert_eq!(ids(&engine), [2]);
+        assert_eq!(engine.pending_writes(), 0);
+
+        // Removing then re-adding an id is allowed before the commit
+        engine.remove_document(2);
+        engine.add_document(doc(2, "", "replaced fox")).unwrap();
+        engine.commit().unwrap();
+        assert_eq!(engine.get_document(2).unwrap().content, "replaced fox");
+
+        let auto = RefreshPolicy::Auto {
+            max_docs: Some(2),
+            interval: None,
+        };
+        engine.set_refresh_policy(auto).unwrap();
+        engine.add_document(doc(3, "", "fox")).unwrap();
+        assert_eq!(engine.document_count(), 1);
+        engine.add_document(doc(4, "", "fox")).unwrap();
+        assert_eq!(engine.document_count(), 3);
+
+        engine.set_refresh_policy(RefreshPolicy::Manual).unwrap();
+        engine.add_document(doc(5, "", "fox")).unwrap();
+        engine.set_refresh_policy(RefreshPolicy::Immediate).unwrap();
+        assert_eq!(engine.document_count(), 4);
+
+        // A quiet index still refreshes once its interval has passed
+        let auto = RefreshPolicy::Auto {
+            max_docs: None,
+            interval: Some(Duration::from_millis(100)),
+        };
+        engine.set_refresh_policy(auto).unwrap();
+        engine.add_document(doc(6, "", "fox")).unwrap();
+        engine.commit_if_due().unwrap();
+        assert_eq!(engine.pending_writes(), 1);
+        std::thread::sleep(Duration::from_millis(150));
+        engine.commit_if_due().unwrap();
+        assert_eq!(engine.pending_writes(), 0);
+        assert_eq!(engine.document_count(), 5);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_scoring() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        for id in 0..PARALLEL_MIN_CANDIDATES as u64 * 2 {
+            let content = format!("common word {}", "rare ".repeat(id as usize % 7));
+            engine.add_document(doc(id, "", &content)).unwrap();
+        }
+        let doc_ids: RoaringTreemap = (0..PARALLEL_MIN_CANDIDATES as u64 * 2).collect();
+        let terms = ["common".to_string(), "rare".to_string()];
+        let sequential: Vec<(u64, f64)> = doc_ids
+            .iter()
+            .map(|doc_id| (doc_id, engine.ranker().compute_score(DocId(doc_id), &terms)))
+            .collect();
+        assert_eq!(engine.score_documents(&doc_ids, &terms), sequential);
+    }
+
+    #[test]
+    fn test_index_record_options() {
+        use crate::settings::{FieldDef, IndexRecordOption, Schema};
+
+        let engine = |record| {
+            let schema = Schema::new()
+                .field(FieldDef::new("tags", FieldType::Text).record(IndexRecordOption::DocsOnly));
+            let mut engine = SearchEngine::new(IndexSettings {
+                record,
+                schema,
+                ..IndexSettings::default()
+            })
+            .unwrap();
+            for (id, content) in [(1, "quick brown fox"), (2, "fox is quick, fox is fast")] {
+                engine
+                    .add_document(doc_with(id, content, &[("tags", "wild wild animal")]))
+                    .unwrap();
+            }
+            engine
+        };
+        let full = engine(IndexRecordOption::DocsAndFreqsAndPositions);
+        let freqs = engine(IndexRecordOption::DocsAndFreqs);
+        let posting = freqs.index.posting("fox", DocId(2)).unwrap();
+        assert_eq!((posting.term_freq, posting.positions.len()), (2, 0));
+        let posting = full.index.posting("tags:wild", DocId(1)).unwrap();
+        assert_eq!((posting.term_freq, posting.positions.len()), (1, 0));
+
+        // Frequencies alone score like full postings
+        let scores = |engine: &SearchEngine| -> Vec<(u64, f64)> {
+            let results = engine.search("fox", 10);
+            results
+                .documents
+                .iter()
+                .map(|d| d.id)
+                .zip(results.scores)
+                .collect()
+        };
+        assert_eq!(scores(&freqs), scores(&full));
+        let mut dump = Vec::new();
+        freqs.dump_index(&mut dump).unwrap();
+        assert!(
+            String::from_utf8(dump)
+                .unwrap()
+                .contains("\nfox\t1*1 2*2\n")
+        );
+        assert_eq!(
+            engine(IndexRecordOption::DocsOnly)
+                .index
+                .posting("fox", DocId(2))
+                .unwrap()
+                .term_freq,
+            1
+        );
+
+        // Without positions a phrase only needs its words
+        assert_eq!(full.search("\"quick fox\"", 10).total_matches, 1);
+        assert_eq!(freqs.search("\"quick fox\"", 10).total_matches, 2);
+    }
+
+    #[test]
+    fn test_explain() {
+        let settings = IndexSettings {
+            title_boost: 3.0,
+            ..IndexSettings::default()
+        };
+        let mut engine = SearchEngine::new(settings).unwrap();
+        engine
+            .add_document(doc(1, "Foxes", "a quick fox jumps"))
+            .unwrap();
+        engine
+            .add_document_with_boost(doc(2, "Birds", "a bird and a fox"), 2.0)
+            .unwrap();
+        engine.add_document(doc(3, "Turtles", "slow")).unwrap();
+
+        let results = engine.search("quick fox", 10);
+        let explanation = engine
+            .explain("quick fox", 2, &SearchOptions::default())
+            .unwrap();
+        assert_eq!(explanation.rank, Some(2));
+        assert_eq!(explanation.total_matches, 2);
+        assert_eq!(explanation.score, results.scores[1]);
+        assert_eq!(explanation.doc_boost, 2.0);
+        assert_eq!(explanation.phrase_boost, 1.0);
+        assert_eq!(explanation.terms.len(), 1);
+        let term_score: f64 = explanation.terms.iter().map(|t| t.score).sum();
+        assert!((term_score * explanation.doc_boost - explanation.score).abs() < 1e-9);
+
+        // Title occurrences are weighted by the title boost
+        let explanation = engine.explain("fox", 1, &SearchOptions::default()).unwrap();
+        assert_eq!(explanation.terms[0].occurrences, 2);
+        assert_eq!(explanation.terms[0].term_freq, 4.0);
+
+        let explanation = engine
+            .explain("\"quick fox\"", 1, &SearchOptions::default())
+            .unwrap();
+        assert!(explanation.phrase_boost > 1.0);
+        let explanation = engine.explain("fox", 3, &SearchOptions::default()).unwrap();
+        assert_eq!((explanation.rank, explanation.score), (None, 0.0));
+        assert!(
+            engine
+                .explain("fox", 9, &SearchOptions::default())
+                .is_none()
+        );
+
+        // The search's options apply, as they do to the search itself
+        let options = SearchOptions::new(10).minimum_should_match(MinimumShouldMatch::Count(2));
+        let explanation = engine.explain("quick fox", 2, &options).unwrap();
+        assert_eq!((explanation.rank, explanation.total_matches), (None, 1));
+        let options = SearchOptions::new(10).decay(Decay::linear("age", 0.0, 10.0));
+        engine
+            .add_document(doc_with(4, "an old fox", &[("age", "5")]))
+            .unwrap();
+        let results = engine.search_with_options("fox", &options);
+        let explanation = engine.explain("fox", 4, &options).unwrap();
+        assert!((explanation.decay - 0.75).abs() < 1e-9);
+        assert_eq!(explanation.phrase_boost, 1.0);
+        let position = results.documents.iter().position(|d| d.id == 4).unwrap();
+        assert_eq!(explanation.rank, Some(position + 1));
+        assert_eq!(explanation.score, results.scores[position]);
+    }
+
+    #[test]
+    fn test_warm_up() {
+        let dir = temp_dir("engine-warm-up");
+        let mut engine = SearchEngine::create(&dir, IndexSettings::default()).unwrap();
+        engine
+            .add_document(doc_with(1, "disk error", &[("at", "2001-01-01")]))
+            .unwrap();
+        engine
+            .add_document(doc_with(2, "network error", &[("at", "2999-01-01")]))
+            .unwrap();
+        let policy = TieringPolicy::Age {
+            field: "at".to_string(),
+            max_age: Duration::from_secs(86_400),
+        };
+        assert_eq!(engine.tier(&policy).unwrap(), 1);
+        engine.enable_query_log();
+        engine.search("disk", 10);
+        engine.search("network error", 10);
+        engine.search("disk", 10);
+
+        let reopened = SearchEngine::open(&dir).unwrap();
+        assert_eq!(reopened.loaded_segments(), 0);
+        let warm = reopened.warm_up(&["error"], &[Filter::eq("at", "2999-01-01")]);
+        assert_eq!((warm.segments_loaded, warm.queries_run), (1, 1));
+        // The filter is cached by the hot engine and the loaded segment
+        assert_eq!(warm.filters_cached, 2);
+        assert_eq!(reopened.cached_filters(), 1);
+        assert_eq!(reopened.loaded_segments(), 1);
+
+        // Without queries the query log is replayed, but not added to
+        let warm = engine.warm_up::<&str>(&[], &[]);
+        assert_eq!(warm.queries_run, 2);
+        let logged = engine.suggest("disk", 5);
+        assert!(logged.iter().any(|c| c.text == "disk" && c.frequency == 2));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let dir = temp_dir("engine-replay");
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("queries.log");
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine.add_document(doc(1, "", "disk error")).unwrap();
+        engine.add_document(doc(2, "", "network error")).unwrap();
+        engine.record_queries(&log).unwrap();
+        engine.search("error", 10);
+        engine.search("disk", 5);
+
+        let records = read_query_log(&log).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            (records[0].query.as_str(), records[0].total_matches),
+            ("error", 2)
+        );
+        assert_eq!(records[1].limit, 5);
+
+        engine.add_document(doc(3, "", "disk full")).unwrap();
+        let report = replay(&engine, &records);
+        assert_eq!(report.queries, 2);
+        assert_eq!(report.changed_matches, [("disk".to_string(), 1, 2)]);
+        // Replaying records too, since the engine is still recording
+        assert_eq!(read_query_log(&log).unwrap().len(), 4);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_deterministic_build() {
+        use crate::settings::Schema;
+
+        // N-grams share positions, and unstored content is saved as tokens
+        let settings = IndexSettings {
+            analyzer: AnalyzerPreset::EdgeNGram,
+            schema: Schema::new().store_content(false),
+            ..IndexSettings::default()
+        };
+        let docs = [
+            doc(1, "Search", "inverted index construction"),
+            doc(2, "Ranking", "scoring with bm25 and field boosts"),
+            doc(3, "", "deterministic snapshots are diffable"),
+        ];
+        let build = |name: &str, docs: Vec<Document>| {
+            let dir = temp_dir(name);
+            let mut engine = SearchEngine::create(&dir, settings.clone()).unwrap();
+            for document in docs {
+                engine.add_document(document).unwrap();
+            }
+            engine.flush().unwrap();
+            let mut dump = Vec::new();
+            engine.dump_index(&mut dump).unwrap();
+            let documents = std::fs::read(dir.join(crate::storage::DOCUMENTS_FILE)).unwrap();
+            std::fs::remove_dir_all(dir).unwrap();
+            (dump, documents)
+        };
+        let forward = build("engine-build-forward", docs.to_vec());
+        let backward = build("engine-build-backward", docs.into_iter().rev().collect());
+        assert_eq!(forward, backward);
+    }
+
+    #[test]
+    fn test_tiering() {
+        let dir = temp_dir("engine-tiering");
+        let mut engine = SearchEngine::create(&dir, IndexSettings::default()).unwrap();
+        engine
+            .add_document(doc_with(1, "disk error", &[("at", "2001-01-01")]))
+            .unwrap();
+        engine
+            .add_document(doc_with(2, "disk error again", &[("at", "2002-01-01")]))
+            .unwrap();
+        engine
+            .add_document(doc_with(3, "network error", &[("at", "2999-01-01")]))
+            .unwrap();
+        engine.add_document(doc(4, "", "undated error")).unwrap();
+
+        let policy = TieringPolicy::Age {
+            field: "at".to_string(),
+            max_age: Duration::from_secs(86_400),
+        };
+        assert_eq!(engine.tier(&policy).unwrap(), 2);
+        assert_eq!(engine.document_count(), 2);
+        assert_eq!(engine.cold_segments()[0].doc_count, 2);
+        assert_eq!(engine.next_document_id(), 5);
+        assert_eq!(engine.loaded_segments(), 0);
+
+        // Cold documents are loaded lazily and merged into the results
+        let results = engine.search("disk", 10);
+        assert_eq!(results.total_matches, 2);
+        assert_eq!(engine.loaded_segments(), 1);
+        let results = engine.search_with_options("error", &SearchOptions::new(2).offset(1));
+        assert_eq!(results.total_matches, 4);
+        assert_eq!(results.documents.len(), 2);
+
+        // Access policy: documents not returned since the last pass go cold
+        let access = TieringPolicy::Access { min_hits: 1 };
+        let mut engine = SearchEngine::open(&dir).unwrap();
+        assert_eq!(engine.tier(&access).unwrap(), 0);
+        engine.search("network", 10);
+        assert_eq!(engine.tier(&access).unwrap(), 1);
+        assert_eq!(engine.cold_segments().len(), 2);
+        assert!(engine.get_document(3).is_some());
+        assert_eq!(engine.search("undated", 10).documents[0].id, 4);
+
+        // Compaction merges both segments without changing results
+        assert_eq!(engine.compact().unwrap(), 1);
+        assert_eq!(engine.compact().unwrap(), 0);
+        let engine = SearchEngine::open(&dir).unwrap();
+        assert_eq!(engine.cold_segments().len(), 1);
+        assert_eq!(engine.cold_segments()[0].doc_count, 3);
+        assert_eq!(engine.search("error", 10).total_matches, 4);
+        assert_eq!(engine.next_document_id(), 5);
+        assert!(
+            SearchEngine::new(IndexSettings::default())
+                .unwrap()
+                .tier(&policy)
+                .is_err()
+        );
+
+        // A damaged segment fails a verified open, or is skipped once searched
+        let path = dir.join(engine.cold_segments()[0].file_name());
+        let mut bytes = std::fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+        assert!(SearchEngine::open_verified(&dir).is_err());
+        let engine = SearchEngine::open(&dir).unwrap();
+        assert_eq!(engine.search("error", 10).total_matches, 1);
+        assert_eq!(engine.loaded_segments(), 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_routing() {
+        let dir = temp_dir("engine-routing");
+        let settings = IndexSettings {
+            routing_field: Some("tenant".to_string()),
+            ..IndexSettings::default()
+        };
+        let mut engine = SearchEngine::create(&dir, settings).unwrap();
+        for (id, tenant) in [(1, "acme"), (2, "globex"), (3, "acme")] {
+            let metadata = [("tenant", tenant), ("at", "2001-01-01")];
+            engine
+                .add_document(doc_with(id, "quarterly report", &metadata))
+                .unwrap();
+        }
+        engine
+            .add_document(doc_with(4, "quarterly report", &[("at", "2001-01-01")]))
+            .unwrap();
+        let policy = TieringPolicy::Age {
+            field: "at".to_string(),
+            max_age: Duration::from_secs(1),
+        };
+        assert_eq!(engine.tier(&policy).unwrap(), 4);
+        let routes: Vec<&[String]> = engine
+            .cold_segments()
+            .iter()
+            .map(|s| s.routing_values.as_slice())
+            .collect();
+        assert_eq!(routes.len(), 3);
+
+        // A query routed to acme loads the acme segment and the one without
+        // routing values, skipping globex
+        let engine = SearchEngine::open(&dir).unwrap();
+        let options = SearchOptions::new(10).filter(Filter::eq("tenant", "acme"));
+        let results = engine.search_with_options("report", &options);
+        assert_eq!(results.total_matches, 2);
+        assert_eq!(engine.loaded_segments(), 2);
+        let options =
+            SearchOptions::new(10).filter(Filter::one_of("tenant", ["globex", "initech"]));
+        assert_eq!(
+            engine.search_with_options("report", &options).total_matches,
+            1
+        );
+        assert_eq!(engine.loaded_segments(), 3);
+
+        // Unrouted queries search every segment
+        assert_eq!(engine.search("report", 10).total_matches, 4);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Segments tiered before the routing field was set are still searched
+        let mut engine = SearchEngine::create(&dir, IndexSettings::default()).unwrap();
+        let metadata = [("tenant", "acme"), ("at", "2001-01-01")];
+        engine
+            .add_document(doc_with(1, "quarterly report", &metadata))
+            .unwrap();
+        assert_eq!(engine.tier(&policy).unwrap(), 1);
+        let routed = IndexSettings {
+            routing_field: Some("tenant".to_string()),
+            ..IndexSettings::default()
+        };
+        Storage::open(&dir).unwrap().save_settings(&routed).unwrap();
+        let engine = SearchEngine::open(&dir).unwrap();
+        let options = SearchOptions::new(10).filter(Filter::eq("tenant", "acme"));
+        let results = engine.search_with_options("report", &options);
+        assert_eq!(results.total_matches, 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_schema() {
+        use crate::settings::{FieldDef, Schema};
+
+        let schema = Schema::new()
+            .field(FieldDef::new("author", FieldType::Text).analyzer(AnalyzerPreset::Simple))
+            .field(FieldDef::new("notes", FieldType::Text).stored(false))
+            .field(FieldDef::new("zip", FieldType::Keyword))
+            .field(FieldDef::new("price", FieldType::Numeric))
+            .strict(true);
+        let settings = IndexSettings {
+            schema,
+            ..IndexSettings::default()
+        };
+        let dir = temp_dir("engine-schema");
+        let mut engine = SearchEngine::create(&dir, settings).unwrap();
+        let metadata = [
+            ("author", "Ada Lovelace"),
+            ("notes", "internal draft"),
+            ("zip", "01234"),
+            ("price", "12"),
+        ];
+        engine
+            .add_document(doc_with(1, "Analytical engine notes", &metadata))
+            .unwrap();
+        engine
+            .add_document(doc_with(2, "Lovelace biography", &[("zip", "9")]))
+            .unwrap();
+        assert!(
+            engine
+                .add_document(doc_with(3, "bad", &[("price", "cheap")]))
+                .is_err()
+        );
+        assert!(
+            engine
+                .add_document(doc_with(3, "bad", &[("colour", "red")]))
+                .is_err()
+        );
+
+        // Field clauses only match their field
+        let ids = |engine: &SearchEngine, query: &str| -> Vec<u64> {
+            let mut ids: Vec<u64> = engine
+                .search(query, 10)
+                .documents
+                .iter()
+                .map(|d| d.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids(&engine, "lovelace"), vec![2]);
+        assert_eq!(ids(&engine, "author:lovelace"), vec![1]);
+        assert_eq!(ids(&engine, r#"author:"ada lovelace""#), vec![1]);
+        assert_eq!(ids(&engine, "notes:draft"), vec![1]);
+
+        // Unstored values are searchable but not returned, and survive a reopen
+        let document = engine.get_document(1).unwrap();
+        assert!(!document.metadata.contains_key("notes"));
+        assert_eq!(document.metadata["zip"], "01234");
+        assert!(engine.doc_values.get_number("zip", DocId(1)).is_none());
+        engine.flush().unwrap();
+        let reopened = SearchEngine::open(&dir).unwrap();
+        assert_eq!(reopened.settings().schema, engine.settings().schema);
+        assert_eq!(ids(&reopened, "notes:draft"), vec![1]);
+        assert!(
+            !reopened
+                .get_document(1)
+                .unwrap()
+                .metadata
+                .contains_key("notes")
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_unstored_content() {
+        use crate::settings::Schema;
+
+        let settings = IndexSettings {
+            schema: Schema::new().store_content(false),
+            title_boost: 2.0,
+            ..IndexSettings::default()
+        };
+        let dir = temp_dir("engine-unstored-content");
+        let mut engine = SearchEngine::create(&dir, settings).unwrap();
+        engine
+            .add_document(doc(1, "Foxes", "brown foxes running quickly"))
+            .unwrap();
+        engine
+            .add_document(doc(2, "Dogs", "lazy dogs running"))
+            .unwrap();
+        let results = engine.search("foxes running", 10);
+        assert_eq!(results.documents[0].id, 1);
+        assert_eq!(results.documents[0].title, "Foxes");
+        assert!(results.documents[0].content.is_empty());
+        let score = results.scores[0];
+        engine.flush().unwrap();
+
+        // Only the analyzed tokens are written
+        let saved = std::fs::read(dir.join(crate::storage::DOCUMENTS_FILE)).unwrap();
+        assert!(!saved.windows(7).any(|w| w == b"running"));
+
+        let reopened = SearchEngine::open(&dir).unwrap();
+        let results = reopened.search("foxes running", 10);
+        assert_eq!(results.documents[0].id, 1);
+        assert!((results.scores[0] - score).abs() < 1e-9);
+        assert_eq!(reopened.search(r#""brown foxes""#, 10).total_matches, 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_chunked_documents() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine.add_document(doc(0, "Short", "a fox story")).unwrap();
+        let long = doc(
+            100,
+            "Long",
+            "Foxes live in dens. They hunt at night. A fox eats mice. \
+             Rabbits fear them. Dens have many exits.",
+        );
+        let ids = engine.add_chunked(long, &Chunker::sentences(2, 1)).unwrap();
+        assert_eq!(ids, vec![101, 102, 103, 104]);
+        assert_eq!(
+            engine.get_document(103).unwrap().content,
+            "A fox eats mice. Rabbits fear them."
+        );
+
+        let hits = engine.search_parents("fox", &SearchOptions::new(10));
+        let parents: Vec<(u64, usize)> = hits
+            .iter()
+            .map(|hit| (hit.parent_id, hit.matching_chunks))
+            .collect();
+        assert_eq!(parents.len(), 2);
+        assert!(parents.contains(&(100, 3)));
+        assert!(parents.contains(&(0, 1)));
+        let hits = engine.search_parents("exits", &SearchOptions::new(10));
+        assert_eq!(hits[0].parent_id, 100);
+        assert_eq!(hits[0].best_chunk.id, 104);
+        assert!(
+            engine
+                .add_chunked(doc(200, "", "text"), &Chunker::tokens(1, 1))
+                .is_err()
+        );
+
+        // Chunk ids never reuse a document's id or the parent's
+        let chunker = Chunker::sentences(1, 0);
+        assert!(matches!(
+            engine.add_chunked(doc(0, "", "Taken. Id."), &chunker),
+            Err(MSErrors::DuplicateDocument { doc_id: 0 })
+        ));
+        let ids = engine.add_chunked(doc(300, "", "Far. Ahead."), &chunker);
+        assert_eq!(ids.unwrap(), vec![301, 302]);
+        assert_eq!(engine.next_document_id(), 303);
+        let last = doc(DocId::MAX.get() - 1, "", "No. Room.");
+        assert!(engine.add_chunked(last, &chunker).is_err());
+        assert_eq!(engine.next_document_id(), 303);
+    }
+
+    #[test]
+    fn test_keyword_fields() {
+        use crate::settings::{FieldDef, Schema};
+
+        let schema = Schema::new()
+            .field(FieldDef::new("email", FieldType::Keyword))
+            .field(FieldDef::new("tag", FieldType::Keyword));
+        let mut engine = SearchEngine::new(IndexSettings {
+            schema,
+            ..IndexSettings::default()
+        })
+        .unwrap();
+        let metadata = [("email", "Ada.Lovelace@example.com"), ("tag", "C++")];
+        engine
+            .add_document(doc_with(1, "Notes on the engine", &metadata))
+            .unwrap();
+        engine
+            .add_document(doc_with(2, "ada", &[("tag", "C")]))
+            .unwrap();
+
+        let ids = |query: &str| -> Vec<u64> {
+            engine
+                .search(query, 10)
+                .documents
+                .iter()
+                .map(|d| d.id)
+                .collect()
+        };
+        assert_eq!(ids("email:Ada.Lovelace@example.com"), vec![1]);
+        assert_eq!(ids("tag:C++"), vec![1]);
+        assert_eq!(ids("tag:C"), vec![2]);
+        // Only the exact value matches, and keyword values are not free text
+        assert!(ids("email:ada.lovelace@example.com").is_empty());
+        assert!(ids("email:lovelace").is_empty());
+        assert_eq!(ids("ada"), vec![2]);
+    }
+
+    #[test]
+    fn test_field_presets() {
+        use crate::settings::{FieldDef, Schema};
+
+        let schema = Schema::new()
+            .field(FieldDef::new("tags", FieldType::Text).analyzer(AnalyzerPreset::Keyword))
+            .field(
+                FieldDef::new("title_autocomplete", FieldType::Text)
+                    .analyzer(AnalyzerPreset::EdgeNGram),
+            );
+        let settings: IndexSettings = IndexSettings {
+            schema,
+            ..IndexSettings::default()
+        }
+        .to_string()
+        .parse()
+        .unwrap();
+        let mut engine = SearchEngine::new(settings).unwrap();
+        let metadata = [
+            ("tags", "Rust"),
+            ("title_autocomplete", "Searching engines"),
+        ];
+        engine
+            .add_document(doc_with(1, "Engines", &metadata))
+            .unwrap();
+        engine
+            .add_document(doc_with(2, "Rust notes", &[("tags", "rust lang")]))
+            .unwrap();
+
+        let ids = |query: &str| -> Vec<u64> {
+            let results = engine.search(query, 10);
+            results.documents.iter().map(|d| d.id).collect()
+        };
+        assert_eq!(ids("title_autocomplete:sea"), [1]);
+        assert_eq!(ids("title_autocomplete:engi"), [1]);
+        assert_eq!(ids("tags:Rust"), [1]);
+        assert!(ids("tags:rust").is_empty());
+    }
+
+    #[test]
+    fn test_search_analyzer() {
+        use crate::settings::{FieldDef, Schema};
+
+        let field = FieldDef::new("suggest", FieldType::Text).analyzer(AnalyzerPreset::EdgeNGram);
+        let engine = |field: FieldDef| {
+            let mut engine = SearchEngine::new(IndexSettings {
+                schema: Schema::new().field(field),
+                ..IndexSettings::default()
+            })
+            .unwrap();
+            engine
+                .add_document(doc_with(1, "One", &[("suggest", "searching")]))
+                .unwrap();
+            engine
+                .add_document(doc_with(2, "Two", &[("suggest", "second")]))
+                .unwrap();
+            engine
+        };
+        let ids = |engine: &SearchEngine, query: &str| -> Vec<u64> {
+            let mut ids: Vec<u64> = engine
+                .search(query, 10)
+                .documents
+                .iter()
+                .map(|d| d.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        // Query n-grams "se" and "sea" also match "second"
+        let both = engine(field.clone());
+        assert_eq!(ids(&both, "suggest:sea"), [1, 2]);
+        let typed = engine(field.clone().search_analyzer(AnalyzerPreset::Standard));
+        assert_eq!(ids(&typed, "suggest:sea"), [1]);
+        assert_eq!(ids(&typed, "suggest:searching"), [1]);
+
+        let mut custom = engine(field);
+        let plain = Tokenizer::with_preset(AnalyzerPreset::Simple, Language::English).unwrap();
+        custom
+            .set_field_search_analyzer("suggest", plain.clone())
+            .unwrap();
+        assert_eq!(ids(&custom, "suggest:sec"), [2]);
+        assert!(custom.set_field_search_analyzer("title", plain).is_err());
+    }
+
+    #[test]
+    fn test_knn_search() {
+        use crate::settings::{FieldDef, Schema};
+
+        let schema = Schema::new().field(FieldDef::new("embedding", FieldType::Vector).dims(3));
+        let mut engine = SearchEngine::new(IndexSettings {
+            schema,
+            ..IndexSettings::default()
+        })
+        .unwrap();
+        let vectors = [
+            (1, "[1, 0, 0]", "en"),
+            (2, "[0.9, 0.1, 0]", "de"),
+            (3, "[0, 1, 0]", "en"),
+        ];
+        for (id, vector, lang) in vectors {
+            engine
+                .add_document(doc_with(id, "", &[("embedding", vector), ("lang", lang)]))
+                .unwrap();
+        }
+        engine.add_document(doc(4, "No vector", "")).unwrap();
+        let bad = doc_with(5, "", &[("embedding", "[1, 0]")]);
+        assert!(engine.add_document(bad).is_err());
+
+        let ids = |results: SearchResults| -> Vec<u64> {
+            results.documents.iter().map(|d| d.id).collect()
+        };
+        let query = [1.0, 0.2, 0.0];
+        let results = engine
+            .knn_search("embedding", &query, &SearchOptions::new(2))
+            .unwrap();
+        assert!(results.scores[0] > results.scores[1]);
+        assert_eq!(ids(results), [2, 1]);
+        let english = SearchOptions::new(2).filter(Filter::eq("lang", "en"));
+        let results = engine.knn_search("embedding", &query, &english).unwrap();
+        assert_eq!(ids(results), [1, 3]);
+
+        engine.remove_document(2);
+        let results = engine
+            .knn_search("embedding", &query, &SearchOptions::new(1))
+            .unwrap();
+        assert_eq!(ids(results), [1]);
+        let far = SearchOptions::new(usize::MAX).offset(usize::MAX);
+        assert!(ids(engine.knn_search("embedding", &query, &far).unwrap()).is_empty());
+        let options = SearchOptions::new(1);
+        assert!(engine.knn_search("embedding", &[1.0], &options).is_err());
+        assert!(engine.knn_search("lang", &query, &options).is_err());
+    }
+
+    #[test]
+    fn test_hybrid_search() {
+        use crate::settings::{FieldDef, Schema};
+
+        let schema = Schema::new().field(FieldDef::new("embedding", FieldType::Vector).dims(3));
+        let mut engine = SearchEngine::new(IndexSettings {
+            schema,
+            ..IndexSettings::default()
+        })
+        .unwrap();
+        let documents = [
+            (1, "rust compiler internals", "[0, 0, 1]", "en"),
+            (2, "cooking pasta at home", "[1, 0, 0]", "en"),
+            (3, "rust rust ownership", "[0.9, 0.1, 0]", "de"),
+        ];
+        for (id, content, vector, lang) in documents {
+            let metadata = [("embedding", vector), ("lang", lang)];
+            engine
+                .add_document(doc_with(id, content, &metadata))
+                .unwrap();
+        }
+
+        let search = |fusion: Fusion, options: &SearchOptions| -> Vec<u64> {
+            let results = engine
+                .hybrid_search("rust", "embedding", &[1.0, 0.0, 0.0], fusion, options)
+                .unwrap();
+            results.documents.iter().map(|d| d.id).collect()
+        };
+        let options = SearchOptions::new(10);
+        // 3 ranks high on both sides, 2 only matches the vector
+        assert_eq!(search(Fusion::default(), &options), [3, 1, 2]);
+        let vector_only = Fusion::Weighted {
+            lexical: 0.0,
+            vector: 1.0,
+        };
+        assert_eq!(search(vector_only, &options), [2, 3, 1]);
+        let english = SearchOptions::new(10).filter(Filter::eq("lang", "en"));
+        assert_eq!(search(Fusion::default(), &english), [1, 2]);
+        let missing = engine.hybrid_search("rust", "lang", &[1.0], Fusion::default(), &options);
+        assert!(matches!(missing, Err(MSErrors::SchemaMismatch { .. })));
+    }
+
+    #[test]
+    fn test_fuse_results() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine
+            .add_document(doc(1, "Rust", "a systems language"))
+            .unwrap();
+        engine
+            .add_document(doc(2, "Cooking", "rust on an old pan"))
+            .unwrap();
+        engine
+            .add_document(doc(3, "Rust and Go", "rust compared with go"))
+            .unwrap();
+
+        let parts = vec![
+            engine.search("rust go", 10),
+            engine.search("rust", 10),
+            engine.search("nothing", 10),
+        ];
+        let fused = SearchResults::fuse(parts, rank::RRF_K, 2);
+        // 3 leads both lists
+        assert_eq!(fused.documents[0].id, 3);
+        assert_eq!(fused.documents.len(), 2);
+        assert_eq!(fused.total_matches, 3);
+        assert!(fused.scores[0] > fused.scores[1]);
+    }
+
+    #[test]
+    fn test_geo_search() {
+        use crate::settings::{FieldDef, Schema};
+
+        let schema = Schema::new().field(FieldDef::new("location", FieldType::GeoPoint));
+        let mut engine = SearchEngine::new(IndexSettings {
+            schema,
+            ..IndexSettings::default()
+        })
+        .unwrap();
+        let stores = [
+            (1, "52.5200, 13.4050"), // Berlin
+            (2, "52.3906, 13.0645"), // Potsdam
+            (3, "48.8566, 2.3522"),  // Paris
+        ];
+        for (id, location) in stores {
+            engine
+                .add_document(doc_with(id, "coffee store", &[("location", location)]))
+                .unwrap();
+        }
+        engine.add_document(doc(4, "", "online store")).unwrap();
+
+        let ids = |options: &SearchOptions| -> Vec<u64> {
+            let results = engine.search_with_options("store", options);
+            results.documents.iter().map(|d| d.id).collect()
+        };
+        let near_berlin = Filter::within_radius("location", 52.52, 13.405, 50.0).unwrap();
+        let options = SearchOptions::new(10)
+            .filter(near_berlin)
+            .sort_by(SortSpec::distance("location", 52.52, 13.405).unwrap());
+        assert_eq!(ids(&options), [1, 2]);
+        let from_paris =
+            SearchOptions::new(10).sort_by(SortSpec::distance("location", 48.85, 2.35).unwrap());
+        assert_eq!(ids(&from_paris), [3, 2, 1, 4]);
+        assert!(Filter::within_radius("location", 95.0, 0.0, 10.0).is_err());
+        assert!(Filter::within_radius("location", 52.52, 13.405, -1.0).is_err());
+        assert!(Filter::within_radius("location", 52.52, 13.405, f64::NAN).is_err());
+        assert!(SortSpec::distance("location", f64::NAN, 0.0).is_err());
+        assert!(SortSpec::distance("location", 0.0, 181.0).is_err());
+    }
+
+    #[test]
+    fn test_multi_valued_fields() {
+        use crate::settings::{FieldDef, Schema};
+
+        let schema = Schema::new()
+            .field(FieldDef::new("tags", FieldType::Keyword).multi(true))
+            .field(FieldDef::new("sizes", FieldType::Numeric).multi(true));
+        let mut engine = SearchEngine::new(IndexSettings {
+            schema,
+            ..IndexSettings::default()
+        })
+        .unwrap();
+        let shoes = [
+            (1, "Rust, Outdoor", "38, 42"),
+            (2, "Outdoor", "44"),
+            (3, "Office", "36, 37"),
+        ];
+        for (id, tags, sizes) in shoes {
+            let metadata = [("tags", tags), ("sizes", sizes)];
+            engine
+                .add_document(doc_with(id, "shoes", &metadata))
+                .unwrap();
+        }
+        assert!(
+            engine
+                .add_document(doc_with(4, "shoes", &[("sizes", "40, large")]))
+                .is_err()
+        );
+
+        let ids = |query: &str, options: SearchOptions| -> Vec<u64> {
+            let mut ids: Vec<u64> = engine
+                .search_with_options(query, &options)
+                .documents
+                .iter()
+                .map(|d| d.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids("tags:Outdoor", SearchOptions::new(10)), [1, 2]);
+        let outdoor = SearchOptions::new(10).filter(Filter::eq("tags", "Outdoor"));
+        assert_eq!(ids("shoes", outdoor), [1, 2]);
+        let large = SearchOptions::new(10).filter(Filter::range("sizes", Some(40.0), None));
+        assert_eq!(ids("shoes", large), [1, 2]);
+        assert_eq!(
+            ids("shoes sizes:[37 TO 38]", SearchOptions::new(10)),
+            [1, 3]
+        );
+
+        let results = engine.search_with_options("shoes", &SearchOptions::new(10).facet("tags"));
+        let tags = &results.facets["tags"];
+        assert_eq!((tags["Outdoor"], tags["Rust"], tags["Office"]), (2, 1, 1));
+    }
+
+    #[test]
+    fn test_bool_and_enum_fields() {
+        use crate::settings::{FieldDef, Schema};
+
+        let schema = Schema::new()
+            .field(FieldDef::new("published", FieldType::Bool))
+            .field(FieldDef::new("status", FieldType::Enum).choices(&["draft", "review", "live"]));
+        let mut engine = SearchEngine::new(IndexSettings {
+            schema,
+            ..IndexSettings::default()
+        })
+        .unwrap();
+        let posts = [
+            (1, "TRUE", "live"),
+            (2, "false", "draft"),
+            (3, "True", "review"),
+        ];
+        for (id, published, status) in posts {
+            let metadata = [("published", published), ("status", status)];
+            engine
+                .add_document(doc_with(id, "post", &metadata))
+                .unwrap();
+        }
+        let error = engine
+            .add_document(doc_with(4, "post", &[("status", "deleted")]))
+            .unwrap_err();
+        assert!(error.to_string().contains("one of draft, review, live"));
+
+        let ids = |query: &str, options: SearchOptions| -> Vec<u64> {
+            let mut ids: Vec<u64> = engine
+                .search_with_options(query, &options)
+                .documents
+                .iter()
+                .map(|d| d.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids("published:true", SearchOptions::new(10)), [1, 3]);
+        assert_eq!(ids("published:False", SearchOptions::new(10)), [2]);
+        assert_eq!(ids("status:draft", SearchOptions::new(10)), [2]);
+        let published = SearchOptions::new(10).filter(Filter::eq("published", "true"));
+        assert_eq!(ids("post", published), [1, 3]);
+        assert_eq!(
+            engine.get_document(1).unwrap().metadata["published"],
+            "true"
+        );
+    }
+
+    #[test]
+    fn test_raw_fields() {
+        use crate::settings::{FieldDef, Schema};
+
+        let schema = Schema::new()
+            .field(FieldDef::new("author", FieldType::Text))
+            .raw_fields(true);
+        let mut engine = SearchEngine::new(IndexSettings {
+            schema,
+            ..IndexSettings::default()
+        })
+        .unwrap();
+        let authors = [
+            (1, "Ada Lovelace"),
+            (2, "Ada Byron"),
+            (3, "The Lovelace Society"),
+        ];
+        for (id, author) in authors {
+            engine
+                .add_document(doc_with(id, "notes", &[("author", author)]))
+                .unwrap();
+        }
+
+        let ids = |query: &str, options: SearchOptions| -> Vec<u64> {
+            let mut ids: Vec<u64> = engine
+                .search_with_options(query, &options)
+                .documents
+                .iter()
+                .map(|d| d.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids("author:lovelace", SearchOptions::new(10)), [1, 3]);
+        assert_eq!(
+            ids(r#"author.raw:"Ada Lovelace""#, SearchOptions::new(10)),
+            [1]
+        );
+        assert!(ids("author.raw:ada", SearchOptions::new(10)).is_empty());
+        let exact = SearchOptions::new(10).filter(Filter::eq("author.raw", "Ada Byron"));
+        assert_eq!(ids("notes", exact), [2]);
+
+        let results =
+            engine.search_with_options("notes", &SearchOptions::new(10).facet("author.raw"));
+        assert_eq!(results.facets["author.raw"]["The Lovelace Society"], 1);
+
+        // The keyword copy sorts by the whole value
+        let sorted = |direction: Direction| -> Vec<u64> {
+            let options = SearchOptions::new(10).sort_by(SortSpec::field("author.raw", direction));
+            let results = engine.search_with_options("notes", &options);
+            results.documents.iter().map(|d| d.id).collect()
+        };
+        assert_eq!(sorted(Direction::Ascending), [2, 1, 3]);
+        assert_eq!(sorted(Direction::Descending), [3, 1, 2]);
+        assert!(
+            !engine
+                .get_document(1)
+                .unwrap()
+                .metadata
+                .contains_key("author.raw")
+        );
+    }
+
+    #[test]
+    fn test_field_analyzer() {
+        use crate::settings::{FieldDef, Schema};
+        use crate::tokenizer::{BaseTokenizer, Language, LowercaseFilter, NGramFilter};
+
+        let schema = Schema::new().field(FieldDef::new("name", FieldType::Text));
+        let mut engine = SearchEngine::new(IndexSettings {
+            schema,
+            ..IndexSettings::default()
+        })
+        .unwrap();
+        let prefixes = Tokenizer::custom(BaseTokenizer::Letter, Language::English)
+            .with_filter(LowercaseFilter)
+            .with_filter(NGramFilter::edge(2, 8));
+        assert!(engine.set_field_analyzer("size", prefixes.clone()).is_err());
+        engine.set_field_analyzer("name", prefixes.clone()).unwrap();
+        engine
+            .add_document(doc_with(1, "Notes", &[("name", "Lovelace")]))
+            .unwrap();
+
+        let results = engine.search("name:love", 10);
+        assert_eq!(results.documents[0].id, 1);
+        assert!(engine.set_field_analyzer("name", prefixes).is_err());
+    }
+
+    #[test]
+    fn test_detect_language() {
+        let settings = IndexSettings {
+            detect_language: true,
+            ..IndexSettings::default()
+        };
+        let mut engine = SearchEngine::new(settings).unwrap();
+        engine
+            .add_document(doc(1, "Cats", "The cat is sleeping on the table"))
+            .unwrap();
+        engine
+            .add_document(doc(
+                2,
+                "Chats",
+                "Les chats dorment dans la maison et sur la table",
+            ))
+            .unwrap();
+        let language = |id| engine.get_document(id).unwrap().metadata[LANGUAGE_FIELD].clone();
+        assert_eq!(
+            (language(1), language(2)),
+            ("english".into(), "french".into())
+        );
+
+        // "dans" is a French stop word, so it is not indexed
+        assert!(engine.search("dans", 10).documents.is_empty());
+        let options = SearchOptions {
+            filters: vec![Filter::eq(LANGUAGE_FIELD, "french")],
+            ..SearchOptions::default()
+        };
+        let results = engine.search_with_options("table maison", &options);
+        let ids: Vec<u64> = results.documents.iter().map(|d| d.id).collect();
+        assert_eq!(ids, [2]);
+    }
+
+    #[test]
+    fn test_compact_merge_policy() {
+        let dir = temp_dir("engine-merge-policy");
+        let settings = IndexSettings {
+            merge_policy: MergePolicy::NoMerge,
+            ..IndexSettings::default()
+        };
+        let mut engine = SearchEngine::create(&dir, settings).unwrap();
+        let policy = TieringPolicy::Age {
+            field: "at".to_string(),
+            max_age: Duration::from_secs(86_400),
+        };
+        for id in 1..=3 {
+            engine
+                .add_document(doc_with(id, "old error", &[("at", "2001-01-01")]))
+                .unwrap();
+            assert_eq!(engine.tier(&policy).unwrap(), 1);
+        }
+        assert_eq!(engine.compact().unwrap(), 0);
+        assert_eq!(engine.cold_segments().len(), 3);
+
+        // Two segments at a time: three become two, then one
+        let update = SettingsUpdate {
+            merge_policy: Some(MergePolicy::Log { merge_factor: 2 }),
+            ..SettingsUpdate::default()
+        };
+        engine.update_settings(update).unwrap();
+        assert_eq!(engine.compact().unwrap(), 1);
+        assert_eq!(engine.cold_segments().len(), 2);
+        assert_eq!(engine.compact().unwrap(), 1);
+        assert_eq!(engine.cold_segments()[0].doc_count, 3);
+        assert_eq!(engine.search("error", 10).total_matches, 3);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_cache_size() {
+        let settings = IndexSettings {
+            cache_size: 1,
+            ..IndexSettings::default()
+        };
+        let mut engine = SearchEngine::new(settings).unwrap();
+        engine
+            .add_document(doc_with(1, "text", &[("lang", "en")]))
+            .unwrap();
+        let filters = [Filter::eq("lang", "en"), Filter::eq("lang", "de")];
+        engine.warm_up::<&str>(&[], &filters);
+        assert_eq!(engine.cached_filters(), 1);
+
+        // Updating the size takes effect at once
+        let update = SettingsUpdate {
+            cache_size: Some(0),
+            ..SettingsUpdate::default()
+        };
+        engine.update_settings(update).unwrap();
+        assert_eq!(engine.cached_filters(), 0);
+        engine.warm_up::<&str>(&[], &filters);
+        assert_eq!(engine.cached_filters(), 0);
+    }
+
+    #[test]
+    fn test_update_settings_persists() {
+        let dir = temp_dir("engine-settings");
+        let mut engine = SearchEngine::create(&dir, IndexSettings::default()).unwrap();
+        engine
+            .add_document_with_boost(doc(1, "Foxes", "quick fox"), 2.0)
+            .unwrap();
+        engine.flush().unwrap();
+
+        let update = SettingsUpdate {
+            k1: Some(1.2),
+            b: Some(0.3),
+            ..SettingsUpdate::default()
+        };
+        engine.update_settings(update).unwrap();
+        assert!(SearchEngine::create(&dir, IndexSettings::default()).is_err());
+        let invalid = IndexSettings {
+            b: 2.0,
+            ..IndexSettings::default()
+        };
+        assert!(SearchEngine::new(invalid).is_err());
+
+        let reopened = SearchEngine::open(&dir).unwrap();
+        assert_eq!(reopened.settings().k1, 1.2);
+        assert_eq!(reopened.settings().b, 0.3);
+        assert_eq!(reopened.document_count(), 1);
+        assert_eq!(reopened.search("fox", 10).documents[0].id, 1);
+        assert_eq!(reopened.doc_stats.doc_boost(DocId(1)), 2.0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::prelude::ProptestConfig::with_cases(16))]
+
+        // A saved and reopened index answers queries like the one it was saved from
+        #[test]
+        fn prop_reopened_index_searches_alike(
+            contents in vec(vec(0usize..8, 1..12), 1..20),
+            query in vec(0usize..8, 1..3),
+        ) {
+            const WORDS: [&str; 8] =
+                ["disk", "error", "network", "rust", "search", "index", "fast", "slow"];
+            let dir = temp_dir("engine-prop-reopen");
+            let mut engine = SearchEngine::create(&dir, IndexSettings::default()).unwrap();
+            for (id, words) in contents.iter().enumerate() {
+                let content: Vec<&str> = words.iter().map(|&word| WORDS[word]).collect();
+                engine.add_document(doc(id as u64, "", &content.join(" "))).unwrap();
+            }
+            engine.flush().unwrap();
+            let query: Vec<&str> = query.iter().map(|&word| WORDS[word]).collect();
+            let query = query.join(" ");
+            let before = engine.search(&query, 50);
+            drop(engine);
+            let after = SearchEngine::open(&dir).unwrap().search(&query, 50);
+            std::fs::remove_dir_all(&dir).unwrap();
+
+            let ids = |results: &SearchResults| -> Vec<u64> {
+                results.documents.iter().map(|d| d.id).collect()
+            };
+            proptest::prop_assert_eq!(ids(&before), ids(&after));
+            proptest::prop_assert_eq!(before.scores, after.scores);
+        }
+    }
 }
@@ -0,0 +1,273 @@
+use std::cmp::Ordering;
+
+use super::decay::Decay;
+use super::filter::{Filter, geo_point};
+use crate::errors::MSErrors;
+use crate::indexer::GeoPoint;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Direction {
+    Ascending,
+    #[default]
+    Descending,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SortKey {
+    #[default]
+    Score,
+    Field(String),   // Numeric or date metadata field
+    Keyword(String), // Keyword field or `.raw` copy, by whole value in byte order
+    DocId,
+    Distance(String, GeoPoint), // Distance of a geo-point field from the point, in km
+}
+
+impl SortKey {
+    // The metadata field sorted on, if any
+    pub(crate) fn field(&self) -> Option<&str> {
+        match self {
+            SortKey::Field(field) | SortKey::Keyword(field) | SortKey::Distance(field, _) => {
+                Some(field)
+            }
+            SortKey::Score | SortKey::DocId => None,
+        }
+    }
+}
+
+// Order of the returned hits; ties are broken by score, then doc id
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SortSpec {
+    pub key: SortKey,
+    pub direction: Direction,
+}
+
+// Value of the sort field of a hit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SortValue<'a> {
+    Number(f64),
+    Text(&'a str),
+}
+
+impl SortValue<'_> {
+    fn compare(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (SortValue::Number(x), SortValue::Number(y)) => x.total_cmp(y),
+            (SortValue::Text(x), SortValue::Text(y)) => x.cmp(y),
+            (SortValue::Number(_), SortValue::Text(_)) => Ordering::Less,
+            (SortValue::Text(_), SortValue::Number(_)) => Ordering::Greater,
+        }
+    }
+}
+
+// A hit as seen by the sort: (doc id, score, value of the sort field)
+pub(crate) type SortableHit<'a> = (u64, f64, Option<SortValue<'a>>);
+
+impl SortSpec {
+    pub(crate) fn compare(&self, a: &SortableHit, b: &SortableHit) -> Ordering {
+        let by_score = |a: &SortableHit, b: &SortableHit| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(Ordering::Equal)
+                .then(a.0.cmp(&b.0))
+        };
+        let directed = |ordering: Ordering| match self.direction {
+            Direction::Ascending => ordering,
+            Direction::Descending => ordering.reverse(),
+        };
+        match &self.key {
+            SortKey::Score => directed(by_score(b, a)),
+            SortKey::DocId => directed(a.0.cmp(&b.0)),
+            SortKey::Field(_) | SortKey::Keyword(_) | SortKey::Distance(..) => match (a.2, b.2) {
+                (Some(x), Some(y)) => directed(x.compare(&y)),
+                (a_value, b_value) => b_value.is_some().cmp(&a_value.is_some()),
+            }
+            .then(by_score(a, b)),
+        }
+    }
+
+    // Most relevant first, the default
+    pub fn score() -> Self {
+        SortSpec::default()
+    }
+
+    /*
+    Documents without a value for the field come last in either direction.
+    Keyword fields and `.raw` copies are sorted as by `keyword`.
+    */
+    pub fn field(field: &str, direction: Direction) -> Self {
+        SortSpec {
+            key: SortKey::Field(field.to_string()),
+            direction,
+        }
+    }
+
+    // By the whole value of a keyword field, in byte order
+    pub fn keyword(field: &str, direction: Direction) -> Self {
+        SortSpec {
+            key: SortKey::Keyword(field.to_string()),
+            direction,
+        }
+    }
+
+    /*
+    Nearest to (lat, lon) first; documents without a location come last.
+    Fails on invalid coordinates, like `Filter::within_radius`.
+    */
+    pub fn distance(field: &str, lat: f64, lon: f64) -> Result<Self, MSErrors> {
+        Ok(SortSpec {
+            key: SortKey::Distance(field.to_string(), geo_point(lat, lon)?),
+            direction: Direction::Ascending,
+        })
+    }
+
+    pub fn doc_id(direction: Direction) -> Self {
+        SortSpec {
+            key: SortKey::DocId,
+            direction,
+        }
+    }
+}
+
+// How many of a query's terms a document must contain to match
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MinimumShouldMatch {
+    Count(usize),
+    Percent(u8), // Of the query terms, rounded down
+}
+
+impl MinimumShouldMatch {
+    // Number of terms required out of `terms`, at least one when there are any
+    pub(crate) fn required(&self, terms: usize) -> usize {
+        let required = match *self {
+            MinimumShouldMatch::Count(count) => count.min(terms),
+            MinimumShouldMatch::Percent(percent) => terms * usize::from(percent.min(100)) / 100,
+        };
+        required.max(terms.min(1))
+    }
+}
+
+// Per-query options for `SearchEngine::search_with_options`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SearchOptions {
+    pub limit: usize,
+    pub offset: usize,        // Number of top hits to skip, for paging
+    pub facets: Vec<String>,  // Metadata fields to count values of over all matches
+    pub filters: Vec<Filter>, // Every filter must match for a document to be scored
+    pub sort_by: SortSpec,
+    pub collapse: Option<String>, // Return only the best hit per value of this field
+    pub minimum_should_match: Option<MinimumShouldMatch>, // Any one term by default
+    pub approximate_top_k: Option<f64>, // Maximum chance of missing a top hit, see `approximate`
+    pub fields: Option<Vec<String>>, // Fields of the returned documents, all when unset
+    pub constant_score: bool,     // Match without scoring, see `constant_score`
+    pub decays: Vec<Decay>,       // Multiplied into the scores of the hits
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            limit: 10,
+            offset: 0,
+            facets: Vec::new(),
+            filters: Vec::new(),
+            sort_by: SortSpec::score(),
+            collapse: None,
+            minimum_should_match: None,
+            approximate_top_k: None,
+            fields: None,
+            constant_score: false,
+            decays: Vec::new(),
+        }
+    }
+}
+
+impl SearchOptions {
+    pub fn new(limit: usize) -> Self {
+        SearchOptions {
+            limit,
+            ..SearchOptions::default()
+        }
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn facet(mut self, field: &str) -> Self {
+        self.facets.push(field.to_string());
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn sort_by(mut self, sort_by: SortSpec) -> Self {
+        self.sort_by = sort_by;
+        self
+    }
+
+    pub fn collapse(mut self, field: &str) -> Self {
+        self.collapse = Some(field.to_string());
+        self
+    }
+
+    pub fn minimum_should_match(mut self, minimum: MinimumShouldMatch) -> Self {
+        self.minimum_should_match = Some(minimum);
+        self
+    }
+
+    /*
+    Stop scoring once the estimated chance that an unscored match belongs in
+    the returned hits is below `max_error`. Applies to plain term queries
+    sorted by score, without facets, collapsing or phrases; `total_matches`
+    then counts every match, scored or not.
+    */
+    pub fn approximate(mut self, max_error: f64) -> Self {
+        self.approximate_top_k = Some(max_error);
+        self
+    }
+
+    /*
+    Return only these fields of each hit: "title", "content" or metadata
+    keys. Documents are built from the requested fields instead of cloned
+    whole, which matters for large content.
+    */
+    pub fn fields<S: AsRef<str>>(mut self, fields: impl IntoIterator<Item = S>) -> Self {
+        let fields = fields.into_iter().map(|f| f.as_ref().to_string());
+        self.fields = Some(fields.collect());
+        self
+    }
+
+    /*
+    Skip scoring, for pure filtering such as every document with a given
+    tag: query terms, phrases and filters still have to match, but every
+    hit scores 1, so hits come in doc id order unless sorted by a field.
+    An empty query matches every document, leaving the filters to select.
+    */
+    pub fn constant_score(mut self) -> Self {
+        self.constant_score = true;
+        self
+    }
+
+    /*
+    Multiply each hit's score by the decay of one of its fields, e.g. by
+    document age; several decays multiply together. Not applied to
+    constant-score searches. Invalid decays are ignored, except by
+    `try_search_with_options`, which rejects them.
+    */
+    pub fn decay(mut self, decay: Decay) -> Self {
+        self.decays.push(decay);
+        self
+    }
+}
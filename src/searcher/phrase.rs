@@ -0,0 +1,121 @@
+use crate::errors::MSErrors;
+
+// A quoted run of words, optionally followed by `~slop`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Phrase {
+    pub text: String,
+    pub slop: usize, // Extra positions allowed between the words
+}
+
+/*
+Pull `"..."` and `"..."~N` clauses out of a query string. The words of each
+phrase stay in the returned text so they are still scored as terms; only
+the quotes and slop are removed. An unclosed quote is left as is.
+*/
+pub(crate) fn extract_phrases(query: &str) -> (String, Vec<Phrase>) {
+    let mut text = String::new();
+    let mut phrases = Vec::new();
+    let mut rest = query;
+    while let Some(open) = rest.find('"') {
+        let Some(close) = rest[open + 1..].find('"').map(|i| open + 1 + i) else {
+            break;
+        };
+        let phrase = &rest[open + 1..close];
+        text.push_str(&rest[..open]);
+        text.push(' ');
+        text.push_str(phrase);
+        text.push(' ');
+
+        let mut slop = 0;
+        rest = &rest[close + 1..];
+        if let Some(after) = rest.strip_prefix('~') {
+            let len = after
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(after.len());
+            if let Ok(value) = after[..len].parse() {
+                slop = value;
+                rest = &after[len..];
+            }
+        }
+        phrases.push(Phrase {
+            text: phrase.to_string(),
+            slop,
+        });
+    }
+    text.push_str(rest);
+    (text, phrases)
+}
+
+// Check that every quote of a query is closed, which `extract_phrases` does not require
+pub(crate) fn check_quotes(query: &str) -> Result<(), MSErrors> {
+    let quotes: Vec<usize> = query.match_indices('"').map(|(idx, _)| idx).collect();
+    if quotes.len() % 2 == 1 {
+        return Err(MSErrors::QueryParseError {
+            position: query.len(),
+            expected: vec!["\"".to_string()],
+            message: format!("unclosed quote at byte {}", quotes[quotes.len() - 1]),
+        });
+    }
+    Ok(())
+}
+
+/*
+Smallest number of extra positions between the terms of a phrase, given
+the sorted positions of each term in one document. Terms must appear in
+phrase order; adjacent terms give 0. None if some term never follows the
+previous one.
+*/
+pub(crate) fn match_distance(positions: &[&[usize]]) -> Option<usize> {
+    let (first, others) = positions.split_first()?;
+    let mut best: Option<usize> = None;
+    'starts: for &start in first.iter() {
+        let mut previous = start;
+        for term_positions in others {
+            let next = term_positions.partition_point(|&p| p <= previous);
+            match term_positions.get(next) {
+                Some(&position) => previous = position,
+                // Later starts cannot find a match either
+                None => break 'starts,
+            }
+        }
+        let distance = previous - start - others.len();
+        best = Some(best.map_or(distance, |best| best.min(distance)));
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_phrases() {
+        let (text, phrases) = extract_phrases(r#"fox "quick jumps"~2 "lazy dog" "open"#);
+        assert_eq!(
+            text.split_whitespace().collect::<Vec<_>>(),
+            ["fox", "quick", "jumps", "lazy", "dog", "\"open"]
+        );
+        assert_eq!(
+            phrases,
+            vec![
+                Phrase {
+                    text: "quick jumps".to_string(),
+                    slop: 2
+                },
+                Phrase {
+                    text: "lazy dog".to_string(),
+                    slop: 0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_match_distance() {
+        assert_eq!(match_distance(&[&[3][..], &[4]]), Some(0));
+        assert_eq!(match_distance(&[&[0, 7][..], &[2, 9]]), Some(1));
+        assert_eq!(match_distance(&[&[5][..], &[1]]), None);
+        assert_eq!(match_distance(&[&[1, 4][..], &[6], &[2, 8]]), Some(2));
+        assert_eq!(match_distance(&[&[2][..]]), Some(0));
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+use super::{SearchEngine, SearchOptions, SearchResults};
+use crate::document::Document;
+use crate::errors::MSErrors;
+
+/*
+The single writer of an engine shared with `IndexReader`s. Writes go to
+the writer's own engine; `commit` publishes a snapshot of it that readers
+pick up with an atomic pointer swap, so a reader sees all of a commit or
+none of it and never waits for the writer. Snapshots share the engine's
+documents and postings, and the writer copies only what it changes next.
+*/
+pub struct IndexWriter {
+    engine: SearchEngine,
+    published: Arc<ArcSwap<SearchEngine>>,
+}
+
+impl IndexWriter {
+    // Take over an engine, publishing its current documents
+    pub fn new(engine: SearchEngine) -> Self {
+        let published = Arc::new(ArcSwap::from_pointee(engine.snapshot()));
+        IndexWriter { engine, published }
+    }
+
+    pub fn reader(&self) -> IndexReader {
+        IndexReader {
+            published: self.published.clone(),
+        }
+    }
+
+    pub fn add_document(&mut self, document: Document) -> Result<(), MSErrors> {
+        self.engine.add_document(document)
+    }
+
+    pub fn remove_document(&mut self, doc_id: u64) -> Option<Document> {
+        self.engine.remove_document(doc_id)
+    }
+
+    // The writer's engine, with changes not yet published
+    pub fn engine(&self) -> &SearchEngine {
+        &self.engine
+    }
+
+    pub fn engine_mut(&mut self) -> &mut SearchEngine {
+        &mut self.engine
+    }
+
+    /*
+    Apply writes buffered by the engine's refresh policy, then publish the
+    result to every reader. Searches already running finish on the
+    snapshot they started with.
+    */
+    pub fn commit(&mut self) -> Result<(), MSErrors> {
+        self.engine.commit()?;
+        self.published.store(Arc::new(self.engine.snapshot()));
+        Ok(())
+    }
+
+    // Commit if the engine's auto refresh policy calls for it, e.g. from a timer
+    pub fn commit_if_due(&mut self) -> Result<(), MSErrors> {
+        if self.engine.buffer.is_due() {
+            self.commit()?;
+        }
+        Ok(())
+    }
+}
+
+// Searches the last snapshot committed by an `IndexWriter`; cheap to clone
+#[derive(Clone)]
+pub struct IndexReader {
+    published: Arc<ArcSwap<SearchEngine>>,
+}
+
+impl IndexReader {
+    // The current snapshot, for several searches over the same documents
+    pub fn searcher(&self) -> Arc<SearchEngine> {
+        self.published.load_full()
+    }
+
+    pub fn search(&self, query: &str, limit: usize) -> SearchResults {
+        self.published.load().search(query, limit)
+    }
+
+    pub fn search_with_options(&self, query: &str, options: &SearchOptions) -> SearchResults {
+        self.published.load().search_with_options(query, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::DocId;
+    use crate::settings::IndexSettings;
+    use std::collections::HashMap;
+    use std::thread;
+
+    fn doc(id: u64, content: &str) -> Document {
+        Document {
+            id,
+            title: String::new(),
+            content: content.to_string(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_reader_snapshots() {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        engine.add_document(doc(1, "first fox")).unwrap();
+        let mut writer = IndexWriter::new(engine);
+        let reader = writer.reader();
+        assert_eq!(reader.search("fox", 10).total_matches, 1);
+
+        writer.add_document(doc(2, "second fox")).unwrap();
+        writer.remove_document(1);
+        assert_eq!(reader.search("fox", 10).documents[0].id, 1);
+        let before = reader.searcher();
+        writer.commit().unwrap();
+
+        // Readers on other threads see the commit; held snapshots do not
+        let first = thread::spawn(move || reader.search("fox", 10).documents[0].id)
+            .join()
+            .unwrap();
+        assert_eq!(first, 2);
+        assert_eq!(before.search("fox", 10).documents[0].id, 1);
+        assert_eq!(writer.engine().document_count(), 1);
+
+        // Commits share the writer's state instead of copying it
+        let published = writer.reader().searcher();
+        assert!(Arc::ptr_eq(
+            &published.documents,
+            &writer.engine().documents
+        ));
+        writer.add_document(doc(3, "third fox")).unwrap();
+        writer.commit().unwrap();
+        let next = writer.reader().searcher();
+        assert!(!Arc::ptr_eq(&published.documents, &next.documents));
+        let kept = DocId(2);
+        assert!(Arc::ptr_eq(
+            &published.documents[&kept],
+            &next.documents[&kept]
+        ));
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::{SearchEngine, SearchOptions, tiering};
+use crate::errors::MSErrors;
+
+/*
+One recorded search, written as a tab-separated line:
+`timestamp  latency_us  total_matches  limit  query`. Whitespace in the
+query is collapsed so that it stays on one line. Only the query text and
+limit are kept; filters, sorting and other options are not replayed.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryRecord {
+    pub timestamp: u64, // Seconds since the epoch
+    pub latency: Duration,
+    pub total_matches: usize,
+    pub limit: usize,
+    pub query: String,
+}
+
+impl fmt::Display for QueryRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let query: Vec<&str> = self.query.split_whitespace().collect();
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}",
+            self.timestamp,
+            self.latency.as_micros(),
+            self.total_matches,
+            self.limit,
+            query.join(" ")
+        )
+    }
+}
+
+impl FromStr for QueryRecord {
+    type Err = MSErrors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || MSErrors::ParseError(format!("invalid query record: {}", s));
+        let mut parts = s.splitn(5, '\t');
+        let mut number = || -> Result<u64, MSErrors> {
+            parts
+                .next()
+                .and_then(|n| n.parse().ok())
+                .ok_or_else(invalid)
+        };
+        let (timestamp, latency, total_matches, limit) =
+            (number()?, number()?, number()?, number()?);
+        let query = parts.next().ok_or_else(invalid)?;
+        Ok(QueryRecord {
+            timestamp,
+            latency: Duration::from_micros(latency),
+            total_matches: total_matches as usize,
+            limit: limit as usize,
+            query: query.to_string(),
+        })
+    }
+}
+
+// Appends a record of every search to a file
+pub(super) struct QueryRecorder {
+    file: Mutex<File>,
+}
+
+impl QueryRecorder {
+    pub fn create(path: &Path) -> Result<Self, MSErrors> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| MSErrors::io(path, e))?;
+        Ok(QueryRecorder {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.file.is_poisoned()
+    }
+
+    /*
+    Searches still succeed when the record cannot be written. A panic while
+    writing poisons the lock; later records are then dropped rather than
+    panicking the search, and `is_poisoned` reports it.
+    */
+    pub fn record(&self, record: &QueryRecord) {
+        let line = format!("{}\n", record);
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.write_all(line.as_bytes());
+        }
+    }
+}
+
+pub(super) fn record_of(
+    query: &str,
+    limit: usize,
+    total_matches: usize,
+    latency: Duration,
+) -> QueryRecord {
+    QueryRecord {
+        timestamp: tiering::now_secs(),
+        latency,
+        total_matches,
+        limit,
+        query: query.to_string(),
+    }
+}
+
+// Read the records of a file written by `SearchEngine::record_queries`
+pub fn read_query_log(path: impl AsRef<Path>) -> Result<Vec<QueryRecord>, MSErrors> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|e| MSErrors::io(path, e))?;
+    let mut records = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| MSErrors::io(path, e))?;
+        if !line.trim().is_empty() {
+            records.push(line.parse()?);
+        }
+    }
+    Ok(records)
+}
+
+// Spread of the latencies of a set of queries
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LatencySummary {
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub max: Duration,
+}
+
+impl LatencySummary {
+    pub fn of(latencies: &[Duration]) -> Self {
+        if latencies.is_empty() {
+            return LatencySummary::default();
+        }
+        let mut sorted = latencies.to_vec();
+        sorted.sort();
+        // Nearest-rank percentile
+        let percentile = |p: usize| sorted[(sorted.len() * p).div_ceil(100).max(1) - 1];
+        LatencySummary {
+            mean: sorted.iter().sum::<Duration>() / sorted.len() as u32,
+            p50: percentile(50),
+            p95: percentile(95),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/*
+Recorded against replayed performance of a query log, to compare an index
+or settings before and after tuning. Replaying searches hot and cold
+tiers like the original searches, with the recorded limit.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayReport {
+    pub queries: usize,
+    pub recorded: LatencySummary,
+    pub replayed: LatencySummary,
+    pub changed_matches: Vec<(String, usize, usize)>, // Query, recorded and replayed total matches
+}
+
+// Run every record against `engine`, in order
+pub fn replay(engine: &SearchEngine, records: &[QueryRecord]) -> ReplayReport {
+    let mut replayed = Vec::with_capacity(records.len());
+    let mut changed_matches = Vec::new();
+    for record in records {
+        let start = Instant::now();
+        let results = engine.search_with_options(&record.query, &SearchOptions::new(record.limit));
+        replayed.push(start.elapsed());
+        if results.total_matches != record.total_matches {
+            changed_matches.push((
+                record.query.clone(),
+                record.total_matches,
+                results.total_matches,
+            ));
+        }
+    }
+    let recorded: Vec<Duration> = records.iter().map(|record| record.latency).collect();
+    ReplayReport {
+        queries: records.len(),
+        recorded: LatencySummary::of(&recorded),
+        replayed: LatencySummary::of(&replayed),
+        changed_matches,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_record() {
+        let record = record_of("rust\tbook\n club", 10, 3, Duration::from_micros(1250));
+        let line = record.to_string();
+        assert!(line.ends_with("\t1250\t3\t10\trust book club"));
+        let parsed: QueryRecord = line.parse().unwrap();
+        assert_eq!(parsed.query, "rust book club");
+        assert_eq!((parsed.latency, parsed.total_matches), (record.latency, 3));
+        assert!("12\tfast\t3\t10\tq".parse::<QueryRecord>().is_err());
+        match read_query_log("missing/queries.log") {
+            Err(MSErrors::Io { path, .. }) => assert_eq!(path, Path::new("missing/queries.log")),
+            other => panic!("expected an io error, got {:?}", other),
+        }
+
+        let ms = Duration::from_millis;
+        let summary = LatencySummary::of(&[ms(4), ms(1), ms(3), ms(2)]);
+        assert_eq!(
+            (summary.p50, summary.p95, summary.max),
+            (ms(2), ms(4), ms(4))
+        );
+        assert_eq!(summary.mean, Duration::from_micros(2500));
+    }
+}
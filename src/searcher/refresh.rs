@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::storage::StoredDocument;
+
+// When writes to an engine become searchable
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum RefreshPolicy {
+    #[default]
+    Immediate, // As soon as the write returns
+    Manual, // On `SearchEngine::commit` only
+    // On commit, or once either limit is reached; checked on each write and by `commit_if_due`
+    Auto {
+        max_docs: Option<usize>,
+        interval: Option<Duration>,
+    },
+}
+
+// A write waiting for the next commit
+pub(crate) enum PendingWrite {
+    Add(StoredDocument),
+    Remove(u64),
+}
+
+// Writes made since the last commit, in order
+pub(crate) struct WriteBuffer {
+    pub policy: RefreshPolicy,
+    writes: Vec<PendingWrite>,
+    last_writes: HashMap<u64, usize>, // Index of the last write to each document
+    max_added_id: Option<u64>,        // Highest id among the pending additions
+    last_commit: Instant,
+}
+
+impl Default for WriteBuffer {
+    fn default() -> Self {
+        WriteBuffer {
+            policy: RefreshPolicy::Immediate,
+            writes: Vec::new(),
+            last_writes: HashMap::new(),
+            max_added_id: None,
+            last_commit: Instant::now(),
+        }
+    }
+}
+
+impl WriteBuffer {
+    pub fn is_buffering(&self) -> bool {
+        self.policy != RefreshPolicy::Immediate
+    }
+
+    // Whether the auto policy calls for a commit
+    pub fn is_due(&self) -> bool {
+        match self.policy {
+            RefreshPolicy::Auto { max_docs, interval } => {
+                max_docs.is_some_and(|max_docs| self.writes.len() >= max_docs)
+                    || interval.is_some_and(|interval| self.last_commit.elapsed() >= interval)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn push(&mut self, write: PendingWrite) {
+        let doc_id = match &write {
+            PendingWrite::Add((document, _, _)) => {
+                self.max_added_id = self.max_added_id.max(Some(document.id));
+                document.id
+            }
+            PendingWrite::Remove(doc_id) => *doc_id,
+        };
+        self.last_writes.insert(doc_id, self.writes.len());
+        self.writes.push(write);
+    }
+
+    pub fn len(&self) -> usize {
+        self.writes.len()
+    }
+
+    // Take the writes to apply, restarting the interval
+    pub fn drain(&mut self) -> Vec<PendingWrite> {
+        self.last_commit = Instant::now();
+        self.last_writes.clear();
+        self.max_added_id = None;
+        std::mem::take(&mut self.writes)
+    }
+
+    // The last pending write to a document, if any
+    pub fn last_write(&self, doc_id: u64) -> Option<&PendingWrite> {
+        self.last_writes.get(&doc_id).map(|&i| &self.writes[i])
+    }
+
+    // Highest id among the pending additions
+    pub fn max_added_id(&self) -> Option<u64> {
+        self.max_added_id
+    }
+}
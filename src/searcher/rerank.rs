@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+// Top hits reranked when no window is set
+const DEFAULT_WINDOW: usize = 100;
+
+// What a reranker knows about one of the top hits
+#[derive(Debug, Clone, PartialEq)]
+pub struct RerankFeatures {
+    pub doc_id: u64,
+    pub score: f64,           // First-stage score, with boosts, phrases and decays
+    pub matched_terms: usize, // Query terms the document contains
+    pub title_matches: usize, // Query terms in its title
+    pub doc_length: usize,    // Tokens, not counting metadata fields
+    pub metadata: Vec<Option<f64>>, // Numeric values of the reranked fields, in order
+}
+
+impl RerankFeatures {
+    /*
+    The features as a flat vector, for models trained on one: the score,
+    matched terms, title matches and length, then each metadata value, 0
+    when the document has none.
+    */
+    pub fn values(&self) -> Vec<f64> {
+        let mut values = vec![
+            self.score,
+            self.matched_terms as f64,
+            self.title_matches as f64,
+            self.doc_length as f64,
+        ];
+        values.extend(self.metadata.iter().map(|value| value.unwrap_or(0.0)));
+        values
+    }
+}
+
+/*
+A second-stage model reordering the top hits of a query, e.g. one trained
+for learning to rank. Closures implement it, so a model run elsewhere,
+such as through ONNX Runtime, can be plugged in as a callback.
+*/
+pub trait Reranker: Send + Sync {
+    // The final score of a hit; higher ranks first
+    fn score(&self, features: &RerankFeatures) -> f64;
+}
+
+impl<F> Reranker for F
+where
+    F: Fn(&RerankFeatures) -> f64 + Send + Sync,
+{
+    fn score(&self, features: &RerankFeatures) -> f64 {
+        self(features)
+    }
+}
+
+/*
+A linear model over `RerankFeatures::values`. Features without a weight
+are ignored, and weights without a feature count for nothing.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearReranker {
+    pub weights: Vec<f64>,
+    pub bias: f64,
+}
+
+impl LinearReranker {
+    pub fn new(weights: Vec<f64>) -> Self {
+        LinearReranker { weights, bias: 0.0 }
+    }
+
+    pub fn bias(mut self, bias: f64) -> Self {
+        self.bias = bias;
+        self
+    }
+}
+
+impl Reranker for LinearReranker {
+    fn score(&self, features: &RerankFeatures) -> f64 {
+        let values = features.values();
+        self.bias
+            + self
+                .weights
+                .iter()
+                .zip(&values)
+                .map(|(weight, value)| weight * value)
+                .sum::<f64>()
+    }
+}
+
+/*
+How the engine reranks: the best `window` hits of the first stage, merged
+across hot and cold tiers, are rescored by the reranker and sorted by that
+score. They stay ahead of the hits past the window, which keep their order
+and first-stage score; a window covering every page keeps scores in
+order.
+*/
+#[derive(Clone)]
+pub struct Rerank {
+    pub(crate) reranker: Arc<dyn Reranker>,
+    pub(crate) window: usize,       // Hits rescored, 100 by default
+    pub(crate) fields: Vec<String>, // Numeric fields read into the features
+}
+
+impl Rerank {
+    pub fn new(reranker: impl Reranker + 'static) -> Self {
+        Rerank {
+            reranker: Arc::new(reranker),
+            window: DEFAULT_WINDOW,
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn window(mut self, window: usize) -> Self {
+        self.window = window;
+        self
+    }
+
+    // Pass the value of a numeric or date field among the features
+    pub fn field(mut self, field: &str) -> Self {
+        self.fields.push(field.to_string());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_reranker() {
+        let features = RerankFeatures {
+            doc_id: 1,
+            score: 2.0,
+            matched_terms: 2,
+            title_matches: 1,
+            doc_length: 10,
+            metadata: vec![Some(4.0), None],
+        };
+        assert_eq!(features.values(), vec![2.0, 2.0, 1.0, 10.0, 4.0, 0.0]);
+        let linear = LinearReranker::new(vec![1.0, 0.0, 3.0, -0.1, 0.5]).bias(1.0);
+        assert!((linear.score(&features) - 7.0).abs() < 1e-12);
+        // Weights past the features are ignored
+        let long = LinearReranker::new(vec![1.0; 10]);
+        assert_eq!(long.score(&features), 19.0);
+    }
+}
@@ -0,0 +1,308 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::{Filter, SearchEngine, SearchOptions, SearchResults, merge_results};
+use crate::document::Document;
+use crate::document::date::{format_date, parse_rfc3339};
+use crate::errors::MSErrors;
+use crate::indexer::parse_number;
+use crate::settings::IndexSettings;
+
+const DAY_SECS: u64 = 86_400;
+
+// Length of the time period covered by one partition
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RolloverPeriod {
+    Daily,
+    Weekly, // Weeks start on Monday
+}
+
+impl RolloverPeriod {
+    // Start of the period containing `secs`
+    fn start(&self, secs: u64) -> u64 {
+        let day = secs / DAY_SECS;
+        match self {
+            RolloverPeriod::Daily => day * DAY_SECS,
+            // 1970-01-01 was a Thursday, three days after a Monday; the
+            // days before the first Monday fall in a week starting at 0
+            RolloverPeriod::Weekly => day.saturating_sub((day + 3) % 7) * DAY_SECS,
+        }
+    }
+
+    fn length(&self) -> u64 {
+        match self {
+            RolloverPeriod::Daily => DAY_SECS,
+            RolloverPeriod::Weekly => 7 * DAY_SECS,
+        }
+    }
+}
+
+// When `PartitionedIndex` starts a new partition and when it drops old ones
+#[derive(Debug, Clone, PartialEq)]
+pub struct RolloverPolicy {
+    pub period: RolloverPeriod,
+    pub date_field: String, // Metadata date deciding a document's period; now if missing
+    pub max_documents: Option<usize>, // Also roll over once a partition holds this many
+    pub retention: Option<Duration>, // Partitions whose period ended longer ago are dropped
+}
+
+struct Partition {
+    name: String, // Directory name: start date, plus ".N" for size rollovers
+    start: u64,
+    engine: SearchEngine,
+}
+
+/*
+An index split into one directory per time period, in the style of log
+index lifecycle management. Documents go to the partition of their period,
+searches run over every partition and merge the results, and expired
+partitions are deleted as a whole rather than document by document.
+*/
+pub struct PartitionedIndex {
+    root: PathBuf,
+    settings: IndexSettings,
+    policy: RolloverPolicy,
+    partitions: Vec<Partition>, // Ordered by start, then by size rollover
+}
+
+impl PartitionedIndex {
+    // Open the partitions under `root`, creating the directory if needed
+    pub fn open(
+        root: impl AsRef<Path>,
+        settings: IndexSettings,
+        policy: RolloverPolicy,
+    ) -> Result<Self, MSErrors> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root).map_err(|e| MSErrors::io(&root, e))?;
+        let entries = fs::read_dir(&root).map_err(|e| MSErrors::io(&root, e))?;
+
+        let mut partitions = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| MSErrors::io(&root, e))?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let date = name.split('.').next().unwrap_or_default();
+            let Some(start) = parse_rfc3339(date) else {
+                continue;
+            };
+            partitions.push(Partition {
+                engine: SearchEngine::open(entry.path())?,
+                start: start as u64,
+                name,
+            });
+        }
+        partitions.sort_by(|a, b| {
+            a.start
+                .cmp(&b.start)
+                .then(a.name.len().cmp(&b.name.len()))
+                .then(a.name.cmp(&b.name))
+        });
+        Ok(PartitionedIndex {
+            root,
+            settings,
+            policy,
+            partitions,
+        })
+    }
+
+    // Partition directory names, oldest first
+    pub fn partitions(&self) -> Vec<&str> {
+        self.partitions.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    pub fn document_count(&self) -> usize {
+        self.partitions
+            .iter()
+            .map(|p| p.engine.document_count())
+            .sum()
+    }
+
+    // Smallest id greater than every id in any partition
+    pub fn next_document_id(&self) -> u64 {
+        self.partitions
+            .iter()
+            .map(|p| p.engine.next_document_id())
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Add a document to the partition of its period, rolling over if needed
+    pub fn add_document(&mut self, document: Document) -> Result<(), MSErrors> {
+        let secs = document
+            .metadata
+            .get(&self.policy.date_field)
+            .and_then(|value| parse_number(value))
+            .map_or_else(super::tiering::now_secs, |secs| secs.max(0.0) as u64);
+        let start = self.policy.period.start(secs);
+
+        let full = |p: &Partition| {
+            self.policy
+                .max_documents
+                .is_some_and(|max| p.engine.document_count() >= max)
+        };
+        let current = self.partitions.iter().rposition(|p| p.start == start);
+        let index = match current {
+            Some(index) if !full(&self.partitions[index]) => index,
+            _ => {
+                let rollovers = self.partitions.iter().filter(|p| p.start == start).count();
+                let date = format_date(start as i64);
+                let name = match rollovers {
+                    0 => date,
+                    n => format!("{}.{}", date, n),
+                };
+                let engine = SearchEngine::create(self.root.join(&name), self.settings.clone())?;
+                let partition = Partition {
+                    name,
+                    start,
+                    engine,
+                };
+                let position = self.partitions.partition_point(|p| p.start <= start);
+                self.partitions.insert(position, partition);
+                position
+            }
+        };
+        self.partitions[index].engine.add_document(document)
+    }
+
+    pub fn flush(&self) -> Result<(), MSErrors> {
+        for partition in &self.partitions {
+            partition.engine.flush()?;
+        }
+        Ok(())
+    }
+
+    /*
+    Search every partition and merge the hits. Partitions whose period lies
+    outside a Range filter on the date field are skipped.
+    */
+    pub fn search_with_options(&self, query: &str, options: &SearchOptions) -> SearchResults {
+        let mut partition_options = options.clone();
+        partition_options.offset = 0;
+        partition_options.limit = options.offset.saturating_add(options.limit);
+
+        let length = self.policy.period.length() as f64;
+        let parts = self
+            .partitions
+            .iter()
+            .filter(|partition| {
+                let (start, end) = (partition.start as f64, partition.start as f64 + length);
+                options.filters.iter().all(|filter| match filter {
+                    Filter::Range(field, min, max) if *field == self.policy.date_field => {
+                        min.is_none_or(|min| end > min) && max.is_none_or(|max| start <= max)
+                    }
+                    _ => true,
+                })
+            })
+            .map(|partition| {
+                partition
+                    .engine
+                    .search_with_options(query, &partition_options)
+            })
+            .collect();
+        merge_results(parts, options)
+    }
+
+    pub fn search(&self, query: &str, limit: usize) -> SearchResults {
+        self.search_with_options(query, &SearchOptions::new(limit))
+    }
+
+    // Delete partitions whose period ended more than `retention` before `now`
+    pub fn drop_expired(&mut self, now: u64) -> Result<usize, MSErrors> {
+        let Some(retention) = self.policy.retention else {
+            return Ok(0);
+        };
+        let length = self.policy.period.length();
+        let cutoff = now.saturating_sub(retention.as_secs());
+        // A partition is only forgotten once its directory is gone
+        let mut dropped = 0;
+        while let Some(i) = self
+            .partitions
+            .iter()
+            .position(|p| p.start + length <= cutoff)
+        {
+            let path = self.root.join(&self.partitions[i].name);
+            fs::remove_dir_all(&path).map_err(|e| MSErrors::io(path, e))?;
+            self.partitions.remove(i);
+            dropped += 1;
+        }
+        Ok(dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn event(id: u64, at: &str, content: &str) -> Document {
+        Document {
+            id,
+            title: String::new(),
+            content: content.to_string(),
+            metadata: HashMap::from([("at".to_string(), at.to_string())]),
+        }
+    }
+
+    #[test]
+    fn test_rollover() {
+        let dir = std::env::temp_dir().join(format!("mini-search-rollover-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let policy = RolloverPolicy {
+            period: RolloverPeriod::Daily,
+            date_field: "at".to_string(),
+            max_documents: Some(2),
+            retention: Some(Duration::from_secs(2 * DAY_SECS)),
+        };
+        let mut index =
+            PartitionedIndex::open(&dir, IndexSettings::default(), policy.clone()).unwrap();
+        index
+            .add_document(event(1, "2024-05-01T08:00:00Z", "disk full"))
+            .unwrap();
+        index
+            .add_document(event(2, "2024-05-01T09:00:00Z", "disk ok"))
+            .unwrap();
+        index
+            .add_document(event(3, "2024-05-01T10:00:00Z", "disk full again"))
+            .unwrap();
+        index
+            .add_document(event(4, "2024-05-03T10:00:00Z", "network down"))
+            .unwrap();
+        assert_eq!(
+            index.partitions(),
+            vec!["2024-05-01", "2024-05-01.1", "2024-05-03"]
+        );
+        index.flush().unwrap();
+
+        let mut index = PartitionedIndex::open(&dir, IndexSettings::default(), policy).unwrap();
+        assert_eq!(index.document_count(), 4);
+        assert_eq!(index.next_document_id(), 5);
+        assert_eq!(index.search("disk", 10).total_matches, 3);
+        let options = SearchOptions::new(10).filter(Filter::after("at", "2024-05-02").unwrap());
+        assert_eq!(
+            index
+                .search_with_options("disk network", &options)
+                .total_matches,
+            1
+        );
+
+        // Two days after May 3rd began, only May 1st has fully expired
+        let now = parse_rfc3339("2024-05-04T12:00:00Z").unwrap() as u64;
+        assert_eq!(index.drop_expired(now).unwrap(), 2);
+        assert_eq!(index.partitions(), vec!["2024-05-03"]);
+        assert_eq!(index.search("disk", 10).total_matches, 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_weekly_period() {
+        // 2024-05-01 was a Wednesday; its week starts on Monday the 29th of April
+        let wednesday = parse_rfc3339("2024-05-01T12:00:00Z").unwrap() as u64;
+        let monday = parse_rfc3339("2024-04-29").unwrap() as u64;
+        assert_eq!(RolloverPeriod::Weekly.start(wednesday), monday);
+        assert_eq!(RolloverPeriod::Weekly.start(monday), monday);
+        for day in 0..4 {
+            assert_eq!(RolloverPeriod::Weekly.start(day * DAY_SECS), 0);
+        }
+        assert_eq!(RolloverPeriod::Weekly.start(4 * DAY_SECS), 4 * DAY_SECS);
+    }
+}
@@ -0,0 +1,82 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use super::SearchEngine;
+use crate::document::Document;
+
+// A scored hit; its stored fields are only read when asked for
+#[derive(Clone, Copy)]
+pub struct Hit<'a> {
+    pub doc_id: u64,
+    pub score: f64,
+    engine: &'a SearchEngine,
+}
+
+impl<'a> Hit<'a> {
+    pub fn document(&self) -> Option<&'a Document> {
+        self.engine.get_document(self.doc_id)
+    }
+}
+
+// Heap entry ordered best first: higher score, then lower id
+pub(super) struct Ranked(pub f64, pub u64);
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(other.1.cmp(&self.1))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+/*
+Hits of a query in score order, produced lazily. Matches are scored up
+front, but they are only ordered as they are consumed, through a binary
+heap, and no document is cloned. Taking the first few hits of a large
+match set is therefore much cheaper than a full search.
+*/
+pub struct SearchIter<'a> {
+    engine: &'a SearchEngine,
+    heap: BinaryHeap<Ranked>,
+}
+
+impl<'a> SearchIter<'a> {
+    pub(crate) fn new(engine: &'a SearchEngine, scored_docs: Vec<(u64, f64)>) -> Self {
+        let heap = scored_docs
+            .into_iter()
+            .map(|(doc_id, score)| Ranked(score, doc_id))
+            .collect();
+        SearchIter { engine, heap }
+    }
+}
+
+impl<'a> Iterator for SearchIter<'a> {
+    type Item = Hit<'a>;
+
+    fn next(&mut self) -> Option<Hit<'a>> {
+        let Ranked(score, doc_id) = self.heap.pop()?;
+        Some(Hit {
+            doc_id,
+            score,
+            engine: self.engine,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.heap.len(), Some(self.heap.len()))
+    }
+}
+
+impl ExactSizeIterator for SearchIter<'_> {}
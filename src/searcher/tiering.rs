@@ -0,0 +1,201 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::{Filter, SearchEngine};
+use crate::errors::MSErrors;
+use crate::indexer::{DocId, DocValues};
+use crate::rank::Similarity;
+use crate::settings::IndexSettings;
+use crate::storage::{SegmentMeta, Storage};
+
+// Number of cold segments kept loaded in memory after being queried
+const MAX_LOADED_SEGMENTS: usize = 4;
+
+// Which hot documents `SearchEngine::tier` moves to a cold segment
+#[derive(Debug, Clone, PartialEq)]
+pub enum TieringPolicy {
+    // Documents whose date (or epoch seconds) in `field` is older than `max_age`
+    Age { field: String, max_age: Duration },
+    // Documents returned by fewer than `min_hits` searches since the last pass
+    Access { min_hits: u64 },
+}
+
+pub(crate) fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/*
+Routing values a query is restricted to by its top-level Eq and In filters
+on the routing field, or None if it may match any value.
+*/
+pub(crate) fn routed_values(filters: &[Filter], field: &str) -> Option<HashSet<String>> {
+    let mut routes: Option<HashSet<String>> = None;
+    for filter in filters {
+        let values: HashSet<String> = match filter {
+            Filter::Eq(f, value) if f == field => HashSet::from([value.clone()]),
+            Filter::In(f, values) if f == field => values.iter().cloned().collect(),
+            _ => continue,
+        };
+        routes = Some(match routes {
+            Some(routes) => routes.intersection(&values).cloned().collect(),
+            None => values,
+        });
+    }
+    routes
+}
+
+struct LoadedSegment {
+    id: u64,
+    engine: Arc<SearchEngine>,
+    last_used: AtomicU64, // Tick of the last search that used it
+}
+
+/*
+State of the cold tier: segments on disk, an LRU of the ones recently
+loaded to answer queries, and per-document hit counts for the access policy.
+Only documents present at the previous pass have a hit count, so documents
+added since then always stay hot. Searches only take the LRU's lock to
+read it, unless they load a segment, and count hits atomically.
+*/
+#[derive(Default)]
+pub(crate) struct Tiering {
+    pub segments: Vec<SegmentMeta>,
+    loaded: RwLock<Vec<LoadedSegment>>,
+    clock: AtomicU64, // Ticks once per use of a loaded segment
+    hits: HashMap<DocId, AtomicU64>,
+}
+
+impl Tiering {
+    pub fn new(segments: Vec<SegmentMeta>) -> Self {
+        Tiering {
+            segments,
+            ..Tiering::default()
+        }
+    }
+
+    // True if a thread panicked while holding the tier's state
+    pub fn is_poisoned(&self) -> bool {
+        self.loaded.is_poisoned()
+    }
+
+    pub fn is_cold(
+        &self,
+        policy: &TieringPolicy,
+        doc_values: &DocValues,
+        doc_id: DocId,
+        now: u64,
+    ) -> bool {
+        match policy {
+            TieringPolicy::Age { field, max_age } => doc_values
+                .get_number(field, doc_id)
+                .is_some_and(|date| date < now as f64 - max_age.as_secs_f64()),
+            TieringPolicy::Access { min_hits } => self
+                .hits
+                .get(&doc_id)
+                .is_some_and(|hits| hits.load(Ordering::Relaxed) < *min_hits),
+        }
+    }
+
+    pub fn record_hits(&self, doc_ids: impl Iterator<Item = DocId>) {
+        for doc_id in doc_ids {
+            if let Some(count) = self.hits.get(&doc_id) {
+                count.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    // Start counting hits afresh for the documents that stayed hot
+    pub fn reset_hits(&mut self, doc_ids: impl Iterator<Item = DocId>) {
+        self.hits = doc_ids.map(|doc_id| (doc_id, AtomicU64::new(0))).collect();
+    }
+
+    // An in-memory engine over the segment's documents, loaded on first use
+    pub fn segment_engine(
+        &self,
+        segment: &SegmentMeta,
+        storage: &Storage,
+        settings: &IndexSettings,
+        similarity: &Arc<dyn Similarity>,
+    ) -> Result<Arc<SearchEngine>, MSErrors> {
+        if let Some(entry) = self
+            .loaded
+            .read()
+            .unwrap()
+            .iter()
+            .find(|e| e.id == segment.id)
+        {
+            entry.last_used.store(self.tick(), Ordering::Relaxed);
+            return Ok(entry.engine.clone());
+        }
+
+        // Read without holding the lock, so searches of loaded segments go on;
+        // scored like the hot documents, so that scores merge across tiers
+        let mut engine = SearchEngine::new(settings.clone())?;
+        engine.similarity = similarity.clone();
+        for stored in storage.load_segment_documents(segment)? {
+            engine.add_stored_document(stored)?;
+        }
+        let mut loaded = self.loaded.write().unwrap();
+        if let Some(entry) = loaded.iter().find(|e| e.id == segment.id) {
+            // Another search loaded it meanwhile
+            return Ok(entry.engine.clone());
+        }
+        let engine = Arc::new(engine);
+        loaded.push(LoadedSegment {
+            id: segment.id,
+            engine: engine.clone(),
+            last_used: AtomicU64::new(self.tick()),
+        });
+        if loaded.len() > MAX_LOADED_SEGMENTS
+            && let Some(oldest) =
+                (0..loaded.len()).min_by_key(|&i| loaded[i].last_used.load(Ordering::Relaxed))
+        {
+            loaded.remove(oldest);
+        }
+        Ok(engine)
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /*
+    Load the most recent segments, as many as are kept loaded, so the
+    first queries do not wait on the disk. Segments that cannot be read
+    are skipped.
+    */
+    pub fn preload(
+        &self,
+        storage: &Storage,
+        settings: &IndexSettings,
+        similarity: &Arc<dyn Similarity>,
+    ) -> Vec<Arc<SearchEngine>> {
+        let first = self.segments.len().saturating_sub(MAX_LOADED_SEGMENTS);
+        self.segments[first..]
+            .iter()
+            .filter_map(|segment| {
+                self.segment_engine(segment, storage, settings, similarity)
+                    .ok()
+            })
+            .collect()
+    }
+
+    // Drop every loaded segment, to be loaded again with new settings
+    pub fn unload(&mut self) {
+        self.loaded.get_mut().unwrap().clear();
+    }
+
+    // Swap in a new segment list, dropping every loaded segment
+    pub fn replace_segments(&mut self, segments: Vec<SegmentMeta>) {
+        self.segments = segments;
+        self.loaded.get_mut().unwrap().clear();
+    }
+
+    pub fn loaded_segments(&self) -> usize {
+        self.loaded.read().unwrap().len()
+    }
+}
@@ -0,0 +1,107 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::stream::Ranked;
+
+/*
+Score candidates in decreasing order of their score upper bound, keeping
+the best `k`, and stop as soon as the rest are unlikely to matter.
+
+Once `k` hits are held, only candidates whose bound beats the k-th score
+could still enter. If there are none, the scan stops with an exact
+result. Otherwise the chance that one of them would enter is estimated
+from how often such candidates did enter so far (with a Laplace prior),
+and the scan stops with an approximate result once that chance is below
+`max_error`. Returns the hits in no particular order, and whether they
+are approximate.
+*/
+pub(crate) fn scan(
+    mut bounds: Vec<(f64, u64)>,
+    k: usize,
+    max_error: f64,
+    mut score: impl FnMut(u64) -> f64,
+) -> (Vec<(u64, f64)>, bool) {
+    bounds.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+    let mut top: BinaryHeap<Reverse<Ranked>> = BinaryHeap::with_capacity(k + 1);
+    let (mut tries, mut entered) = (0u32, 0u32);
+    let mut approximate = false;
+    for (i, &(_, doc_id)) in bounds.iter().enumerate() {
+        if k == 0 {
+            break;
+        }
+        let kth = (top.len() == k).then(|| top.peek().unwrap().0.0);
+        if let Some(kth) = kth {
+            let contenders = bounds[i..].partition_point(|&(bound, _)| bound > kth);
+            if contenders == 0 {
+                break;
+            }
+            let rate = f64::from(entered + 1) / f64::from(tries + 2);
+            if 1.0 - (1.0 - rate).powi(contenders as i32) < max_error {
+                approximate = true;
+                break;
+            }
+        }
+
+        let hit = Ranked(score(doc_id), doc_id);
+        if hit.0 <= 0.0 {
+            continue;
+        }
+        if let Some(kth) = top.peek().filter(|_| kth.is_some()) {
+            tries += 1;
+            if hit > kth.0 {
+                entered += 1;
+            }
+        }
+        top.push(Reverse(hit));
+        if top.len() > k {
+            top.pop();
+        }
+    }
+    let hits = top
+        .into_iter()
+        .map(|Reverse(Ranked(score, doc_id))| (doc_id, score))
+        .collect();
+    (hits, approximate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut hits: Vec<(u64, f64)>) -> Vec<u64> {
+        hits.sort_by(|a, b| b.1.total_cmp(&a.1));
+        hits.into_iter().map(|(doc_id, _)| doc_id).collect()
+    }
+
+    #[test]
+    fn test_exact_stop() {
+        // Bounds are tight enough to prove the top 2 after three candidates
+        let bounds = vec![(10.0, 1), (9.0, 2), (8.0, 3), (2.0, 4), (1.0, 5)];
+        let mut scored = Vec::new();
+        let (hits, approximate) = scan(bounds, 2, 0.01, |doc_id| {
+            scored.push(doc_id);
+            [0.0, 9.0, 3.0, 7.0, 2.0, 1.0][doc_id as usize]
+        });
+        assert_eq!(sorted(hits), vec![1, 3]);
+        assert!(!approximate);
+        assert_eq!(scored, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_approximate_stop() {
+        // Loose bounds: every later candidate scores lower than the first ones
+        let bounds = (1..=1000).map(|doc_id| (100.0, doc_id)).collect();
+        let mut scored = 0;
+        let (hits, approximate) = scan(bounds, 3, 0.05, |doc_id| {
+            scored += 1;
+            1.0 / doc_id as f64
+        });
+        assert_eq!(sorted(hits), vec![1, 2, 3]);
+        assert!(approximate);
+        assert!(scored < 1000);
+
+        let (hits, approximate) = scan(vec![(1.0, 1)], 3, 0.05, |_| 1.0);
+        assert_eq!(hits, vec![(1, 1.0)]);
+        assert!(!approximate);
+    }
+}
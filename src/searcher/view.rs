@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+
+use crate::highlight::Fragment;
+
+// One page of results, shaped for rendering in terminal or web frontends
+#[derive(Debug, Clone)]
+pub struct ResultPage {
+    pub query: String,
+    pub page: usize, // 0-based
+    pub page_size: usize,
+    pub total_matches: usize,
+    pub query_time_ms: u64,
+    pub hits: Vec<HitView>,
+    pub facets: HashMap<String, HashMap<String, usize>>, // field -> value -> count
+}
+
+impl ResultPage {
+    pub fn total_pages(&self) -> usize {
+        self.total_matches.div_ceil(self.page_size.max(1))
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.page < self.total_pages().saturating_sub(1)
+    }
+
+    pub fn has_previous(&self) -> bool {
+        self.page > 0
+    }
+}
+
+// A single hit with pre-highlighted text
+#[derive(Debug, Clone)]
+pub struct HitView {
+    pub doc_id: u64,
+    pub rank: usize, // 1-based position across all pages
+    pub score: f64,
+    pub title: String,
+    pub snippet: Vec<Fragment>, // Short window around the first match
+    pub preview: Vec<Fragment>, // Full content with every match highlighted
+    pub metadata: Vec<(String, String)>, // Sorted by key
+}
@@ -0,0 +1,658 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use serde_json::{Map, Value, json};
+
+use crate::document::Document;
+use crate::errors::MSErrors;
+use crate::highlight::Fragment;
+use crate::searcher::{Filter, ResultPage, SearchEngine, SearchOptions, SearchResults};
+
+// Demo search page, served at `/`
+const INDEX_HTML: &str = include_str!("index.html");
+// Hits per page when the request does not say
+const DEFAULT_PAGE_SIZE: usize = 10;
+const MAX_PAGE_SIZE: usize = 100;
+// How long a client may take to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+// Largest request body accepted, in bytes
+const MAX_BODY_LEN: usize = 16 * 1024 * 1024;
+// Longest a writable server keeps writes in memory only, checked on each request
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json(status: u16, value: Value) -> Self {
+        Response {
+            status,
+            content_type: "application/json",
+            body: value.to_string(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Response::json(status, json!({ "error": message }))
+    }
+
+    // Engine errors carry their stable code for clients to match on
+    fn engine_error(error: &MSErrors) -> Self {
+        let status = match error {
+            MSErrors::DocumentNotFound => 404,
+            MSErrors::DuplicateDocument { .. } => 409,
+            MSErrors::IndexingError(_)
+            | MSErrors::InvalidField { .. }
+            | MSErrors::InvalidTerm { .. }
+            | MSErrors::QueryParseError { .. }
+            | MSErrors::SchemaMismatch { .. }
+            | MSErrors::ParseError(_)
+            | MSErrors::Json(_) => 400,
+            MSErrors::Busy(_) => 503,
+            _ => 500,
+        };
+        Response::json(
+            status,
+            json!({ "error": error.to_string(), "code": error.code() }),
+        )
+    }
+}
+
+// Read-only for the demo, or writable for the standalone service
+enum EngineRef<'a> {
+    Shared(&'a SearchEngine),
+    Exclusive(&'a mut SearchEngine),
+}
+
+impl EngineRef<'_> {
+    fn get(&self) -> &SearchEngine {
+        match self {
+            EngineRef::Shared(engine) => engine,
+            EngineRef::Exclusive(engine) => engine,
+        }
+    }
+}
+
+/*
+Minimal HTTP front end for an engine, meant for demos and end-to-end
+checks rather than production. Requests are handled one at a time.
+
+  GET /                    the embedded search page
+  GET /api/search          q, page, size, facet (repeatable), filter=field:value (repeatable)
+  GET /api/documents/{id}  one stored document
+  GET /search              q, limit; the raw `SearchResults`
+
+A writable server also accepts the following. Flushing a persistent
+engine rewrites its documents file, so writes are flushed together on
+the first request 5 seconds after the last flush, or on `POST /commit`:
+
+  PUT /docs                a JSON document {id, title, content, metadata}, added
+                           or replaced; without an id the next free one is used
+  DELETE /docs/{id}        remove a document
+  POST /commit             flush the writes made so far
+
+`facets` are the metadata fields counted when a search names none.
+*/
+pub struct Server<'a> {
+    engine: EngineRef<'a>,
+    facets: Vec<String>,
+    last_flush: Instant,
+}
+
+impl<'a> Server<'a> {
+    pub fn new(engine: &'a SearchEngine, facets: Vec<String>) -> Self {
+        Server {
+            engine: EngineRef::Shared(engine),
+            facets,
+            last_flush: Instant::now(),
+        }
+    }
+
+    // A server that also accepts document updates
+    pub fn writable(engine: &'a mut SearchEngine, facets: Vec<String>) -> Self {
+        Server {
+            engine: EngineRef::Exclusive(engine),
+            facets,
+            last_flush: Instant::now(),
+        }
+    }
+
+    // Accept connections until the listener fails
+    pub fn serve(&mut self, address: impl ToSocketAddrs) -> io::Result<()> {
+        let listener = TcpListener::bind(address)?;
+        for stream in listener.incoming() {
+            // A broken connection only affects its own client
+            let _ = stream.and_then(|stream| self.handle_connection(stream));
+        }
+        Ok(())
+    }
+
+    fn handle_connection(&mut self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        // Only the body length is needed, but every header must be read
+        let mut content_length = 0;
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+            if let Some((name, value)) = header.split_once(':')
+                && name.trim().eq_ignore_ascii_case("content-length")
+            {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+            header.clear();
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let response = match (parts.next(), parts.next()) {
+            (Some(_), Some(_)) if content_length > MAX_BODY_LEN => {
+                Response::error(413, "request body is too large")
+            }
+            (Some(method), Some(target)) => {
+                let mut body = Vec::with_capacity(content_length);
+                (&mut reader)
+                    .take(content_length as u64)
+                    .read_to_end(&mut body)?;
+                match String::from_utf8(body) {
+                    Ok(body) => self.handle(method, target, &body),
+                    Err(_) => Response::error(400, "request body is not UTF-8"),
+                }
+            }
+            _ => Response::error(400, "malformed request"),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            response.status,
+            reason(response.status),
+            response.content_type,
+            response.body.len()
+        )?;
+        stream.write_all(response.body.as_bytes())?;
+        stream.flush()
+    }
+
+    fn handle(&mut self, method: &str, target: &str, body: &str) -> Response {
+        let path = target.split_once('?').map_or(target, |(path, _)| path);
+        if method == "POST" && path == "/commit" {
+            return match self.flush() {
+                Some(Ok(())) => Response::json(200, json!({ "flushed": true })),
+                Some(Err(e)) => Response::engine_error(&e),
+                None => Response::error(405, "this server is read-only"),
+            };
+        }
+        let response = self.respond(method, target, path, body);
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL
+            && let Some(Err(e)) = self.flush()
+        {
+            return Response::engine_error(&e);
+        }
+        response
+    }
+
+    // Flush a writable engine, None if the server is read-only
+    fn flush(&mut self) -> Option<Result<(), MSErrors>> {
+        let EngineRef::Exclusive(engine) = &mut self.engine else {
+            return None;
+        };
+        let flushed = engine.flush();
+        if flushed.is_ok() {
+            self.last_flush = Instant::now();
+        }
+        Some(flushed)
+    }
+
+    fn respond(&mut self, method: &str, target: &str, path: &str, body: &str) -> Response {
+        match (method, &mut self.engine) {
+            ("GET", EngineRef::Exclusive(engine)) => {
+                // Searches cannot commit, so a quiet index refreshes here;
+                // failed additions are dropped as in `commit`
+                let _ = engine.commit_if_due();
+                self.route(target)
+            }
+            ("GET", _) => self.route(target),
+            ("PUT" | "DELETE" | "POST", EngineRef::Shared(_)) => {
+                Response::error(405, "this server is read-only")
+            }
+            ("PUT", EngineRef::Exclusive(engine)) if path == "/docs" => put_document(engine, body),
+            ("DELETE", EngineRef::Exclusive(engine)) => match path.strip_prefix("/docs/") {
+                Some(id) => delete_document(engine, id),
+                None => Response::error(404, "not found"),
+            },
+            ("PUT" | "POST", _) => Response::error(404, "not found"),
+            _ => Response::error(405, "unsupported method"),
+        }
+    }
+
+    fn route(&self, target: &str) -> Response {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let params = parse_query_string(query);
+        match path {
+            "/" | "/index.html" => Response {
+                status: 200,
+                content_type: "text/html",
+                body: INDEX_HTML.to_string(),
+            },
+            "/api/search" => self.search(&params),
+            "/search" => self.raw_search(&params),
+            _ => match path.strip_prefix("/api/documents/") {
+                Some(id) => self.document(id),
+                None => Response::error(404, "not found"),
+            },
+        }
+    }
+
+    fn search(&self, params: &[(String, String)]) -> Response {
+        let number = |name: &str, default: usize| match values(params, name).next() {
+            Some(value) => value
+                .parse::<usize>()
+                .map_err(|_| Response::error(400, &format!("\"{}\" must be a number", name))),
+            None => Ok(default),
+        };
+        let (page, size) = match (number("page", 0), number("size", DEFAULT_PAGE_SIZE)) {
+            (Ok(page), Ok(size)) => (page, size.clamp(1, MAX_PAGE_SIZE)),
+            (Err(response), _) | (_, Err(response)) => return response,
+        };
+
+        let Some(offset) = page.checked_mul(size) else {
+            return Response::error(400, "\"page\" is too large");
+        };
+        let mut options = SearchOptions::new(size).offset(offset);
+        let mut facets: Vec<&str> = values(params, "facet").collect();
+        if facets.is_empty() {
+            facets = self.facets.iter().map(String::as_str).collect();
+        }
+        for field in facets {
+            options = options.facet(field);
+        }
+        for filter in values(params, "filter") {
+            let Some((field, value)) = filter.split_once(':') else {
+                return Response::error(400, "filters are written field:value");
+            };
+            options = options.filter(Filter::eq(field, value));
+        }
+
+        let query = values(params, "q").next().unwrap_or_default();
+        if let Err(e) = self.engine.get().check_query(query) {
+            return Response::engine_error(&e);
+        }
+        let page = self.engine.get().search_page_with_options(query, &options);
+        Response::json(200, page_json(&page))
+    }
+
+    fn raw_search(&self, params: &[(String, String)]) -> Response {
+        let limit = match values(params, "limit").next().map(str::parse::<usize>) {
+            Some(Ok(limit)) => limit.min(MAX_PAGE_SIZE),
+            Some(Err(_)) => return Response::error(400, "\"limit\" must be a number"),
+            None => DEFAULT_PAGE_SIZE,
+        };
+        let query = values(params, "q").next().unwrap_or_default();
+        if let Err(e) = self.engine.get().check_query(query) {
+            return Response::engine_error(&e);
+        }
+        let results = self.engine.get().search(query, limit);
+        Response::json(200, results_json(&results))
+    }
+
+    fn document(&self, id: &str) -> Response {
+        let Ok(id) = id.parse() else {
+            return Response::error(400, "document ids are numbers");
+        };
+        match self.engine.get().get_document(id) {
+            Some(document) => Response::json(200, document_json(document)),
+            None => Response::error(404, "no such document"),
+        }
+    }
+}
+
+// Add or replace a document, keeping the old one if the new one is rejected
+fn put_document(engine: &mut SearchEngine, body: &str) -> Response {
+    let (id, mut document) = match parse_document(body) {
+        Ok(fields) => fields,
+        Err(message) => return Response::error(400, &message),
+    };
+    let id = id.unwrap_or_else(|| engine.next_document_id());
+    document.id = id;
+    let replaced = engine.remove_document(id);
+    if let Err(e) = engine.add_document(document) {
+        if let Some(old) = replaced {
+            let _ = engine.add_document(old);
+        }
+        return Response::engine_error(&e);
+    }
+    let status = if replaced.is_some() { 200 } else { 201 };
+    Response::json(status, json!({ "id": id, "replaced": replaced.is_some() }))
+}
+
+fn delete_document(engine: &mut SearchEngine, id: &str) -> Response {
+    let Ok(id) = id.parse() else {
+        return Response::error(400, "document ids are numbers");
+    };
+    if engine.remove_document(id).is_none() {
+        return Response::error(404, "no such document");
+    }
+    Response::json(200, json!({ "id": id, "deleted": true }))
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+// Decode `key=value&...` pairs, with `+` and %XX escapes
+fn parse_query_string(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+// Every value given for `name`, in request order
+fn values<'a>(params: &'a [(String, String)], name: &'a str) -> impl Iterator<Item = &'a str> {
+    params
+        .iter()
+        .filter(move |(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (_, Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', None) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, None) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn fragments_json(fragments: &[Fragment]) -> Value {
+    fragments
+        .iter()
+        .map(|f| json!({ "text": f.text, "highlighted": f.highlighted }))
+        .collect()
+}
+
+fn page_json(page: &ResultPage) -> Value {
+    let hits: Vec<Value> = page
+        .hits
+        .iter()
+        .map(|hit| {
+            let metadata: Map<String, Value> = hit
+                .metadata
+                .iter()
+                .map(|(key, value)| (key.clone(), json!(value)))
+                .collect();
+            json!({
+                "id": hit.doc_id,
+                "rank": hit.rank,
+                "score": hit.score,
+                "title": hit.title,
+                "snippet": fragments_json(&hit.snippet),
+                "metadata": metadata,
+            })
+        })
+        .collect();
+    // Facet values as [value, count] pairs, most frequent first
+    let facets: Map<String, Value> = page
+        .facets
+        .iter()
+        .map(|(field, counts)| {
+            let mut counts: Vec<(&String, &usize)> = counts.iter().collect();
+            counts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+            (field.clone(), json!(counts))
+        })
+        .collect();
+    json!({
+        "query": page.query,
+        "page": page.page,
+        "page_size": page.page_size,
+        "total_pages": page.total_pages(),
+        "total_matches": page.total_matches,
+        "query_time_ms": page.query_time_ms,
+        "hits": hits,
+        "facets": facets,
+    })
+}
+
+fn results_json(results: &SearchResults) -> Value {
+    let suggestions: Vec<Value> = results
+        .suggestions
+        .iter()
+        .map(|s| {
+            json!({
+                "original": s.original,
+                "suggestion": s.suggestion,
+                "distance": s.distance,
+                "doc_freq": s.doc_freq,
+            })
+        })
+        .collect();
+    json!({
+        "documents": results.documents.iter().map(document_json).collect::<Vec<_>>(),
+        "scores": results.scores,
+        "group_counts": results.group_counts,
+        "total_matches": results.total_matches,
+        "query_time_ms": results.query_time_ms,
+        "facets": results.facets,
+        "suggestions": suggestions,
+        "is_approximate": results.is_approximate,
+    })
+}
+
+/*
+Parse a document written like `document_json` output. Metadata values may
+be any scalar; a missing id is left as `None` for the caller to assign.
+*/
+fn parse_document(body: &str) -> Result<(Option<u64>, Document), String> {
+    let value: Value = serde_json::from_str(body).map_err(|e| format!("invalid JSON: {}", e))?;
+    let Value::Object(object) = value else {
+        return Err("expected a JSON object".to_string());
+    };
+    let text = |name: &str| match object.get(name) {
+        None | Some(Value::Null) => Ok(String::new()),
+        Some(Value::String(text)) => Ok(text.clone()),
+        Some(_) => Err(format!("\"{}\" must be a string", name)),
+    };
+    let id = match object.get("id") {
+        None | Some(Value::Null) => None,
+        Some(id) => Some(id.as_u64().ok_or("\"id\" must be a non-negative integer")?),
+    };
+    let mut metadata = HashMap::new();
+    if let Some(fields) = object.get("metadata") {
+        let fields = fields.as_object().ok_or("\"metadata\" must be an object")?;
+        for (key, value) in fields {
+            let value = match value {
+                Value::String(text) => text.clone(),
+                Value::Number(_) | Value::Bool(_) => value.to_string(),
+                _ => return Err(format!("metadata field {} must be a scalar", key)),
+            };
+            metadata.insert(key.clone(), value);
+        }
+    }
+    let document = Document {
+        id: id.unwrap_or(0),
+        title: text("title")?,
+        content: text("content")?,
+        metadata,
+    };
+    Ok((id, document))
+}
+
+fn document_json(document: &Document) -> Value {
+    json!({
+        "id": document.id,
+        "title": document.title,
+        "content": document.content,
+        "metadata": document.metadata,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::IndexSettings;
+    use std::collections::HashMap;
+
+    fn engine() -> SearchEngine {
+        let mut engine = SearchEngine::new(IndexSettings::default()).unwrap();
+        for (id, title, lang) in [(1, "Rust search", "en"), (2, "Rust Suche", "de")] {
+            engine
+                .add_document(Document {
+                    id,
+                    title: title.to_string(),
+                    content: format!("{} engine", title),
+                    metadata: HashMap::from([("lang".to_string(), lang.to_string())]),
+                })
+                .unwrap();
+        }
+        engine
+    }
+
+    #[test]
+    fn test_parse_query_string() {
+        assert_eq!(
+            parse_query_string("q=rust+search%21&filter=lang%3Aen&bad=%zz&flag"),
+            vec![
+                ("q".to_string(), "rust search!".to_string()),
+                ("filter".to_string(), "lang:en".to_string()),
+                ("bad".to_string(), "%zz".to_string()),
+                ("flag".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_routes() {
+        let engine = engine();
+        let server = Server::new(&engine, vec!["lang".to_string()]);
+
+        let home = server.route("/");
+        assert_eq!(home.content_type, "text/html");
+        assert!(home.body.contains("/api/search"));
+
+        let response = server.route("/api/search?q=rust&size=1");
+        assert_eq!(response.status, 200);
+        let body: Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(body["total_matches"], 2);
+        assert_eq!(body["total_pages"], 2);
+        assert_eq!(body["hits"].as_array().unwrap().len(), 1);
+        assert_eq!(body["facets"]["lang"].as_array().unwrap().len(), 2);
+
+        let response = server.route("/api/search?q=rust&filter=lang:de");
+        let body: Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(body["hits"][0]["id"], 2);
+        assert_eq!(body["hits"][0]["snippet"][0]["highlighted"], true);
+
+        assert_eq!(server.route("/api/search?page=x").status, 400);
+        let huge = format!("/api/search?q=rust&page={}", usize::MAX / 2);
+        assert_eq!(server.route(&huge).status, 400);
+        assert_eq!(server.route("/api/search?filter=lang").status, 400);
+        let response = server.route("/api/search?q=%22rust+book");
+        assert_eq!(response.status, 400);
+        let body: Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(body["code"], 302);
+        let document: Value = serde_json::from_str(&server.route("/api/documents/1").body).unwrap();
+        assert_eq!(document["metadata"]["lang"], "en");
+        assert_eq!(server.route("/api/documents/9").status, 404);
+        assert_eq!(server.route("/missing").status, 404);
+
+        let results: Value =
+            serde_json::from_str(&server.route("/search?q=rust&limit=1").body).unwrap();
+        assert_eq!(results["total_matches"], 2);
+        assert_eq!(results["documents"].as_array().unwrap().len(), 1);
+        assert_eq!(server.route("/search?limit=x").status, 400);
+    }
+
+    #[test]
+    fn test_document_routes() {
+        let mut engine = engine();
+        assert_eq!(
+            Server::new(&engine, Vec::new())
+                .handle("DELETE", "/docs/1", "")
+                .status,
+            405
+        );
+        let mut server = Server::writable(&mut engine, Vec::new());
+
+        let response = server.handle(
+            "PUT",
+            "/docs",
+            r#"{"title": "Tokio", "content": "async rust"}"#,
+        );
+        assert_eq!(response.status, 201);
+        let body: Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(body["id"], 3);
+
+        let replace =
+            r#"{"id": 1, "title": "Replaced", "content": "", "metadata": {"year": 2024}}"#;
+        assert_eq!(server.handle("PUT", "/docs", replace).status, 200);
+        assert_eq!(server.handle("PUT", "/docs", "[1]").status, 400);
+        assert_eq!(server.handle("DELETE", "/docs/2", "").status, 200);
+        assert_eq!(server.handle("DELETE", "/docs/2", "").status, 404);
+
+        let results: Value =
+            serde_json::from_str(&server.handle("GET", "/search?q=rust", "").body).unwrap();
+        assert_eq!(results["documents"][0]["id"], 3);
+        assert_eq!(results["total_matches"], 1);
+        let document = server.engine.get().get_document(1).unwrap();
+        assert_eq!(document.metadata["year"], "2024");
+
+        // Writes to a persistent engine wait for the interval or a commit
+        let dir = std::env::temp_dir().join(format!("mini-search-server-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut engine = SearchEngine::create(&dir, IndexSettings::default()).unwrap();
+        let mut server = Server::writable(&mut engine, Vec::new());
+        let put = r#"{"id": 1, "title": "Rust", "content": "ownership"}"#;
+        assert_eq!(server.handle("PUT", "/docs", put).status, 201);
+        assert_eq!(server.engine.get().health().pending_changes, 1);
+        assert_eq!(server.handle("POST", "/commit", "").status, 200);
+        assert_eq!(server.engine.get().health().pending_changes, 0);
+        server.last_flush -= FLUSH_INTERVAL;
+        assert_eq!(server.handle("DELETE", "/docs/1", "").status, 200);
+        assert_eq!(server.engine.get().health().pending_changes, 0);
+        assert_eq!(SearchEngine::open(&dir).unwrap().document_count(), 0);
+        assert_eq!(
+            Server::new(&engine, Vec::new())
+                .handle("POST", "/commit", "")
+                .status,
+            405
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,692 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::errors::MSErrors;
+use crate::tokenizer::{
+    AnalyzerPreset, BaseTokenizer, LANGUAGE_FIELD, Language, STOP_WORDS_VERSION, StemFilter,
+    StopWordFilter, StopWordStage, Tokenizer,
+};
+
+mod schema;
+
+pub use schema::{FieldDef, FieldType, RAW_SUFFIX, Schema};
+
+// How segments are merged when the index is compacted
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MergePolicy {
+    NoMerge,
+    Log { merge_factor: usize },
+}
+
+impl fmt::Display for MergePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergePolicy::NoMerge => write!(f, "none"),
+            MergePolicy::Log { merge_factor } => write!(f, "log:{}", merge_factor),
+        }
+    }
+}
+
+impl FromStr for MergePolicy {
+    type Err = MSErrors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "none" => Ok(MergePolicy::NoMerge),
+            Some(("log", factor)) => {
+                let merge_factor = factor.parse().map_err(|_| {
+                    MSErrors::ParseError(format!("invalid merge factor: {}", factor))
+                })?;
+                Ok(MergePolicy::Log { merge_factor })
+            }
+            _ => Err(MSErrors::ParseError(format!("unknown merge policy: {}", s))),
+        }
+    }
+}
+
+// How corpus statistics used for scoring are maintained
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum StatisticsMode {
+    #[default]
+    Exact,
+    Approximate, // Sketched document frequencies and sampled lengths
+}
+
+impl fmt::Display for StatisticsMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatisticsMode::Exact => write!(f, "exact"),
+            StatisticsMode::Approximate => write!(f, "approximate"),
+        }
+    }
+}
+
+impl FromStr for StatisticsMode {
+    type Err = MSErrors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(StatisticsMode::Exact),
+            "approximate" => Ok(StatisticsMode::Approximate),
+            _ => Err(MSErrors::ParseError(format!(
+                "unknown statistics mode: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/*
+What postings record for each term of a document. Term frequencies let
+BM25 weigh repeated words; positions and offsets are needed for phrase
+proximity, field boosts and rebuilding unstored content, and take up most
+of the index.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum IndexRecordOption {
+    DocsOnly,     // Every matching document scores as one occurrence
+    DocsAndFreqs, // Occurrence counts without positions
+    #[default]
+    DocsAndFreqsAndPositions,
+}
+
+impl IndexRecordOption {
+    pub fn has_freqs(&self) -> bool {
+        *self != IndexRecordOption::DocsOnly
+    }
+
+    pub fn has_positions(&self) -> bool {
+        *self == IndexRecordOption::DocsAndFreqsAndPositions
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndexRecordOption::DocsOnly => "docs",
+            IndexRecordOption::DocsAndFreqs => "freqs",
+            IndexRecordOption::DocsAndFreqsAndPositions => "positions",
+        }
+    }
+}
+
+impl fmt::Display for IndexRecordOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for IndexRecordOption {
+    type Err = MSErrors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "docs" => Ok(IndexRecordOption::DocsOnly),
+            "freqs" => Ok(IndexRecordOption::DocsAndFreqs),
+            "positions" => Ok(IndexRecordOption::DocsAndFreqsAndPositions),
+            _ => Err(MSErrors::ParseError(format!(
+                "unknown index record option: {}",
+                s
+            ))),
+        }
+    }
+}
+
+// Settings persisted alongside an index
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct IndexSettings {
+    pub analyzer: AnalyzerPreset,      // Analysis chain, fixed at creation
+    pub language: Language,            // Analyzer language, fixed at creation
+    pub k1: f64,                       // BM25 term frequency saturation
+    pub b: f64,                        // BM25 length normalization
+    pub merge_policy: MergePolicy,     // How `compact` merges cold segments
+    pub cache_size: usize,             // Most filters the filter cache tracks, 0 to cache none
+    pub title_boost: f64,              // Index-time weight of title terms
+    pub content_boost: f64,            // Index-time weight of content terms
+    pub statistics: StatisticsMode,    // Scoring statistics, fixed at creation
+    pub routing_field: Option<String>, // Metadata field segments are partitioned by
+    pub schema: Schema,                // Metadata field types, fixed at creation
+    pub record: IndexRecordOption,     // Postings detail of title and content, fixed at creation
+    pub token_pattern: Option<String>, // Regex of the words of the `pattern` analyzer
+    pub classify_tokens: bool,         // Keep URLs, emails, @mentions and #hashtags whole
+    pub keep_digits: bool, // Keep digits in the words of letter-only analyzers, fixed at creation
+    pub keep_compounds: bool, // Keep "x86_64" or "3.14" whole instead of splitting at '_' and '.'
+    pub stop_words: StopWordStage, // Where the `english` analyzer removes stop words, fixed at creation
+    pub stop_words_version: u32,   // Version of the built-in stop lists, fixed at creation
+    pub detect_language: bool, // Analyze each document in its detected language, fixed at creation
+}
+
+impl Default for IndexSettings {
+    fn default() -> Self {
+        IndexSettings {
+            analyzer: AnalyzerPreset::English,
+            language: Language::English,
+            k1: 1.5,
+            b: 0.75,
+            merge_policy: MergePolicy::Log { merge_factor: 10 },
+            cache_size: 1024,
+            title_boost: 1.0,
+            content_boost: 1.0,
+            statistics: StatisticsMode::Exact,
+            routing_field: None,
+            schema: Schema::default(),
+            record: IndexRecordOption::default(),
+            token_pattern: None,
+            classify_tokens: false,
+            keep_digits: false,
+            keep_compounds: false,
+            stop_words: StopWordStage::default(),
+            stop_words_version: STOP_WORDS_VERSION,
+            detect_language: false,
+        }
+    }
+}
+
+// The subset of settings that can change without reindexing
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SettingsUpdate {
+    pub k1: Option<f64>,
+    pub b: Option<f64>,
+    pub merge_policy: Option<MergePolicy>,
+    pub cache_size: Option<usize>,
+}
+
+impl IndexSettings {
+    // Apply a runtime update, validating the new values first
+    pub fn apply(&mut self, update: &SettingsUpdate) -> Result<(), MSErrors> {
+        let mut updated = self.clone();
+        if let Some(k1) = update.k1 {
+            updated.k1 = k1;
+        }
+        if let Some(b) = update.b {
+            updated.b = b;
+        }
+        if let Some(merge_policy) = update.merge_policy {
+            updated.merge_policy = merge_policy;
+        }
+        if let Some(cache_size) = update.cache_size {
+            updated.cache_size = cache_size;
+        }
+        updated.validate()?;
+        *self = updated;
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), MSErrors> {
+        if !(self.k1.is_finite() && self.k1 >= 0.0) {
+            return Err(MSErrors::ParseError(format!(
+                "k1 must be >= 0, got {}",
+                self.k1
+            )));
+        }
+        if !(0.0..=1.0).contains(&self.b) {
+            return Err(MSErrors::ParseError(format!(
+                "b must be in [0, 1], got {}",
+                self.b
+            )));
+        }
+        for (name, boost) in [
+            ("title_boost", self.title_boost),
+            ("content_boost", self.content_boost),
+        ] {
+            if !(boost.is_finite() && boost > 0.0) {
+                return Err(MSErrors::ParseError(format!(
+                    "{} must be > 0, got {}",
+                    name, boost
+                )));
+            }
+        }
+        if let MergePolicy::Log { merge_factor } = self.merge_policy
+            && merge_factor < 2
+        {
+            return Err(MSErrors::ParseError(format!(
+                "merge factor must be >= 2, got {}",
+                merge_factor
+            )));
+        }
+        if let Some(pattern) = &self.token_pattern {
+            BaseTokenizer::matches(pattern)?;
+        }
+        StopWordFilter::versioned(self.language, self.stop_words_version)?;
+        // Fail on open rather than index every document unstemmed
+        for (preset, language) in self.analyzers() {
+            if preset == AnalyzerPreset::English {
+                StemFilter::new(language)?;
+            }
+        }
+        // Detected languages are stored in metadata, which the schema must allow
+        if self.detect_language {
+            match self.schema.get(LANGUAGE_FIELD) {
+                Some(field) if !field.field_type.accepts(Language::English.as_str()) => {
+                    return Err(MSErrors::ParseError(format!(
+                        "detected languages are stored in {}, which is a {} field",
+                        LANGUAGE_FIELD,
+                        field.field_type.as_str()
+                    )));
+                }
+                None if self.schema.strict => {
+                    return Err(MSErrors::ParseError(format!(
+                        "detecting languages needs a {} field in a strict schema",
+                        LANGUAGE_FIELD
+                    )));
+                }
+                _ => {}
+            }
+        }
+        // Field boosts and unstored content are resolved through positions
+        if !self.record.has_positions() {
+            if self.title_boost != 1.0 || self.content_boost != 1.0 {
+                return Err(MSErrors::ParseError(format!(
+                    "title and content boosts need positions, but record is {}",
+                    self.record
+                )));
+            }
+            if !self.schema.store_content {
+                return Err(MSErrors::ParseError(format!(
+                    "unstored content needs positions, but record is {}",
+                    self.record
+                )));
+            }
+        }
+        self.schema.validate()
+    }
+
+    // Every analyzer the engine builds: the index's, those of fields and those of detected languages
+    fn analyzers(&self) -> Vec<(AnalyzerPreset, Language)> {
+        let mut analyzers = vec![(self.analyzer, self.language)];
+        for field in &self.schema.fields {
+            for preset in [field.analyzer, field.search_analyzer]
+                .into_iter()
+                .flatten()
+            {
+                analyzers.push((preset, self.language));
+            }
+        }
+        if self.detect_language {
+            analyzers.extend(
+                Language::ALL
+                    .into_iter()
+                    .map(|language| (self.analyzer, language)),
+            );
+        }
+        analyzers
+    }
+
+    // The analysis chain of `preset` in this index's language and token options
+    pub fn tokenizer(&self, preset: AnalyzerPreset) -> Result<Tokenizer, MSErrors> {
+        self.tokenizer_in(preset, self.language)
+    }
+
+    // Same, in another language, for documents detected to be written in it
+    pub fn tokenizer_in(
+        &self,
+        preset: AnalyzerPreset,
+        language: Language,
+    ) -> Result<Tokenizer, MSErrors> {
+        let mut tokenizer = Tokenizer::with_preset_and_stop_words(
+            preset,
+            language,
+            self.stop_words,
+            self.stop_words_version,
+        )?;
+        if self.classify_tokens {
+            tokenizer = tokenizer.with_classifier();
+        }
+        /*
+        Letter-only analyzers such as `english` drop digits unless asked to
+        keep them; keeping compounds keeps their digits too. Indexes built
+        without either must be reindexed for them to apply.
+        */
+        let letters = matches!(tokenizer.base(), BaseTokenizer::Letter);
+        if self.keep_compounds
+            && (letters || matches!(tokenizer.base(), BaseTokenizer::Alphanumeric))
+        {
+            return Ok(tokenizer.with_base(BaseTokenizer::Compound));
+        }
+        if self.keep_digits && letters {
+            return Ok(tokenizer.with_base(BaseTokenizer::Alphanumeric));
+        }
+        match &self.token_pattern {
+            Some(pattern) if preset == AnalyzerPreset::Pattern => {
+                Ok(tokenizer.with_base(BaseTokenizer::matches(pattern)?))
+            }
+            _ => Ok(tokenizer),
+        }
+    }
+}
+
+/*
+Settings are stored as one `key = value` pair per line.
+Blank lines and lines starting with '#' are ignored, unknown keys are rejected.
+*/
+impl fmt::Display for IndexSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "analyzer = {}", self.analyzer.as_str())?;
+        writeln!(f, "language = {}", self.language.as_str())?;
+        writeln!(f, "k1 = {}", self.k1)?;
+        writeln!(f, "b = {}", self.b)?;
+        writeln!(f, "merge_policy = {}", self.merge_policy)?;
+        writeln!(f, "cache_size = {}", self.cache_size)?;
+        writeln!(f, "title_boost = {}", self.title_boost)?;
+        writeln!(f, "content_boost = {}", self.content_boost)?;
+        writeln!(f, "statistics = {}", self.statistics)?;
+        if let Some(field) = &self.routing_field {
+            writeln!(f, "routing_field = {}", field)?;
+        }
+        if self.record != IndexRecordOption::default() {
+            writeln!(f, "record = {}", self.record)?;
+        }
+        if let Some(pattern) = &self.token_pattern {
+            writeln!(f, "token_pattern = {}", pattern)?;
+        }
+        if self.classify_tokens {
+            writeln!(f, "classify_tokens = true")?;
+        }
+        if self.keep_digits {
+            writeln!(f, "keep_digits = true")?;
+        }
+        if self.keep_compounds {
+            writeln!(f, "keep_compounds = true")?;
+        }
+        if self.stop_words != StopWordStage::default() {
+            writeln!(f, "stop_words = {}", self.stop_words.as_str())?;
+        }
+        writeln!(f, "stop_words_version = {}", self.stop_words_version)?;
+        if self.detect_language {
+            writeln!(f, "detect_language = true")?;
+        }
+        if self.schema.strict {
+            writeln!(f, "strict_schema = true")?;
+        }
+        if !self.schema.store_content {
+            writeln!(f, "store_content = false")?;
+        }
+        if self.schema.raw_fields {
+            writeln!(f, "raw_fields = true")?;
+        }
+        for field in &self.schema.fields {
+            writeln!(f, "field.{} = {}", field.name, field)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for IndexSettings {
+    type Err = MSErrors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut settings = IndexSettings {
+            stop_words_version: 1, // Settings files from before stop lists were versioned
+            ..IndexSettings::default()
+        };
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| MSErrors::ParseError(format!("invalid settings line: {}", line)))?;
+            let invalid = || MSErrors::ParseError(format!("invalid value for {}: {}", key, value));
+            match key {
+                "analyzer" => settings.analyzer = value.parse()?,
+                "language" => settings.language = value.parse()?,
+                "k1" => settings.k1 = value.parse().map_err(|_| invalid())?,
+                "b" => settings.b = value.parse().map_err(|_| invalid())?,
+                "merge_policy" => settings.merge_policy = value.parse()?,
+                "cache_size" => settings.cache_size = value.parse().map_err(|_| invalid())?,
+                "title_boost" => settings.title_boost = value.parse().map_err(|_| invalid())?,
+                "content_boost" => settings.content_boost = value.parse().map_err(|_| invalid())?,
+                "statistics" => settings.statistics = value.parse()?,
+                "routing_field" => settings.routing_field = Some(value.to_string()),
+                /*
+                Written by older builds. The term dictionary is always
+                sorted now, so only the ordered mode can still be asked for.
+                */
+                "ordered_index" => {
+                    if !value.parse::<bool>().map_err(|_| invalid())? {
+                        return Err(MSErrors::ParseError(
+                            "ordered_index = false is no longer supported, the term dictionary is always sorted"
+                                .to_string(),
+                        ));
+                    }
+                }
+                "record" => settings.record = value.parse()?,
+                "token_pattern" => settings.token_pattern = Some(value.to_string()),
+                "classify_tokens" => {
+                    settings.classify_tokens = value.parse().map_err(|_| invalid())?
+                }
+                "keep_digits" => settings.keep_digits = value.parse().map_err(|_| invalid())?,
+                "keep_compounds" => {
+                    settings.keep_compounds = value.parse().map_err(|_| invalid())?
+                }
+                "stop_words" => settings.stop_words = value.parse()?,
+                "stop_words_version" => {
+                    settings.stop_words_version = value.parse().map_err(|_| invalid())?
+                }
+                "detect_language" => {
+                    settings.detect_language = value.parse().map_err(|_| invalid())?
+                }
+                "strict_schema" => settings.schema.strict = value.parse().map_err(|_| invalid())?,
+                "store_content" => {
+                    settings.schema.store_content = value.parse().map_err(|_| invalid())?
+                }
+                "raw_fields" => {
+                    settings.schema.raw_fields = value.parse().map_err(|_| invalid())?
+                }
+                _ if key.starts_with("field.") => settings.schema.parse_field(&key[6..], value)?,
+                _ => return Err(MSErrors::ParseError(format!("unknown setting: {}", key))),
+            }
+        }
+        settings.validate()?;
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Settings written to a settings file read back unchanged
+    fn assert_round_trip(settings: &IndexSettings) {
+        let parsed: IndexSettings = settings.to_string().parse().unwrap();
+        assert_eq!(&parsed, settings);
+    }
+
+    // Terms of `text` under the settings' own analyzer
+    fn terms(settings: &IndexSettings, text: &str) -> Vec<String> {
+        settings
+            .tokenizer(settings.analyzer)
+            .unwrap()
+            .tokenize(text)
+            .into_iter()
+            .map(|t| t.term)
+            .collect()
+    }
+
+    #[test]
+    fn test_settings_round_trip() {
+        let settings = IndexSettings {
+            k1: 1.2,
+            b: 0.5,
+            merge_policy: MergePolicy::NoMerge,
+            cache_size: 64,
+            ..IndexSettings::default()
+        };
+        let parsed: IndexSettings = settings.to_string().parse().unwrap();
+        assert_eq!(parsed, settings);
+    }
+
+    #[test]
+    fn test_analyzer_and_boost_settings() {
+        assert_round_trip(&IndexSettings {
+            title_boost: 2.5,
+            analyzer: AnalyzerPreset::Whitespace,
+            ..IndexSettings::default()
+        });
+    }
+
+    #[test]
+    fn test_statistics_setting() {
+        assert_round_trip(&IndexSettings {
+            statistics: StatisticsMode::Approximate,
+            ..IndexSettings::default()
+        });
+    }
+
+    #[test]
+    fn test_routing_field_setting() {
+        assert_round_trip(&IndexSettings {
+            routing_field: Some("tenant".to_string()),
+            ..IndexSettings::default()
+        });
+    }
+
+    #[test]
+    fn test_schema_setting() {
+        assert_round_trip(&IndexSettings {
+            schema: Schema::new()
+                .field(FieldDef::new("price", FieldType::Numeric).indexed(false))
+                .field(FieldDef::new("body", FieldType::Text).analyzer(AnalyzerPreset::Simple))
+                .strict(true)
+                .store_content(false)
+                .raw_fields(true),
+            ..IndexSettings::default()
+        });
+    }
+
+    #[test]
+    fn test_ordered_index_setting() {
+        // Settings files from older builds still open
+        assert!("ordered_index = true".parse::<IndexSettings>().is_ok());
+        assert!("ordered_index = false".parse::<IndexSettings>().is_err());
+        assert!("ordered_index = yes".parse::<IndexSettings>().is_err());
+    }
+
+    #[test]
+    fn test_stop_words_version_setting() {
+        // Settings files from older builds keep the stop list they were built with
+        let old: IndexSettings = "k1 = 1.2".parse().unwrap();
+        assert_eq!(old.stop_words_version, 1);
+        assert_eq!(
+            IndexSettings::default().stop_words_version,
+            STOP_WORDS_VERSION
+        );
+        assert!("stop_words_version = 9".parse::<IndexSettings>().is_err());
+        assert_round_trip(&IndexSettings::default());
+    }
+
+    #[test]
+    fn test_record_setting() {
+        let settings = IndexSettings {
+            record: IndexRecordOption::DocsAndFreqs,
+            ..IndexSettings::default()
+        };
+        assert!(settings.to_string().contains("record = freqs\n"));
+        assert_round_trip(&settings);
+    }
+
+    #[test]
+    fn test_token_pattern_setting() {
+        let settings: IndexSettings = "analyzer = pattern\ntoken_pattern = [a-z]+-\\d+"
+            .parse()
+            .unwrap();
+        assert_eq!(terms(&settings, "Fixes ABC-12 and abc-7"), ["abc-7"]);
+        assert_round_trip(&settings);
+    }
+
+    #[test]
+    fn test_keep_compounds_setting() {
+        let settings = IndexSettings {
+            keep_compounds: true,
+            ..IndexSettings::default()
+        };
+        assert_eq!(
+            terms(&settings, "Build x86_64 with pi 3.14."),
+            ["build", "x86_64", "pi", "3.14"]
+        );
+        assert_round_trip(&settings);
+    }
+
+    #[test]
+    fn test_stop_words_setting() {
+        assert_round_trip(&IndexSettings {
+            stop_words: StopWordStage::Both,
+            ..IndexSettings::default()
+        });
+    }
+
+    #[test]
+    fn test_detect_language_setting() {
+        assert_round_trip(&IndexSettings {
+            detect_language: true,
+            ..IndexSettings::default()
+        });
+    }
+
+    #[test]
+    fn test_keep_digits_setting() {
+        // Digits are dropped by the english analyzer unless kept
+        let text = "error 404 on ipv6";
+        assert_eq!(terms(&IndexSettings::default(), text), ["error", "ipv"]);
+        let settings = IndexSettings {
+            keep_digits: true,
+            ..IndexSettings::default()
+        };
+        assert_eq!(terms(&settings, text), ["error", "404", "ipv6"]);
+        assert_round_trip(&settings);
+    }
+
+    #[test]
+    fn test_settings_parse_errors() {
+        assert!("k1 = fast".parse::<IndexSettings>().is_err());
+        assert!("colour = blue".parse::<IndexSettings>().is_err());
+        assert!("merge_policy = log:1".parse::<IndexSettings>().is_err());
+        assert!("title_boost = 0".parse::<IndexSettings>().is_err());
+        assert!("record = everything".parse::<IndexSettings>().is_err());
+        assert!("token_pattern = [a-".parse::<IndexSettings>().is_err());
+        assert!(
+            "detect_language = true\nstrict_schema = true"
+                .parse::<IndexSettings>()
+                .is_err()
+        );
+        assert!(
+            "record = docs\ntitle_boost = 2"
+                .parse::<IndexSettings>()
+                .is_err()
+        );
+        assert!(
+            "field.tag = keyword, analyzer=simple"
+                .parse::<IndexSettings>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_apply_update() {
+        let mut settings = IndexSettings::default();
+        let update = SettingsUpdate {
+            k1: Some(2.0),
+            cache_size: Some(10),
+            ..SettingsUpdate::default()
+        };
+        settings.apply(&update).unwrap();
+        assert_eq!(settings.k1, 2.0);
+        assert_eq!(settings.cache_size, 10);
+
+        // Invalid updates leave the settings untouched
+        let bad = SettingsUpdate {
+            k1: Some(3.0),
+            b: Some(1.5),
+            ..SettingsUpdate::default()
+        };
+        assert!(settings.apply(&bad).is_err());
+        assert_eq!(settings.k1, 2.0);
+    }
+}
@@ -0,0 +1,644 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::IndexRecordOption;
+use crate::document::Document;
+use crate::errors::MSErrors;
+use crate::indexer::{GeoPoint, parse_number, parse_vector, split_values};
+use crate::tokenizer::AnalyzerPreset;
+
+// Type of a metadata field
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum FieldType {
+    Text,    // Analyzed, searchable with `field:word`
+    Keyword, // Exact value, for filters, facets and `field:value`
+    Numeric,
+    Date,     // RFC 3339, or seconds since the epoch
+    Bool,     // `true` or `false`, stored lowercase and searchable with `field:true`
+    Enum,     // One of the field's `choices`, for filters, facets and `field:value`
+    Vector,   // Numbers such as `[0.1, 0.2]`, `dims` of them, for k-NN search
+    GeoPoint, // `lat,lon` in degrees, for radius filters and distance sorting
+}
+
+impl FieldType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FieldType::Text => "text",
+            FieldType::Keyword => "keyword",
+            FieldType::Numeric => "numeric",
+            FieldType::Date => "date",
+            FieldType::Bool => "bool",
+            FieldType::Enum => "enum",
+            FieldType::Vector => "vector",
+            FieldType::GeoPoint => "geo_point",
+        }
+    }
+
+    // Whether `value` is a valid value of this type
+    pub fn accepts(&self, value: &str) -> bool {
+        match self {
+            // The choices of an enum are checked by `FieldDef::accepts`
+            FieldType::Text | FieldType::Keyword | FieldType::Enum => true,
+            FieldType::Numeric => value.trim().parse::<f64>().is_ok_and(f64::is_finite),
+            FieldType::Date => parse_number(value).is_some(),
+            FieldType::Bool => {
+                matches!(value.trim().to_ascii_lowercase().as_str(), "true" | "false")
+            }
+            FieldType::Vector => parse_vector(value).is_some(),
+            FieldType::GeoPoint => GeoPoint::parse(value).is_some(),
+        }
+    }
+}
+
+impl FromStr for FieldType {
+    type Err = MSErrors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(FieldType::Text),
+            "keyword" => Ok(FieldType::Keyword),
+            "numeric" => Ok(FieldType::Numeric),
+            "date" => Ok(FieldType::Date),
+            "bool" => Ok(FieldType::Bool),
+            "enum" => Ok(FieldType::Enum),
+            "vector" => Ok(FieldType::Vector),
+            "geo_point" => Ok(FieldType::GeoPoint),
+            _ => Err(MSErrors::ParseError(format!("unknown field type: {}", s))),
+        }
+    }
+}
+
+/*
+One metadata field. Unindexed fields are kept with the document but
+cannot be filtered, faceted, sorted or searched on; unstored fields are
+indexed but left out of the documents returned by the engine. Text fields
+are analyzed with `analyzer`, or the index analyzer when unset, both when
+indexed and in `field:value` queries; keyword fields are indexed verbatim,
+without tokenizing or stemming. Title and content use the index analyzer.
+A text field can set `search_analyzer` to analyze queries differently,
+e.g. index edge n-grams for autocomplete but search the words as typed. `record`
+sets what the postings of text and keyword fields keep. Vector fields
+must set `dims`, the length of every vector, and are searched with
+`SearchEngine::knn_search` rather than by query.
+
+Bool and enum fields are indexed as single terms, like keywords, so that
+`published:true` or `status:draft` finds documents. Bool values are
+stored as `true` or `false` whatever their case. An enum field lists its
+`choices`, and a document with any other value is rejected; choices are
+matched exactly, case included.
+
+A `multi` field holds several values separated by commas, such as tags
+`rust, search`, as the JSON parser writes arrays. Each value is checked,
+indexed and faceted on its own; filters and `field:value` queries match
+if any value does, and sorting uses the smallest value. Vector and
+geo-point values contain commas themselves, so they cannot be multi.
+*/
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldDef {
+    pub name: String,
+    pub field_type: FieldType,
+    pub indexed: bool,
+    pub stored: bool,
+    pub analyzer: Option<AnalyzerPreset>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub search_analyzer: Option<AnalyzerPreset>, // Analyzer of `field:value` queries, `analyzer` when unset
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub record: IndexRecordOption,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dims: Option<usize>, // Length of the vectors of a vector field
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub multi: bool, // Holds comma-separated values
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub choices: Vec<String>, // Values allowed in an enum field
+}
+
+impl FieldDef {
+    // An indexed and stored field
+    pub fn new(name: &str, field_type: FieldType) -> Self {
+        FieldDef {
+            name: name.to_string(),
+            field_type,
+            indexed: true,
+            stored: true,
+            analyzer: None,
+            search_analyzer: None,
+            record: IndexRecordOption::default(),
+            dims: None,
+            multi: false,
+            choices: Vec::new(),
+        }
+    }
+
+    pub fn indexed(mut self, indexed: bool) -> Self {
+        self.indexed = indexed;
+        self
+    }
+
+    pub fn stored(mut self, stored: bool) -> Self {
+        self.stored = stored;
+        self
+    }
+
+    pub fn analyzer(mut self, analyzer: AnalyzerPreset) -> Self {
+        self.analyzer = Some(analyzer);
+        self
+    }
+
+    pub fn search_analyzer(mut self, analyzer: AnalyzerPreset) -> Self {
+        self.search_analyzer = Some(analyzer);
+        self
+    }
+
+    pub fn record(mut self, record: IndexRecordOption) -> Self {
+        self.record = record;
+        self
+    }
+
+    pub fn dims(mut self, dims: usize) -> Self {
+        self.dims = Some(dims);
+        self
+    }
+
+    pub fn multi(mut self, multi: bool) -> Self {
+        self.multi = multi;
+        self
+    }
+
+    pub fn choices(mut self, choices: &[&str]) -> Self {
+        self.choices = choices.iter().map(|choice| choice.to_string()).collect();
+        self
+    }
+
+    // Whether `value` is a valid single value of this field
+    pub fn accepts(&self, value: &str) -> bool {
+        match self.field_type {
+            FieldType::Enum => self.choices.iter().any(|choice| choice == value.trim()),
+            field_type => field_type.accepts(value),
+        }
+    }
+
+    // The values of `value`: its comma-separated parts if the field is multi-valued
+    pub fn values<'a>(&self, value: &'a str) -> Vec<&'a str> {
+        if self.multi {
+            split_values(value).collect()
+        } else {
+            vec![value]
+        }
+    }
+}
+
+// Written as `type[, unindexed][, unstored][, analyzer=name][, search_analyzer=name][, record=option][, dims=n][, multi][, choices=a|b]`
+impl fmt::Display for FieldDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.field_type.as_str())?;
+        if !self.indexed {
+            write!(f, ", unindexed")?;
+        }
+        if !self.stored {
+            write!(f, ", unstored")?;
+        }
+        if let Some(analyzer) = self.analyzer {
+            write!(f, ", analyzer={}", analyzer.as_str())?;
+        }
+        if let Some(analyzer) = self.search_analyzer {
+            write!(f, ", search_analyzer={}", analyzer.as_str())?;
+        }
+        if self.record != IndexRecordOption::default() {
+            write!(f, ", record={}", self.record)?;
+        }
+        if let Some(dims) = self.dims {
+            write!(f, ", dims={}", dims)?;
+        }
+        if self.multi {
+            write!(f, ", multi")?;
+        }
+        if !self.choices.is_empty() {
+            write!(f, ", choices={}", self.choices.join("|"))?;
+        }
+        Ok(())
+    }
+}
+
+impl FieldDef {
+    fn parse(name: &str, definition: &str) -> Result<Self, MSErrors> {
+        let mut parts = definition.split(',').map(str::trim);
+        let field_type = parts.next().unwrap_or_default().parse()?;
+        let mut field = FieldDef::new(name, field_type);
+        for option in parts {
+            match option.split_once('=').map(|(k, v)| (k.trim(), v.trim())) {
+                None if option == "unindexed" => field.indexed = false,
+                None if option == "unstored" => field.stored = false,
+                None if option == "multi" => field.multi = true,
+                Some(("analyzer", analyzer)) => field.analyzer = Some(analyzer.parse()?),
+                Some(("search_analyzer", analyzer)) => {
+                    field.search_analyzer = Some(analyzer.parse()?)
+                }
+                Some(("record", record)) => field.record = record.parse()?,
+                Some(("choices", choices)) => {
+                    field.choices = choices.split('|').map(|c| c.trim().to_string()).collect()
+                }
+                Some(("dims", dims)) => {
+                    field.dims = Some(dims.parse().map_err(|_| {
+                        MSErrors::ParseError(format!("invalid dims for field {}: {}", name, dims))
+                    })?)
+                }
+                _ => {
+                    return Err(MSErrors::ParseError(format!(
+                        "unknown option for field {}: {}",
+                        name, option
+                    )));
+                }
+            }
+        }
+        Ok(field)
+    }
+}
+
+// Suffix of the keyword copy of a text field, e.g. `author.raw`
+pub const RAW_SUFFIX: &str = ".raw";
+
+/*
+Types and options of metadata fields, checked when documents are added.
+Fields missing from the schema are accepted as before, with values that
+parse as numbers or dates indexed as such, unless the schema is strict.
+An empty schema accepts everything.
+
+Document content is stored unless `store_content` is off; the content is
+then indexed but returned empty, without snippets, and its analyzed tokens
+are saved in place of the text.
+
+With `raw_fields`, every indexed text field also gets a keyword copy
+named with `RAW_SUFFIX`, e.g. `author.raw`, indexed verbatim without
+stop words or stemming. `author:ada` then searches the words while
+`author.raw:"Ada Lovelace"`, facets and filters on `author.raw` use the
+exact value. Raw copies are indexed from their text field and never
+stored, so documents cannot set them.
+*/
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Schema {
+    pub fields: Vec<FieldDef>,
+    pub strict: bool,        // Reject fields missing from the schema
+    pub store_content: bool, // Keep document content, not just its tokens
+    pub raw_fields: bool,    // Index a `.raw` keyword copy of every text field
+}
+
+impl Default for Schema {
+    fn default() -> Self {
+        Schema {
+            fields: Vec::new(),
+            strict: false,
+            store_content: true,
+            raw_fields: false,
+        }
+    }
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Schema::default()
+    }
+
+    pub fn store_content(mut self, store_content: bool) -> Self {
+        self.store_content = store_content;
+        self
+    }
+
+    pub fn raw_fields(mut self, raw_fields: bool) -> Self {
+        self.raw_fields = raw_fields;
+        self
+    }
+
+    pub fn field(mut self, field: FieldDef) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FieldDef> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    // Name of the keyword copy of `field`, if it has one
+    pub fn raw_field(&self, field: &FieldDef) -> Option<String> {
+        let copied = self.raw_fields && field.indexed && field.field_type == FieldType::Text;
+        copied.then(|| format!("{}{}", field.name, RAW_SUFFIX))
+    }
+
+    // Whether `name` holds exact values sorted as text: a keyword or enum field, or a keyword copy
+    pub fn is_keyword(&self, name: &str) -> bool {
+        self.raw_source(name).is_some()
+            || self.get(name).is_some_and(|field| {
+                matches!(field.field_type, FieldType::Keyword | FieldType::Enum)
+            })
+    }
+
+    // The text field whose keyword copy is named `name`
+    pub fn raw_source(&self, name: &str) -> Option<&FieldDef> {
+        let field = self.get(name.strip_suffix(RAW_SUFFIX)?)?;
+        self.raw_field(field).is_some().then_some(field)
+    }
+
+    pub fn validate(&self) -> Result<(), MSErrors> {
+        for (i, field) in self.fields.iter().enumerate() {
+            // Names are written as `name = definition` and queried as `name:value`
+            let reserved = |c: char| c.is_whitespace() || c == ':' || c == '=';
+            if field.name.is_empty() || field.name.contains(reserved) {
+                return Err(MSErrors::ParseError(format!(
+                    "invalid field name: \"{}\"",
+                    field.name
+                )));
+            }
+            if self.fields[..i]
+                .iter()
+                .any(|other| other.name == field.name)
+            {
+                return Err(MSErrors::ParseError(format!(
+                    "field {} is defined twice",
+                    field.name
+                )));
+            }
+            if let Some(source) = self.raw_source(&field.name) {
+                return Err(MSErrors::ParseError(format!(
+                    "field {} clashes with the raw copy of {}",
+                    field.name, source.name
+                )));
+            }
+            let analyzed = field.analyzer.is_some() || field.search_analyzer.is_some();
+            if analyzed && field.field_type != FieldType::Text {
+                return Err(MSErrors::ParseError(format!(
+                    "field {} has an analyzer but is not a text field",
+                    field.name
+                )));
+            }
+            let searchable = matches!(
+                field.field_type,
+                FieldType::Text | FieldType::Keyword | FieldType::Bool | FieldType::Enum
+            );
+            if field.record != IndexRecordOption::default() && !searchable {
+                return Err(MSErrors::ParseError(format!(
+                    "field {} sets record but is not indexed as terms",
+                    field.name
+                )));
+            }
+            let vector = field.field_type == FieldType::Vector;
+            if vector != field.dims.is_some_and(|dims| dims > 0) {
+                return Err(MSErrors::ParseError(format!(
+                    "field {}: vector fields, and only they, must set dims above 0",
+                    field.name
+                )));
+            }
+            let enumerated = field.field_type == FieldType::Enum;
+            if enumerated == field.choices.is_empty() {
+                return Err(MSErrors::ParseError(format!(
+                    "field {}: enum fields, and only they, must list choices",
+                    field.name
+                )));
+            }
+            // Choices are written separated by `|` and split on commas when multi
+            if let Some(choice) = field
+                .choices
+                .iter()
+                .find(|choice| choice.trim().is_empty() || choice.contains([',', '|']))
+            {
+                return Err(MSErrors::ParseError(format!(
+                    "field {}: invalid choice \"{}\"",
+                    field.name, choice
+                )));
+            }
+            if field.multi && matches!(field.field_type, FieldType::Vector | FieldType::GeoPoint) {
+                return Err(MSErrors::ParseError(format!(
+                    "field {}: {} fields cannot be multi-valued",
+                    field.name,
+                    field.field_type.as_str()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // Check a document's metadata against the schema
+    pub fn check(&self, document: &Document) -> Result<(), MSErrors> {
+        for (name, value) in &document.metadata {
+            match self.get(name) {
+                Some(field)
+                    if field.field_type == FieldType::Vector
+                        && parse_vector(value).map(|vector| vector.len()) != field.dims =>
+                {
+                    return Err(MSErrors::InvalidField {
+                        doc_id: document.id,
+                        field: name.clone(),
+                        message: format!(
+                            "expects a vector of {} numbers, got \"{}\"",
+                            field.dims.unwrap_or_default(),
+                            value
+                        ),
+                    });
+                }
+                Some(field) if !field.values(value).iter().all(|value| field.accepts(value)) => {
+                    let expected = match field.field_type {
+                        FieldType::Enum => format!("one of {}", field.choices.join(", ")),
+                        field_type => format!("a {} value", field_type.as_str()),
+                    };
+                    return Err(MSErrors::InvalidField {
+                        doc_id: document.id,
+                        field: name.clone(),
+                        message: format!("expects {}, got \"{}\"", expected, value),
+                    });
+                }
+                None if self.raw_source(name).is_some() => {
+                    return Err(MSErrors::InvalidField {
+                        doc_id: document.id,
+                        field: name.clone(),
+                        message: "is the raw copy of a text field".to_string(),
+                    });
+                }
+                None if self.strict => {
+                    return Err(MSErrors::InvalidField {
+                        doc_id: document.id,
+                        field: name.clone(),
+                        message: "is not in the schema".to_string(),
+                    });
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Write the bool values of a checked document as `true` or `false`
+    pub fn normalize(&self, document: &mut Document) {
+        for field in &self.fields {
+            if field.field_type != FieldType::Bool {
+                continue;
+            }
+            if let Some(value) = document.metadata.get_mut(&field.name) {
+                let values: Vec<String> = field
+                    .values(value)
+                    .iter()
+                    .map(|value| value.trim().to_ascii_lowercase())
+                    .collect();
+                *value = values.join(", ");
+            }
+        }
+    }
+
+    // Parse the definition stored under `field.<name>` in the settings file
+    pub(crate) fn parse_field(&mut self, name: &str, definition: &str) -> Result<(), MSErrors> {
+        self.fields.push(FieldDef::parse(name, definition)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn document(metadata: &[(&str, &str)]) -> Document {
+        Document {
+            id: 7,
+            title: String::new(),
+            content: "text".to_string(),
+            metadata: metadata
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn test_check_document() {
+        let schema = Schema::new()
+            .field(FieldDef::new("price", FieldType::Numeric))
+            .field(FieldDef::new("published", FieldType::Date))
+            .field(FieldDef::new("draft", FieldType::Bool))
+            .field(FieldDef::new("embedding", FieldType::Vector).dims(2));
+        let valid = document(&[
+            ("price", "9.5"),
+            ("published", "2024-05-01"),
+            ("draft", "False"),
+            ("embedding", "[0.5, -1]"),
+            ("other", "x"),
+        ]);
+        assert!(schema.check(&valid).is_ok());
+        assert!(schema.check(&document(&[("price", "cheap")])).is_err());
+        assert!(schema.check(&document(&[("draft", "yes")])).is_err());
+        assert!(
+            schema
+                .check(&document(&[("embedding", "[1, 2, 3]")]))
+                .is_err()
+        );
+        let sizes = Schema::new().field(FieldDef::new("sizes", FieldType::Numeric).multi(true));
+        assert!(sizes.check(&document(&[("sizes", "38, 39.5")])).is_ok());
+        assert!(sizes.check(&document(&[("sizes", "38, large")])).is_err());
+        let located = Schema::new().field(FieldDef::new("location", FieldType::GeoPoint));
+        assert!(
+            located
+                .check(&document(&[("location", "52.52,13.4")]))
+                .is_ok()
+        );
+        assert!(
+            located
+                .check(&document(&[("location", "152.52,13.4")]))
+                .is_err()
+        );
+
+        let status = Schema::new()
+            .field(FieldDef::new("status", FieldType::Enum).choices(&["draft", "live"]))
+            .field(FieldDef::new("flags", FieldType::Bool).multi(true));
+        assert!(status.check(&document(&[("status", "live")])).is_ok());
+        assert!(status.check(&document(&[("status", "Live")])).is_err());
+        let mut flags = document(&[("flags", "TRUE, false")]);
+        assert!(status.check(&flags).is_ok());
+        status.normalize(&mut flags);
+        assert_eq!(flags.metadata["flags"], "true, false");
+
+        let raw = Schema::new()
+            .field(FieldDef::new("author", FieldType::Text))
+            .raw_fields(true);
+        assert_eq!(raw.raw_source("author.raw").unwrap().name, "author");
+        assert!(raw.raw_source("price.raw").is_none());
+        assert!(raw.check(&document(&[("author.raw", "x")])).is_err());
+        let clash = raw.field(FieldDef::new("author.raw", FieldType::Keyword));
+        assert!(clash.validate().is_err());
+
+        let strict = schema.strict(true);
+        let error = strict.check(&document(&[("other", "x")])).unwrap_err();
+        assert!(matches!(error, MSErrors::InvalidField { doc_id: 7, .. }));
+    }
+
+    #[test]
+    fn test_field_definitions() {
+        let field = FieldDef::new("body", FieldType::Text)
+            .stored(false)
+            .analyzer(AnalyzerPreset::Whitespace)
+            .search_analyzer(AnalyzerPreset::Keyword)
+            .record(IndexRecordOption::DocsAndFreqs);
+        assert_eq!(
+            field.to_string(),
+            "text, unstored, analyzer=whitespace, search_analyzer=keyword, record=freqs"
+        );
+        assert_eq!(FieldDef::parse("body", &field.to_string()).unwrap(), field);
+        assert!(FieldDef::parse("x", "text, hidden").is_err());
+        assert!(FieldDef::parse("x", "blob").is_err());
+
+        let keyword_analyzer = Schema::new()
+            .field(FieldDef::new("tag", FieldType::Keyword).analyzer(AnalyzerPreset::Simple));
+        assert!(keyword_analyzer.validate().is_err());
+        let keyword_search = Schema::new().field(
+            FieldDef::new("tag", FieldType::Keyword).search_analyzer(AnalyzerPreset::Simple),
+        );
+        assert!(keyword_search.validate().is_err());
+        let twice = Schema::new()
+            .field(FieldDef::new("a", FieldType::Bool))
+            .field(FieldDef::new("a", FieldType::Text));
+        assert!(twice.validate().is_err());
+        for name in ["a=b", "a b", "a\u{a0}b", "a:b", ""] {
+            let invalid = Schema::new().field(FieldDef::new(name, FieldType::Keyword));
+            assert!(invalid.validate().is_err(), "{:?}", name);
+        }
+        let vector = FieldDef::new("embedding", FieldType::Vector)
+            .stored(false)
+            .dims(384);
+        assert_eq!(vector.to_string(), "vector, unstored, dims=384");
+        assert_eq!(
+            FieldDef::parse("embedding", &vector.to_string()).unwrap(),
+            vector
+        );
+        let tags = FieldDef::new("tags", FieldType::Keyword).multi(true);
+        assert_eq!(tags.to_string(), "keyword, multi");
+        assert_eq!(FieldDef::parse("tags", "keyword, multi").unwrap(), tags);
+        let status = FieldDef::new("status", FieldType::Enum).choices(&["draft", "live"]);
+        assert_eq!(status.to_string(), "enum, choices=draft|live");
+        assert_eq!(
+            FieldDef::parse("status", &status.to_string()).unwrap(),
+            status
+        );
+        let no_choices = Schema::new().field(FieldDef::new("status", FieldType::Enum));
+        assert!(no_choices.validate().is_err());
+        let keyword_choices =
+            Schema::new().field(FieldDef::new("tag", FieldType::Keyword).choices(&["a"]));
+        assert!(keyword_choices.validate().is_err());
+        let empty_choice =
+            Schema::new().field(FieldDef::new("status", FieldType::Enum).choices(&["a", " "]));
+        assert!(empty_choice.validate().is_err());
+        let multi_point = Schema::new().field(FieldDef::new("at", FieldType::GeoPoint).multi(true));
+        assert!(multi_point.validate().is_err());
+        let no_dims = Schema::new().field(FieldDef::new("embedding", FieldType::Vector));
+        assert!(no_dims.validate().is_err());
+        let numeric_dims = Schema::new().field(FieldDef::new("price", FieldType::Numeric).dims(3));
+        assert!(numeric_dims.validate().is_err());
+        let numeric_record = Schema::new()
+            .field(FieldDef::new("price", FieldType::Numeric).record(IndexRecordOption::DocsOnly));
+        assert!(numeric_record.validate().is_err());
+    }
+}
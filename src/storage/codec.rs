@@ -0,0 +1,186 @@
+use crate::errors::MSErrors;
+
+// Little-endian binary encoder used by the on-disk files
+#[derive(Default)]
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Encoder { buf: Vec::new() }
+    }
+
+    pub fn put_u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn put_u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn put_u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn put_f64(&mut self, value: f64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    // Strings are written as a u32 byte length followed by UTF-8 bytes
+    pub fn put_str(&mut self, value: &str) {
+        self.put_bytes(value.as_bytes());
+    }
+
+    // A u32 length followed by the bytes
+    pub fn put_bytes(&mut self, value: &[u8]) {
+        self.put_u32(value.len() as u32);
+        self.buf.extend_from_slice(value);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+// Reads values back in the order they were written by `Encoder`
+pub struct Decoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Decoder { buf, pos: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.buf.len()
+    }
+
+    // Bytes read so far, the offset of the next value
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+
+    /*
+    Room to reserve for `count` values read from the input. Each takes at
+    least a byte, so a corrupt count cannot reserve more than is left.
+    */
+    pub fn capacity(&self, count: usize) -> usize {
+        count.min(self.buf.len() - self.pos)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], MSErrors> {
+        if self.buf.len() - self.pos < len {
+            return Err(MSErrors::Corrupt {
+                offset: self.pos,
+                message: "unexpected end of data".to_string(),
+            });
+        }
+        let bytes = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn get_u8(&mut self) -> Result<u8, MSErrors> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn get_u32(&mut self) -> Result<u32, MSErrors> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub fn get_u64(&mut self) -> Result<u64, MSErrors> {
+        let bytes = self.take(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub fn get_f64(&mut self) -> Result<f64, MSErrors> {
+        let bytes = self.take(8)?;
+        Ok(f64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub fn get_bytes(&mut self) -> Result<Vec<u8>, MSErrors> {
+        let len = self.get_u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    pub fn get_str(&mut self) -> Result<String, MSErrors> {
+        let len = self.get_u32()? as usize;
+        let start = self.pos;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| MSErrors::Corrupt {
+            offset: start,
+            message: "invalid UTF-8".to_string(),
+        })
+    }
+}
+
+// Lookup table of the reflected CRC-32 polynomial, one entry per byte value
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut idx = 0;
+    while idx < 256 {
+        let mut crc = idx as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[idx] = crc;
+        idx += 1;
+    }
+    table
+};
+
+// CRC-32 (IEEE, as used by zip and PNG) of `bytes`
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut encoder = Encoder::new();
+        encoder.put_u8(7);
+        encoder.put_u32(42);
+        encoder.put_u64(u64::MAX);
+        encoder.put_f64(0.75);
+        encoder.put_str("héllo");
+        encoder.put_bytes(&[0, 255]);
+        let bytes = encoder.into_bytes();
+
+        let mut decoder = Decoder::new(&bytes);
+        assert_eq!(decoder.get_u8().unwrap(), 7);
+        assert_eq!(decoder.get_u32().unwrap(), 42);
+        assert_eq!(decoder.get_u64().unwrap(), u64::MAX);
+        assert_eq!(decoder.get_f64().unwrap(), 0.75);
+        assert_eq!(decoder.get_str().unwrap(), "héllo");
+        assert_eq!(decoder.get_bytes().unwrap(), [0, 255]);
+        assert!(decoder.is_empty());
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_truncated_input() {
+        let mut decoder = Decoder::new(&[1, 2]);
+        assert!(decoder.get_u32().is_err());
+        assert_eq!(decoder.capacity(usize::MAX), 2);
+    }
+}
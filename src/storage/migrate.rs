@@ -0,0 +1,262 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{DOCUMENTS_FILE, DocumentRef, FORMAT_VERSION, SETTINGS_FILE, Storage, StoredDocument};
+use crate::errors::MSErrors;
+
+// What `migrate` rewrote
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStats {
+    pub from_version: u32,
+    pub documents: usize, // Including those in segments
+    pub segments: usize,
+}
+
+/*
+Copy the index at `old` into a new index directory at `new`, rewriting
+every file in the current format. The old index is only read, so it stays
+usable by the build that wrote it. The settings file is written last: an
+interrupted migration leaves a directory that cannot be opened.
+*/
+pub fn migrate(old: impl AsRef<Path>, new: impl AsRef<Path>) -> Result<MigrationStats, MSErrors> {
+    let source = Storage {
+        root: old.as_ref().to_path_buf(),
+        legacy: true,
+    };
+    if !source.exists(SETTINGS_FILE) {
+        return Err(MSErrors::StorageError(format!(
+            "{} is not an index directory",
+            source.root.display()
+        )));
+    }
+    let from_version = source.format_version()?;
+    if from_version > FORMAT_VERSION {
+        return Err(MSErrors::StorageError(format!(
+            "{} uses index format {}, newer than this build supports",
+            source.root.display(),
+            from_version
+        )));
+    }
+    let settings = source.load_settings()?;
+
+    let target = Storage::create(new)?;
+    if target.exists(SETTINGS_FILE) {
+        return Err(MSErrors::StorageError(format!(
+            "an index already exists at {}",
+            target.root.display()
+        )));
+    }
+
+    let mut stats = MigrationStats {
+        from_version,
+        documents: 0,
+        segments: 0,
+    };
+    if source.exists(DOCUMENTS_FILE) {
+        let documents = source.load_documents()?;
+        target.save_documents(documents.iter().map(as_ref))?;
+        stats.documents += documents.len();
+    }
+    let segments = source.load_segments()?;
+    for segment in &segments {
+        let documents = source.load_segment_documents(segment)?;
+        target.save_segment_documents(segment, documents.iter().map(as_ref))?;
+        stats.documents += documents.len();
+    }
+    if !segments.is_empty() {
+        target.save_segments(&segments)?;
+    }
+    stats.segments = segments.len();
+
+    target.save_settings(&settings)?;
+    Ok(stats)
+}
+
+/*
+Upgrade the index at `path` in place: migrate it into a sibling directory,
+then swap the two. The old index is moved aside to `<name>.old` during the
+swap and deleted once the upgraded index is in place, so an interrupted
+upgrade always leaves one complete index behind, which the next upgrade
+recovers before starting over.
+*/
+pub fn upgrade(path: impl AsRef<Path>) -> Result<MigrationStats, MSErrors> {
+    let path = path.as_ref();
+    let (upgraded, old) = (sibling(path, "upgrade")?, sibling(path, "old")?);
+    recover(path, &upgraded, &old)?;
+    let stats = migrate(path, &upgraded)?;
+    fs::rename(path, &old).map_err(|e| MSErrors::io(path, e))?;
+    fs::rename(&upgraded, path).map_err(|e| MSErrors::io(&upgraded, e))?;
+    fs::remove_dir_all(&old).map_err(|e| MSErrors::io(&old, e))?;
+    Ok(stats)
+}
+
+/*
+Undo what an interrupted upgrade left behind. If the index was moved
+aside but the upgraded copy is not in place, the swap is finished when
+that copy is complete (its settings file is written last), otherwise the
+old index is put back. Leftovers are only deleted once `path` holds an
+index again.
+*/
+fn recover(path: &Path, upgraded: &Path, old: &Path) -> Result<(), MSErrors> {
+    if !path.exists() {
+        let source = if upgraded.join(SETTINGS_FILE).is_file() {
+            upgraded
+        } else if old.exists() {
+            old
+        } else {
+            // Nothing to recover; migrating reports the missing index
+            return Ok(());
+        };
+        fs::rename(source, path).map_err(|e| MSErrors::io(source, e))?;
+    }
+    let index = Storage {
+        root: path.to_path_buf(),
+        legacy: true,
+    };
+    if !index.exists(SETTINGS_FILE) {
+        return Err(MSErrors::StorageError(format!(
+            "{} is not an index directory",
+            path.display()
+        )));
+    }
+    index.format_version()?;
+    index.load_settings()?;
+    for leftover in [upgraded, old] {
+        if leftover.exists() {
+            fs::remove_dir_all(leftover).map_err(|e| MSErrors::io(leftover, e))?;
+        }
+    }
+    Ok(())
+}
+
+// `path` with `.suffix` added to its last component
+fn sibling(path: &Path, suffix: &str) -> Result<PathBuf, MSErrors> {
+    let Some(name) = path.file_name() else {
+        return Err(MSErrors::StorageError(format!(
+            "{} is not an index directory",
+            path.display()
+        )));
+    };
+    Ok(path.with_file_name(format!("{}.{}", name.to_string_lossy(), suffix)))
+}
+
+fn as_ref((document, boost, tokens): &StoredDocument) -> DocumentRef<'_> {
+    (document, *boost, tokens.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Document;
+    use crate::searcher::SearchEngine;
+    use crate::settings::IndexSettings;
+    use crate::storage::codec::Encoder;
+    use crate::storage::encode_document;
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn test_migrate_format_1() {
+        let base = std::env::temp_dir().join(format!("mini-search-migrate-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        let (old, new) = (base.join("old"), base.join("new"));
+
+        // A format 1 index: documents file without a header
+        let storage = Storage::create(&old).unwrap();
+        storage.save_settings(&IndexSettings::default()).unwrap();
+        let mut encoder = Encoder::new();
+        encoder.put_u64(1);
+        let document = Document {
+            id: 4,
+            title: "Old".to_string(),
+            content: "written before headers".to_string(),
+            metadata: HashMap::new(),
+        };
+        encode_document(&mut encoder, &document);
+        encoder.put_f64(1.0);
+        storage
+            .write(DOCUMENTS_FILE, &encoder.into_bytes())
+            .unwrap();
+
+        let error = SearchEngine::open(&old).err().unwrap();
+        assert!(error.to_string().contains("migrate"));
+
+        let stats = migrate(&old, &new).unwrap();
+        assert_eq!(
+            (stats.from_version, stats.documents, stats.segments),
+            (1, 1, 0)
+        );
+        let engine = SearchEngine::open(&new).unwrap();
+        assert_eq!(engine.get_document(4), Some(&document));
+        assert_eq!(engine.search("headers", 10).total_matches, 1);
+        // The target must be a fresh directory
+        assert!(migrate(&old, &new).is_err());
+        fs::remove_dir_all(base).unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_in_place() {
+        let base = std::env::temp_dir().join(format!("mini-search-upgrade-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        let path = base.join("index");
+
+        // A format 3 index: same layout as today without tokens, but no checksum
+        let mut engine = SearchEngine::create(&path, IndexSettings::default()).unwrap();
+        engine
+            .add_document(Document {
+                id: 1,
+                title: "Kept".to_string(),
+                content: "survives the upgrade".to_string(),
+                metadata: HashMap::new(),
+            })
+            .unwrap();
+        engine.flush().unwrap();
+        let storage = Storage::open(&path).unwrap();
+        let mut bytes = storage.read(DOCUMENTS_FILE).unwrap();
+        bytes.truncate(bytes.len() - 4);
+        bytes[4..8].copy_from_slice(&3u32.to_le_bytes());
+        storage.write(DOCUMENTS_FILE, &bytes).unwrap();
+        assert!(SearchEngine::open(&path).is_err());
+
+        let stats = upgrade(&path).unwrap();
+        assert_eq!((stats.from_version, stats.documents), (3, 1));
+        let engine = SearchEngine::open(&path).unwrap();
+        assert_eq!(engine.search("upgrade", 10).total_matches, 1);
+        assert!(!base.join("index.old").exists());
+        assert!(!base.join("index.upgrade").exists());
+        fs::remove_dir_all(base).unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_recovers_interrupted_swap() {
+        let base =
+            std::env::temp_dir().join(format!("mini-search-interrupted-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        let path = base.join("index");
+        let (upgraded, old) = (base.join("index.upgrade"), base.join("index.old"));
+        let mut engine = SearchEngine::create(&path, IndexSettings::default()).unwrap();
+        engine
+            .add_document(Document {
+                id: 1,
+                title: "Kept".to_string(),
+                content: "survives an interrupted upgrade".to_string(),
+                metadata: HashMap::new(),
+            })
+            .unwrap();
+        engine.flush().unwrap();
+
+        // Stopped between the two renames: the swap is finished
+        migrate(&path, &upgraded).unwrap();
+        fs::rename(&path, &old).unwrap();
+        upgrade(&path).unwrap();
+        assert_eq!(SearchEngine::open(&path).unwrap().document_count(), 1);
+
+        // Stopped with only the old index left: it is put back
+        fs::rename(&path, &old).unwrap();
+        fs::create_dir_all(&upgraded).unwrap();
+        upgrade(&path).unwrap();
+        assert_eq!(SearchEngine::open(&path).unwrap().document_count(), 1);
+        assert!(!old.exists() && !upgraded.exists());
+        fs::remove_dir_all(base).unwrap();
+    }
+}
@@ -0,0 +1,579 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::document::Document;
+use crate::errors::MSErrors;
+use crate::settings::IndexSettings;
+use crate::tokenizer::{Token, TokenKind};
+
+pub mod codec;
+mod migrate;
+mod segment;
+
+use codec::{Decoder, Encoder, crc32};
+pub use migrate::{MigrationStats, migrate, upgrade};
+pub use segment::SegmentMeta;
+
+pub const SETTINGS_FILE: &str = "settings";
+pub const DOCUMENTS_FILE: &str = "documents";
+pub const SEGMENTS_FILE: &str = "segments";
+
+/*
+Version of the binary files (documents, segments) written by this build.
+Since format 2 each file starts with MAGIC and its format version; format
+1 files have no header, but the same layout otherwise. Format 3 adds the
+analyzed tokens of documents whose content is not stored. Format 4 adds
+the kind and payload of each of those tokens, and ends each file with a
+CRC-32 of everything before it, checked whenever the file is read.
+Indexes in an older format must be upgraded with `migrate` before they
+can be opened; only `migrate` reads files in older formats.
+*/
+pub const FORMAT_VERSION: u32 = 4;
+const MAGIC: &[u8; 4] = b"MSIX";
+const HEADER_LEN: usize = MAGIC.len() + 4;
+const CHECKSUM_LEN: usize = 4;
+
+// A document read from disk with its boost, and its analyzed title and
+// content when the content itself is not stored
+pub type StoredDocument = (Document, f64, Option<Vec<Token>>);
+// A document to write, as above
+pub type DocumentRef<'a> = (&'a Document, f64, Option<&'a [Token]>);
+
+// An index directory on disk
+#[derive(Clone)]
+pub struct Storage {
+    root: PathBuf,
+    legacy: bool, // Also read files in older formats, for `migrate`
+}
+
+impl Storage {
+    // Create the index directory (and parents) if needed
+    pub fn create(root: impl AsRef<Path>) -> Result<Self, MSErrors> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root).map_err(|e| MSErrors::io(&root, e))?;
+        Ok(Storage {
+            root,
+            legacy: false,
+        })
+    }
+
+    // Open an existing index directory, which must contain a settings file
+    pub fn open(root: impl AsRef<Path>) -> Result<Self, MSErrors> {
+        let storage = Storage {
+            root: root.as_ref().to_path_buf(),
+            legacy: false,
+        };
+        if !storage.exists(SETTINGS_FILE) {
+            return Err(MSErrors::StorageError(format!(
+                "{} is not an index directory",
+                storage.root.display()
+            )));
+        }
+        let version = storage.format_version()?;
+        if version != FORMAT_VERSION {
+            return Err(MSErrors::StorageError(format!(
+                "{} uses index format {}, this build reads format {}; upgrade it with `mini-search upgrade` or `mini-search migrate`",
+                storage.root.display(),
+                version,
+                FORMAT_VERSION
+            )));
+        }
+        Ok(storage)
+    }
+
+    // Format of the index files, taken from the first file found
+    pub fn format_version(&self) -> Result<u32, MSErrors> {
+        let Some(name) = [SEGMENTS_FILE, DOCUMENTS_FILE]
+            .into_iter()
+            .find(|name| self.exists(name))
+        else {
+            return Ok(FORMAT_VERSION);
+        };
+        let path = self.root.join(name);
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        File::open(&path)
+            .and_then(|file| file.take(HEADER_LEN as u64).read_to_end(&mut header))
+            .map_err(|e| MSErrors::io(path, e))?;
+        Ok(split_header(&header).0)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.root.join(name).is_file()
+    }
+
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, MSErrors> {
+        let path = self.root.join(name);
+        fs::read(&path).map_err(|e| MSErrors::io(path, e))
+    }
+
+    // Write through a temporary file and rename so readers never see a partial file
+    pub fn write(&self, name: &str, bytes: &[u8]) -> Result<(), MSErrors> {
+        let path = self.root.join(name);
+        let tmp = self.root.join(format!("{}.tmp", name));
+        fs::write(&tmp, bytes)
+            .and_then(|_| fs::rename(&tmp, &path))
+            .map_err(|e| MSErrors::io(path, e))
+    }
+
+    /*
+    Check the header and checksum of a binary file without decoding it, for
+    files that are only read later on, such as cold segments.
+    */
+    pub fn verify_file(&self, name: &str) -> Result<(), MSErrors> {
+        let bytes = self.read(name)?;
+        self.checked_body(name, &bytes).map(|_| ())
+    }
+
+    // Delete a file if it exists
+    pub fn remove(&self, name: &str) -> Result<(), MSErrors> {
+        let path = self.root.join(name);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(MSErrors::io(path, e)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn load_settings(&self) -> Result<IndexSettings, MSErrors> {
+        let bytes = self.read(SETTINGS_FILE)?;
+        let text = String::from_utf8(bytes)
+            .map_err(|_| MSErrors::StorageError("settings file is not valid UTF-8".to_string()))?;
+        text.parse()
+    }
+
+    pub fn save_settings(&self, settings: &IndexSettings) -> Result<(), MSErrors> {
+        self.write(SETTINGS_FILE, settings.to_string().as_bytes())
+    }
+
+    // Load the stored documents and their boosts, or none if nothing has been flushed yet
+    pub fn load_documents(&self) -> Result<Vec<StoredDocument>, MSErrors> {
+        if !self.exists(DOCUMENTS_FILE) {
+            return Ok(Vec::new());
+        }
+        self.read_documents(DOCUMENTS_FILE)
+    }
+
+    pub fn save_documents<'a>(
+        &self,
+        documents: impl ExactSizeIterator<Item = DocumentRef<'a>>,
+    ) -> Result<(), MSErrors> {
+        self.write_documents(DOCUMENTS_FILE, documents)
+    }
+
+    // Segments moved out of memory, oldest first
+    pub fn load_segments(&self) -> Result<Vec<SegmentMeta>, MSErrors> {
+        if !self.exists(SEGMENTS_FILE) {
+            return Ok(Vec::new());
+        }
+        self.decode_file(SEGMENTS_FILE, |_, bytes| segment::decode_segments(bytes))
+    }
+
+    pub fn save_segments(&self, segments: &[SegmentMeta]) -> Result<(), MSErrors> {
+        self.write_versioned(SEGMENTS_FILE, segment::encode_segments(segments))
+    }
+
+    pub fn load_segment_documents(
+        &self,
+        segment: &SegmentMeta,
+    ) -> Result<Vec<StoredDocument>, MSErrors> {
+        self.read_documents(&segment.file_name())
+    }
+
+    pub fn save_segment_documents<'a>(
+        &self,
+        segment: &SegmentMeta,
+        documents: impl ExactSizeIterator<Item = DocumentRef<'a>>,
+    ) -> Result<(), MSErrors> {
+        self.write_documents(&segment.file_name(), documents)
+    }
+
+    fn read_documents(&self, name: &str) -> Result<Vec<StoredDocument>, MSErrors> {
+        self.decode_file(name, |version, bytes| {
+            let mut decoder = Decoder::new(bytes);
+            let count = decoder.get_u64()? as usize;
+            let mut documents = Vec::with_capacity(decoder.capacity(count));
+            for _ in 0..count {
+                let document = decode_document(&mut decoder)?;
+                let boost = decoder.get_f64()?;
+                let tokens = match version {
+                    1 | 2 => None,
+                    _ => decode_tokens(&mut decoder, version)?,
+                };
+                documents.push((document, boost, tokens));
+            }
+            Ok(documents)
+        })
+    }
+
+    fn write_documents<'a>(
+        &self,
+        name: &str,
+        documents: impl ExactSizeIterator<Item = DocumentRef<'a>>,
+    ) -> Result<(), MSErrors> {
+        let mut encoder = Encoder::new();
+        encoder.put_u64(documents.len() as u64);
+        for (document, boost, tokens) in documents {
+            encode_document(&mut encoder, document);
+            encoder.put_f64(boost);
+            encode_tokens(&mut encoder, tokens);
+        }
+        self.write_versioned(name, encoder.into_bytes())
+    }
+
+    /*
+    Decode the body of a binary file of any supported format, given its
+    version. Data that cannot be decoded is reported as Corrupt, with the
+    offset within the file and its path in the message.
+    */
+    fn decode_file<T>(
+        &self,
+        name: &str,
+        decode: impl FnOnce(u32, &[u8]) -> Result<T, MSErrors>,
+    ) -> Result<T, MSErrors> {
+        let bytes = self.read(name)?;
+        let (version, header_len, body) = self.checked_body(name, &bytes)?;
+        decode(version, body).map_err(|error| match error {
+            MSErrors::Corrupt { offset, message } => {
+                corrupt_in(&self.root.join(name), header_len + offset, message)
+            }
+            error => error,
+        })
+    }
+
+    /*
+    Version, header length and body of a binary file whose checksum matches.
+    Every file must have the header and checksum of the current format; a
+    legacy storage, read by `migrate`, also accepts older formats, checking
+    the checksum of the files that have one.
+    */
+    fn checked_body<'a>(
+        &self,
+        name: &str,
+        bytes: &'a [u8],
+    ) -> Result<(u32, usize, &'a [u8]), MSErrors> {
+        let corrupted = |offset, message| corrupt_in(&self.root.join(name), offset, message);
+        let (version, header_len) = split_header(bytes);
+        if !self.legacy {
+            if header_len == 0 {
+                return Err(corrupted(0, "missing header".to_string()));
+            }
+            if version != FORMAT_VERSION {
+                let message = format!(
+                    "format version {} in a format {} index",
+                    version, FORMAT_VERSION
+                );
+                return Err(corrupted(MAGIC.len(), message));
+            }
+        }
+        if version > FORMAT_VERSION {
+            return Err(MSErrors::StorageError(format!(
+                "{} has format version {}, newer than this build supports",
+                name, version
+            )));
+        }
+        if version < 4 {
+            return Ok((version, header_len, &bytes[header_len..]));
+        }
+        let Some(end) = bytes
+            .len()
+            .checked_sub(CHECKSUM_LEN)
+            .filter(|&end| end >= header_len)
+        else {
+            return Err(corrupted(bytes.len(), "missing checksum".to_string()));
+        };
+        let stored = u32::from_le_bytes(bytes[end..].try_into().unwrap());
+        let actual = crc32(&bytes[..end]);
+        if stored != actual {
+            let message = format!(
+                "checksum mismatch: stored {:08x}, computed {:08x}",
+                stored, actual
+            );
+            return Err(corrupted(end, message));
+        }
+        Ok((version, header_len, &bytes[header_len..end]))
+    }
+
+    fn write_versioned(&self, name: &str, body: Vec<u8>) -> Result<(), MSErrors> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + body.len() + CHECKSUM_LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend(body);
+        let checksum = crc32(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        self.write(name, &bytes)
+    }
+}
+
+// Damage at an offset of a file, naming the file in the message
+fn corrupt_in(file: &Path, offset: usize, message: String) -> MSErrors {
+    MSErrors::Corrupt {
+        offset,
+        message: format!("{}: {}", file.display(), message),
+    }
+}
+
+// Format version and header length of a binary file
+fn split_header(bytes: &[u8]) -> (u32, usize) {
+    match bytes.strip_prefix(MAGIC).and_then(|rest| rest.get(..4)) {
+        Some(version) => (u32::from_le_bytes(version.try_into().unwrap()), HEADER_LEN),
+        None => (1, 0),
+    }
+}
+
+fn encode_document(encoder: &mut Encoder, document: &Document) {
+    encoder.put_u64(document.id);
+    encoder.put_str(&document.title);
+    encoder.put_str(&document.content);
+
+    // Sort metadata keys so the file contents are stable
+    let mut metadata: Vec<_> = document.metadata.iter().collect();
+    metadata.sort();
+    encoder.put_u32(metadata.len() as u32);
+    for (key, value) in metadata {
+        encoder.put_str(key);
+        encoder.put_str(value);
+    }
+}
+
+// A presence flag, then each token's term, position, byte offsets, kind and payload
+fn encode_tokens(encoder: &mut Encoder, tokens: Option<&[Token]>) {
+    let Some(tokens) = tokens else {
+        encoder.put_u8(0);
+        return;
+    };
+    encoder.put_u8(1);
+    encoder.put_u32(tokens.len() as u32);
+    for token in tokens {
+        encoder.put_str(&token.term);
+        encoder.put_u32(token.position as u32);
+        encoder.put_u32(token.offset.0 as u32);
+        encoder.put_u32(token.offset.1 as u32);
+        encoder.put_u8(token.kind.code());
+        match &token.payload {
+            Some(payload) => {
+                encoder.put_u8(1);
+                encoder.put_bytes(payload);
+            }
+            None => encoder.put_u8(0),
+        }
+    }
+}
+
+fn decode_tokens(decoder: &mut Decoder, version: u32) -> Result<Option<Vec<Token>>, MSErrors> {
+    if decoder.get_u8()? == 0 {
+        return Ok(None);
+    }
+    let count = decoder.get_u32()? as usize;
+    let mut tokens = Vec::with_capacity(decoder.capacity(count));
+    for _ in 0..count {
+        let term = decoder.get_str()?;
+        let position = decoder.get_u32()? as usize;
+        let offset = (decoder.get_u32()? as usize, decoder.get_u32()? as usize);
+        // Format 3 tokens have no kind or payload
+        let kind = match version {
+            3 => TokenKind::Word,
+            _ => {
+                let offset = decoder.offset();
+                let code = decoder.get_u8()?;
+                TokenKind::from_code(code).ok_or_else(|| MSErrors::Corrupt {
+                    offset,
+                    message: format!("unknown token kind {}", code),
+                })?
+            }
+        };
+        let payload = match version {
+            3 => None,
+            _ => match decoder.get_u8()? {
+                0 => None,
+                _ => Some(decoder.get_bytes()?),
+            },
+        };
+        tokens.push(Token {
+            term,
+            position,
+            offset,
+            kind,
+            payload,
+        });
+    }
+    Ok(Some(tokens))
+}
+
+fn decode_document(decoder: &mut Decoder) -> Result<Document, MSErrors> {
+    let id = decoder.get_u64()?;
+    let title = decoder.get_str()?;
+    let content = decoder.get_str()?;
+    let count = decoder.get_u32()?;
+    let mut metadata = HashMap::with_capacity(decoder.capacity(count as usize));
+    for _ in 0..count {
+        let key = decoder.get_str()?;
+        let value = decoder.get_str()?;
+        metadata.insert(key, value);
+    }
+    Ok(Document {
+        id,
+        title,
+        content,
+        metadata,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mini-search-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_open_requires_settings() {
+        let dir = temp_dir("storage-open");
+        let storage = Storage::create(&dir).unwrap();
+        assert!(Storage::open(&dir).is_err());
+
+        storage.save_settings(&IndexSettings::default()).unwrap();
+        let storage = Storage::open(&dir).unwrap();
+        assert_eq!(storage.load_settings().unwrap(), IndexSettings::default());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_documents_round_trip() {
+        let dir = temp_dir("storage-docs");
+        let storage = Storage::create(&dir).unwrap();
+        assert!(storage.load_documents().unwrap().is_empty());
+
+        let document = Document {
+            id: 7,
+            title: "Title".to_string(),
+            content: "Some content".to_string(),
+            metadata: HashMap::from([("lang".to_string(), "en".to_string())]),
+        };
+        storage
+            .save_documents([(&document, 1.5, None)].into_iter())
+            .unwrap();
+        assert_eq!(
+            storage.load_documents().unwrap(),
+            vec![(document, 1.5, None)]
+        );
+
+        // A truncated file is reported with its path and the offset of the damage
+        let bytes = storage.read(DOCUMENTS_FILE).unwrap();
+        storage
+            .write(DOCUMENTS_FILE, &bytes[..bytes.len() - 4])
+            .unwrap();
+        match storage.load_documents() {
+            Err(MSErrors::Corrupt { offset, message }) => {
+                assert!(message.starts_with(&dir.join(DOCUMENTS_FILE).display().to_string()));
+                assert!(offset > 0 && offset < bytes.len() - 4);
+            }
+            other => panic!("expected Corrupt, got {:?}", other),
+        }
+
+        // So is a flipped bit that would still decode
+        let mut flipped = bytes.clone();
+        flipped[HEADER_LEN + 8] ^= 1;
+        storage.write(DOCUMENTS_FILE, &flipped).unwrap();
+        for result in [
+            storage.load_documents().map(|_| ()),
+            storage.verify_file(DOCUMENTS_FILE),
+        ] {
+            match result {
+                Err(MSErrors::Corrupt { offset, message }) => {
+                    assert_eq!(offset, bytes.len() - CHECKSUM_LEN);
+                    assert!(message.contains("checksum mismatch"));
+                }
+                other => panic!("expected Corrupt, got {:?}", other),
+            }
+        }
+
+        // A damaged header does not pass the file off as an older format
+        for (byte, expected) in [(0, "missing header"), (MAGIC.len(), "format version")] {
+            let mut damaged = bytes.clone();
+            damaged[byte] ^= 1;
+            storage.write(DOCUMENTS_FILE, &damaged).unwrap();
+            match storage.verify_file(DOCUMENTS_FILE) {
+                Err(MSErrors::Corrupt { message, .. }) => assert!(message.contains(expected)),
+                other => panic!("expected Corrupt, got {:?}", other),
+            }
+        }
+
+        // A count past the end of the file fails without reserving for it
+        storage
+            .write_versioned(DOCUMENTS_FILE, u64::MAX.to_le_bytes().to_vec())
+            .unwrap();
+        assert!(matches!(
+            storage.load_documents(),
+            Err(MSErrors::Corrupt { .. })
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_segments_round_trip() {
+        let dir = temp_dir("storage-segments");
+        let storage = Storage::create(&dir).unwrap();
+        assert!(storage.load_segments().unwrap().is_empty());
+
+        let segment = SegmentMeta {
+            id: 3,
+            doc_count: 1,
+            max_doc_id: 9,
+            created: 1714564800,
+            routing_values: vec!["acme".to_string()],
+        };
+        let document = Document {
+            id: 9,
+            title: String::new(),
+            content: String::new(),
+            metadata: HashMap::new(),
+        };
+        let tokens = vec![Token {
+            term: "archiv".to_string(),
+            position: 0,
+            offset: (1, 9),
+            kind: TokenKind::Hashtag,
+            payload: Some(vec![7]),
+        }];
+        storage
+            .save_segment_documents(&segment, [(&document, 1.0, Some(&tokens[..]))].into_iter())
+            .unwrap();
+        storage
+            .save_segments(std::slice::from_ref(&segment))
+            .unwrap();
+        assert_eq!(storage.load_segments().unwrap(), vec![segment.clone()]);
+        assert_eq!(
+            storage.load_segment_documents(&segment).unwrap(),
+            vec![(document, 1.0, Some(tokens))]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_unknown_token_kind() {
+        let mut encoder = Encoder::new();
+        encoder.put_u8(1);
+        encoder.put_u32(1);
+        encoder.put_str("x");
+        for value in [0, 0, 1] {
+            encoder.put_u32(value);
+        }
+        encoder.put_u8(255);
+        let bytes = encoder.into_bytes();
+        match decode_tokens(&mut Decoder::new(&bytes), FORMAT_VERSION) {
+            Err(MSErrors::Corrupt { offset, message }) => {
+                assert_eq!(offset, bytes.len() - 1);
+                assert!(message.contains("unknown token kind 255"));
+            }
+            other => panic!("expected Corrupt, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use super::codec::{Decoder, Encoder};
+use crate::errors::MSErrors;
+
+// Description of an immutable batch of documents stored in its own file
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SegmentMeta {
+    pub id: u64,
+    pub doc_count: usize,
+    pub max_doc_id: u64,
+    pub created: u64,                // Seconds since the Unix epoch
+    pub routing_values: Vec<String>, // Values of the routing field held by the segment
+}
+
+impl SegmentMeta {
+    // Name of the file holding the segment's documents
+    pub fn file_name(&self) -> String {
+        format!("segment-{}", self.id)
+    }
+}
+
+pub(super) fn encode_segments(segments: &[SegmentMeta]) -> Vec<u8> {
+    let mut encoder = Encoder::new();
+    encoder.put_u32(segments.len() as u32);
+    for segment in segments {
+        encoder.put_u64(segment.id);
+        encoder.put_u64(segment.doc_count as u64);
+        encoder.put_u64(segment.max_doc_id);
+        encoder.put_u64(segment.created);
+        encoder.put_u32(segment.routing_values.len() as u32);
+        for value in &segment.routing_values {
+            encoder.put_str(value);
+        }
+    }
+    encoder.into_bytes()
+}
+
+pub(super) fn decode_segments(bytes: &[u8]) -> Result<Vec<SegmentMeta>, MSErrors> {
+    let mut decoder = Decoder::new(bytes);
+    let count = decoder.get_u32()? as usize;
+    let mut segments = Vec::with_capacity(decoder.capacity(count));
+    for _ in 0..count {
+        let id = decoder.get_u64()?;
+        let doc_count = decoder.get_u64()? as usize;
+        let max_doc_id = decoder.get_u64()?;
+        let created = decoder.get_u64()?;
+        let routing_values = (0..decoder.get_u32()?)
+            .map(|_| decoder.get_str())
+            .collect::<Result<_, _>>()?;
+        segments.push(SegmentMeta {
+            id,
+            doc_count,
+            max_doc_id,
+            created,
+            routing_values,
+        });
+    }
+    Ok(segments)
+}
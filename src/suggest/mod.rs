@@ -0,0 +1,122 @@
+use crate::indexer::{InvertedIndex, Statistics};
+
+mod trie;
+
+pub use trie::Trie;
+
+// Terms in fewer documents than this get spelling suggestions
+const SUGGEST_BELOW_DOC_FREQ: usize = 2;
+// Suggestions returned per misspelled term
+const MAX_CORRECTIONS: usize = 3;
+
+// A proposed replacement for one query word
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Suggestion {
+    pub original: String,   // Word as typed in the query
+    pub suggestion: String, // Replacement as it appears in the indexed text
+    pub distance: usize,    // Edit distance between the analyzed terms
+    pub doc_freq: usize,    // Documents containing the replacement
+}
+
+// A completion for a typed prefix
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub text: String,
+    pub frequency: usize, // Documents containing the word, plus times it was searched for
+}
+
+// Largest edit distance worth suggesting for a term of this length
+fn max_distance(term: &str) -> usize {
+    match term.chars().count() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+/*
+Levenshtein distance between two strings, or None once it is known to
+exceed `max`. Only a band of width 2 * max + 1 around the diagonal is
+computed.
+*/
+pub fn edit_distance(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        let mut row_min = current[0];
+        for j in 1..=b.len() {
+            let substitution = previous[j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            current[j] = substitution.min(previous[j] + 1).min(current[j - 1] + 1);
+            row_min = row_min.min(current[j]);
+        }
+        if row_min > max {
+            return None;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    (previous[b.len()] <= max).then_some(previous[b.len()])
+}
+
+/*
+Dictionary terms close to `term`, best first: by edit distance, then by
+document frequency. Only terms found in more documents than `term` itself
+are proposed, and nothing is proposed for terms that are common enough.
+*/
+pub fn corrections(index: &InvertedIndex, term: &str) -> Vec<(String, usize, usize)> {
+    let doc_freq = index.doc_freq(term);
+    let max = max_distance(term);
+    if doc_freq >= SUGGEST_BELOW_DOC_FREQ || max == 0 {
+        return Vec::new();
+    }
+    let mut candidates: Vec<(String, usize, usize)> = index
+        .terms()
+        .filter_map(|candidate| {
+            let candidate_freq = index.doc_freq(candidate);
+            if candidate_freq <= doc_freq {
+                return None;
+            }
+            let distance = edit_distance(term, candidate, max)?;
+            Some((candidate.clone(), distance, candidate_freq))
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.1.cmp(&b.1).then(b.2.cmp(&a.2)).then(a.0.cmp(&b.0)));
+    candidates.truncate(MAX_CORRECTIONS);
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::DocId;
+    use crate::tokenizer::{AnalyzerPreset, Language, Tokenizer};
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting", 3), Some(3));
+        assert_eq!(edit_distance("kitten", "sitting", 2), None);
+        assert_eq!(edit_distance("fox", "fox", 0), Some(0));
+        assert_eq!(edit_distance("", "ab", 2), Some(2));
+        assert_eq!(edit_distance("café", "cafe", 1), Some(1));
+    }
+
+    #[test]
+    fn test_corrections() {
+        let tokenizer = Tokenizer::with_preset(AnalyzerPreset::Simple, Language::English).unwrap();
+        let mut index = InvertedIndex::new(tokenizer);
+        index.index_document(DocId(1), "search engine");
+        index.index_document(DocId(2), "search index");
+        index.index_document(DocId(3), "research");
+
+        let found = corrections(&index, "serch");
+        assert_eq!(found[0], ("search".to_string(), 1, 2));
+        assert!(corrections(&index, "search").is_empty());
+        assert!(corrections(&index, "zzzzzz").is_empty());
+    }
+}
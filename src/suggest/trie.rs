@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+
+#[derive(Default, Clone)]
+struct Node {
+    children: BTreeMap<char, Node>,
+    count: usize, // Times the word ending here was inserted
+}
+
+// Prefix tree of words with a frequency per word
+#[derive(Default, Clone)]
+pub struct Trie {
+    root: Node,
+}
+
+impl Trie {
+    pub fn new() -> Self {
+        Trie::default()
+    }
+
+    pub fn insert(&mut self, word: &str) {
+        let mut node = &mut self.root;
+        for ch in word.chars() {
+            node = node.children.entry(ch).or_default();
+        }
+        node.count += 1;
+    }
+
+    // Decrement a word's frequency, pruning branches that become empty
+    pub fn remove(&mut self, word: &str) {
+        fn remove_from(node: &mut Node, mut chars: std::str::Chars) -> bool {
+            match chars.next() {
+                None => node.count = node.count.saturating_sub(1),
+                Some(ch) => {
+                    if let Some(child) = node.children.get_mut(&ch)
+                        && remove_from(child, chars)
+                    {
+                        node.children.remove(&ch);
+                    }
+                }
+            }
+            node.count == 0 && node.children.is_empty()
+        }
+        remove_from(&mut self.root, word.chars());
+    }
+
+    pub fn frequency(&self, word: &str) -> usize {
+        let mut node = &self.root;
+        for ch in word.chars() {
+            match node.children.get(&ch) {
+                Some(child) => node = child,
+                None => return 0,
+            }
+        }
+        node.count
+    }
+
+    // Words starting with `prefix`, most frequent first, then alphabetically
+    pub fn completions(&self, prefix: &str, limit: usize) -> Vec<(String, usize)> {
+        let mut node = &self.root;
+        for ch in prefix.chars() {
+            match node.children.get(&ch) {
+                Some(child) => node = child,
+                None => return Vec::new(),
+            }
+        }
+        let mut words = Vec::new();
+        let mut stack = vec![(prefix.to_string(), node)];
+        while let Some((word, node)) = stack.pop() {
+            if node.count > 0 {
+                words.push((word.clone(), node.count));
+            }
+            for (ch, child) in &node.children {
+                let mut next = word.clone();
+                next.push(*ch);
+                stack.push((next, child));
+            }
+        }
+        words.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        words.truncate(limit);
+        words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completions() {
+        let mut trie = Trie::new();
+        for word in ["search", "searching", "search", "sea", "seal", "other"] {
+            trie.insert(word);
+        }
+        assert_eq!(
+            trie.completions("sea", 3),
+            vec![
+                ("search".to_string(), 2),
+                ("sea".to_string(), 1),
+                ("seal".to_string(), 1)
+            ]
+        );
+        assert!(trie.completions("x", 3).is_empty());
+
+        trie.remove("search");
+        trie.remove("searching");
+        assert_eq!(trie.frequency("search"), 1);
+        assert_eq!(trie.frequency("searching"), 0);
+        assert_eq!(trie.completions("searchi", 3), vec![]);
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::document::Document;
+use crate::searcher::{SearchEngine, SearchResults};
+use crate::settings::IndexSettings;
+
+/*
+Helpers for relevance tests in applications embedding mini-search: build a
+tiny in-memory index from literals, then assert on the order of the ids a
+query returns. A failed assertion prints expected and actual rankings side
+by side, with scores, so a regression shows which documents moved.
+
+    let index = TestIndex::from_docs(&[(1, "Rust", "systems language"), (2, "Go", "language")]);
+    index.assert_ranking("rust language", &[1, 2]);
+*/
+pub struct TestIndex {
+    engine: SearchEngine,
+}
+
+impl TestIndex {
+    pub fn new() -> Self {
+        TestIndex::with_settings(IndexSettings::default())
+    }
+
+    #[track_caller]
+    pub fn with_settings(settings: IndexSettings) -> Self {
+        TestIndex {
+            engine: SearchEngine::new(settings).expect("invalid index settings"),
+        }
+    }
+
+    // An index of (id, title, content) literals
+    pub fn from_docs(docs: &[(u64, &str, &str)]) -> Self {
+        docs.iter()
+            .fold(TestIndex::new(), |index, &(id, title, content)| {
+                index.doc(id, title, content)
+            })
+    }
+
+    #[track_caller]
+    pub fn doc(self, id: u64, title: &str, content: &str) -> Self {
+        self.doc_with(id, title, content, &[])
+    }
+
+    // Panics if the engine rejects the document, e.g. on a duplicate id
+    #[track_caller]
+    pub fn doc_with(
+        mut self,
+        id: u64,
+        title: &str,
+        content: &str,
+        metadata: &[(&str, &str)],
+    ) -> Self {
+        let document = Document {
+            id,
+            title: title.to_string(),
+            content: content.to_string(),
+            metadata: metadata
+                .iter()
+                .map(|&(key, value)| (key.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>(),
+        };
+        if let Err(e) = self.engine.add_document(document) {
+            panic!("failed to index document {}: {}", id, e);
+        }
+        self
+    }
+
+    pub fn engine(&self) -> &SearchEngine {
+        &self.engine
+    }
+
+    pub fn engine_mut(&mut self) -> &mut SearchEngine {
+        &mut self.engine
+    }
+
+    // Ids of every match, best first
+    pub fn ranked(&self, query: &str) -> Vec<u64> {
+        self.results(query).documents.iter().map(|d| d.id).collect()
+    }
+
+    // Every match must appear, in exactly this order
+    #[track_caller]
+    pub fn assert_ranking(&self, query: &str, expected: &[u64]) {
+        let results = self.results(query);
+        if let Some(diff) = ranking_diff(query, expected, &results, results.documents.len()) {
+            panic!("{}", diff);
+        }
+    }
+
+    // The best matches must be these, in this order; lower ranks are ignored
+    #[track_caller]
+    pub fn assert_top(&self, query: &str, expected: &[u64]) {
+        let results = self.results(query);
+        if let Some(diff) = ranking_diff(query, expected, &results, expected.len()) {
+            panic!("{}", diff);
+        }
+    }
+
+    fn results(&self, query: &str) -> SearchResults {
+        self.engine
+            .search(query, self.engine.document_count().max(1))
+    }
+}
+
+impl Default for TestIndex {
+    fn default() -> Self {
+        TestIndex::new()
+    }
+}
+
+/*
+A table of the first `depth` ranks of `results` against `expected`, rows
+that differ marked with '>'; `None` when they agree.
+*/
+fn ranking_diff(
+    query: &str,
+    expected: &[u64],
+    results: &SearchResults,
+    depth: usize,
+) -> Option<String> {
+    let actual: Vec<u64> = results.documents.iter().take(depth).map(|d| d.id).collect();
+    if actual == expected {
+        return None;
+    }
+    let cell = |id: Option<&u64>| id.map_or("-".to_string(), u64::to_string);
+    let mut diff = format!(
+        "ranking for {:?} differs\n    rank  expected  actual  score\n",
+        query
+    );
+    for rank in 0..expected.len().max(actual.len()) {
+        let (want, got) = (expected.get(rank), actual.get(rank));
+        let score = results
+            .scores
+            .get(rank)
+            .filter(|_| got.is_some())
+            .map_or(String::new(), |score| format!("{:.4}", score));
+        let marker = if want == got { ' ' } else { '>' };
+        let _ = writeln!(
+            diff,
+            "  {} {:>4}  {:>8}  {:>6}  {}",
+            marker,
+            rank + 1,
+            cell(want),
+            cell(got),
+            score
+        );
+    }
+    Some(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranking_helpers() {
+        let index = TestIndex::from_docs(&[
+            (1, "Rust", "a systems language"),
+            (2, "Go", "a language with garbage collection"),
+            (3, "Cooking", "recipes"),
+        ])
+        .doc_with(
+            4,
+            "Rust book",
+            "learning the rust language",
+            &[("kind", "book")],
+        );
+
+        assert_eq!(index.ranked("recipes"), [3]);
+        index.assert_top("rust", &[4]);
+        index.assert_ranking("collection", &[2]);
+
+        let results = index.results("language");
+        let diff = ranking_diff("language", &[3, 1], &results, results.documents.len()).unwrap();
+        let lines: Vec<&str> = diff.lines().collect();
+        assert_eq!(lines[0], "ranking for \"language\" differs");
+        assert!(lines[2].starts_with("  >    1         3       1"));
+        assert!(lines[4].starts_with("  >    3         -       4"));
+    }
+}
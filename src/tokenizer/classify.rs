@@ -0,0 +1,61 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::TokenKind;
+
+static URL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^(?i:https?|ftp)://[^\s<>"'`]+"#).unwrap());
+static EMAIL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\w[\w.%+-]*@[\w-]+(?:\.[\w-]+)+").unwrap());
+static MENTION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^@\w+").unwrap());
+static HASHTAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^#\w+").unwrap());
+
+/*
+Kind and byte length of the URL, email, @mention or #hashtag at the start
+of `text`, if there is one. Punctuation ending a URL is left out, so a URL
+closing a sentence or a parenthesis is still matched alone.
+*/
+pub(super) fn classify(text: &str) -> Option<(TokenKind, usize)> {
+    let kinds: &[TokenKind] = match text.chars().next()? {
+        '@' => &[TokenKind::Mention],
+        '#' => &[TokenKind::Hashtag],
+        ch if ch.is_alphanumeric() => &[TokenKind::Url, TokenKind::Email],
+        _ => return None,
+    };
+    kinds.iter().find_map(|kind| {
+        let pattern = match kind {
+            TokenKind::Url => &URL,
+            TokenKind::Email => &EMAIL,
+            TokenKind::Mention => &MENTION,
+            _ => &HASHTAG,
+        };
+        let found = pattern.find(text)?.as_str();
+        let len = match kind {
+            TokenKind::Url => found
+                .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '}'])
+                .len(),
+            _ => found.len(),
+        };
+        Some((*kind, len))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let url = "https://example.com/a?b=1).";
+        assert_eq!(classify(url), Some((TokenKind::Url, url.len() - 2)));
+        assert_eq!(
+            classify("ada.lovelace@example.co.uk, hi"),
+            Some((TokenKind::Email, 26))
+        );
+        assert_eq!(classify("@ada_l!"), Some((TokenKind::Mention, 6)));
+        assert_eq!(classify("#rust2024 "), Some((TokenKind::Hashtag, 9)));
+        assert_eq!(classify("ada@localhost"), None);
+        assert_eq!(classify("# heading"), None);
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use super::Language;
+use super::filters::{STOP_WORDS_VERSION, stop_words};
+
+// Metadata field holding the detected language of a document
+pub const LANGUAGE_FIELD: &str = "language";
+
+static STOP_WORDS: LazyLock<Vec<HashSet<&'static str>>> = LazyLock::new(|| {
+    Language::ALL
+        .iter()
+        .map(|&language| {
+            stop_words(language, STOP_WORDS_VERSION)
+                .iter()
+                .copied()
+                .collect()
+        })
+        .collect()
+});
+
+/*
+Guesses the language of a text by counting the stop words of each
+language among its first `max_words` words. Function words make up a
+large share of any running text, so a sentence or two is usually enough.
+Texts with fewer than `min_matches` stop words of the best language, or
+where two languages tie, are left undetected.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LanguageDetector {
+    pub min_matches: usize,
+    pub max_words: usize,
+}
+
+impl Default for LanguageDetector {
+    fn default() -> Self {
+        LanguageDetector {
+            min_matches: 2,
+            max_words: 500,
+        }
+    }
+}
+
+impl LanguageDetector {
+    pub fn new() -> Self {
+        LanguageDetector::default()
+    }
+
+    pub fn detect(&self, text: &str) -> Option<Language> {
+        let mut counts = [0; Language::ALL.len()];
+        let words = text
+            .split(|ch: char| !ch.is_alphabetic())
+            .filter(|word| !word.is_empty())
+            .take(self.max_words);
+        for word in words {
+            let word = word.to_lowercase();
+            for (count, stops) in counts.iter_mut().zip(STOP_WORDS.iter()) {
+                if stops.contains(word.as_str()) {
+                    *count += 1;
+                }
+            }
+        }
+        let best = counts.iter().copied().max()?;
+        if best < self.min_matches || counts.iter().filter(|&&count| count == best).count() > 1 {
+            return None;
+        }
+        let index = counts.iter().position(|&count| count == best)?;
+        Some(Language::ALL[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        let detector = LanguageDetector::new();
+        let texts = [
+            ("The cat is sleeping on the table.", Language::English),
+            ("Le chat dort sur la table, et il rêve.", Language::French),
+            (
+                "Die Katze schläft auf dem Tisch und träumt.",
+                Language::German,
+            ),
+            (
+                "El gato duerme en la mesa y sueña con los peces.",
+                Language::Spanish,
+            ),
+        ];
+        for (text, language) in texts {
+            assert_eq!(detector.detect(text), Some(language), "{}", text);
+        }
+        assert_eq!(detector.detect("Lovelace Babbage"), None);
+        assert_eq!(detector.detect(""), None);
+    }
+}
@@ -0,0 +1,308 @@
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use stemmer::Stemmer;
+
+use super::{Language, Token, TokenKind};
+use crate::errors::MSErrors;
+
+/*
+One step of an analysis chain. Each token of a word is passed through the
+filters in order; a filter pushes what the token becomes onto `out`:
+nothing to drop it, one token to rewrite its term, kind or payload, or
+several to add terms at the same position (synonyms, n-grams). Positions
+are assigned after the last filter, so changes to them are ignored.
+*/
+pub trait TokenFilter: fmt::Debug + Send + Sync {
+    fn filter(&self, token: Token, out: &mut Vec<Token>);
+}
+
+// A copy of `token` with another term
+fn with_term(token: &Token, term: String) -> Token {
+    Token {
+        term,
+        ..token.clone()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LowercaseFilter;
+
+impl TokenFilter for LowercaseFilter {
+    fn filter(&self, mut token: Token, out: &mut Vec<Token>) {
+        token.term = token.term.to_lowercase();
+        out.push(token);
+    }
+}
+
+/*
+Version of the built-in stop lists used by new indexes. Indexes keep the
+version they were created with, since a longer list changes how their
+queries are analyzed. Version 1 is the short English list of older
+indexes; the lists of other languages are the same in every version.
+*/
+pub const STOP_WORDS_VERSION: u32 = 2;
+
+const ENGLISH_STOP_WORDS_V1: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "has", "he", "in", "is", "it",
+    "its", "of", "on", "that", "the", "to", "was", "were", "will", "with",
+];
+
+// Pronouns, articles, auxiliaries, conjunctions and the most common prepositions
+const ENGLISH_STOP_WORDS: &[&str] = &[
+    "a", "am", "an", "and", "are", "as", "at", "be", "been", "being", "but", "by", "did", "do",
+    "does", "for", "from", "had", "has", "have", "he", "her", "hers", "him", "his", "i", "if",
+    "in", "into", "is", "it", "its", "me", "my", "no", "nor", "not", "of", "on", "or", "our",
+    "ours", "she", "so", "such", "than", "that", "the", "their", "theirs", "them", "then", "there",
+    "these", "they", "this", "those", "to", "us", "was", "we", "were", "what", "when", "which",
+    "who", "whom", "will", "with", "would", "you", "your", "yours",
+];
+
+const FRENCH_STOP_WORDS: &[&str] = &[
+    "au", "aux", "avec", "ce", "ces", "dans", "de", "des", "du", "elle", "en", "est", "et", "il",
+    "ils", "je", "la", "le", "les", "leur", "lui", "ma", "mais", "me", "mes", "moi", "mon", "ne",
+    "nous", "on", "ou", "par", "pas", "pour", "qu", "que", "qui", "sa", "se", "ses", "son", "sont",
+    "sur", "ta", "te", "tes", "toi", "ton", "tu", "un", "une", "vos", "votre", "vous",
+];
+
+const GERMAN_STOP_WORDS: &[&str] = &[
+    "aber", "als", "am", "an", "auch", "auf", "aus", "bei", "bin", "bis", "da", "das", "dass",
+    "dem", "den", "der", "des", "die", "du", "ein", "eine", "einen", "einer", "er", "es", "für",
+    "hat", "ich", "im", "in", "ist", "mit", "nach", "nicht", "noch", "nur", "oder", "sich", "sie",
+    "sind", "über", "um", "und", "uns", "von", "vor", "war", "wie", "wir", "zu", "zum", "zur",
+];
+
+const SPANISH_STOP_WORDS: &[&str] = &[
+    "al", "como", "con", "de", "del", "el", "ella", "ellos", "en", "es", "esta", "este", "fue",
+    "ha", "la", "las", "le", "lo", "los", "más", "me", "mi", "no", "nos", "o", "para", "pero",
+    "por", "que", "se", "si", "sin", "son", "su", "sus", "también", "te", "tu", "un", "una", "y",
+    "ya", "yo",
+];
+
+// The built-in stop words of `language` in list `version`
+pub(super) fn stop_words(language: Language, version: u32) -> &'static [&'static str] {
+    match language {
+        Language::English if version == 1 => ENGLISH_STOP_WORDS_V1,
+        Language::English => ENGLISH_STOP_WORDS,
+        Language::French => FRENCH_STOP_WORDS,
+        Language::German => GERMAN_STOP_WORDS,
+        Language::Spanish => SPANISH_STOP_WORDS,
+    }
+}
+
+// Drops common words, leaving other kinds of tokens; put it after lowercasing
+#[derive(Debug, Clone)]
+pub struct StopWordFilter {
+    words: HashSet<String>,
+}
+
+impl StopWordFilter {
+    // The latest built-in stop words of `language`
+    pub fn new(language: Language) -> Self {
+        StopWordFilter::from_words(stop_words(language, STOP_WORDS_VERSION).iter().copied())
+    }
+
+    // The built-in stop words of `language` in list `version`
+    pub fn versioned(language: Language, version: u32) -> Result<Self, MSErrors> {
+        if !(1..=STOP_WORDS_VERSION).contains(&version) {
+            return Err(MSErrors::ParseError(format!(
+                "unknown stop word list version: {}",
+                version
+            )));
+        }
+        Ok(StopWordFilter::from_words(
+            stop_words(language, version).iter().copied(),
+        ))
+    }
+
+    pub fn from_words<'a>(words: impl IntoIterator<Item = &'a str>) -> Self {
+        StopWordFilter {
+            words: words.into_iter().map(String::from).collect(),
+        }
+    }
+}
+
+impl TokenFilter for StopWordFilter {
+    fn filter(&self, token: Token, out: &mut Vec<Token>) {
+        if token.kind != TokenKind::Word || !self.words.contains(&token.term) {
+            out.push(token);
+        }
+    }
+}
+
+thread_local! {
+    // Stemmers are not thread-safe, so each thread builds its own once per
+    // language and reuses it for every token after that
+    static STEMMERS: RefCell<HashMap<&'static str, Stemmer>> = RefCell::new(HashMap::new());
+}
+
+// Run `f` with this thread's stemmer for `language`, None if it cannot be built
+fn with_stemmer<T>(language: Language, f: impl FnOnce(&mut Stemmer) -> T) -> Option<T> {
+    STEMMERS.with_borrow_mut(|stemmers| {
+        let algorithm = language.as_str();
+        let stemmer = match stemmers.entry(algorithm) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(Stemmer::new(algorithm)?),
+        };
+        Some(f(stemmer))
+    })
+}
+
+fn no_stemmer(language: Language) -> MSErrors {
+    MSErrors::TokenizeError(format!("no stemmer for {}", language.as_str()))
+}
+
+/*
+Reduces words to their stem, e.g. "jumps" and "jumping" to "jump"; other
+kinds are kept as is. The stemmer is built when the filter is, so a
+language without one fails there; other threads build their own copy of
+the same algorithm on first use.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct StemFilter {
+    language: Language,
+}
+
+impl StemFilter {
+    // Fails if no stemmer is available for `language`
+    pub fn new(language: Language) -> Result<Self, MSErrors> {
+        with_stemmer(language, |_| ()).ok_or_else(|| no_stemmer(language))?;
+        Ok(StemFilter { language })
+    }
+}
+
+impl TokenFilter for StemFilter {
+    fn filter(&self, mut token: Token, out: &mut Vec<Token>) {
+        if token.kind == TokenKind::Word {
+            token.term = with_stemmer(self.language, |stemmer| stemmer.stem(&token.term))
+                .expect("the stemmer was built with the filter");
+        }
+        out.push(token);
+    }
+}
+
+// Adds the synonyms of a term at its position, keeping the term itself
+#[derive(Debug, Clone, Default)]
+pub struct SynonymFilter {
+    synonyms: HashMap<String, Vec<String>>,
+}
+
+impl SynonymFilter {
+    pub fn new() -> Self {
+        SynonymFilter::default()
+    }
+
+    // Map `term` to `synonyms`; terms are compared after the filters before this one
+    pub fn add<'a>(mut self, term: &str, synonyms: impl IntoIterator<Item = &'a str>) -> Self {
+        let entry = self.synonyms.entry(term.to_string()).or_default();
+        for synonym in synonyms {
+            if synonym != term && !entry.iter().any(|s| s == synonym) {
+                entry.push(synonym.to_string());
+            }
+        }
+        self
+    }
+}
+
+impl TokenFilter for SynonymFilter {
+    fn filter(&self, token: Token, out: &mut Vec<Token>) {
+        let synonyms = self.synonyms.get(&token.term).into_iter().flatten();
+        let added: Vec<Token> = synonyms.map(|s| with_term(&token, s.clone())).collect();
+        out.push(token);
+        out.extend(added);
+    }
+}
+
+/*
+Replaces a term with its character n-grams of `min` to `max` characters,
+or only those starting the term when `edge` is set (for search-as-you-type).
+Terms shorter than `min` are kept whole.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct NGramFilter {
+    min: usize,
+    max: usize,
+    edge: bool,
+}
+
+impl NGramFilter {
+    pub fn new(min: usize, max: usize) -> Self {
+        NGramFilter {
+            min: min.max(1),
+            max: max.max(min),
+            edge: false,
+        }
+    }
+
+    pub fn edge(min: usize, max: usize) -> Self {
+        NGramFilter {
+            edge: true,
+            ..NGramFilter::new(min, max)
+        }
+    }
+}
+
+impl TokenFilter for NGramFilter {
+    fn filter(&self, token: Token, out: &mut Vec<Token>) {
+        let chars: Vec<char> = token.term.chars().collect();
+        if chars.len() < self.min {
+            out.push(token);
+            return;
+        }
+        let starts = if self.edge { 0..1 } else { 0..chars.len() };
+        for start in starts {
+            let longest = self.max.min(chars.len() - start);
+            for len in self.min..=longest {
+                out.push(with_term(
+                    &token,
+                    chars[start..start + len].iter().collect(),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(filter: &dyn TokenFilter, term: &str) -> Vec<String> {
+        let token = Token {
+            term: term.to_string(),
+            position: 0,
+            offset: (0, term.len()),
+            kind: TokenKind::Word,
+            payload: None,
+        };
+        let mut out = Vec::new();
+        filter.filter(token, &mut out);
+        out.into_iter().map(|t| t.term).collect()
+    }
+
+    #[test]
+    fn test_filters() {
+        assert_eq!(apply(&LowercaseFilter, "Fox"), ["fox"]);
+        let stop = StopWordFilter::new(Language::English);
+        assert!(apply(&stop, "the").is_empty());
+        assert!(apply(&stop, "this").is_empty());
+        // Older indexes keep the short list
+        let short = StopWordFilter::versioned(Language::English, 1).unwrap();
+        assert_eq!(apply(&short, "this"), ["this"]);
+        assert!(apply(&short, "was").is_empty());
+        assert!(StopWordFilter::versioned(Language::English, STOP_WORDS_VERSION + 1).is_err());
+        assert_eq!(
+            apply(&StemFilter::new(Language::English).unwrap(), "jumps"),
+            ["jump"]
+        );
+
+        let synonyms = SynonymFilter::new().add("car", ["auto", "car", "automobile"]);
+        assert_eq!(apply(&synonyms, "car"), ["car", "auto", "automobile"]);
+        assert_eq!(apply(&synonyms, "bus"), ["bus"]);
+
+        assert_eq!(apply(&NGramFilter::new(2, 3), "fox"), ["fo", "fox", "ox"]);
+        assert_eq!(apply(&NGramFilter::edge(1, 3), "foxes"), ["f", "fo", "fox"]);
+        assert_eq!(apply(&NGramFilter::edge(2, 3), "a"), ["a"]);
+    }
+}
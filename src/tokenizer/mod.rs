@@ -1,110 +1,531 @@
-use std::collections::HashSet;
-use stemmer::Stemmer;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use regex::Regex;
+
+use crate::errors::MSErrors;
+
+mod classify;
+mod detect;
+mod filters;
+mod stream;
+
+pub use detect::{LANGUAGE_FIELD, LanguageDetector};
+pub use filters::{
+    LowercaseFilter, NGramFilter, STOP_WORDS_VERSION, StemFilter, StopWordFilter, SynonymFilter,
+    TokenFilter,
+};
+pub use stream::{TokenStream, Tokens};
 
 // Define supported languages (extendable for future use)
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Language {
     English,
+    French,
+    German,
+    Spanish,
+}
+
+impl Language {
+    pub const ALL: [Language; 4] = [
+        Language::English,
+        Language::French,
+        Language::German,
+        Language::Spanish,
+    ];
+
+    // Name used in persisted settings, metadata and by the stemmer
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Language::English => "english",
+            Language::French => "french",
+            Language::German => "german",
+            Language::Spanish => "spanish",
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = MSErrors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "english" | "en" => Ok(Language::English),
+            "french" | "fr" => Ok(Language::French),
+            "german" | "de" => Ok(Language::German),
+            "spanish" | "es" => Ok(Language::Spanish),
+            other => Err(MSErrors::ParseError(format!("unknown language: {}", other))),
+        }
+    }
+}
+
+/*
+What a token was recognized as. Kinds are kept in postings, so filters
+and scorers can treat classes of tokens differently; URLs, emails,
+mentions and hashtags are only recognized with `Tokenizer::with_classifier`.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TokenKind {
+    #[default]
+    Word,
+    Number, // Digits, possibly with a decimal point
+    Cjk,    // A single Chinese or Japanese character
+    Url,
+    Email,
+    Mention, // @name
+    Hashtag, // #topic
+}
+
+impl TokenKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenKind::Word => "word",
+            TokenKind::Number => "number",
+            TokenKind::Cjk => "cjk",
+            TokenKind::Url => "url",
+            TokenKind::Email => "email",
+            TokenKind::Mention => "mention",
+            TokenKind::Hashtag => "hashtag",
+        }
+    }
+
+    // Stable code used in binary index files
+    pub fn code(&self) -> u8 {
+        match self {
+            TokenKind::Word => 0,
+            TokenKind::Url => 1,
+            TokenKind::Email => 2,
+            TokenKind::Mention => 3,
+            TokenKind::Hashtag => 4,
+            TokenKind::Number => 5,
+            TokenKind::Cjk => 6,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(TokenKind::Word),
+            1 => Some(TokenKind::Url),
+            2 => Some(TokenKind::Email),
+            3 => Some(TokenKind::Mention),
+            4 => Some(TokenKind::Hashtag),
+            5 => Some(TokenKind::Number),
+            6 => Some(TokenKind::Cjk),
+            _ => None,
+        }
+    }
 }
 
-// Token struct to hold term, position, and offset
-#[derive(Debug, PartialEq)]
+// Token struct to hold term, position, offset, kind and payload
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     pub term: String,
     pub position: usize,
     pub offset: (usize, usize),
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kind: TokenKind,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub payload: Option<Vec<u8>>, // Set by custom filters, kept with positions
 }
 
+// Built-in analysis chains, selectable by name in the index settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum AnalyzerPreset {
+    Standard,   // Alphanumeric words, lowercased
+    Simple,     // Letter-only words, lowercased
+    Whitespace, // Split on whitespace only, lowercased
+    Keyword,    // The whole input as a single token, unchanged
+    Stop,       // Simple plus stop-word removal
+    English,    // Stop-word removal and stemming
+    Path,       // Each leading part of a '/'-separated path or URL, unchanged
+    Pattern,    // Matches of the index's token pattern, lowercased
+    EdgeNGram,  // Standard, indexing the leading 2 to 20 characters of each word, for autocomplete
+}
+
+impl AnalyzerPreset {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnalyzerPreset::Standard => "standard",
+            AnalyzerPreset::Simple => "simple",
+            AnalyzerPreset::Whitespace => "whitespace",
+            AnalyzerPreset::Keyword => "keyword",
+            AnalyzerPreset::Stop => "stop",
+            AnalyzerPreset::English => "english",
+            AnalyzerPreset::Path => "path",
+            AnalyzerPreset::Pattern => "pattern",
+            AnalyzerPreset::EdgeNGram => "edge_ngram",
+        }
+    }
+
+    fn base(&self) -> BaseTokenizer {
+        match self {
+            AnalyzerPreset::Standard | AnalyzerPreset::EdgeNGram => BaseTokenizer::Alphanumeric,
+            AnalyzerPreset::Whitespace => BaseTokenizer::Whitespace,
+            AnalyzerPreset::Keyword => BaseTokenizer::Keyword,
+            AnalyzerPreset::Path => BaseTokenizer::PathHierarchy('/'),
+            AnalyzerPreset::Pattern => BaseTokenizer::Matches(
+                Regex::new(DEFAULT_TOKEN_PATTERN).expect("default token pattern is valid"),
+            ),
+            _ => BaseTokenizer::Letter,
+        }
+    }
+
+    fn filters(
+        &self,
+        language: Language,
+        stop_stage: StopWordStage,
+        stop_version: u32,
+    ) -> Result<Vec<Arc<dyn TokenFilter>>, MSErrors> {
+        let mut filters: Vec<Arc<dyn TokenFilter>> = Vec::new();
+        if !matches!(self, AnalyzerPreset::Keyword | AnalyzerPreset::Path) {
+            filters.push(Arc::new(LowercaseFilter));
+        }
+        let stems = *self == AnalyzerPreset::English;
+        let stops = matches!(self, AnalyzerPreset::Stop | AnalyzerPreset::English);
+        let stop_words = || StopWordFilter::versioned(language, stop_version);
+        if stops && (!stems || stop_stage != StopWordStage::AfterStem) {
+            filters.push(Arc::new(stop_words()?));
+        }
+        if stems {
+            filters.push(Arc::new(StemFilter::new(language)?));
+            if stop_stage != StopWordStage::BeforeStem {
+                filters.push(Arc::new(stop_words()?));
+            }
+        }
+        if *self == AnalyzerPreset::EdgeNGram {
+            filters.push(Arc::new(NGramFilter::edge(2, 20)));
+        }
+        Ok(filters)
+    }
+}
+
+impl FromStr for AnalyzerPreset {
+    type Err = MSErrors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "standard" => Ok(AnalyzerPreset::Standard),
+            "simple" => Ok(AnalyzerPreset::Simple),
+            "whitespace" => Ok(AnalyzerPreset::Whitespace),
+            "keyword" => Ok(AnalyzerPreset::Keyword),
+            "stop" => Ok(AnalyzerPreset::Stop),
+            "english" => Ok(AnalyzerPreset::English),
+            "path" => Ok(AnalyzerPreset::Path),
+            "pattern" => Ok(AnalyzerPreset::Pattern),
+            "edge_ngram" => Ok(AnalyzerPreset::EdgeNGram),
+            other => Err(MSErrors::ParseError(format!("unknown analyzer: {}", other))),
+        }
+    }
+}
+
+// Where chains that stem remove stop words
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum StopWordStage {
+    #[default]
+    BeforeStem, // Words as written, lowercased
+    AfterStem, // Stems equal to a stop word; "does" stems to "doe" and is kept
+    Both,
+}
+
+impl StopWordStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StopWordStage::BeforeStem => "before_stem",
+            StopWordStage::AfterStem => "after_stem",
+            StopWordStage::Both => "both",
+        }
+    }
+}
+
+impl FromStr for StopWordStage {
+    type Err = MSErrors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "before_stem" => Ok(StopWordStage::BeforeStem),
+            "after_stem" => Ok(StopWordStage::AfterStem),
+            "both" => Ok(StopWordStage::Both),
+            other => Err(MSErrors::ParseError(format!(
+                "unknown stop word stage: {}",
+                other
+            ))),
+        }
+    }
+}
+
+// Words of the `pattern` preset unless the index sets its own pattern
+pub const DEFAULT_TOKEN_PATTERN: &str = r"\w+";
+
+// How text is split into words before the token filters run
 #[derive(Debug, Clone)]
-pub struct Tokenizer {
-    language: Language,
-    stop_words: HashSet<String>,
+pub enum BaseTokenizer {
+    Alphanumeric,        // Words of letters and digits, split on whitespace and punctuation
+    Letter,              // Same, but digits are left out of words
+    Compound, // Alphanumeric, but '.' and '_' between letters or digits join them: "x86_64", "3.14"
+    Whitespace, // Everything between whitespace
+    Keyword,  // The trimmed input as a single word
+    Matches(Regex), // Every match of the pattern
+    Split(Regex), // The non-empty text between matches of the pattern
+    PathHierarchy(char), // Each leading part of a path, e.g. "/a", "/a/b", "/a/b/c"
+}
+
+impl BaseTokenizer {
+    // Words are the matches of `pattern`
+    pub fn matches(pattern: &str) -> Result<Self, MSErrors> {
+        Ok(BaseTokenizer::Matches(compile(pattern)?))
+    }
+
+    // Words are the text between matches of `pattern`
+    pub fn split(pattern: &str) -> Result<Self, MSErrors> {
+        Ok(BaseTokenizer::Split(compile(pattern)?))
+    }
+
+    // Whether words are found by scanning characters rather than the whole text
+    fn is_char_based(&self) -> bool {
+        matches!(
+            self,
+            BaseTokenizer::Alphanumeric
+                | BaseTokenizer::Compound
+                | BaseTokenizer::Letter
+                | BaseTokenizer::Whitespace
+        )
+    }
+
+    // Words of `text` with their byte ranges, for the bases that are not char based
+    fn words(&self, text: &str) -> Vec<(String, (usize, usize))> {
+        let word = |start: usize, end: usize| (text[start..end].to_string(), (start, end));
+        match self {
+            BaseTokenizer::Matches(regex) => regex
+                .find_iter(text)
+                .filter(|m| !m.is_empty())
+                .map(|m| word(m.start(), m.end()))
+                .collect(),
+            BaseTokenizer::Split(regex) => {
+                let mut words = Vec::new();
+                let mut start = 0;
+                for m in regex.find_iter(text) {
+                    if m.start() > start {
+                        words.push(word(start, m.start()));
+                    }
+                    start = start.max(m.end());
+                }
+                if start < text.len() {
+                    words.push(word(start, text.len()));
+                }
+                words
+            }
+            BaseTokenizer::PathHierarchy(delimiter) => {
+                let trimmed = text.trim();
+                let start = text.len() - text.trim_start().len();
+                let mut words = Vec::new();
+                let mut previous = None;
+                for (idx, ch) in trimmed.char_indices() {
+                    // A prefix ends before each delimiter that follows a component
+                    if ch == *delimiter && previous.is_some_and(|p| p != *delimiter) {
+                        words.push(word(start, start + idx));
+                    }
+                    previous = Some(ch);
+                }
+                if previous.is_some_and(|p| p != *delimiter) {
+                    words.push(word(start, start + trimmed.len()));
+                }
+                words
+            }
+            _ => {
+                let trimmed = text.trim();
+                let start = text.len() - text.trim_start().len();
+                if trimmed.is_empty() {
+                    return Vec::new();
+                }
+                vec![word(start, start + trimmed.len())]
+            }
+        }
+    }
+
+    fn is_separator(&self, ch: char) -> bool {
+        match self {
+            BaseTokenizer::Whitespace => ch.is_whitespace(),
+            _ => ch.is_whitespace() || ch.is_ascii_punctuation(),
+        }
+    }
+
+    fn is_word_char(&self, ch: char) -> bool {
+        match self {
+            BaseTokenizer::Alphanumeric | BaseTokenizer::Compound => ch.is_alphanumeric(),
+            BaseTokenizer::Whitespace => true,
+            _ => ch.is_alphabetic(),
+        }
+    }
+
+    // Whether each Chinese or Japanese character is a word of its own
+    fn splits_cjk(&self) -> bool {
+        !matches!(self, BaseTokenizer::Whitespace)
+    }
+
+    // Whether `ch` joins the letters or digits around it into one word
+    fn joins(&self, ch: char, prev: Option<char>, next: Option<char>) -> bool {
+        matches!(self, BaseTokenizer::Compound)
+            && matches!(ch, '.' | '_')
+            && prev.is_some_and(char::is_alphanumeric)
+            && next.is_some_and(char::is_alphanumeric)
+    }
 }
 
 /*
-Iterates through characters to track offsets precisely, handling UTF-8 correctly.
-Converts letters to lowercase and collects them into words.
-Splits on whitespace or punctuation, creating tokens when a word ends.
-Applies stemming and filters stop words to produce clean tokens.
-Tracks position (0-based index in the token sequence) and offset (start/end byte positions in the original text).
+An analyzer: a base tokenizer splitting text into words, then a chain of
+token filters applied to each word in order. Presets cover the usual
+chains; custom ones are built with `Tokenizer::custom` and `with_filter`:
+
+    Tokenizer::custom(BaseTokenizer::Letter, Language::English)
+        .with_filter(LowercaseFilter)
+        .with_filter(NGramFilter::edge(2, 10))
+
+Words every filter dropped take no position, so phrases match across
+stop words; terms added by a filter share the position of their word.
 */
+#[derive(Debug, Clone)]
+pub struct Tokenizer {
+    language: Language,
+    preset: Option<AnalyzerPreset>, // The preset the chain was built from, None for custom ones
+    base: BaseTokenizer,
+    filters: Vec<Arc<dyn TokenFilter>>,
+    classify: bool, // Keep URLs, emails, mentions and hashtags whole
+}
+
 impl Tokenizer {
-    // Create a new Tokenizer with language and stop words
-    pub fn new(language: Language) -> Self {
-        let stop_words = match language {
-            Language::English => {
-                let words = vec![
-                    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "has", "he",
-                    "in", "is", "it", "its", "of", "on", "that", "the", "to", "was", "were",
-                    "will", "with",
-                ];
-                words
-                    .into_iter()
-                    .map(String::from)
-                    .collect::<HashSet<String>>()
-            }
-        };
+    /*
+    Create a new Tokenizer with language, stop words and stemming. Fails if
+    no stemmer can be built for the language, rather than on the first
+    document analyzed.
+    */
+    pub fn new(language: Language) -> Result<Self, MSErrors> {
+        Tokenizer::with_preset(AnalyzerPreset::English, language)
+    }
+
+    // Create a Tokenizer for one of the built-in analyzer presets; fails like `new` for stemming ones
+    pub fn with_preset(preset: AnalyzerPreset, language: Language) -> Result<Self, MSErrors> {
+        Tokenizer::with_preset_and_stop_words(
+            preset,
+            language,
+            StopWordStage::default(),
+            STOP_WORDS_VERSION,
+        )
+    }
+
+    /*
+    Same, with stop list `stop_version`, removed at `stop_stage` if the
+    preset stems. Fails on an unknown list version.
+    */
+    pub fn with_preset_and_stop_words(
+        preset: AnalyzerPreset,
+        language: Language,
+        stop_stage: StopWordStage,
+        stop_version: u32,
+    ) -> Result<Self, MSErrors> {
+        Ok(Tokenizer {
+            language,
+            preset: Some(preset),
+            base: preset.base(),
+            filters: preset.filters(language, stop_stage, stop_version)?,
+            classify: false,
+        })
+    }
+
+    // A Tokenizer with no filters yet, not even lowercasing
+    pub fn custom(base: BaseTokenizer, language: Language) -> Self {
         Tokenizer {
             language,
-            stop_words,
+            preset: None,
+            base,
+            filters: Vec::new(),
+            classify: false,
         }
     }
 
+    // Replace the base tokenizer, keeping the filters
+    pub fn with_base(mut self, base: BaseTokenizer) -> Self {
+        self.base = base;
+        self
+    }
+
+    /*
+    Recognize URLs, emails, @mentions and #hashtags before splitting words,
+    keeping each as a single token of its `TokenKind` rather than cutting
+    it at punctuation. Only applies to bases that split on punctuation.
+    */
+    pub fn with_classifier(mut self) -> Self {
+        self.classify = true;
+        self
+    }
+
+    // Append a filter to the end of the chain
+    pub fn with_filter(mut self, filter: impl TokenFilter + 'static) -> Self {
+        self.filters.push(Arc::new(filter));
+        self
+    }
+
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /*
+    The preset the chain was built from, even if its base or filters were
+    changed since; chains built with `custom` report `Standard`.
+    */
+    #[deprecated(note = "chains can be changed after they are built; inspect `base` instead")]
+    pub fn preset(&self) -> AnalyzerPreset {
+        self.preset.unwrap_or(AnalyzerPreset::Standard)
+    }
+
+    pub fn base(&self) -> &BaseTokenizer {
+        &self.base
+    }
+
     pub fn tokenize(&self, text: &str) -> Vec<Token> {
-        let mut tokens = Vec::new();
-        let mut position = 0;
-        let mut char_iter = text.char_indices();
-        let mut current_word = String::new();
-        let mut start_offset = 0;
-
-        // Initialize stemmer for English
-        let mut stemmer = Stemmer::new("english").expect("Failed to initialize stemmer");
-
-        while let Some((idx, ch)) = char_iter.next() {
-            if ch.is_alphabetic() {
-                current_word.push(ch.to_ascii_lowercase());
-            } else if ch.is_whitespace() || ch.is_ascii_punctuation() {
-                if !current_word.is_empty() {
-                    // Process the current word
-                    let stemmed = stemmer.stem(&current_word);
-                    if !self.stop_words.contains(&stemmed) && !stemmed.is_empty() {
-                        tokens.push(Token {
-                            term: stemmed,
-                            position,
-                            offset: (start_offset, idx),
-                        });
-                        position += 1;
-                    }
-                    current_word.clear();
-                }
-                // Update start offset for the next word
-                start_offset = idx + ch.len_utf8();
-            }
-        }
+        self.token_stream(text).collect()
+    }
 
-        // Handle the last word if it exists
-        if !current_word.is_empty() {
-            let stemmed = stemmer.stem(&current_word);
-            if !self.stop_words.contains(&stemmed) && !stemmed.is_empty() {
-                tokens.push(Token {
-                    term: stemmed,
-                    position,
-                    offset: (start_offset, text.len()),
-                });
-            }
-        }
+    // Tokens of `text`, produced as the stream is consumed
+    pub fn token_stream<'a>(&'a self, text: &'a str) -> Tokens<'a> {
+        Tokens::new(self, text)
+    }
+}
 
-        tokens
+// Han ideographs and kana, written without spaces between words
+fn is_cjk(ch: char) -> bool {
+    matches!(ch, '\u{3040}'..='\u{30ff}' | '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}')
+}
+
+// Kind of a word found by a char-based base tokenizer
+fn word_kind(word: &str) -> TokenKind {
+    if word.chars().all(|ch| ch.is_numeric() || ch == '.') {
+        TokenKind::Number
+    } else {
+        TokenKind::Word
     }
 }
 
+fn compile(pattern: &str) -> Result<Regex, MSErrors> {
+    Regex::new(pattern)
+        .map_err(|e| MSErrors::ParseError(format!("invalid token pattern {}: {}", pattern, e)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_tokenize_basic() {
-        let tokenizer = Tokenizer::new(Language::English);
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
         let text = "The quick foxes jump!";
         let tokens = tokenizer.tokenize(text);
         let expected = vec![
@@ -112,16 +533,22 @@ mod tests {
                 term: String::from("quick"),
                 position: 0,
                 offset: (4, 9),
+                kind: TokenKind::Word,
+                payload: None,
             },
             Token {
                 term: String::from("fox"),
                 position: 1,
                 offset: (10, 15),
+                kind: TokenKind::Word,
+                payload: None,
             },
             Token {
                 term: String::from("jump"),
                 position: 2,
                 offset: (16, 20),
+                kind: TokenKind::Word,
+                payload: None,
             },
         ];
         assert_eq!(tokens, expected);
@@ -129,22 +556,46 @@ mod tests {
 
     #[test]
     fn test_tokenize_empty() {
-        let tokenizer = Tokenizer::new(Language::English);
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
         let tokens = tokenizer.tokenize("");
         assert_eq!(tokens, vec![]);
     }
 
     #[test]
     fn test_tokenize_stop_words() {
-        let tokenizer = Tokenizer::new(Language::English);
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
         let text = "The and is";
         let tokens = tokenizer.tokenize(text);
         assert_eq!(tokens, vec![]);
     }
 
+    #[test]
+    fn test_stop_word_stage() {
+        let text = "This was the fox that has been jumping over those dogs";
+        let terms_at = |stage: &str, text: &str| {
+            let tokenizer = Tokenizer::with_preset_and_stop_words(
+                AnalyzerPreset::English,
+                Language::English,
+                stage.parse().unwrap(),
+                STOP_WORDS_VERSION,
+            )
+            .unwrap();
+            terms(&tokenizer, text)
+        };
+        assert_eq!(
+            terms_at("before_stem", text),
+            ["fox", "jump", "over", "dog"]
+        );
+        assert_eq!(terms_at("both", text), ["fox", "jump", "over", "dog"]);
+        // Only stems that read as a stop word are removed after stemming
+        assert_eq!(terms_at("after_stem", "the fox does"), ["fox", "doe"]);
+        assert_eq!(terms_at("after_stem", "hi doe"), ["hi", "doe"]);
+        assert!("during".parse::<StopWordStage>().is_err());
+    }
+
     #[test]
     fn test_tokenize_punctuation() {
-        let tokenizer = Tokenizer::new(Language::English);
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
         let text = "Hello, world!!!";
         let tokens = tokenizer.tokenize(text);
         let expected = vec![
@@ -152,13 +603,192 @@ mod tests {
                 term: String::from("hello"),
                 position: 0,
                 offset: (0, 5),
+                kind: TokenKind::Word,
+                payload: None,
             },
             Token {
                 term: String::from("world"),
                 position: 1,
                 offset: (7, 12),
+                kind: TokenKind::Word,
+                payload: None,
             },
         ];
         assert_eq!(tokens, expected);
     }
+
+    fn terms(tokenizer: &Tokenizer, text: &str) -> Vec<String> {
+        tokenizer
+            .tokenize(text)
+            .into_iter()
+            .map(|t| t.term)
+            .collect()
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_presets() {
+        let text = "The Quick-Fox's  ipv6 Jumps";
+        let analyze = |name: &str| {
+            let preset: AnalyzerPreset = name.parse().unwrap();
+            terms(
+                &Tokenizer::with_preset(preset, Language::English).unwrap(),
+                text,
+            )
+        };
+        assert_eq!(
+            analyze("standard"),
+            ["the", "quick", "fox", "s", "ipv6", "jumps"]
+        );
+        assert_eq!(
+            analyze("simple"),
+            ["the", "quick", "fox", "s", "ipv", "jumps"]
+        );
+        assert_eq!(
+            analyze("whitespace"),
+            ["the", "quick-fox's", "ipv6", "jumps"]
+        );
+        assert_eq!(analyze("keyword"), [text]);
+        let keyword = Tokenizer::with_preset(AnalyzerPreset::Keyword, Language::English).unwrap();
+        assert_eq!(keyword.preset(), AnalyzerPreset::Keyword);
+        assert_eq!(analyze("stop"), ["quick", "fox", "s", "ipv", "jumps"]);
+        assert_eq!(analyze("english"), ["quick", "fox", "s", "ipv", "jump"]);
+        assert_eq!(
+            analyze("edge_ngram"),
+            [
+                "th", "the", "qu", "qui", "quic", "quick", "fo", "fox", "s", "ip", "ipv", "ipv6"
+            ]
+            .into_iter()
+            .chain(["ju", "jum", "jump", "jumps"])
+            .collect::<Vec<_>>()
+        );
+        assert!("fancy".parse::<AnalyzerPreset>().is_err());
+    }
+
+    #[test]
+    fn test_keyword_offsets() {
+        let tokenizer = Tokenizer::with_preset(AnalyzerPreset::Keyword, Language::English).unwrap();
+        let tokens = tokenizer.tokenize("  New York ");
+        assert_eq!(
+            tokens,
+            vec![Token {
+                term: String::from("New York"),
+                position: 0,
+                offset: (2, 10),
+                kind: TokenKind::Word,
+                payload: None,
+            }]
+        );
+        assert_eq!(tokenizer.tokenize("   "), vec![]);
+    }
+
+    #[test]
+    fn test_pattern_and_path_bases() {
+        let path = Tokenizer::with_preset(AnalyzerPreset::Path, Language::English).unwrap();
+        assert_eq!(
+            terms(&path, " /usr//local/Bin/ "),
+            ["/usr", "/usr//local", "/usr//local/Bin"]
+        );
+        assert_eq!(path.tokenize("/usr/local")[1].offset, (0, 10));
+
+        let pattern = Tokenizer::with_preset(AnalyzerPreset::Pattern, Language::English).unwrap();
+        assert_eq!(
+            terms(&pattern, "snake_case, CamelCase!"),
+            ["snake_case", "camelcase"]
+        );
+        let split = Tokenizer::custom(BaseTokenizer::split(r"\s*;\s*").unwrap(), Language::English);
+        let tokens = split.tokenize("red wine; ;blue cheese");
+        assert_eq!(tokens[1].term, "blue cheese");
+        assert_eq!((tokens[1].position, tokens[1].offset), (1, (11, 22)));
+        assert!(BaseTokenizer::matches("(").is_err());
+    }
+
+    #[test]
+    fn test_classified_tokens() {
+        let text = "Mail Ada@example.com, see (https://example.com/docs). #Rust @ada";
+        let plain = Tokenizer::with_preset(AnalyzerPreset::Standard, Language::English).unwrap();
+        assert_eq!(terms(&plain, text)[..4], ["mail", "ada", "example", "com"]);
+
+        let tokenizer = plain.with_classifier();
+        let tokens = tokenizer.tokenize(text);
+        let kinds: Vec<(&str, TokenKind)> =
+            tokens.iter().map(|t| (t.term.as_str(), t.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                ("mail", TokenKind::Word),
+                ("ada@example.com", TokenKind::Email),
+                ("see", TokenKind::Word),
+                ("https://example.com/docs", TokenKind::Url),
+                ("#rust", TokenKind::Hashtag),
+                ("@ada", TokenKind::Mention),
+            ]
+        );
+        assert_eq!(tokens[3].offset, (27, 51));
+        assert_eq!(tokens[4].position, 4);
+    }
+
+    #[test]
+    fn test_token_kinds() {
+        let tokenizer = Tokenizer::new(Language::English)
+            .unwrap()
+            .with_base(BaseTokenizer::Alphanumeric);
+        let tokens = tokenizer.tokenize("Errors 404 in 3.14 東京");
+        let kinds: Vec<(&str, TokenKind)> =
+            tokens.iter().map(|t| (t.term.as_str(), t.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                ("error", TokenKind::Word),
+                ("404", TokenKind::Number),
+                ("3", TokenKind::Number),
+                ("14", TokenKind::Number),
+                ("東", TokenKind::Cjk),
+                ("京", TokenKind::Cjk),
+            ]
+        );
+        assert_eq!((tokens[5].position, tokens[5].offset), (5, (22, 25)));
+    }
+
+    #[test]
+    fn test_custom_chain() {
+        let tokenizer = Tokenizer::custom(BaseTokenizer::Letter, Language::English)
+            .with_filter(LowercaseFilter)
+            .with_filter(StopWordFilter::new(Language::English))
+            .with_filter(SynonymFilter::new().add("quick", ["fast"]));
+        let tokens = tokenizer.tokenize("The Quick fox");
+        let positions: Vec<(&str, usize)> = tokens
+            .iter()
+            .map(|t| (t.term.as_str(), t.position))
+            .collect();
+        assert_eq!(positions, [("quick", 0), ("fast", 0), ("fox", 1)]);
+        assert_eq!(tokens[1].offset, (4, 9));
+
+        let keyword = Tokenizer::custom(BaseTokenizer::Keyword, Language::English)
+            .with_filter(LowercaseFilter);
+        assert_eq!(terms(&keyword, " New York"), ["new york"]);
+    }
+
+    proptest::proptest! {
+        // Offsets of every token slice the input on character boundaries
+        #[test]
+        fn prop_offsets_slice_input(text in "\\PC{0,64}", preset in 0usize..9) {
+            let presets = [
+                AnalyzerPreset::Standard,
+                AnalyzerPreset::Simple,
+                AnalyzerPreset::Whitespace,
+                AnalyzerPreset::Keyword,
+                AnalyzerPreset::Stop,
+                AnalyzerPreset::English,
+                AnalyzerPreset::Path,
+                AnalyzerPreset::Pattern,
+                AnalyzerPreset::EdgeNGram,
+            ];
+            let tokenizer = Tokenizer::with_preset(presets[preset], Language::English).unwrap();
+            for token in tokenizer.tokenize(&text) {
+                let (start, end) = token.offset;
+                proptest::prop_assert!(start <= end && text.get(start..end).is_some());
+            }
+        }
+    }
 }
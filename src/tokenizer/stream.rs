@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+use std::str::CharIndices;
+
+use super::{Token, TokenKind, Tokenizer, classify, is_cjk, word_kind};
+
+/*
+Analyzed tokens of a text, in position order. Token offsets are byte
+ranges of `text()`, so consumers such as entity taggers or passage
+chunkers can map tokens back to the original words. This trait is part of
+the stable API: new methods only get added with default implementations.
+*/
+pub trait TokenStream: Iterator<Item = Token> {
+    // The text being analyzed
+    fn text(&self) -> &str;
+}
+
+// Lazily produces the tokens of `Tokenizer::tokenize`, one word at a time
+pub struct Tokens<'a> {
+    tokenizer: &'a Tokenizer,
+    text: &'a str,
+    chars: CharIndices<'a>,
+    word_start: usize,
+    position: usize,                                   // Position of the next token
+    pending: VecDeque<Token>, // Analyzed terms of the current word not returned yet
+    words: Option<VecDeque<(String, (usize, usize))>>, // Found up front by whole-text bases
+    done: bool,
+}
+
+impl<'a> Tokens<'a> {
+    pub(super) fn new(tokenizer: &'a Tokenizer, text: &'a str) -> Self {
+        Tokens {
+            tokenizer,
+            text,
+            chars: text.char_indices(),
+            word_start: 0,
+            position: 0,
+            pending: VecDeque::new(),
+            words: None,
+            done: false,
+        }
+    }
+
+    // Next word of the base tokenizer, its byte range and kind
+    fn next_word(&mut self) -> Option<(String, (usize, usize), TokenKind)> {
+        if self.done {
+            return None;
+        }
+        let base = &self.tokenizer.base;
+        if !base.is_char_based() {
+            let text = self.text;
+            let words = self.words.get_or_insert_with(|| base.words(text).into());
+            let word = words.pop_front();
+            self.done = word.is_none();
+            return word.map(|(word, offset)| (word, offset, TokenKind::Word));
+        }
+        let mut word = String::new();
+        let mut prev = None;
+        loop {
+            let before = self.chars.clone();
+            let Some((idx, ch)) = self.chars.next() else {
+                break;
+            };
+            if base.splits_cjk() && is_cjk(ch) {
+                if !word.is_empty() {
+                    // End the word here and read the character again on the next call
+                    self.chars = before;
+                    let start = self.word_start;
+                    self.word_start = idx;
+                    let kind = word_kind(&word);
+                    return Some((word, (start, idx), kind));
+                }
+                let end = idx + ch.len_utf8();
+                self.word_start = end;
+                return Some((ch.to_string(), (idx, end), TokenKind::Cjk));
+            }
+            if word.is_empty()
+                && self.tokenizer.classify
+                && !prev.is_some_and(char::is_alphanumeric)
+                && let Some((kind, len)) = classify::classify(&self.text[idx..])
+            {
+                let end = idx + len;
+                while self.chars.clone().next().is_some_and(|(i, _)| i < end) {
+                    self.chars.next();
+                }
+                self.word_start = end;
+                return Some((self.text[idx..end].to_string(), (idx, end), kind));
+            }
+            let next = self.chars.clone().next().map(|(_, next)| next);
+            if base.joins(ch, prev, next) {
+                word.push(ch);
+            } else if base.is_separator(ch) {
+                let start = self.word_start;
+                // Update start offset for the next word
+                self.word_start = idx + ch.len_utf8();
+                if !word.is_empty() {
+                    let kind = word_kind(&word);
+                    return Some((word, (start, idx), kind));
+                }
+            } else if base.is_word_char(ch) {
+                word.push(ch);
+            }
+            prev = Some(ch);
+        }
+        // Handle the last word if it exists
+        self.done = true;
+        let kind = word_kind(&word);
+        (!word.is_empty()).then_some((word, (self.word_start, self.text.len()), kind))
+    }
+
+    // Run a word through the filters, queueing its tokens at the next position
+    fn analyze(&mut self, word: String, offset: (usize, usize), kind: TokenKind) {
+        let mut tokens = vec![Token {
+            term: word,
+            position: self.position,
+            offset,
+            kind,
+            payload: None,
+        }];
+        for filter in &self.tokenizer.filters {
+            let mut filtered = Vec::with_capacity(tokens.len());
+            for token in tokens {
+                filter.filter(token, &mut filtered);
+            }
+            tokens = filtered;
+        }
+        tokens.retain(|token| !token.term.is_empty());
+        if tokens.is_empty() {
+            return;
+        }
+        for mut token in tokens {
+            token.position = self.position;
+            self.pending.push_back(token);
+        }
+        self.position += 1;
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        loop {
+            if let Some(token) = self.pending.pop_front() {
+                return Some(token);
+            }
+            let (word, offset, kind) = self.next_word()?;
+            self.analyze(word, offset, kind);
+        }
+    }
+}
+
+impl TokenStream for Tokens<'_> {
+    fn text(&self) -> &str {
+        self.text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Language;
+
+    // Written against the trait only, as an external consumer would be
+    fn words(stream: impl TokenStream) -> Vec<String> {
+        let text = stream.text().to_string();
+        stream
+            .map(|token| text[token.offset.0..token.offset.1].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_token_stream() {
+        let tokenizer = Tokenizer::new(Language::English).unwrap();
+        let text = "The foxes, jumping over the lazy dogs";
+        let streamed: Vec<Token> = tokenizer.token_stream(text).collect();
+        assert_eq!(streamed, tokenizer.tokenize(text));
+        assert_eq!(
+            words(tokenizer.token_stream(text)),
+            ["foxes", "jumping", "over", "lazy", "dogs"]
+        );
+
+        let mut stream = tokenizer.token_stream("the a an quick");
+        assert_eq!(stream.next().unwrap().position, 0);
+        assert!(stream.next().is_none());
+    }
+}
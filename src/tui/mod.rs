@@ -0,0 +1,236 @@
+use std::io;
+
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+};
+
+use crate::highlight::Fragment;
+use crate::searcher::{ResultPage, SearchEngine};
+
+// Rows used by each hit in the result list (title + snippet)
+const ROWS_PER_HIT: u16 = 2;
+
+struct App<'a> {
+    engine: &'a SearchEngine,
+    query: String,
+    page: usize,
+    page_size: usize,
+    results: Option<ResultPage>,
+    list_state: ListState,
+}
+
+impl<'a> App<'a> {
+    fn new(engine: &'a SearchEngine) -> Self {
+        App {
+            engine,
+            query: String::new(),
+            page: 0,
+            page_size: 10,
+            results: None,
+            list_state: ListState::default(),
+        }
+    }
+
+    fn refresh(&mut self) {
+        if self.query.trim().is_empty() {
+            self.results = None;
+            self.list_state.select(None);
+            return;
+        }
+        let results = self
+            .engine
+            .search_page(&self.query, self.page, self.page_size);
+        self.list_state
+            .select((!results.hits.is_empty()).then_some(0));
+        self.results = Some(results);
+    }
+
+    // Returns false when the user asked to quit
+    fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        match code {
+            KeyCode::Esc => return false,
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.page = 0;
+                self.refresh();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.page = 0;
+                self.refresh();
+            }
+            KeyCode::Down => self.list_state.select_next(),
+            KeyCode::Up => self.list_state.select_previous(),
+            KeyCode::PageDown if self.results.as_ref().is_some_and(ResultPage::has_next) => {
+                self.page += 1;
+                self.refresh();
+            }
+            KeyCode::PageUp if self.page > 0 => {
+                self.page -= 1;
+                self.refresh();
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [query_area, main_area, status_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list_area, preview_area] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(main_area);
+
+        // Fit one page of hits into the list, minus its borders
+        let page_size = (list_area.height.saturating_sub(2) / ROWS_PER_HIT).max(1) as usize;
+        if page_size != self.page_size {
+            self.page_size = page_size;
+            self.page = 0;
+            self.refresh();
+        }
+
+        let query = Paragraph::new(self.query.as_str())
+            .block(Block::default().borders(Borders::ALL).title(" Search "));
+        frame.render_widget(query, query_area);
+        frame.set_cursor_position((
+            query_area.x + 1 + self.query.chars().count() as u16,
+            query_area.y + 1,
+        ));
+
+        let hits = self.results.as_ref().map_or(&[][..], |r| &r.hits[..]);
+        let items: Vec<ListItem> = hits
+            .iter()
+            .map(|hit| {
+                let title = Line::from(vec![
+                    Span::styled(
+                        format!("{}. ", hit.rank),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(
+                        hit.title.clone(),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                ]);
+                ListItem::new(Text::from(vec![title, fragments_line(&hit.snippet)]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(" Results "))
+            .highlight_style(Style::default().bg(Color::DarkGray));
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+
+        let selected = self.list_state.selected().and_then(|i| hits.get(i));
+        let preview = match selected {
+            Some(hit) => {
+                let mut lines = vec![Line::styled(
+                    hit.title.clone(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )];
+                lines.extend(hit.metadata.iter().map(|(key, value)| {
+                    Line::styled(
+                        format!("{}: {}", key, value),
+                        Style::default().fg(Color::DarkGray),
+                    )
+                }));
+                lines.push(Line::default());
+                lines.extend(fragments_text(&hit.preview).lines);
+                Text::from(lines)
+            }
+            None => Text::default(),
+        };
+        let preview = Paragraph::new(preview)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title(" Preview "));
+        frame.render_widget(preview, preview_area);
+
+        let status = match &self.results {
+            Some(results) => format!(
+                " {} matches in {} ms, page {}/{}  (PgUp/PgDn: page, ↑/↓: select, Esc: quit)",
+                results.total_matches,
+                results.query_time_ms,
+                results.page + 1,
+                results.total_pages().max(1),
+            ),
+            None => format!(
+                " {} documents  (type to search, Esc: quit)",
+                self.engine.document_count()
+            ),
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+}
+
+fn highlight_style() -> Style {
+    Style::default().fg(Color::Black).bg(Color::Yellow)
+}
+
+// Snippets are rendered on a single line
+fn fragments_line(fragments: &[Fragment]) -> Line<'static> {
+    Line::from(
+        fragments
+            .iter()
+            .map(|f| {
+                let text = f.text.replace('\n', " ");
+                if f.highlighted {
+                    Span::styled(text, highlight_style())
+                } else {
+                    Span::raw(text)
+                }
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+// Previews keep the original line breaks
+fn fragments_text(fragments: &[Fragment]) -> Text<'static> {
+    let mut lines = vec![Line::default()];
+    for fragment in fragments {
+        let style = if fragment.highlighted {
+            highlight_style()
+        } else {
+            Style::default()
+        };
+        for (i, part) in fragment.text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(Line::default());
+            }
+            if !part.is_empty() {
+                lines
+                    .last_mut()
+                    .unwrap()
+                    .push_span(Span::styled(part.to_string(), style));
+            }
+        }
+    }
+    Text::from(lines)
+}
+
+fn run_app(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && !app.handle_key(key.code, key.modifiers)
+        {
+            return Ok(());
+        }
+    }
+}
+
+// Run the interactive search UI until the user quits
+pub fn run(engine: &SearchEngine) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run_app(&mut terminal, &mut App::new(engine));
+    ratatui::restore();
+    result
+}